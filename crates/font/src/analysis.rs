@@ -0,0 +1,357 @@
+use crate::geometry::{bezier_point, hodograph, Point, Segment};
+use crate::boolean::{self_intersections, winding_number};
+use crate::sink::OutlineSink;
+use crate::truetype::outline::{Contour, Outline, PathSegment};
+
+// 曲線の極値がこれ以上端点からはみ出していたら、極値に点がないとみなす（フォント単位）
+const EXTREMUM_TOLERANCE: f32 = 1.0;
+// 面積の計算誤差。これより小さい面積は0とみなす（フォント単位の2乗）
+const AREA_EPSILON: f64 = 1e-6;
+
+// 輪郭の向き（y軸上向き）。TrueType では外側の輪郭が時計回り、穴が反時計回り
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+  Clockwise,
+  CounterClockwise,
+  // 面積が0で向きが決まらない
+  Degenerate,
+}
+
+impl Direction {
+  fn from_area(area: f64) -> Self {
+    if area < -AREA_EPSILON {
+      Direction::Clockwise
+    } else if area > AREA_EPSILON {
+      Direction::CounterClockwise
+    } else {
+      Direction::Degenerate
+    }
+  }
+}
+
+// 曲線1本ぶんの (x dy - y dx) / 2 の積分。被積分関数は高々5次なので3点のガウス・ルジャンドル求積で厳密に求まる
+fn curve_area(points: &[Point]) -> f64 {
+  let derivative = hodograph(points);
+  let offset = 0.5 * (0.6f64).sqrt();
+  [(0.5 - offset, 5.0 / 18.0), (0.5, 8.0 / 18.0), (0.5 + offset, 5.0 / 18.0)]
+    .iter()
+    .map(|&(t, weight)| {
+      weight * bezier_point(points, t).cross(bezier_point(&derivative, t)) / 2.0
+    })
+    .sum()
+}
+
+fn point(x: f32, y: f32) -> Point {
+  Point::new(x as f64, y as f64)
+}
+
+// 曲線の1軸について、極値が端点の範囲から許容値を超えてはみ出しているか
+fn overshoots(value: f32, start: f32, end: f32) -> bool {
+  value > start.max(end) + EXTREMUM_TOLERANCE || value < start.min(end) - EXTREMUM_TOLERANCE
+}
+
+// 流し込まれた輪郭の符号付き面積と、線分ごとの問題を集める
+#[derive(Debug, Default)]
+struct ContourSink {
+  area: f64,
+  zero_length_segments: Vec<(f32, f32)>,
+  missing_extrema: Vec<(f32, f32)>,
+  // 線分の中点。入れ子の判定に使う
+  samples: Vec<(f32, f32)>,
+  start: (f32, f32),
+  current: (f32, f32),
+}
+
+impl ContourSink {
+  fn segment(&mut self, points: &[(f32, f32)]) {
+    let wide: Vec<Point> = points.iter().map(|&(x, y)| point(x, y)).collect();
+    self.area += curve_area(&wide);
+    if points.windows(2).all(|w| w[0] == w[1]) {
+      self.zero_length_segments.push(points[0]);
+    }
+    let middle = bezier_point(&wide, 0.5);
+    self.samples.push((middle.x as f32, middle.y as f32));
+    self.current = points[points.len() - 1];
+  }
+}
+
+impl OutlineSink for ContourSink {
+  fn move_to(&mut self, x: f32, y: f32) {
+    self.start = (x, y);
+    self.current = (x, y);
+  }
+
+  fn line_to(&mut self, x: f32, y: f32) {
+    self.segment(&[self.current, (x, y)]);
+  }
+
+  fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+    let (x0, y0) = self.current;
+    let segment = Segment::Quad(point(x0, y0), point(x1, y1), point(x, y));
+    for t in segment.extrema() {
+      let p = segment.point(t);
+      let (px, py) = (p.x as f32, p.y as f32);
+      if overshoots(px, x0, x) || overshoots(py, y0, y) {
+        self.missing_extrema.push((px, py));
+      }
+    }
+    self.segment(&[self.current, (x1, y1), (x, y)]);
+  }
+
+  fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+    let (x0, y0) = self.current;
+    let segment = Segment::Cubic(point(x0, y0), point(x1, y1), point(x2, y2), point(x, y));
+    for t in segment.extrema() {
+      let p = segment.point(t);
+      let (px, py) = (p.x as f32, p.y as f32);
+      if overshoots(px, x0, x) || overshoots(py, y0, y) {
+        self.missing_extrema.push((px, py));
+      }
+    }
+    self.segment(&[self.current, (x1, y1), (x2, y2), (x, y)]);
+  }
+
+  fn close(&mut self) {
+    if self.current != self.start {
+      self.line_to(self.start.0, self.start.1);
+    }
+  }
+}
+
+// 輪郭1つの検査結果
+#[derive(Debug, Clone)]
+pub struct ContourReport {
+  // 符号付き面積。時計回りなら負
+  pub area: f64,
+  pub direction: Direction,
+  // この輪郭を囲む最も内側の輪郭の番号
+  pub parent: Option<usize>,
+  // 囲んでいる輪郭の数。奇数なら穴
+  pub depth: usize,
+  pub self_intersections: Vec<(f32, f32)>,
+  pub zero_length_segments: Vec<(f32, f32)>,
+  // 次の点と同じ座標にある点の番号（輪郭内での番号）
+  pub duplicate_points: Vec<usize>,
+  // 点が置かれていない曲線の極値の位置
+  pub missing_extrema: Vec<(f32, f32)>,
+}
+
+impl ContourReport {
+  pub fn is_hole(&self) -> bool {
+    self.depth % 2 == 1
+  }
+
+  // 入れ子の深さから期待される向きと逆になっているか
+  pub fn has_wrong_direction(&self) -> bool {
+    let expected = if self.is_hole() { Direction::CounterClockwise } else { Direction::Clockwise };
+    self.direction != Direction::Degenerate && self.direction != expected
+  }
+
+  pub fn issues(&self) -> Vec<String> {
+    let mut issues = Vec::new();
+    if self.direction == Direction::Degenerate {
+      issues.push("面積が0の輪郭です".to_string());
+    } else if self.has_wrong_direction() {
+      let (kind, direction) = if self.is_hole() { ("穴", "時計回り") } else { ("外側の輪郭", "反時計回り") };
+      issues.push(format!("{}が{}になっています", kind, direction));
+    }
+    for &(x, y) in &self.self_intersections {
+      issues.push(format!("自己交差 ({}, {})", x, y));
+    }
+    for &(x, y) in &self.zero_length_segments {
+      issues.push(format!("長さ0の線分 ({}, {})", x, y));
+    }
+    for &index in &self.duplicate_points {
+      issues.push(format!("重複した点 #{}", index));
+    }
+    for &(x, y) in &self.missing_extrema {
+      issues.push(format!("極値に点がありません ({}, {})", x.round(), y.round()));
+    }
+    issues
+  }
+}
+
+// グリフ全体の検査結果
+#[derive(Debug, Clone)]
+pub struct OutlineReport {
+  pub contours: Vec<ContourReport>,
+}
+
+impl OutlineReport {
+  pub fn is_clean(&self) -> bool {
+    self.contours.iter().all(|contour| contour.issues().is_empty())
+  }
+
+  // 問題を「輪郭{番号}: 内容」の形で並べる
+  pub fn issues(&self) -> Vec<String> {
+    self.contours.iter()
+      .enumerate()
+      .flat_map(|(index, contour)| contour.issues().into_iter().map(move |issue| format!("輪郭{}: {}", index, issue)))
+      .collect()
+  }
+}
+
+impl Contour {
+  // 曲線を含めた符号付き面積（y軸上向きで反時計回りが正）
+  pub fn signed_area(&self) -> f64 {
+    let mut sink = ContourSink::default();
+    self.draw(&mut sink);
+    sink.area
+  }
+
+  pub fn direction(&self) -> Direction {
+    Direction::from_area(self.signed_area())
+  }
+
+  fn duplicate_points(&self) -> Vec<usize> {
+    let n = self.points.len();
+    if n < 2 {
+      return Vec::new();
+    }
+    (0..n)
+      .filter(|&i| {
+        let (p, q) = (self.points[i], self.points[(i + 1) % n]);
+        p.x == q.x && p.y == q.y
+      })
+      .collect()
+  }
+}
+
+impl Outline {
+  pub fn analyze(&self) -> OutlineReport {
+    let sinks: Vec<ContourSink> = self.contours.iter()
+      .map(|contour| {
+        let mut sink = ContourSink::default();
+        contour.draw(&mut sink);
+        sink
+      })
+      .collect();
+    let segments: Vec<Vec<PathSegment>> = self.contours.iter().map(|contour| contour.segments()).collect();
+
+    // 輪郭 i の中点の過半数が輪郭 j の内側にあれば、j が i を囲んでいるとみなす
+    let encloses = |j: usize, i: usize| {
+      let samples = &sinks[i].samples;
+      let inside = samples.iter().filter(|&&(x, y)| winding_number(&segments[j], x, y) != 0).count();
+      !samples.is_empty() && inside * 2 > samples.len()
+    };
+    // 囲んでいる輪郭のうち面積が最も小さいものを親とする
+    let parents: Vec<Option<usize>> = (0..sinks.len())
+      .map(|i| {
+        (0..sinks.len())
+          .filter(|&j| j != i && sinks[j].area.abs() > sinks[i].area.abs() && encloses(j, i))
+          .min_by(|&a, &b| sinks[a].area.abs().total_cmp(&sinks[b].area.abs()))
+      })
+      .collect();
+
+    let contours = self.contours.iter()
+      .zip(sinks)
+      .enumerate()
+      .map(|(i, (contour, sink))| {
+        // 親は必ず面積が大きいので、たどっていけば終わる
+        let mut depth = 0;
+        let mut ancestor = parents[i];
+        while let Some(j) = ancestor {
+          depth += 1;
+          ancestor = parents[j];
+        }
+        ContourReport {
+          area: sink.area,
+          direction: Direction::from_area(sink.area),
+          parent: parents[i],
+          depth,
+          self_intersections: self_intersections(&segments[i]),
+          zero_length_segments: sink.zero_length_segments,
+          duplicate_points: contour.duplicate_points(),
+          missing_extrema: sink.missing_extrema,
+        }
+      })
+      .collect();
+    OutlineReport { contours }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::truetype::outline::Point as GlyphPoint;
+
+  fn contour(points: &[(i16, i16, bool)]) -> Contour {
+    Contour { points: points.iter().map(|&(x, y, on_curve)| GlyphPoint { x, y, on_curve }).collect() }
+  }
+
+  // (x0, y0) から (x1, y1) までの長方形。clockwise なら TrueType の外側の輪郭の向き
+  fn rectangle(x0: i16, y0: i16, x1: i16, y1: i16, clockwise: bool) -> Contour {
+    let mut points = vec![(x0, y0, true), (x0, y1, true), (x1, y1, true), (x1, y0, true)];
+    if !clockwise {
+      points.reverse();
+    }
+    contour(&points)
+  }
+
+  #[test]
+  fn o_with_a_hole_and_an_island() {
+    let outline = Outline {
+      contours: vec![
+        rectangle(0, 0, 100, 100, true),
+        rectangle(20, 20, 80, 80, false),
+        rectangle(40, 40, 60, 60, true),
+      ],
+    };
+    let report = outline.analyze();
+    let summary: Vec<(f64, Direction, Option<usize>, usize)> = report.contours.iter()
+      .map(|c| (c.area, c.direction, c.parent, c.depth))
+      .collect();
+    assert_eq!(
+      summary,
+      [
+        (-10000.0, Direction::Clockwise, None, 0),
+        (3600.0, Direction::CounterClockwise, Some(0), 1),
+        (-400.0, Direction::Clockwise, Some(1), 2),
+      ]
+    );
+    assert!(report.contours[1].is_hole());
+    assert!(report.is_clean(), "{:?}", report.issues());
+  }
+
+  #[test]
+  fn hole_in_the_wrong_direction() {
+    let outline = Outline { contours: vec![rectangle(0, 0, 100, 100, true), rectangle(20, 20, 80, 80, true)] };
+    let report = outline.analyze();
+    assert!(report.contours[1].has_wrong_direction());
+    assert_eq!(report.issues(), ["輪郭1: 穴が時計回りになっています"]);
+  }
+
+  #[test]
+  fn curved_area_is_exact() {
+    // 高さ 50 の放物線の下の面積は 2/3 × 100 × 50
+    let arch = contour(&[(0, 0, true), (50, 100, false), (100, 0, true)]);
+    assert!((arch.signed_area() + 10000.0 / 3.0).abs() < 1e-9);
+    assert_eq!(arch.direction(), Direction::Clockwise);
+  }
+
+  #[test]
+  fn figure_eight_crosses_itself() {
+    let outline = Outline { contours: vec![contour(&[(0, 0, true), (100, 100, true), (100, 0, true), (0, 100, true)])] };
+    let report = &outline.analyze().contours[0];
+    assert_eq!(report.direction, Direction::Degenerate);
+    assert_eq!(report.self_intersections, [(50.0, 50.0)]);
+  }
+
+  #[test]
+  fn duplicate_points_make_zero_length_segments() {
+    let outline = Outline { contours: vec![contour(&[(0, 0, true), (0, 0, true), (0, 100, true), (100, 100, true), (100, 0, true)])] };
+    let report = &outline.analyze().contours[0];
+    assert_eq!(report.duplicate_points, [0]);
+    assert_eq!(report.zero_length_segments, [(0.0, 0.0)]);
+    assert_eq!(report.issues(), ["長さ0の線分 (0, 0)", "重複した点 #0"]);
+  }
+
+  #[test]
+  fn extremum_without_an_on_curve_point() {
+    let arch = Outline { contours: vec![contour(&[(0, 0, true), (50, 100, false), (100, 0, true)])] };
+    assert_eq!(arch.analyze().contours[0].missing_extrema, [(50.0, 50.0)]);
+    // 頂点に点を置けば極値は端点になる
+    let split = Outline { contours: vec![contour(&[(0, 0, true), (0, 50, false), (50, 50, true), (100, 50, false), (100, 0, true)])] };
+    assert!(split.analyze().contours[0].missing_extrema.is_empty());
+  }
+}
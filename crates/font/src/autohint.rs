@@ -0,0 +1,187 @@
+use crate::truetype::SimpleGlyph;
+
+// ブルーゾーンを探すときに見る文字の組。FreeType の autofit と同じ考え方で、文字体系ごとに決めておく
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Script {
+  Latin,
+  Cjk,
+}
+
+// ブルーゾーンの種類。top はゾーンの上側がインクの外になる（文字の上端をそろえる）もの
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlueKind {
+  CapitalTop,
+  CapitalBottom,
+  Ascender,
+  SmallTop,
+  SmallBottom,
+  Descender,
+  IdeographTop,
+  IdeographBottom,
+}
+
+impl BlueKind {
+  pub fn is_top(self) -> bool {
+    matches!(self, BlueKind::CapitalTop | BlueKind::Ascender | BlueKind::SmallTop | BlueKind::IdeographTop)
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      BlueKind::CapitalTop => "capital-top",
+      BlueKind::CapitalBottom => "capital-bottom",
+      BlueKind::Ascender => "ascender",
+      BlueKind::SmallTop => "x-height",
+      BlueKind::SmallBottom => "baseline",
+      BlueKind::Descender => "descender",
+      BlueKind::IdeographTop => "ideograph-top",
+      BlueKind::IdeographBottom => "ideograph-bottom",
+    }
+  }
+}
+
+// (ゾーン, 参照する文字)
+const LATIN_BLUES: [(BlueKind, &str); 6] = [
+  (BlueKind::CapitalTop, "THEZOCQS"),
+  (BlueKind::CapitalBottom, "HEZLOCUS"),
+  (BlueKind::Ascender, "fijkdbh"),
+  (BlueKind::SmallTop, "xzroesc"),
+  (BlueKind::SmallBottom, "xzroesc"),
+  (BlueKind::Descender, "pqgjy"),
+];
+
+// 漢字は四角い枠いっぱいに書かれる字の上端と下端をそろえる
+const CJK_BLUES: [(BlueKind, &str); 2] = [
+  (BlueKind::IdeographTop, "口国田日目中"),
+  (BlueKind::IdeographBottom, "口国田日目中"),
+];
+
+// 標準のステム幅を測る文字
+const LATIN_STANDARD: &str = "o";
+const CJK_STANDARD: &str = "口";
+
+// 高さをそろえる帯。平らな字の端の位置 reference と、丸い字がはみ出す位置 overshoot（フォント単位）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlueZone {
+  pub kind: BlueKind,
+  pub reference: f64,
+  pub overshoot: f64,
+}
+
+// 自動ヒンティングに使うフォント全体の寸法
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutohintMetrics {
+  pub script: Script,
+  pub units_per_em: u16,
+  pub blue_zones: Vec<BlueZone>,
+  // 横のステムの標準の太さ（垂直に測った幅、フォント単位）
+  pub standard_width: Option<f64>,
+}
+
+impl AutohintMetrics {
+  pub fn blue_zone(&self, kind: BlueKind) -> Option<&BlueZone> {
+    self.blue_zones.iter().find(|zone| zone.kind == kind)
+  }
+}
+
+impl Script {
+  pub fn blues(self) -> &'static [(BlueKind, &'static str)] {
+    match self {
+      Script::Latin => &LATIN_BLUES,
+      Script::Cjk => &CJK_BLUES,
+    }
+  }
+
+  pub fn standard_character(self) -> &'static str {
+    match self {
+      Script::Latin => LATIN_STANDARD,
+      Script::Cjk => CJK_STANDARD,
+    }
+  }
+}
+
+// グリフの上端（top）か下端の位置と、そこが丸い（オフカーブ点で作られている）かどうか
+pub fn glyph_extremum(glyph: &SimpleGlyph, top: bool) -> Option<(f64, bool)> {
+  let points = &glyph.points;
+  let extremum = if top {
+    points.iter().map(|p| p.y).max()?
+  } else {
+    points.iter().map(|p| p.y).min()?
+  };
+  // 端の点かその隣がオフカーブ点なら曲線の頂点とみなす
+  let mut round = false;
+  let mut start = 0;
+  for &end in &glyph.end_pts_of_contours {
+    let end = end as usize;
+    let contour = match points.get(start..=end) {
+      Some(contour) => contour,
+      None => break,
+    };
+    let n = contour.len();
+    for (i, point) in contour.iter().enumerate() {
+      if point.y != extremum {
+        continue;
+      }
+      let previous = contour[(i + n - 1) % n];
+      let next = contour[(i + 1) % n];
+      if !point.on_curve || !previous.on_curve || !next.on_curve {
+        round = true;
+      }
+    }
+    start = end + 1;
+  }
+  Some((extremum as f64, round))
+}
+
+// 参照する字の端の位置から1つのブルーゾーンを作る。平らな字がなければ丸い字の位置を、丸い字がなければ平らな字の位置を両方に使う
+pub fn blue_zone(kind: BlueKind, extrema: &[(f64, bool)]) -> Option<BlueZone> {
+  let average = |round: bool| {
+    let values: Vec<f64> = extrema.iter().filter(|e| e.1 == round).map(|e| e.0).collect();
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+  };
+  let (flat, round) = (average(false), average(true));
+  let reference = flat.or(round)?;
+  let overshoot = round.unwrap_or(reference);
+  Some(BlueZone { kind, reference, overshoot })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::truetype::Point;
+
+  fn glyph(points: &[(i16, i16, bool)]) -> SimpleGlyph {
+    let points: Vec<Point> = points.iter().map(|&(x, y, on_curve)| Point { x, y, on_curve }).collect();
+    SimpleGlyph {
+      end_pts_of_contours: vec![points.len() as u16 - 1],
+      instruction_length: 0,
+      instructions: Vec::new(),
+      flags: points.iter().map(|p| p.on_curve as u8).collect(),
+      x_coordinates: points.iter().map(|p| p.x).collect(),
+      y_coordinates: points.iter().map(|p| p.y).collect(),
+      points,
+    }
+  }
+
+  #[test]
+  fn extrema_of_flat_and_round_glyphs() {
+    // 平らな上端の "x" と、オフカーブ点で上にはみ出す "o"
+    let x = glyph(&[(0, 0, true), (0, 500, true), (400, 500, true), (400, 0, true)]);
+    let o = glyph(&[(0, 250, true), (0, 510, false), (400, 510, false), (400, 250, true), (200, -10, false)]);
+    assert_eq!(glyph_extremum(&x, true), Some((500.0, false)));
+    assert_eq!(glyph_extremum(&x, false), Some((0.0, false)));
+    assert_eq!(glyph_extremum(&o, true), Some((510.0, true)));
+    assert_eq!(glyph_extremum(&o, false), Some((-10.0, true)));
+  }
+
+  #[test]
+  fn blue_zone_from_flat_and_round_extrema() {
+    let zone = blue_zone(BlueKind::SmallTop, &[(500.0, false), (502.0, false), (510.0, true)]).unwrap();
+    assert_eq!((zone.reference, zone.overshoot), (501.0, 510.0));
+    // 丸い字しかなければ両方に、平らな字しかなければ両方にその位置を使う
+    let zone = blue_zone(BlueKind::SmallTop, &[(510.0, true)]).unwrap();
+    assert_eq!((zone.reference, zone.overshoot), (510.0, 510.0));
+    let zone = blue_zone(BlueKind::CapitalTop, &[(700.0, false)]).unwrap();
+    assert_eq!((zone.reference, zone.overshoot), (700.0, 700.0));
+    assert_eq!(blue_zone(BlueKind::Descender, &[]), None);
+  }
+}
@@ -0,0 +1,160 @@
+use crate::geometry::{Point, Segment};
+use crate::sink::OutlineSink;
+use crate::truetype::outline::Outline;
+
+// 外接矩形（フォント単位、y軸上向き）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+  pub x_min: f32,
+  pub y_min: f32,
+  pub x_max: f32,
+  pub y_max: f32,
+}
+
+impl BoundingBox {
+  pub fn from_point(x: f32, y: f32) -> Self {
+    BoundingBox { x_min: x, y_min: y, x_max: x, y_max: y }
+  }
+
+  pub fn add_point(&mut self, x: f32, y: f32) {
+    self.x_min = self.x_min.min(x);
+    self.y_min = self.y_min.min(y);
+    self.x_max = self.x_max.max(x);
+    self.y_max = self.y_max.max(y);
+  }
+
+  pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+    let mut result = *self;
+    result.add_point(other.x_min, other.y_min);
+    result.add_point(other.x_max, other.y_max);
+    result
+  }
+
+  pub fn contains(&self, other: &BoundingBox) -> bool {
+    self.x_min <= other.x_min && self.y_min <= other.y_min && self.x_max >= other.x_max && self.y_max >= other.y_max
+  }
+
+  pub fn width(&self) -> f32 {
+    self.x_max - self.x_min
+  }
+
+  pub fn height(&self) -> f32 {
+    self.y_max - self.y_min
+  }
+
+  // 整数座標に外側へ丸める（glyf のヘッダと同じ表現）
+  pub fn round_out(&self) -> BoundingBox {
+    BoundingBox {
+      x_min: self.x_min.floor(),
+      y_min: self.y_min.floor(),
+      x_max: self.x_max.ceil(),
+      y_max: self.y_max.ceil(),
+    }
+  }
+}
+
+fn extend(bounds: &mut Option<BoundingBox>, x: f32, y: f32) {
+  match bounds {
+    Some(bounds) => bounds.add_point(x, y),
+    None => *bounds = Some(BoundingBox::from_point(x, y)),
+  }
+}
+
+fn point(x: f32, y: f32) -> Point {
+  Point::new(x as f64, y as f64)
+}
+
+// 流し込まれた輪郭の制御点の外接矩形（コントロールボックス）と、曲線の極値から求めた正確な外接矩形を集める
+#[derive(Debug, Default)]
+pub struct BoundsSink {
+  pub control: Option<BoundingBox>,
+  pub exact: Option<BoundingBox>,
+  current: (f32, f32),
+}
+
+impl BoundsSink {
+  pub fn new() -> Self {
+    BoundsSink::default()
+  }
+}
+
+impl OutlineSink for BoundsSink {
+  fn move_to(&mut self, x: f32, y: f32) {
+    extend(&mut self.control, x, y);
+    extend(&mut self.exact, x, y);
+    self.current = (x, y);
+  }
+
+  fn line_to(&mut self, x: f32, y: f32) {
+    extend(&mut self.control, x, y);
+    extend(&mut self.exact, x, y);
+    self.current = (x, y);
+  }
+
+  fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+    let (x0, y0) = self.current;
+    extend(&mut self.control, x1, y1);
+    extend(&mut self.control, x, y);
+    extend(&mut self.exact, x, y);
+    // 各軸の極値で、もう一方の軸の値も同じ t で求める
+    let segment = Segment::Quad(point(x0, y0), point(x1, y1), point(x, y));
+    for p in segment.extrema().into_iter().map(|t| segment.point(t)) {
+      extend(&mut self.exact, p.x as f32, p.y as f32);
+    }
+    self.current = (x, y);
+  }
+
+  fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+    let (x0, y0) = self.current;
+    extend(&mut self.control, x1, y1);
+    extend(&mut self.control, x2, y2);
+    extend(&mut self.control, x, y);
+    extend(&mut self.exact, x, y);
+    let segment = Segment::Cubic(point(x0, y0), point(x1, y1), point(x2, y2), point(x, y));
+    for p in segment.extrema().into_iter().map(|t| segment.point(t)) {
+      extend(&mut self.exact, p.x as f32, p.y as f32);
+    }
+    self.current = (x, y);
+  }
+
+  fn close(&mut self) {}
+}
+
+impl Outline {
+  // 制御点を含む外接矩形。glyf のヘッダはこれと一致するはず
+  pub fn control_box(&self) -> Option<BoundingBox> {
+    let mut sink = BoundsSink::new();
+    self.draw(&mut sink);
+    sink.control
+  }
+
+  // 曲線の極値から求めた正確な外接矩形
+  pub fn bounding_box(&self) -> Option<BoundingBox> {
+    let mut sink = BoundsSink::new();
+    self.draw(&mut sink);
+    sink.exact
+  }
+}
+
+// glyf ヘッダの外接矩形と、輪郭から計算した値の比較
+#[derive(Debug, Clone, Copy)]
+pub struct BoundsCheck {
+  pub header: BoundingBox,
+  pub control: Option<BoundingBox>,
+  pub exact: Option<BoundingBox>,
+}
+
+impl BoundsCheck {
+  // ヘッダが制御点の外接矩形と一致しているか（輪郭のないグリフはヘッダが0なら一致とみなす）
+  pub fn header_matches_control(&self) -> bool {
+    match self.control {
+      Some(control) => control.round_out() == self.header,
+      None => self.header == BoundingBox::from_point(0.0, 0.0),
+    }
+  }
+
+  // ヘッダが少なくとも曲線全体を覆っているか
+  pub fn header_contains_exact(&self) -> bool {
+    self.exact.is_none_or(|exact| self.header.contains(&exact))
+  }
+}
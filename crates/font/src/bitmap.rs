@@ -0,0 +1,113 @@
+// 埋め込みビットマップの画像の形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+  Png,
+  Jpeg,
+  Tiff,
+}
+
+impl ImageFormat {
+  pub fn name(&self) -> &'static str {
+    match self {
+      ImageFormat::Png => "PNG",
+      ImageFormat::Jpeg => "JPEG",
+      ImageFormat::Tiff => "TIFF",
+    }
+  }
+}
+
+// 埋め込みビットマップ（CBDT、sbix）のグリフ。画像は元の形式のまま持つ
+#[derive(Debug, Clone)]
+pub struct BitmapGlyph {
+  // 画像が作られた ppem
+  pub ppem: u16,
+  pub format: ImageFormat,
+  pub data: Vec<u8>,
+  // 画像の大きさ（ピクセル）
+  pub width: u32,
+  pub height: u32,
+  // 原点から画像の左端まで、ベースラインから画像の上端まで（ピクセル、y軸上向き）
+  pub bearing_x: i32,
+  pub bearing_y: i32,
+  pub advance: u32,
+}
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+fn be16(data: &[u8], at: usize) -> Option<u32> {
+  Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn be32(data: &[u8], at: usize) -> Option<u32> {
+  Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+// PNG の IHDR にある画像の大きさ
+pub fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+  if png.get(..8)? != PNG_SIGNATURE || png.get(12..16)? != b"IHDR" {
+    return None;
+  }
+  Some((be32(png, 16)?, be32(png, 20)?))
+}
+
+// JPEG の SOF セグメントにある画像の大きさ
+fn jpeg_size(jpeg: &[u8]) -> Option<(u32, u32)> {
+  if jpeg.get(..2)? != [0xFF, 0xD8] {
+    return None;
+  }
+  let mut at = 2;
+  loop {
+    if *jpeg.get(at)? != 0xFF {
+      return None;
+    }
+    let marker = *jpeg.get(at + 1)?;
+    // SOF0〜SOF15（DHT、JPG、DAC を除く）
+    if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+      return Some((be16(jpeg, at + 7)?, be16(jpeg, at + 5)?));
+    }
+    at += 2 + be16(jpeg, at + 2)? as usize;
+  }
+}
+
+// TIFF の最初の IFD にある ImageWidth と ImageLength
+fn tiff_size(tiff: &[u8]) -> Option<(u32, u32)> {
+  let little = match tiff.get(..4)? {
+    b"II*\0" => true,
+    b"MM\0*" => false,
+    _ => return None,
+  };
+  let u16_at = |at: usize| {
+    let bytes: [u8; 2] = tiff.get(at..at + 2)?.try_into().ok()?;
+    Some(if little { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) } as u32)
+  };
+  let u32_at = |at: usize| {
+    let bytes: [u8; 4] = tiff.get(at..at + 4)?.try_into().ok()?;
+    Some(if little { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+  };
+  let ifd = u32_at(4)? as usize;
+  let (mut width, mut height) = (None, None);
+  for i in 0..u16_at(ifd)? as usize {
+    let entry = ifd + 2 + i * 12;
+    // 型は SHORT（3）か LONG（4）
+    let value = match u16_at(entry + 2)? {
+      3 => u16_at(entry + 8)?,
+      4 => u32_at(entry + 8)?,
+      _ => continue,
+    };
+    match u16_at(entry)? {
+      256 => width = Some(value),
+      257 => height = Some(value),
+      _ => {}
+    }
+  }
+  Some((width?, height?))
+}
+
+// 画像のヘッダから読んだ大きさ。読めなければ None
+pub fn image_size(format: ImageFormat, data: &[u8]) -> Option<(u32, u32)> {
+  match format {
+    ImageFormat::Png => png_size(data),
+    ImageFormat::Jpeg => jpeg_size(data),
+    ImageFormat::Tiff => tiff_size(data),
+  }
+}
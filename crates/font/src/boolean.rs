@@ -0,0 +1,596 @@
+use crate::geometry::{Point, Segment};
+use crate::truetype::outline::PathSegment;
+
+// 輪郭どうしの演算。塗りは非ゼロ規則で判定する
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BooleanOp {
+  Union,
+  Intersection,
+  // a から b を除く
+  Difference,
+  Xor,
+}
+
+impl BooleanOp {
+  fn apply(&self, a: bool, b: bool) -> bool {
+    match self {
+      BooleanOp::Union => a || b,
+      BooleanOp::Intersection => a && b,
+      BooleanOp::Difference => a && !b,
+      BooleanOp::Xor => a != b,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+  x_min: f64,
+  y_min: f64,
+  x_max: f64,
+  y_max: f64,
+}
+
+impl Rect {
+  // 制御点を含む外接矩形
+  fn of(edge: &Segment) -> Rect {
+    let points = edge.points();
+    let mut rect = Rect { x_min: points[0].x, y_min: points[0].y, x_max: points[0].x, y_max: points[0].y };
+    for p in &points[1..] {
+      rect.x_min = rect.x_min.min(p.x);
+      rect.y_min = rect.y_min.min(p.y);
+      rect.x_max = rect.x_max.max(p.x);
+      rect.y_max = rect.y_max.max(p.y);
+    }
+    rect
+  }
+
+  fn overlaps(&self, other: &Rect, margin: f64) -> bool {
+    self.x_min <= other.x_max + margin
+      && other.x_min <= self.x_max + margin
+      && self.y_min <= other.y_max + margin
+      && other.y_min <= self.y_max + margin
+  }
+}
+
+// y の極値で分割して y について単調な断片にする
+fn monotone_pieces(edge: &Segment) -> Vec<Segment> {
+  let mut ts = edge.y_extrema();
+  ts.retain(|&t| t > 1e-9 && t < 1.0 - 1e-9);
+  edge.split_at(&ts)
+}
+
+// 輪郭の集合を辺の列にする。閉じていない輪郭は始点へ直線で閉じる
+fn to_edges(segments: &[PathSegment]) -> Vec<Segment> {
+  let mut edges = Vec::new();
+  let mut start = Point::new(0.0, 0.0);
+  let mut current = start;
+  let close = |edges: &mut Vec<Segment>, current: Point, start: Point| {
+    if current != start {
+      edges.push(Segment::Line(current, start));
+    }
+  };
+  for segment in segments {
+    let p = |x: f32, y: f32| Point::new(x as f64, y as f64);
+    match *segment {
+      PathSegment::MoveTo(x, y) => {
+        close(&mut edges, current, start);
+        start = p(x, y);
+        current = start;
+      }
+      PathSegment::LineTo(x, y) => {
+        edges.push(Segment::Line(current, p(x, y)));
+        current = p(x, y);
+      }
+      PathSegment::QuadTo(x1, y1, x, y) => {
+        edges.push(Segment::Quad(current, p(x1, y1), p(x, y)));
+        current = p(x, y);
+      }
+      PathSegment::CubicTo(x1, y1, x2, y2, x, y) => {
+        edges.push(Segment::Cubic(current, p(x1, y1), p(x2, y2), p(x, y)));
+        current = p(x, y);
+      }
+      PathSegment::Close => {
+        close(&mut edges, current, start);
+        current = start;
+      }
+    }
+  }
+  close(&mut edges, current, start);
+  edges.retain(|edge| edge.points().windows(2).any(|w| w[0] != w[1]));
+  edges
+}
+
+// x と y を入れ替える。水平に近い辺は入れ替えた座標で縦向きの辺として扱う
+fn swap(p: Point) -> Point {
+  Point::new(p.y, p.x)
+}
+
+// 分割済みの辺を y について単調な断片にしたもの。入れ替えた座標のものも持つ
+struct Arrangement {
+  // (断片, 外接矩形, 元の辺の番号, 入力の番号)
+  pieces: [Vec<(Segment, Rect, usize, usize)>; 2],
+}
+
+impl Arrangement {
+  fn new(edges: &[(Segment, usize)]) -> Self {
+    let pieces = |swapped: bool| {
+      edges.iter()
+        .enumerate()
+        .flat_map(|(index, &(edge, source))| {
+          let edge = if swapped { edge.map(swap) } else { edge };
+          monotone_pieces(&edge).into_iter().map(move |piece| (piece, Rect::of(&piece), index, source))
+        })
+        .collect()
+    };
+    Arrangement { pieces: [pieces(false), pieces(true)] }
+  }
+
+  // p から右へ伸ばした半直線と交わる辺の向きの和を入力ごとに数える。excluded の辺は数えない
+  fn winding(&self, p: Point, swapped: bool, excluded: &[usize]) -> [i32; 2] {
+    let mut winding = [0, 0];
+    for (piece, rect, index, source) in &self.pieces[swapped as usize] {
+      let (y0, y1) = (piece.start().y, piece.end().y);
+      if y0 == y1 || p.x > rect.x_max || excluded.contains(index) {
+        continue;
+      }
+      let (low, high) = if y0 < y1 { (y0, y1) } else { (y1, y0) };
+      if p.y < low || p.y >= high {
+        continue;
+      }
+      let direction = if y1 > y0 { 1 } else { -1 };
+      if p.x < rect.x_min {
+        winding[*source] += direction;
+        continue;
+      }
+      // 単調なので二分法で交点を求める
+      let (mut lo, mut hi) = (0.0, 1.0);
+      for _ in 0..60 {
+        let middle = (lo + hi) / 2.0;
+        let y = piece.point(middle).y;
+        if (y < p.y) == (y1 > y0) {
+          lo = middle;
+        } else {
+          hi = middle;
+        }
+      }
+      if piece.point((lo + hi) / 2.0).x > p.x {
+        winding[*source] += direction;
+      }
+    }
+    winding
+  }
+}
+
+// 2つの線分の交点。重なっている場合は重なりの両端を返す
+fn line_intersections(a0: Point, a1: Point, b0: Point, b1: Point, tolerance: f64) -> Vec<(f64, f64, Point)> {
+  let (da, db) = (a1 - a0, b1 - b0);
+  let denominator = da.cross(db);
+  let (la, lb) = (da.length(), db.length());
+  if la == 0.0 || lb == 0.0 {
+    return vec![];
+  }
+  if denominator.abs() <= 1e-12 * la * lb {
+    // 平行。同じ直線上にあれば重なりの端点を交点とする
+    if da.cross(b0 - a0).abs() / la > tolerance {
+      return vec![];
+    }
+    let project_a = |p: Point| (p - a0).dot(da) / (la * la);
+    let project_b = |p: Point| (p - b0).dot(db) / (lb * lb);
+    let mut result = Vec::new();
+    for (s, p) in [(project_a(b0), b0), (project_a(b1), b1)] {
+      if (0.0..=1.0).contains(&s) {
+        result.push((s, project_b(p), p));
+      }
+    }
+    for (u, p) in [(project_b(a0), a0), (project_b(a1), a1)] {
+      if (0.0..=1.0).contains(&u) {
+        result.push((project_a(p), u, p));
+      }
+    }
+    return result;
+  }
+  let s = (b0 - a0).cross(db) / denominator;
+  let u = (b0 - a0).cross(da) / denominator;
+  let (es, eu) = (tolerance / la, tolerance / lb);
+  if s < -es || s > 1.0 + es || u < -eu || u > 1.0 + eu {
+    return vec![];
+  }
+  let (s, u) = (s.clamp(0.0, 1.0), u.clamp(0.0, 1.0));
+  vec![(s, u, a0.lerp(a1, s))]
+}
+
+// 辺 a, b の交点を (a の t, b の t, 点) で集める。両方が平らになるまで半分に分けていく
+#[allow(clippy::too_many_arguments)]
+fn intersect(
+  a: &Segment, a_range: (f64, f64),
+  b: &Segment, b_range: (f64, f64),
+  tolerance: f64, depth: u32,
+  out: &mut Vec<(f64, f64, Point)>,
+) {
+  if !Rect::of(a).overlaps(&Rect::of(b), tolerance) {
+    return;
+  }
+  let (a_flat, b_flat) = (a.is_flat(tolerance), b.is_flat(tolerance));
+  if (a_flat && b_flat) || depth > 40 {
+    for (s, u, p) in line_intersections(a.start(), a.end(), b.start(), b.end(), tolerance) {
+      out.push((
+        a_range.0 + s * (a_range.1 - a_range.0),
+        b_range.0 + u * (b_range.1 - b_range.0),
+        p,
+      ));
+    }
+    return;
+  }
+  let a_size = { let r = Rect::of(a); (r.x_max - r.x_min).max(r.y_max - r.y_min) };
+  let b_size = { let r = Rect::of(b); (r.x_max - r.x_min).max(r.y_max - r.y_min) };
+  if !a_flat && (b_flat || a_size >= b_size) {
+    let (a0, a1) = a.split(0.5);
+    let middle = (a_range.0 + a_range.1) / 2.0;
+    intersect(&a0, (a_range.0, middle), b, b_range, tolerance, depth + 1, out);
+    intersect(&a1, (middle, a_range.1), b, b_range, tolerance, depth + 1, out);
+  } else {
+    let (b0, b1) = b.split(0.5);
+    let middle = (b_range.0 + b_range.1) / 2.0;
+    intersect(a, a_range, &b0, (b_range.0, middle), tolerance, depth + 1, out);
+    intersect(a, a_range, &b1, (middle, b_range.1), tolerance, depth + 1, out);
+  }
+}
+
+// 近い点を1つの代表点にまとめる
+struct Snapper {
+  points: Vec<Point>,
+  tolerance: f64,
+}
+
+impl Snapper {
+  fn snap(&mut self, p: Point) -> Point {
+    for &q in &self.points {
+      if (q - p).length() <= self.tolerance {
+        return q;
+      }
+    }
+    self.points.push(p);
+    p
+  }
+}
+
+// 全ての辺を交点で分割する
+fn split_edges(edges: &[(Segment, usize)], tolerance: f64) -> Vec<(Segment, usize)> {
+  let mut cuts: Vec<Vec<(f64, Point)>> = vec![Vec::new(); edges.len()];
+  let bounds: Vec<Rect> = edges.iter().map(|(edge, _)| Rect::of(edge)).collect();
+  for i in 0..edges.len() {
+    for j in i + 1..edges.len() {
+      if !bounds[i].overlaps(&bounds[j], tolerance) {
+        continue;
+      }
+      let mut found = Vec::new();
+      intersect(&edges[i].0, (0.0, 1.0), &edges[j].0, (0.0, 1.0), tolerance, 0, &mut found);
+      for (s, u, p) in found {
+        cuts[i].push((s, p));
+        cuts[j].push((u, p));
+      }
+    }
+  }
+
+  let mut snapper = Snapper { points: Vec::new(), tolerance: tolerance * 4.0 };
+  for (edge, _) in edges {
+    snapper.snap(edge.start());
+  }
+  let mut result = Vec::new();
+  for (&(edge, source), mut cuts) in edges.iter().zip(cuts) {
+    cuts.sort_by(|a, b| a.0.total_cmp(&b.0));
+    // 端点や互いに近すぎる分割点は捨てる
+    let mut ts: Vec<(f64, Point)> = Vec::new();
+    for (t, p) in cuts {
+      let near_end = (p - edge.start()).length() <= snapper.tolerance || (p - edge.end()).length() <= snapper.tolerance;
+      let near_previous = ts.last().is_some_and(|&(_, q)| (p - q).length() <= snapper.tolerance);
+      if t > 0.0 && t < 1.0 && !near_end && !near_previous {
+        ts.push((t, p));
+      }
+    }
+    let pieces = edge.split_at(&ts.iter().map(|&(t, _)| t).collect::<Vec<_>>());
+    for piece in pieces {
+      let (start, end) = (snapper.snap(piece.start()), snapper.snap(piece.end()));
+      if start != end {
+        result.push((piece.with_ends(start, end), source));
+      }
+    }
+  }
+  result
+}
+
+// 内側が右手側になる向きにそろえた辺をつないで輪郭にする
+fn stitch(edges: Vec<Segment>) -> Vec<PathSegment> {
+  let mut used = vec![false; edges.len()];
+  let mut segments = Vec::new();
+  let angle = |from: Point, to: Point| from.cross(to).atan2(from.dot(to));
+  for first in 0..edges.len() {
+    if used[first] {
+      continue;
+    }
+    used[first] = true;
+    let start = edges[first].start();
+    let mut contour = vec![edges[first]];
+    let mut current = first;
+    loop {
+      let end = edges[current].end();
+      if end == start {
+        break;
+      }
+      // 行き先が複数あれば最も右に曲がるものを選ぶ（右側の領域の境界をたどる）
+      let incoming = edges[current].direction(1.0);
+      let next = (0..edges.len())
+        .filter(|&i| !used[i] && edges[i].start() == end)
+        .min_by(|&i, &j| angle(incoming, edges[i].direction(0.0)).total_cmp(&angle(incoming, edges[j].direction(0.0))));
+      let Some(next) = next else {
+        break;
+      };
+      used[next] = true;
+      contour.push(edges[next]);
+      current = next;
+    }
+
+    let f = |p: Point| (p.x as f32, p.y as f32);
+    let (x, y) = f(start);
+    segments.push(PathSegment::MoveTo(x, y));
+    for edge in contour {
+      segments.push(match edge {
+        Segment::Line(_, p) => {
+          let (x, y) = f(p);
+          PathSegment::LineTo(x, y)
+        }
+        Segment::Quad(_, c, p) => {
+          let ((x1, y1), (x, y)) = (f(c), f(p));
+          PathSegment::QuadTo(x1, y1, x, y)
+        }
+        Segment::Cubic(_, c1, c2, p) => {
+          let ((x1, y1), (x2, y2), (x, y)) = (f(c1), f(c2), f(p));
+          PathSegment::CubicTo(x1, y1, x2, y2, x, y)
+        }
+      });
+    }
+    segments.push(PathSegment::Close);
+  }
+  segments
+}
+
+// 辺全体の大きさに比例した許容誤差。辺がなければ None
+fn tolerance<'a>(edges: impl Iterator<Item = &'a Segment>) -> Option<f64> {
+  let extent = edges
+    .map(Rect::of)
+    .reduce(|r, s| Rect {
+      x_min: r.x_min.min(s.x_min),
+      y_min: r.y_min.min(s.y_min),
+      x_max: r.x_max.max(s.x_max),
+      y_max: r.y_max.max(s.y_max),
+    })?;
+  let scale = (extent.x_max - extent.x_min).max(extent.y_max - extent.y_min).max(1.0);
+  Some(scale * 1e-5)
+}
+
+// a と b の演算結果の輪郭。重なりのない輪郭になり、TrueType と同じく外側の輪郭は時計回り（y軸上向き）になる
+pub fn boolean(a: &[PathSegment], b: &[PathSegment], op: BooleanOp) -> Vec<PathSegment> {
+  let all: Vec<(Segment, usize)> = to_edges(a).into_iter().map(|edge| (edge, 0))
+    .chain(to_edges(b).into_iter().map(|edge| (edge, 1)))
+    .collect();
+  let Some(tolerance) = tolerance(all.iter().map(|(edge, _)| edge)) else {
+    return Vec::new();
+  };
+
+  let edges = split_edges(&all, tolerance);
+  let arrangement = Arrangement::new(&edges);
+  let same = |p: Point, q: Point| (p - q).length() <= tolerance * 4.0;
+
+  let mut done = vec![false; edges.len()];
+  let mut kept: Vec<Segment> = Vec::new();
+  for i in 0..edges.len() {
+    if done[i] {
+      continue;
+    }
+    let edge = edges[i].0;
+    let (start, end, middle) = (edge.start(), edge.end(), edge.point(0.5));
+    // 重なっている辺をまとめて扱う。向きが逆のものは reversed
+    let group: Vec<(usize, bool)> = (i..edges.len())
+      .filter_map(|j| {
+        let other = &edges[j].0;
+        if !same(other.point(0.5), middle) {
+          return None;
+        }
+        if same(other.start(), start) && same(other.end(), end) {
+          Some((j, false))
+        } else if same(other.start(), end) && same(other.end(), start) {
+          Some((j, true))
+        } else {
+          None
+        }
+      })
+      .collect();
+    for &(j, _) in &group {
+      done[j] = true;
+    }
+
+    // 辺と交わりやすい向きに半直線を伸ばす
+    let tangent = edge.direction(0.5);
+    let swapped = tangent.y.abs() < tangent.x.abs();
+    let (m, d) = if swapped { (swap(middle), swap(tangent)) } else { (middle, tangent) };
+    if d.y == 0.0 {
+      continue;
+    }
+    let excluded: Vec<usize> = group.iter().map(|&(j, _)| j).collect();
+    let base = arrangement.winding(m, swapped, &excluded);
+
+    // 中点の左右にごくわずかにずらした点から見た巻き数。ずらした点からの半直線が辺自身と交わるかは接線の傾きで決まる
+    let side = |left: bool| {
+      let normal = Point::new(-tangent.y, tangent.x) * if left { 1.0 } else { -1.0 };
+      let offset = if swapped { swap(normal) } else { normal };
+      let mut winding = base;
+      for &(j, reversed) in &group {
+        let dy = if reversed { -d.y } else { d.y };
+        if d.x / d.y * offset.y > offset.x {
+          winding[edges[j].1] += if dy > 0.0 { 1 } else { -1 };
+        }
+      }
+      op.apply(winding[0] != 0, winding[1] != 0)
+    };
+    let (left, right) = (side(true), side(false));
+    if left == right {
+      continue;
+    }
+    kept.push(if left { edge.reverse() } else { edge });
+  }
+  stitch(kept)
+}
+
+// 1つのグリフの中で重なっている輪郭を1つにまとめる
+pub fn remove_overlaps(segments: &[PathSegment]) -> Vec<PathSegment> {
+  boolean(segments, &[], BooleanOp::Union)
+}
+
+pub fn union(a: &[PathSegment], b: &[PathSegment]) -> Vec<PathSegment> {
+  boolean(a, b, BooleanOp::Union)
+}
+
+pub fn intersection(a: &[PathSegment], b: &[PathSegment]) -> Vec<PathSegment> {
+  boolean(a, b, BooleanOp::Intersection)
+}
+
+pub fn difference(a: &[PathSegment], b: &[PathSegment]) -> Vec<PathSegment> {
+  boolean(a, b, BooleanOp::Difference)
+}
+
+// 点 (x, y) のまわりを輪郭が回る数（反時計回りが正）
+pub(crate) fn winding_number(segments: &[PathSegment], x: f32, y: f32) -> i32 {
+  let edges: Vec<(Segment, usize)> = to_edges(segments).into_iter().map(|edge| (edge, 0)).collect();
+  Arrangement::new(&edges).winding(Point::new(x as f64, y as f64), false, &[])[0]
+}
+
+// 1つの輪郭が自分自身と交わる点。隣り合う辺の継ぎ目は数えない
+pub(crate) fn self_intersections(segments: &[PathSegment]) -> Vec<(f32, f32)> {
+  let edges = to_edges(segments);
+  let Some(tolerance) = tolerance(edges.iter()) else {
+    return Vec::new();
+  };
+  let n = edges.len();
+  let near = |p: Point, q: Point| (p - q).length() <= tolerance * 4.0;
+  let mut snapper = Snapper { points: Vec::new(), tolerance: tolerance * 4.0 };
+  let mut found = Vec::new();
+  for i in 0..n {
+    for j in i + 1..n {
+      let mut points = Vec::new();
+      intersect(&edges[i], (0.0, 1.0), &edges[j], (0.0, 1.0), tolerance, 0, &mut points);
+      for (_, _, p) in points {
+        let joint_after = j == i + 1 && near(p, edges[i].end());
+        let joint_before = i == 0 && j == n - 1 && near(p, edges[i].start());
+        if joint_after || joint_before {
+          continue;
+        }
+        let before = snapper.points.len();
+        let p = snapper.snap(p);
+        if snapper.points.len() > before {
+          found.push((p.x as f32, p.y as f32));
+        }
+      }
+    }
+  }
+  found
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::convert::flatten;
+  use std::f64::consts::{FRAC_PI_4, PI};
+  use PathSegment::*;
+
+  // 時計回り（y軸上向き）の長方形
+  fn rectangle(x_min: f32, y_min: f32, x_max: f32, y_max: f32) -> Vec<PathSegment> {
+    vec![MoveTo(x_min, y_min), LineTo(x_min, y_max), LineTo(x_max, y_max), LineTo(x_max, y_min), Close]
+  }
+
+  // 45度ずつ、接線の交点を制御点にした2次ベジェの円（時計回り）
+  fn circle(cx: f64, cy: f64, r: f64) -> Vec<PathSegment> {
+    let at = |angle: f64, radius: f64| ((cx + angle.cos() * radius) as f32, (cy + angle.sin() * radius) as f32);
+    let (x, y) = at(0.0, r);
+    let mut segments = vec![MoveTo(x, y)];
+    for i in 0..8 {
+      let (c, e) = (at(-(i as f64 + 0.5) * FRAC_PI_4, r / (FRAC_PI_4 / 2.0).cos()), at(-(i as f64 + 1.0) * FRAC_PI_4, r));
+      segments.push(QuadTo(c.0, c.1, e.0, e.1));
+    }
+    segments.push(Close);
+    segments
+  }
+
+  // 外側が時計回りなので、面積は折れ線の符号付き面積を反転させた和
+  fn area(segments: &[PathSegment]) -> f64 {
+    let mut total = 0.0;
+    let (mut start, mut current) = ((0.0, 0.0), (0.0, 0.0));
+    for segment in flatten(segments, 0.01) {
+      let next = match segment {
+        MoveTo(x, y) => {
+          start = (x as f64, y as f64);
+          current = start;
+          continue;
+        }
+        LineTo(x, y) => (x as f64, y as f64),
+        Close => start,
+        _ => unreachable!(),
+      };
+      total += current.0 * next.1 - next.0 * current.1;
+      current = next;
+    }
+    -total / 2.0
+  }
+
+  fn contours(segments: &[PathSegment]) -> usize {
+    segments.iter().filter(|segment| matches!(segment, MoveTo(..))).count()
+  }
+
+  #[test]
+  fn overlapping_rectangles() {
+    let (a, b) = (rectangle(0.0, 0.0, 100.0, 100.0), rectangle(50.0, 50.0, 150.0, 150.0));
+    for (result, expected) in [(union(&a, &b), 17500.0), (intersection(&a, &b), 2500.0), (difference(&a, &b), 7500.0)] {
+      assert_eq!(contours(&result), 1, "{:?}", result);
+      assert!((area(&result) - expected).abs() < 1e-3, "{} {:?}", expected, result);
+    }
+  }
+
+  #[test]
+  fn rectangle_inside_another_leaves_a_hole() {
+    let (a, b) = (rectangle(0.0, 0.0, 100.0, 100.0), rectangle(25.0, 25.0, 75.0, 75.0));
+    let result = difference(&a, &b);
+    assert_eq!(contours(&result), 2);
+    assert!((area(&result) - 7500.0).abs() < 1e-3);
+  }
+
+  #[test]
+  fn remove_overlaps_merges_a_shared_edge() {
+    let mut segments = rectangle(0.0, 0.0, 100.0, 100.0);
+    segments.extend(rectangle(100.0, 0.0, 200.0, 100.0));
+    let result = remove_overlaps(&segments);
+    assert_eq!(contours(&result), 1, "{:?}", result);
+    assert!((area(&result) - 20000.0).abs() < 1e-3);
+  }
+
+  #[test]
+  fn remove_overlaps_drops_a_duplicated_contour() {
+    let mut segments = rectangle(0.0, 0.0, 100.0, 100.0);
+    segments.extend(rectangle(0.0, 0.0, 100.0, 100.0));
+    let result = remove_overlaps(&segments);
+    assert_eq!(contours(&result), 1, "{:?}", result);
+    assert!((area(&result) - 10000.0).abs() < 1e-3);
+  }
+
+  #[test]
+  fn overlapping_circles() {
+    let (a, b) = (circle(0.0, 0.0, 50.0), circle(50.0, 0.0, 50.0));
+    let single = area(&a);
+    assert!((single - PI * 2500.0).abs() / (PI * 2500.0) < 0.01);
+    // 中心間の距離が半径と同じ2つの円のレンズの面積
+    let lens = 2.0 * 2500.0 * (0.5f64).acos() - 25.0 * 7500.0f64.sqrt();
+    let (united, common, rest) = (union(&a, &b), intersection(&a, &b), difference(&a, &b));
+    assert_eq!((contours(&united), contours(&common), contours(&rest)), (1, 1, 1));
+    assert!((area(&common) - lens).abs() / lens < 0.01, "{} {}", area(&common), lens);
+    assert!((area(&united) + area(&common) - 2.0 * single).abs() < 1.0);
+    assert!((area(&rest) + area(&common) - single).abs() < 1.0);
+  }
+}
@@ -0,0 +1,380 @@
+use std::fmt::Write;
+use crate::bbox::{BoundingBox, BoundsSink};
+use crate::sink::SvgPathBuilder;
+use crate::svg::{fill_attributes, guides, metrics_box, symbol_id, view_box, SvgKind, SvgMetrics, SvgOptions, ViewBoxMode};
+use crate::tables::cpal::Color;
+use crate::tables::paint::{linear_gradient_end, resolve_color, sweep_position, ClipBox, ColorLine, CompositeMode, Extend, Paint};
+use crate::transform::TransformSink;
+use crate::truetype::outline::PathSegment;
+use crate::truetype::Transform;
+
+// 「全体を塗る」ときの矩形の半分の幅（フォント単位）
+const COVER: f32 = 32768.0;
+// 扇形グラデーションを近似する扇の角度（度）
+const SWEEP_STEP: f32 = 1.0;
+
+// COLR バージョン 1 の Paint を SVG の要素にする。グラデーションやマスクは defs にためる
+struct PaintWriter<'a, F: Fn(u16) -> Result<Vec<PathSegment>, String>> {
+  outline: F,
+  palette: &'a [Color],
+  foreground: Color,
+  // 同じ文書に複数のグリフを並べても id がぶつからないようにする接頭辞
+  prefix: String,
+  defs: String,
+  next_id: usize,
+  // 変換を当てはめたグリフの輪郭の外接矩形
+  bounds: BoundsSink,
+}
+
+fn spread_method(extend: Extend) -> &'static str {
+  match extend {
+    Extend::Pad => "pad",
+    Extend::Repeat => "repeat",
+    Extend::Reflect => "reflect",
+  }
+}
+
+// ブレンドモードの mix-blend-mode の名前。Porter-Duff の合成なら None
+fn blend_mode(mode: CompositeMode) -> Option<&'static str> {
+  Some(match mode {
+    CompositeMode::Screen => "screen",
+    CompositeMode::Overlay => "overlay",
+    CompositeMode::Darken => "darken",
+    CompositeMode::Lighten => "lighten",
+    CompositeMode::ColorDodge => "color-dodge",
+    CompositeMode::ColorBurn => "color-burn",
+    CompositeMode::HardLight => "hard-light",
+    CompositeMode::SoftLight => "soft-light",
+    CompositeMode::Difference => "difference",
+    CompositeMode::Exclusion => "exclusion",
+    CompositeMode::Multiply => "multiply",
+    CompositeMode::HslHue => "hue",
+    CompositeMode::HslSaturation => "saturation",
+    CompositeMode::HslColor => "color",
+    CompositeMode::HslLuminosity => "luminosity",
+    CompositeMode::Plus => "plus-lighter",
+    _ => return None,
+  })
+}
+
+fn cover_rect(fill: &str) -> String {
+  format!("<rect x='{}' y='{}' width='{}' height='{}'{} />", -COVER, -COVER, COVER * 2.0, COVER * 2.0, fill)
+}
+
+fn matrix(t: &Transform) -> String {
+  format!("matrix({} {} {} {} {} {})", t.a, t.b, t.c, t.d, t.x, t.y)
+}
+
+fn lerp(a: (f32, f32), b: (f32, f32), t: f32) -> (f32, f32) {
+  (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+impl<F: Fn(u16) -> Result<Vec<PathSegment>, String>> PaintWriter<'_, F> {
+  fn id(&mut self, kind: &str) -> String {
+    self.next_id += 1;
+    format!("{}{}{}", self.prefix, kind, self.next_id)
+  }
+
+  // stop を [0, 1] に並べ直した <stop> の列
+  fn stops(&self, line: &ColorLine) -> String {
+    let (first, last) = line.range().unwrap_or((0.0, 1.0));
+    let mut svg = String::new();
+    for stop in &line.stops {
+      let offset = if last > first { (stop.offset - first) / (last - first) } else { 0.0 };
+      let color = resolve_color(stop.palette_index, stop.alpha, self.palette, self.foreground);
+      write!(svg, "<stop offset='{}' stop-color='{}'", offset, color.to_hex()).unwrap();
+      if color.a != 255 {
+        write!(svg, " stop-opacity='{:.3}'", color.a as f32 / 255.0).unwrap();
+      }
+      svg.push_str(" />");
+    }
+    svg
+  }
+
+  // 線形・放射グラデーションを defs に加え、fill の属性を返す。それ以外の Paint なら None
+  fn simple_fill(&mut self, paint: &Paint) -> Option<String> {
+    match paint {
+      Paint::Solid { palette_index, alpha } => Some(fill_attributes(&resolve_color(*palette_index, *alpha, self.palette, self.foreground))),
+      Paint::LinearGradient { line, p0, p1, p2 } => {
+        // 色の範囲の両端を stop の最初と最後に合わせる
+        let p3 = linear_gradient_end(*p0, *p1, *p2);
+        let (first, last) = line.range().unwrap_or((0.0, 1.0));
+        let (start, end) = (lerp(*p0, p3, first), lerp(*p0, p3, last));
+        let id = self.id("g");
+        write!(
+          self.defs,
+          "<linearGradient id='{}' gradientUnits='userSpaceOnUse' x1='{}' y1='{}' x2='{}' y2='{}' spreadMethod='{}'>{}</linearGradient>",
+          id, start.0, start.1, end.0, end.1, spread_method(line.extend), self.stops(line)
+        )
+        .unwrap();
+        Some(format!(" fill='url(#{})'", id))
+      }
+      Paint::RadialGradient { line, c0, r0, c1, r1 } => {
+        let (first, last) = line.range().unwrap_or((0.0, 1.0));
+        let (start, end) = (lerp(*c0, *c1, first), lerp(*c0, *c1, last));
+        let (start_r, end_r) = (r0 + (r1 - r0) * first, r0 + (r1 - r0) * last);
+        let id = self.id("g");
+        write!(
+          self.defs,
+          "<radialGradient id='{}' gradientUnits='userSpaceOnUse' fx='{}' fy='{}' fr='{}' cx='{}' cy='{}' r='{}' spreadMethod='{}'>{}</radialGradient>",
+          id, start.0, start.1, start_r.max(0.0), end.0, end.1, end_r.max(0.0), spread_method(line.extend), self.stops(line)
+        )
+        .unwrap();
+        Some(format!(" fill='url(#{})'", id))
+      }
+      _ => None,
+    }
+  }
+
+  // SVG には扇形グラデーションがないので、細い扇を並べて近似する
+  fn sweep(&self, line: &ColorLine, center: (f32, f32), start_angle: f32, end_angle: f32) -> String {
+    let mut svg = String::from("<g>");
+    let steps = (360.0 / SWEEP_STEP) as usize;
+    let point = |degrees: f32| {
+      let (sin, cos) = degrees.to_radians().sin_cos();
+      (center.0 + cos * COVER * 2.0, center.1 + sin * COVER * 2.0)
+    };
+    for i in 0..steps {
+      let (a0, a1) = (i as f32 * SWEEP_STEP, (i + 1) as f32 * SWEEP_STEP);
+      let t = sweep_position((a0 + a1) / 2.0, start_angle, end_angle);
+      let [r, g, b, a] = line.color_at(t, self.palette, self.foreground);
+      if a <= 0.0 {
+        continue;
+      }
+      let channel = |c: f32| (c / a * 255.0).round().min(255.0) as u8;
+      let color = Color { r: channel(r), g: channel(g), b: channel(b), a: (a * 255.0).round() as u8 };
+      // 隣の扇との間に隙間ができないよう、少しだけ重ねる
+      let (p0, p1) = (point(a0 - 0.1), point(a1 + 0.1));
+      write!(svg, "<path d='M{} {} L{} {} L{} {} Z'{} />", center.0, center.1, p0.0, p0.1, p1.0, p1.1, fill_attributes(&color)).unwrap();
+    }
+    svg.push_str("</g>");
+    svg
+  }
+
+  // content を mask の不透明度で切り抜く。invert なら mask の外側を残す
+  fn masked(&mut self, content: &str, mask: &str, invert: bool) -> String {
+    let region = format!("x='{}' y='{}' width='{}' height='{}'", -COVER, -COVER, COVER * 2.0, COVER * 2.0);
+    let mask = if invert {
+      let filter = self.id("f");
+      write!(
+        self.defs,
+        "<filter id='{}' filterUnits='userSpaceOnUse' {}><feColorMatrix type='matrix' values='0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 -1 1' /></filter>",
+        filter, region
+      )
+      .unwrap();
+      format!("<g filter='url(#{})'>{}</g>", filter, mask)
+    } else {
+      mask.to_string()
+    };
+    let id = self.id("m");
+    write!(self.defs, "<mask id='{}' maskUnits='userSpaceOnUse' {} style='mask-type:alpha'>{}</mask>", id, region, mask).unwrap();
+    format!("<g mask='url(#{})'>{}</g>", id, content)
+  }
+
+  fn composite(&mut self, source: &Paint, mode: CompositeMode, backdrop: &Paint, ctm: &Transform) -> Result<String, String> {
+    let both = |writer: &mut Self| -> Result<(String, String), String> {
+      Ok((writer.paint(source, ctm)?, writer.paint(backdrop, ctm)?))
+    };
+    // アルファの切り抜きで表せない Atop と Xor は、切り抜いたものを重ねて近似する
+    Ok(match mode {
+      CompositeMode::Clear => String::new(),
+      CompositeMode::Src => self.paint(source, ctm)?,
+      CompositeMode::Dest => self.paint(backdrop, ctm)?,
+      CompositeMode::SrcOver => {
+        let (s, b) = both(self)?;
+        b + &s
+      }
+      CompositeMode::DestOver => {
+        let (s, b) = both(self)?;
+        s + &b
+      }
+      CompositeMode::SrcIn | CompositeMode::SrcOut => {
+        let (s, b) = both(self)?;
+        self.masked(&s, &b, mode == CompositeMode::SrcOut)
+      }
+      CompositeMode::DestIn | CompositeMode::DestOut => {
+        let (s, b) = both(self)?;
+        self.masked(&b, &s, mode == CompositeMode::DestOut)
+      }
+      CompositeMode::SrcAtop => {
+        let (s, b) = both(self)?;
+        let top = self.masked(&s, &b, false);
+        b + &top
+      }
+      CompositeMode::DestAtop => {
+        let (s, b) = both(self)?;
+        let top = self.masked(&b, &s, false);
+        s + &top
+      }
+      CompositeMode::Xor => {
+        let (s, b) = both(self)?;
+        let (source_out, backdrop_out) = (self.masked(&s, &b, true), self.masked(&b, &s, true));
+        backdrop_out + &source_out
+      }
+      _ => {
+        let (s, b) = both(self)?;
+        let name = blend_mode(mode).unwrap_or("normal");
+        format!("<g style='isolation:isolate'>{}<g style='mix-blend-mode:{}'>{}</g></g>", b, name, s)
+      }
+    })
+  }
+
+  // ctm は Paint の座標から文書（フォント単位）への変換で、外接矩形を求めるのに使う
+  fn paint(&mut self, paint: &Paint, ctm: &Transform) -> Result<String, String> {
+    if let Some(fill) = self.simple_fill(paint) {
+      return Ok(cover_rect(&fill));
+    }
+    Ok(match paint {
+      Paint::Layers(layers) => layers.iter().map(|layer| self.paint(layer, ctm)).collect::<Result<String, String>>()?,
+      Paint::SweepGradient { line, center, start_angle, end_angle } => self.sweep(line, *center, *start_angle, *end_angle),
+      Paint::Glyph { glyph_id, paint } => {
+        let segments = (self.outline)(*glyph_id)?;
+        PathSegment::replay(&segments, &mut TransformSink::new(&mut self.bounds, *ctm));
+        let mut builder = SvgPathBuilder::new();
+        PathSegment::replay(&segments, &mut builder);
+        // 単色か線形・放射グラデーションなら輪郭をそのまま塗る
+        match self.simple_fill(paint) {
+          Some(fill) => format!("<path d='{}'{} />", builder.data, fill),
+          None => {
+            let id = self.id("c");
+            write!(self.defs, "<clipPath id='{}'><path d='{}' /></clipPath>", id, builder.data).unwrap();
+            format!("<g clip-path='url(#{})'>{}</g>", id, self.paint(paint, ctm)?)
+          }
+        }
+      }
+      Paint::Transform { transform, paint } => {
+        let inner = transform.then(ctm);
+        format!("<g transform='{}'>{}</g>", matrix(transform), self.paint(paint, &inner)?)
+      }
+      Paint::Composite { source, mode, backdrop } => {
+        format!("<g style='isolation:isolate'>{}</g>", self.composite(source, *mode, backdrop, ctm)?)
+      }
+      Paint::Solid { .. } | Paint::LinearGradient { .. } | Paint::RadialGradient { .. } => unreachable!(),
+    })
+  }
+}
+
+// COLR バージョン 1 のカラーグリフを SVG にする。outline はグリフ ID から輪郭を引く
+// clip があれば描画範囲をその矩形で切り抜き、viewBox の計算にも使う
+pub fn paint_to_svg<F: Fn(u16) -> Result<Vec<PathSegment>, String>>(
+  paint: &Paint,
+  outline: F,
+  palette: &[Color],
+  foreground: Color,
+  clip: Option<ClipBox>,
+  metrics: &SvgMetrics,
+  options: &SvgOptions,
+) -> Result<String, String> {
+  let prefix = match &options.kind {
+    SvgKind::Document => String::new(),
+    SvgKind::Symbol { id } => format!("{}-", symbol_id(id)),
+  };
+  let mut writer = PaintWriter { outline, palette, foreground, prefix, defs: String::new(), next_id: 0, bounds: BoundsSink::new() };
+  let mut body = writer.paint(paint, &Transform::identity())?;
+
+  let glyph_rect = match clip {
+    Some(clip) => {
+      let id = writer.id("clip");
+      write!(
+        writer.defs,
+        "<clipPath id='{}'><rect x='{}' y='{}' width='{}' height='{}' /></clipPath>",
+        id, clip.x_min, clip.y_min, clip.x_max - clip.x_min, clip.y_max - clip.y_min
+      )
+      .unwrap();
+      body = format!("<g clip-path='url(#{})'>{}</g>", id, body);
+      Some(BoundingBox { x_min: clip.x_min, y_min: clip.y_min, x_max: clip.x_max, y_max: clip.y_max })
+    }
+    None => writer.bounds.exact,
+  };
+  let view = match (options.view_box, glyph_rect) {
+    (ViewBoxMode::Tight, Some(rect)) => rect,
+    _ => metrics_box(metrics, glyph_rect),
+  };
+
+  let mut content = String::new();
+  if !writer.defs.is_empty() {
+    write!(content, "<defs>{}</defs>", writer.defs).unwrap();
+  }
+  content.push_str("<g transform='scale(1 -1)'>");
+  if options.guides {
+    content.push_str(&guides(view, glyph_rect, metrics));
+  }
+  content.push_str(&body);
+  content.push_str("</g>");
+
+  let view_box = view_box(&view);
+  Ok(match &options.kind {
+    SvgKind::Document => format!("<svg viewBox='{}' xmlns='http://www.w3.org/2000/svg'>{}</svg>", view_box, content),
+    SvgKind::Symbol { id } => format!("<symbol id='{}' viewBox='{}'>{}</symbol>", symbol_id(id), view_box, content),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tables::colr::FOREGROUND_PALETTE_INDEX;
+  use crate::tables::paint::ColorStop;
+
+  const PALETTE: [Color; 2] = [Color { r: 0xFF, g: 0, b: 0, a: 0xFF }, Color { r: 0, g: 0, b: 0xFF, a: 0x80 }];
+  const FOREGROUND: Color = Color { r: 0x11, g: 0x22, b: 0x33, a: 0xFF };
+
+  fn square(_: u16) -> Result<Vec<PathSegment>, String> {
+    Ok(vec![
+      PathSegment::MoveTo(0.0, 0.0),
+      PathSegment::LineTo(0.0, 100.0),
+      PathSegment::LineTo(100.0, 100.0),
+      PathSegment::LineTo(100.0, 0.0),
+      PathSegment::Close,
+    ])
+  }
+
+  // glyph_id 1 の輪郭を palette_index の色で塗った SVG
+  fn solid(palette_index: u16, alpha: f32) -> String {
+    let paint = Paint::Glyph { glyph_id: 1, paint: Box::new(Paint::Solid { palette_index, alpha }) };
+    let metrics = SvgMetrics { units_per_em: 1000, ascender: 800, descender: -200, advance_width: 500 };
+    paint_to_svg(&paint, square, &PALETTE, FOREGROUND, None, &metrics, &SvgOptions::default()).unwrap()
+  }
+
+  #[test]
+  fn opaque_colors_have_no_fill_opacity() {
+    let svg = solid(0, 1.0);
+    assert!(svg.contains("<path d='M0 0 L 0 100 L 100 100 L 100 0 Z' fill='#ff0000' />"), "{}", svg);
+    assert!(!svg.contains("fill-opacity"));
+  }
+
+  #[test]
+  fn translucent_colors_and_alpha_emit_fill_opacity() {
+    // パレットのアルファ 0x80 と Paint の alpha を掛け合わせる
+    assert!(solid(1, 1.0).contains("fill='#0000ff' fill-opacity='0.502'"));
+    assert!(solid(1, 0.5).contains("fill='#0000ff' fill-opacity='0.251'"));
+    assert!(solid(0, 0.25).contains("fill='#ff0000' fill-opacity='0.251'"));
+  }
+
+  #[test]
+  fn foreground_index_uses_the_text_color() {
+    let svg = solid(FOREGROUND_PALETTE_INDEX, 1.0);
+    assert!(svg.contains("fill='#112233' />"), "{}", svg);
+    // パレットにない色は透明
+    assert!(solid(5, 1.0).contains("fill='#000000' fill-opacity='0.000'"));
+  }
+
+  #[test]
+  fn gradient_stops_carry_stop_opacity() {
+    let line = ColorLine {
+      extend: Extend::Pad,
+      stops: vec![
+        ColorStop { offset: 0.0, palette_index: 0, alpha: 1.0 },
+        ColorStop { offset: 1.0, palette_index: 1, alpha: 1.0 },
+      ],
+    };
+    let paint = Paint::Glyph {
+      glyph_id: 1,
+      paint: Box::new(Paint::LinearGradient { line, p0: (0.0, 0.0), p1: (100.0, 0.0), p2: (0.0, 100.0) }),
+    };
+    let metrics = SvgMetrics { units_per_em: 1000, ascender: 800, descender: -200, advance_width: 500 };
+    let svg = paint_to_svg(&paint, square, &PALETTE, FOREGROUND, None, &metrics, &SvgOptions::default()).unwrap();
+    assert!(svg.contains("<stop offset='0' stop-color='#ff0000' />"), "{}", svg);
+    assert!(svg.contains("<stop offset='1' stop-color='#0000ff' stop-opacity='0.502' />"), "{}", svg);
+    assert!(svg.contains("fill='url(#g1)'"), "{}", svg);
+  }
+}
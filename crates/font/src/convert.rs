@@ -0,0 +1,312 @@
+use crate::geometry::{Point, Segment};
+use crate::sink::OutlineSink;
+use crate::truetype::outline::PathSegment;
+
+// 分割の深さの上限。2^16 本を超える線分には分けない
+const MAX_DEPTH: u32 = 16;
+
+// 点 p と弦 a-b（線分）の距離
+fn chord_distance(p: Point, a: Point, b: Point) -> f64 {
+  let d = b - a;
+  let squared = d.dot(d);
+  let t = if squared == 0.0 { 0.0 } else { ((p - a).dot(d) / squared).clamp(0.0, 1.0) };
+  (p - a.lerp(b, t)).length()
+}
+
+// 曲線と弦の距離の上限。距離は凸関数なので、2次ベジェは制御点と弦の距離の 1/2、3次ベジェは 3/4 を超えない
+fn quad_deviation(p0: Point, p1: Point, p2: Point) -> f64 {
+  chord_distance(p1, p0, p2) / 2.0
+}
+
+fn cubic_deviation(p0: Point, p1: Point, p2: Point, p3: Point) -> f64 {
+  chord_distance(p1, p0, p3).max(chord_distance(p2, p0, p3)) * 3.0 / 4.0
+}
+
+// 2次ベジェを、弦との距離が tolerance 以下になるまで半分に分けて折れ線にする。曲がりの強いところほど細かくなる
+pub fn flatten_quad(p0: Point, p1: Point, p2: Point, tolerance: f64, out: &mut Vec<Point>) {
+  flatten_segment(Segment::Quad(p0, p1, p2), tolerance, 0, out);
+}
+
+// 3次ベジェを折れ線にする。始点を除いた点を out に追加する
+pub fn flatten_cubic(p0: Point, p1: Point, p2: Point, p3: Point, tolerance: f64, out: &mut Vec<Point>) {
+  flatten_segment(Segment::Cubic(p0, p1, p2, p3), tolerance, 0, out);
+}
+
+fn flatten_segment(segment: Segment, tolerance: f64, depth: u32, out: &mut Vec<Point>) {
+  let deviation = match segment {
+    Segment::Line(..) => 0.0,
+    Segment::Quad(p0, p1, p2) => quad_deviation(p0, p1, p2),
+    Segment::Cubic(p0, p1, p2, p3) => cubic_deviation(p0, p1, p2, p3),
+  };
+  if depth >= MAX_DEPTH || deviation <= tolerance {
+    out.push(segment.end());
+    return;
+  }
+  let (a, b) = segment.split(0.5);
+  flatten_segment(a, tolerance, depth + 1, out);
+  flatten_segment(b, tolerance, depth + 1, out);
+}
+
+// 3次ベジェを誤差 tolerance 以内の2次ベジェの列で近似し、(制御点, 終点) を返す
+// 1本の2次ベジェで近似したときの誤差は √3/36 |p3 - 3 p2 + 3 p1 - p0| 以下で、n 等分すると 1/n^3 になる
+pub fn cubic_to_quads(p0: Point, p1: Point, p2: Point, p3: Point, tolerance: f64) -> Vec<(Point, Point)> {
+  let third = (p3 - p2 * 3.0 + p1 * 3.0 - p0).length();
+  let error = third * 3.0_f64.sqrt() / 36.0;
+  let n = (error / tolerance.max(f64::EPSILON)).cbrt().ceil().clamp(1.0, (1u32 << MAX_DEPTH) as f64) as usize;
+
+  let mut quads = Vec::with_capacity(n);
+  let mut rest = Segment::Cubic(p0, p1, p2, p3);
+  for i in 0..n {
+    // 残りの区間から先頭の 1/(n - i) を切り出す
+    let (piece, tail) = rest.split(1.0 / (n - i) as f64);
+    let Segment::Cubic(q0, q1, q2, q3) = piece else {
+      unreachable!()
+    };
+    let control = ((q1 + q2) * 3.0 - q0 - q3) * 0.25;
+    quads.push((control, q3));
+    rest = tail;
+  }
+  // 丸め誤差で終点がずれないようにする
+  if let Some(last) = quads.last_mut() {
+    last.1 = p3;
+  }
+  quads
+}
+
+// 2次ベジェを同じ形の3次ベジェに次数上げし、2つの制御点を返す
+pub fn quad_to_cubic(p0: Point, p1: Point, p2: Point) -> (Point, Point) {
+  (p0.lerp(p1, 2.0 / 3.0), p2.lerp(p1, 2.0 / 3.0))
+}
+
+fn point(x: f32, y: f32) -> Point {
+  Point::new(x as f64, y as f64)
+}
+
+// 曲線を折れ線にしながら別の sink に流す
+pub struct FlattenSink<'a, S: OutlineSink + ?Sized> {
+  pub sink: &'a mut S,
+  pub tolerance: f32,
+  current: Point,
+  points: Vec<Point>,
+}
+
+impl<'a, S: OutlineSink + ?Sized> FlattenSink<'a, S> {
+  pub fn new(sink: &'a mut S, tolerance: f32) -> Self {
+    FlattenSink { sink, tolerance, current: Point::default(), points: Vec::new() }
+  }
+
+  fn emit(&mut self) {
+    for p in self.points.drain(..) {
+      self.sink.line_to(p.x as f32, p.y as f32);
+      self.current = p;
+    }
+  }
+}
+
+impl<S: OutlineSink + ?Sized> OutlineSink for FlattenSink<'_, S> {
+  fn move_to(&mut self, x: f32, y: f32) {
+    self.sink.move_to(x, y);
+    self.current = point(x, y);
+  }
+
+  fn line_to(&mut self, x: f32, y: f32) {
+    self.sink.line_to(x, y);
+    self.current = point(x, y);
+  }
+
+  fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+    flatten_quad(self.current, point(x1, y1), point(x, y), self.tolerance as f64, &mut self.points);
+    self.emit();
+  }
+
+  fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+    flatten_cubic(self.current, point(x1, y1), point(x2, y2), point(x, y), self.tolerance as f64, &mut self.points);
+    self.emit();
+  }
+
+  fn close(&mut self) {
+    self.sink.close();
+  }
+}
+
+// 3次ベジェを2次ベジェに近似しながら流す（CFF の輪郭を TrueType 向けにする）
+pub struct QuadraticSink<'a, S: OutlineSink + ?Sized> {
+  pub sink: &'a mut S,
+  pub tolerance: f32,
+  current: Point,
+}
+
+impl<'a, S: OutlineSink + ?Sized> QuadraticSink<'a, S> {
+  pub fn new(sink: &'a mut S, tolerance: f32) -> Self {
+    QuadraticSink { sink, tolerance, current: Point::default() }
+  }
+}
+
+impl<S: OutlineSink + ?Sized> OutlineSink for QuadraticSink<'_, S> {
+  fn move_to(&mut self, x: f32, y: f32) {
+    self.sink.move_to(x, y);
+    self.current = point(x, y);
+  }
+
+  fn line_to(&mut self, x: f32, y: f32) {
+    self.sink.line_to(x, y);
+    self.current = point(x, y);
+  }
+
+  fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+    self.sink.quad_to(x1, y1, x, y);
+    self.current = point(x, y);
+  }
+
+  fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+    for (control, end) in cubic_to_quads(self.current, point(x1, y1), point(x2, y2), point(x, y), self.tolerance as f64) {
+      self.sink.quad_to(control.x as f32, control.y as f32, end.x as f32, end.y as f32);
+    }
+    self.current = point(x, y);
+  }
+
+  fn close(&mut self) {
+    self.sink.close();
+  }
+}
+
+// 2次ベジェを3次ベジェに次数上げしながら流す
+pub struct CubicSink<'a, S: OutlineSink + ?Sized> {
+  pub sink: &'a mut S,
+  current: Point,
+}
+
+impl<'a, S: OutlineSink + ?Sized> CubicSink<'a, S> {
+  pub fn new(sink: &'a mut S) -> Self {
+    CubicSink { sink, current: Point::default() }
+  }
+}
+
+impl<S: OutlineSink + ?Sized> OutlineSink for CubicSink<'_, S> {
+  fn move_to(&mut self, x: f32, y: f32) {
+    self.sink.move_to(x, y);
+    self.current = point(x, y);
+  }
+
+  fn line_to(&mut self, x: f32, y: f32) {
+    self.sink.line_to(x, y);
+    self.current = point(x, y);
+  }
+
+  fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+    let (c1, c2) = quad_to_cubic(self.current, point(x1, y1), point(x, y));
+    self.sink.cubic_to(c1.x as f32, c1.y as f32, c2.x as f32, c2.y as f32, x, y);
+    self.current = point(x, y);
+  }
+
+  fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+    self.sink.cubic_to(x1, y1, x2, y2, x, y);
+    self.current = point(x, y);
+  }
+
+  fn close(&mut self) {
+    self.sink.close();
+  }
+}
+
+// 曲線を折れ線に置き換えた輪郭
+pub fn flatten(segments: &[PathSegment], tolerance: f32) -> Vec<PathSegment> {
+  let mut result = Vec::with_capacity(segments.len());
+  PathSegment::replay(segments, &mut FlattenSink::new(&mut result, tolerance));
+  result
+}
+
+// 3次ベジェを2次ベジェに置き換えた輪郭
+pub fn to_quadratic(segments: &[PathSegment], tolerance: f32) -> Vec<PathSegment> {
+  let mut result = Vec::with_capacity(segments.len());
+  PathSegment::replay(segments, &mut QuadraticSink::new(&mut result, tolerance));
+  result
+}
+
+// 2次ベジェを3次ベジェに置き換えた輪郭
+pub fn to_cubic(segments: &[PathSegment]) -> Vec<PathSegment> {
+  let mut result = Vec::with_capacity(segments.len());
+  PathSegment::replay(segments, &mut CubicSink::new(&mut result));
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn p(x: f64, y: f64) -> Point {
+    Point::new(x, y)
+  }
+
+  // 曲がりの強いアーチと、変曲点のある S 字
+  fn cubics() -> [Segment; 2] {
+    [
+      Segment::Cubic(p(0.0, 0.0), p(0.0, 100.0), p(100.0, 100.0), p(100.0, 0.0)),
+      Segment::Cubic(p(0.0, 0.0), p(150.0, 100.0), p(-50.0, 100.0), p(100.0, 0.0)),
+    ]
+  }
+
+  fn samples(count: usize) -> impl Iterator<Item = f64> {
+    (0..=count).map(move |i| i as f64 / count as f64)
+  }
+
+  // 点 q と折れ線の距離
+  fn polyline_distance(q: Point, polyline: &[Point]) -> f64 {
+    polyline.windows(2).map(|w| chord_distance(q, w[0], w[1])).fold(f64::MAX, f64::min)
+  }
+
+  #[test]
+  fn cubic_to_quads_stays_within_the_tolerance() {
+    for cubic in cubics() {
+      let Segment::Cubic(p0, p1, p2, p3) = cubic else { unreachable!() };
+      for tolerance in [2.0, 0.5, 0.05] {
+        let quads = cubic_to_quads(p0, p1, p2, p3, tolerance);
+        let n = quads.len() as f64;
+        let mut start = p0;
+        let mut worst: f64 = 0.0;
+        // i 本目の2次ベジェの s は元の曲線の (i + s) / n に当たる
+        for (i, &(control, end)) in quads.iter().enumerate() {
+          let quad = Segment::Quad(start, control, end);
+          for s in samples(32) {
+            worst = worst.max((quad.point(s) - cubic.point((i as f64 + s) / n)).length());
+          }
+          start = end;
+        }
+        assert_eq!(start, p3);
+        assert!(worst <= tolerance, "{} 本で誤差 {} > {}", quads.len(), worst, tolerance);
+      }
+    }
+    // 誤差の上限は分割数の3乗に反比例するので、許容値を 1/8 にすると本数はほぼ 2 倍
+    let [arch, _] = cubics();
+    let Segment::Cubic(p0, p1, p2, p3) = arch else { unreachable!() };
+    assert_eq!(cubic_to_quads(p0, p1, p2, p3, 0.4).len() * 2, cubic_to_quads(p0, p1, p2, p3, 0.05).len());
+  }
+
+  #[test]
+  fn quad_to_cubic_is_exact() {
+    let (p0, p1, p2) = (p(0.0, 0.0), p(30.0, 120.0), p(100.0, -20.0));
+    let (c1, c2) = quad_to_cubic(p0, p1, p2);
+    let (quad, cubic) = (Segment::Quad(p0, p1, p2), Segment::Cubic(p0, c1, c2, p2));
+    for t in samples(64) {
+      assert!((quad.point(t) - cubic.point(t)).length() < 1e-12);
+    }
+  }
+
+  #[test]
+  fn flattening_stays_within_the_tolerance() {
+    let quad = Segment::Quad(p(0.0, 0.0), p(50.0, 200.0), p(100.0, 0.0));
+    for curve in cubics().into_iter().chain([quad]) {
+      for tolerance in [1.0, 0.1] {
+        let mut polyline = vec![curve.start()];
+        match curve {
+          Segment::Quad(p0, p1, p2) => flatten_quad(p0, p1, p2, tolerance, &mut polyline),
+          Segment::Cubic(p0, p1, p2, p3) => flatten_cubic(p0, p1, p2, p3, tolerance, &mut polyline),
+          Segment::Line(..) => unreachable!(),
+        }
+        assert_eq!(*polyline.last().unwrap(), curve.end());
+        let worst = samples(1000).map(|t| polyline_distance(curve.point(t), &polyline)).fold(0.0, f64::max);
+        assert!(worst <= tolerance, "{} 点で誤差 {} > {}", polyline.len(), worst, tolerance);
+      }
+    }
+  }
+}
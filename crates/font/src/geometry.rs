@@ -0,0 +1,307 @@
+use std::ops::{Add, Mul, Sub};
+
+// 平面上の点（ベクトルとしても使う）。曲線の計算は f64 で行う
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point {
+  pub x: f64,
+  pub y: f64,
+}
+
+impl Point {
+  pub fn new(x: f64, y: f64) -> Self {
+    Point { x, y }
+  }
+
+  pub fn dot(self, other: Point) -> f64 {
+    self.x * other.x + self.y * other.y
+  }
+
+  pub fn cross(self, other: Point) -> f64 {
+    self.x * other.y - self.y * other.x
+  }
+
+  pub fn length(self) -> f64 {
+    self.dot(self).sqrt()
+  }
+
+  pub fn normalize(self) -> Point {
+    let length = self.length();
+    if length == 0.0 { Point::new(0.0, 0.0) } else { self * (1.0 / length) }
+  }
+
+  // 進行方向の左手側の単位法線（y軸上向き）
+  pub fn left_normal(self) -> Point {
+    let d = self.normalize();
+    Point::new(-d.y, d.x)
+  }
+
+  pub fn lerp(self, other: Point, t: f64) -> Point {
+    self + (other - self) * t
+  }
+}
+
+impl Add for Point {
+  type Output = Point;
+  fn add(self, other: Point) -> Point {
+    Point::new(self.x + other.x, self.y + other.y)
+  }
+}
+
+impl Sub for Point {
+  type Output = Point;
+  fn sub(self, other: Point) -> Point {
+    Point::new(self.x - other.x, self.y - other.y)
+  }
+}
+
+impl Mul<f64> for Point {
+  type Output = Point;
+  fn mul(self, s: f64) -> Point {
+    Point::new(self.x * s, self.y * s)
+  }
+}
+
+// 直線・2次ベジェ・3次ベジェ
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+  Line(Point, Point),
+  Quad(Point, Point, Point),
+  Cubic(Point, Point, Point, Point),
+}
+
+impl Segment {
+  pub fn points(&self) -> Vec<Point> {
+    match *self {
+      Segment::Line(p0, p1) => vec![p0, p1],
+      Segment::Quad(p0, p1, p2) => vec![p0, p1, p2],
+      Segment::Cubic(p0, p1, p2, p3) => vec![p0, p1, p2, p3],
+    }
+  }
+
+  pub fn start(&self) -> Point {
+    match *self {
+      Segment::Line(p0, _) | Segment::Quad(p0, _, _) | Segment::Cubic(p0, _, _, _) => p0,
+    }
+  }
+
+  pub fn end(&self) -> Point {
+    match *self {
+      Segment::Line(_, p1) | Segment::Quad(_, _, p1) | Segment::Cubic(_, _, _, p1) => p1,
+    }
+  }
+
+  // 制御点はそのままで端点だけ置き換える
+  pub fn with_ends(&self, start: Point, end: Point) -> Segment {
+    match *self {
+      Segment::Line(..) => Segment::Line(start, end),
+      Segment::Quad(_, p1, _) => Segment::Quad(start, p1, end),
+      Segment::Cubic(_, p1, p2, _) => Segment::Cubic(start, p1, p2, end),
+    }
+  }
+
+  pub fn point(&self, t: f64) -> Point {
+    match *self {
+      Segment::Line(p0, p1) => p0.lerp(p1, t),
+      Segment::Quad(p0, p1, p2) => p0.lerp(p1, t).lerp(p1.lerp(p2, t), t),
+      Segment::Cubic(p0, p1, p2, p3) => {
+        let p12 = p1.lerp(p2, t);
+        p0.lerp(p1, t).lerp(p12, t).lerp(p12.lerp(p2.lerp(p3, t), t), t)
+      }
+    }
+  }
+
+  // t における接線方向（長さは正規化しない）
+  pub fn direction(&self, t: f64) -> Point {
+    match *self {
+      Segment::Line(p0, p1) => p1 - p0,
+      Segment::Quad(p0, p1, p2) => {
+        let d = (p1 - p0).lerp(p2 - p1, t);
+        // 制御点が端点と重なると接線が消えるので、弦で代用する
+        if d.length() == 0.0 { p2 - p0 } else { d }
+      }
+      Segment::Cubic(p0, p1, p2, p3) => {
+        let d = (p1 - p0).lerp(p2 - p1, t).lerp((p2 - p1).lerp(p3 - p2, t), t);
+        if d.length() == 0.0 {
+          if t == 0.0 { p2 - p0 } else if t == 1.0 { p3 - p1 } else { p3 - p0 }
+        } else {
+          d
+        }
+      }
+    }
+  }
+
+  // de Casteljau 法で t の位置で分割する
+  pub fn split(&self, t: f64) -> (Segment, Segment) {
+    match *self {
+      Segment::Line(p0, p1) => {
+        let m = p0.lerp(p1, t);
+        (Segment::Line(p0, m), Segment::Line(m, p1))
+      }
+      Segment::Quad(p0, p1, p2) => {
+        let (a, b) = (p0.lerp(p1, t), p1.lerp(p2, t));
+        let m = a.lerp(b, t);
+        (Segment::Quad(p0, a, m), Segment::Quad(m, b, p2))
+      }
+      Segment::Cubic(p0, p1, p2, p3) => {
+        let (a, b, c) = (p0.lerp(p1, t), p1.lerp(p2, t), p2.lerp(p3, t));
+        let (ab, bc) = (a.lerp(b, t), b.lerp(c, t));
+        let m = ab.lerp(bc, t);
+        (Segment::Cubic(p0, a, ab, m), Segment::Cubic(m, bc, c, p3))
+      }
+    }
+  }
+
+  // 昇順の t で順に分割する
+  pub fn split_at(&self, ts: &[f64]) -> Vec<Segment> {
+    let mut pieces = Vec::with_capacity(ts.len() + 1);
+    let mut rest = *self;
+    let mut previous = 0.0;
+    for &t in ts {
+      let local = (t - previous) / (1.0 - previous);
+      let (head, tail) = rest.split(local);
+      pieces.push(head);
+      rest = tail;
+      previous = t;
+    }
+    pieces.push(rest);
+    pieces
+  }
+
+  // x または y が極値をとる t（端点を除く）
+  pub fn extrema(&self) -> Vec<f64> {
+    let mut ts = self.axis_extrema(|p| p.x);
+    ts.extend(self.axis_extrema(|p| p.y));
+    ts
+  }
+
+  // y が極値をとる t（端点を除く、昇順）。ここで分割すると y について単調になる
+  pub fn y_extrema(&self) -> Vec<f64> {
+    let mut ts = self.axis_extrema(|p| p.y);
+    ts.sort_by(f64::total_cmp);
+    ts
+  }
+
+  fn axis_extrema(&self, axis: fn(Point) -> f64) -> Vec<f64> {
+    let ts = match *self {
+      Segment::Line(..) => vec![],
+      Segment::Quad(p0, p1, p2) => {
+        let (a, b, c) = (axis(p0), axis(p1), axis(p2));
+        let denominator = a - 2.0 * b + c;
+        if denominator == 0.0 { vec![] } else { vec![(a - b) / denominator] }
+      }
+      Segment::Cubic(p0, p1, p2, p3) => {
+        let (a, b, c, d) = (axis(p0), axis(p1), axis(p2), axis(p3));
+        // 導関数 / 3 = qa t^2 + qb t + qc
+        let qa = -a + 3.0 * b - 3.0 * c + d;
+        let qb = 2.0 * (a - 2.0 * b + c);
+        let qc = b - a;
+        if qa.abs() < 1e-12 {
+          if qb == 0.0 { vec![] } else { vec![-qc / qb] }
+        } else {
+          let discriminant = qb * qb - 4.0 * qa * qc;
+          if discriminant < 0.0 {
+            vec![]
+          } else {
+            let root = discriminant.sqrt();
+            vec![(-qb + root) / (2.0 * qa), (-qb - root) / (2.0 * qa)]
+          }
+        }
+      }
+    };
+    ts.into_iter().filter(|&t| t > 0.0 && t < 1.0).collect()
+  }
+
+  pub fn map<F: Fn(Point) -> Point>(&self, f: F) -> Segment {
+    match *self {
+      Segment::Line(p0, p1) => Segment::Line(f(p0), f(p1)),
+      Segment::Quad(p0, p1, p2) => Segment::Quad(f(p0), f(p1), f(p2)),
+      Segment::Cubic(p0, p1, p2, p3) => Segment::Cubic(f(p0), f(p1), f(p2), f(p3)),
+    }
+  }
+
+  pub fn reverse(&self) -> Segment {
+    match *self {
+      Segment::Line(p0, p1) => Segment::Line(p1, p0),
+      Segment::Quad(p0, p1, p2) => Segment::Quad(p2, p1, p0),
+      Segment::Cubic(p0, p1, p2, p3) => Segment::Cubic(p3, p2, p1, p0),
+    }
+  }
+
+  // 全部の点が重なっていて長さがない
+  pub fn is_degenerate(&self) -> bool {
+    let start = self.start();
+    self.points().iter().all(|&p| p == start)
+  }
+
+  // 制御点が弦から tolerance 以内なら直線とみなせる
+  pub fn is_flat(&self, tolerance: f64) -> bool {
+    let (p0, p1) = (self.start(), self.end());
+    let chord = p1 - p0;
+    let length = chord.length();
+    self.points().iter().all(|&p| {
+      let distance = if length == 0.0 { (p - p0).length() } else { chord.cross(p - p0).abs() / length };
+      distance <= tolerance
+    })
+  }
+}
+
+// 次数を問わない制御点 points の曲線上の点（de Casteljau 法）
+pub fn bezier_point(points: &[Point], t: f64) -> Point {
+  let mut points = points.to_vec();
+  while points.len() > 1 {
+    points = points.windows(2).map(|w| w[0].lerp(w[1], t)).collect();
+  }
+  points[0]
+}
+
+// 導関数の曲線（ホドグラフ）の制御点。次数が1つ下がる
+pub fn hodograph(points: &[Point]) -> Vec<Point> {
+  let degree = (points.len() - 1) as f64;
+  points.windows(2).map(|w| (w[1] - w[0]) * degree).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn p(x: f64, y: f64) -> Point {
+    Point::new(x, y)
+  }
+
+  #[test]
+  fn split_halves_meet_on_the_curve() {
+    let cubic = Segment::Cubic(p(0.0, 0.0), p(0.0, 3.0), p(3.0, 3.0), p(3.0, 0.0));
+    let (head, tail) = cubic.split(0.25);
+    assert_eq!(head.end(), cubic.point(0.25));
+    assert_eq!(tail.start(), cubic.point(0.25));
+    let mid = head.point(0.5);
+    assert!((mid - cubic.point(0.125)).length() < 1e-12);
+  }
+
+  #[test]
+  fn split_at_keeps_the_global_parameters() {
+    let quad = Segment::Quad(p(0.0, 0.0), p(1.0, 2.0), p(2.0, 0.0));
+    let pieces = quad.split_at(&[0.25, 0.5]);
+    assert_eq!(pieces.len(), 3);
+    assert!((pieces[0].end() - quad.point(0.25)).length() < 1e-12);
+    assert!((pieces[1].end() - quad.point(0.5)).length() < 1e-12);
+    assert_eq!(pieces[2].end(), quad.end());
+  }
+
+  #[test]
+  fn extrema_of_an_arch() {
+    let quad = Segment::Quad(p(0.0, 0.0), p(1.0, 2.0), p(2.0, 0.0));
+    assert_eq!(quad.extrema(), vec![0.5]);
+    let cubic = Segment::Cubic(p(0.0, 0.0), p(0.0, 1.0), p(1.0, 1.0), p(1.0, 0.0));
+    assert_eq!(cubic.y_extrema(), vec![0.5]);
+    assert!(Segment::Line(p(0.0, 0.0), p(1.0, 1.0)).extrema().is_empty());
+  }
+
+  #[test]
+  fn direction_falls_back_when_a_control_point_coincides() {
+    let quad = Segment::Quad(p(0.0, 0.0), p(0.0, 0.0), p(2.0, 2.0));
+    assert_eq!(quad.direction(0.0), p(2.0, 2.0));
+    let cubic = Segment::Cubic(p(0.0, 0.0), p(0.0, 0.0), p(1.0, 0.0), p(1.0, 1.0));
+    assert_eq!(cubic.direction(0.0), p(1.0, 0.0));
+  }
+}
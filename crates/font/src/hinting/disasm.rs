@@ -0,0 +1,318 @@
+use std::fmt::Write;
+
+// 命令の名前と、オペコードの下位ビットに入るフラグの桁数。フラグは fontTools と同じく [01] の2進表記で書く
+// PUSHB / PUSHW のフラグは個数 - 1
+const OPCODES: [(u8, &str, u32); 123] = [
+  (0x00, "SVTCA", 1), (0x02, "SPVTCA", 1), (0x04, "SFVTCA", 1), (0x06, "SPVTL", 1),
+  (0x08, "SFVTL", 1), (0x0A, "SPVFS", 0), (0x0B, "SFVFS", 0), (0x0C, "GPV", 0),
+  (0x0D, "GFV", 0), (0x0E, "SFVTPV", 0), (0x0F, "ISECT", 0), (0x10, "SRP0", 0),
+  (0x11, "SRP1", 0), (0x12, "SRP2", 0), (0x13, "SZP0", 0), (0x14, "SZP1", 0),
+  (0x15, "SZP2", 0), (0x16, "SZPS", 0), (0x17, "SLOOP", 0), (0x18, "RTG", 0),
+  (0x19, "RTHG", 0), (0x1A, "SMD", 0), (0x1B, "ELSE", 0), (0x1C, "JMPR", 0),
+  (0x1D, "SCVTCI", 0), (0x1E, "SSWCI", 0), (0x1F, "SSW", 0), (0x20, "DUP", 0),
+  (0x21, "POP", 0), (0x22, "CLEAR", 0), (0x23, "SWAP", 0), (0x24, "DEPTH", 0),
+  (0x25, "CINDEX", 0), (0x26, "MINDEX", 0), (0x27, "ALIGNPTS", 0), (0x29, "UTP", 0),
+  (0x2A, "LOOPCALL", 0), (0x2B, "CALL", 0), (0x2C, "FDEF", 0), (0x2D, "ENDF", 0),
+  (0x2E, "MDAP", 1), (0x30, "IUP", 1), (0x32, "SHP", 1), (0x34, "SHC", 1),
+  (0x36, "SHZ", 1), (0x38, "SHPIX", 0), (0x39, "IP", 0), (0x3A, "MSIRP", 1),
+  (0x3C, "ALIGNRP", 0), (0x3D, "RTDG", 0), (0x3E, "MIAP", 1), (0x40, "NPUSHB", 0),
+  (0x41, "NPUSHW", 0), (0x42, "WS", 0), (0x43, "RS", 0), (0x44, "WCVTP", 0),
+  (0x45, "RCVT", 0), (0x46, "GC", 1), (0x48, "SCFS", 0), (0x49, "MD", 1),
+  (0x4B, "MPPEM", 0), (0x4C, "MPS", 0), (0x4D, "FLIPON", 0), (0x4E, "FLIPOFF", 0),
+  (0x4F, "DEBUG", 0), (0x50, "LT", 0), (0x51, "LTEQ", 0), (0x52, "GT", 0),
+  (0x53, "GTEQ", 0), (0x54, "EQ", 0), (0x55, "NEQ", 0), (0x56, "ODD", 0),
+  (0x57, "EVEN", 0), (0x58, "IF", 0), (0x59, "EIF", 0), (0x5A, "AND", 0),
+  (0x5B, "OR", 0), (0x5C, "NOT", 0), (0x5D, "DELTAP1", 0), (0x5E, "SDB", 0),
+  (0x5F, "SDS", 0), (0x60, "ADD", 0), (0x61, "SUB", 0), (0x62, "DIV", 0),
+  (0x63, "MUL", 0), (0x64, "ABS", 0), (0x65, "NEG", 0), (0x66, "FLOOR", 0),
+  (0x67, "CEILING", 0), (0x68, "ROUND", 2), (0x6C, "NROUND", 2), (0x70, "WCVTF", 0),
+  (0x71, "DELTAP2", 0), (0x72, "DELTAP3", 0), (0x73, "DELTAC1", 0), (0x74, "DELTAC2", 0),
+  (0x75, "DELTAC3", 0), (0x76, "SROUND", 0), (0x77, "S45ROUND", 0), (0x78, "JROT", 0),
+  (0x79, "JROF", 0), (0x7A, "ROFF", 0), (0x7C, "RUTG", 0), (0x7D, "RDTG", 0),
+  (0x7E, "SANGW", 0), (0x7F, "AA", 0), (0x80, "FLIPPT", 0), (0x81, "FLIPRGON", 0),
+  (0x82, "FLIPRGOFF", 0), (0x85, "SCANCTRL", 0), (0x86, "SDPVTL", 1), (0x88, "GETINFO", 0),
+  (0x89, "IDEF", 0), (0x8A, "ROLL", 0), (0x8B, "MAX", 0), (0x8C, "MIN", 0),
+  (0x8D, "SCANTYPE", 0), (0x8E, "INSTCTRL", 0), (0x91, "GETVARIATION", 0), (0xB0, "PUSHB", 3),
+  (0xB8, "PUSHW", 3), (0xC0, "MDRP", 5), (0xE0, "MIRP", 5),
+];
+
+// オペコードの表記。未定義のもの（IDEF で定義されうる）は INS_XX とする
+pub fn mnemonic(opcode: u8) -> String {
+  match OPCODES.iter().find(|&&(base, _, bits)| opcode >= base && (opcode - base) as u32 >> bits == 0) {
+    Some(&(_, name, 0)) => name.to_string(),
+    Some(&(base, name, bits)) => format!("{}[{:0width$b}]", name, opcode - base, width = bits as usize),
+    None => format!("INS_{:02X}", opcode),
+  }
+}
+
+// 表記からオペコードに戻す
+pub fn opcode(mnemonic: &str) -> Option<u8> {
+  if let Some(hex) = mnemonic.strip_prefix("INS_") {
+    return u8::from_str_radix(hex, 16).ok();
+  }
+  let (name, flags) = match mnemonic.split_once('[') {
+    Some((name, rest)) => (name, Some(rest.strip_suffix(']')?)),
+    None => (mnemonic, None),
+  };
+  let &(base, _, bits) = OPCODES.iter().find(|&&(_, n, _)| n == name)?;
+  match flags {
+    None if bits == 0 => Some(base),
+    Some(flags) if bits > 0 && flags.len() == bits as usize => Some(base + u8::from_str_radix(flags, 2).ok()?),
+    _ => None,
+  }
+}
+
+// 1つの命令。プッシュ命令なら積む値を data に持つ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+  pub offset: usize,
+  pub opcode: u8,
+  pub data: Vec<i32>,
+}
+
+impl Instruction {
+  pub fn mnemonic(&self) -> String {
+    mnemonic(self.opcode)
+  }
+
+  pub fn is_push(&self) -> bool {
+    matches!(self.opcode, 0x40 | 0x41 | 0xB0..=0xBF)
+  }
+}
+
+// 命令列を命令ごとに分け、プッシュするデータを読み出す
+pub fn disassemble(bytecode: &[u8]) -> Result<Vec<Instruction>, String> {
+  let mut instructions = Vec::new();
+  let mut ip = 0;
+  while ip < bytecode.len() {
+    let opcode = bytecode[ip];
+    let (count, words, start) = match opcode {
+      0x40 | 0x41 => {
+        let count = *bytecode.get(ip + 1).ok_or(format!("{}: NPUSH の個数がありません", ip))? as usize;
+        (count, opcode == 0x41, ip + 2)
+      }
+      0xB0..=0xB7 => ((opcode - 0xB0) as usize + 1, false, ip + 1),
+      0xB8..=0xBF => ((opcode - 0xB8) as usize + 1, true, ip + 1),
+      _ => (0, false, ip + 1),
+    };
+    let size = if words { 2 } else { 1 };
+    let end = start + count * size;
+    let data = bytecode.get(start..end).ok_or(format!("{}: プッシュするデータが命令列の外にあります", ip))?;
+    let data = data.chunks(size)
+      .map(|chunk| if words { i16::from_be_bytes([chunk[0], chunk[1]]) as i32 } else { chunk[0] as i32 })
+      .collect();
+    instructions.push(Instruction { offset: ip, opcode, data });
+    ip = end;
+  }
+  Ok(instructions)
+}
+
+// オフセット、字下げした命令、プッシュする値を1行ずつ並べる。FDEF / IDEF には直前に積んだ値から関数番号を添える
+pub fn listing(bytecode: &[u8]) -> Result<String, String> {
+  let mut text = String::new();
+  let mut depth = 0usize;
+  // 関数定義の外で積んだ定数。FDEF が順に取り出す
+  let mut pending: Vec<i32> = Vec::new();
+  // 定義の中にいれば Some。中身は分かれば関数の名前
+  let mut definition: Option<Option<String>> = None;
+  for instruction in disassemble(bytecode)? {
+    let opcode = instruction.opcode;
+    if matches!(opcode, 0x1B | 0x2D | 0x59) {
+      depth = depth.saturating_sub(1);
+    }
+    let mut line = format!("{:5}  {}{}", instruction.offset, "  ".repeat(depth), instruction.mnemonic());
+    for value in &instruction.data {
+      write!(line, " {}", value).unwrap();
+    }
+    let comment = match opcode {
+      0x2C | 0x89 if definition.is_none() => {
+        let name = pending.pop().map(|number| if opcode == 0x2C {
+          format!("関数 {}", number)
+        } else {
+          format!("命令 {} の定義", mnemonic(number as u8))
+        });
+        definition = Some(name.clone());
+        name.map(|name| format!("{} の始まり", name))
+      }
+      0x2D => definition.take().flatten().map(|name| format!("{} の終わり", name)),
+      _ if definition.is_some() => None,
+      _ if instruction.is_push() => {
+        pending.extend(&instruction.data);
+        None
+      }
+      _ => {
+        pending.clear();
+        None
+      }
+    };
+    match comment {
+      Some(comment) => writeln!(text, "{:<40} ; {}", line, comment).unwrap(),
+      None => writeln!(text, "{}", line).unwrap(),
+    }
+    if matches!(opcode, 0x1B | 0x2C | 0x58 | 0x89) {
+      depth += 1;
+    }
+  }
+  Ok(text)
+}
+
+// listing の形式のテキストを命令列に戻す。; 以降はコメント、行頭の数字はオフセットとして読み飛ばす
+// PUSH と書くと値に合わせて一番短いプッシュ命令を選ぶ
+pub fn assemble(text: &str) -> Result<Vec<u8>, String> {
+  let mut bytecode = Vec::new();
+  for (number, line) in text.lines().enumerate() {
+    let line = line.split(';').next().unwrap_or("");
+    let mut tokens = line.split_whitespace().peekable();
+    if tokens.peek().is_some_and(|token| token.starts_with(|c: char| c.is_ascii_digit())) {
+      tokens.next();
+    }
+    let Some(name) = tokens.next() else {
+      continue;
+    };
+    let error = |message: String| format!("{}行目: {}", number + 1, message);
+    let values = tokens
+      .map(|token| token.parse::<i32>().map_err(|_| error(format!("値を解釈できません: {}", token))))
+      .collect::<Result<Vec<_>, _>>()?;
+    if name == "PUSH" {
+      push(&mut bytecode, &values).map_err(error)?;
+      continue;
+    }
+    let opcode = opcode(name).ok_or_else(|| error(format!("不明な命令です: {}", name)))?;
+    let (count, words) = match opcode {
+      0x40 | 0x41 => (None, opcode == 0x41),
+      0xB0..=0xB7 => (Some((opcode - 0xB0) as usize + 1), false),
+      0xB8..=0xBF => (Some((opcode - 0xB8) as usize + 1), true),
+      _ => (Some(0), false),
+    };
+    match count {
+      Some(count) if count != values.len() => {
+        return Err(error(format!("{} には値が{}個必要です", name, count)));
+      }
+      None if values.len() > 255 => return Err(error("NPUSH で積める値は255個までです".to_string())),
+      _ => {}
+    }
+    bytecode.push(opcode);
+    if count.is_none() {
+      bytecode.push(values.len() as u8);
+    }
+    for &value in &values {
+      if words {
+        let value = i16::try_from(value).map_err(|_| error(format!("ワードに収まりません: {}", value)))?;
+        bytecode.extend(value.to_be_bytes());
+      } else {
+        let value = u8::try_from(value).map_err(|_| error(format!("バイトに収まりません: {}", value)))?;
+        bytecode.push(value);
+      }
+    }
+  }
+  Ok(bytecode)
+}
+
+// 値を一番短いプッシュ命令で積む。8個までは PUSHB / PUSHW、それを超えれば NPUSHB / NPUSHW
+fn push(bytecode: &mut Vec<u8>, values: &[i32]) -> Result<(), String> {
+  if let Some(value) = values.iter().find(|&&value| i16::try_from(value).is_err()) {
+    return Err(format!("ワードに収まりません: {}", value));
+  }
+  for chunk in values.chunks(255) {
+    let words = chunk.iter().any(|&value| !(0..=255).contains(&value));
+    match (chunk.len() <= 8, words) {
+      (true, false) => bytecode.push(0xB0 + chunk.len() as u8 - 1),
+      (true, true) => bytecode.push(0xB8 + chunk.len() as u8 - 1),
+      (false, words) => bytecode.extend([if words { 0x41 } else { 0x40 }, chunk.len() as u8]),
+    }
+    for &value in chunk {
+      if words {
+        bytecode.extend((value as i16).to_be_bytes());
+      } else {
+        bytecode.push(value as u8);
+      }
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // 2つの関数定義、IF / ELSE、各種のプッシュ、フラグ付きの命令と未定義の命令
+  const PROGRAM: [u8; 29] = [
+    0xB1, 0x01, 0x00, // PUSHB[001] 1 0
+    0x2C, // FDEF
+    0xB8, 0xFF, 0x38, // PUSHW[000] -200
+    0x58, 0x20, 0x1B, 0x21, 0x59, // IF DUP ELSE POP EIF
+    0x2D, // ENDF
+    0x2C, // FDEF
+    0x40, 0x02, 0x05, 0x06, // NPUSHB 5 6
+    0x41, 0x01, 0x01, 0x00, // NPUSHW 256
+    0x2D, // ENDF
+    0x3F, 0xC5, 0xA0, 0x2E, 0x30, 0x31, // MIAP[1] MDRP[00101] INS_A0 MDAP[0] IUP[0] IUP[1]
+  ];
+
+  #[test]
+  fn every_opcode_round_trips_through_its_mnemonic() {
+    for byte in 0..=255u8 {
+      assert_eq!(opcode(&mnemonic(byte)), Some(byte), "{}", mnemonic(byte));
+    }
+    assert_eq!(mnemonic(0xC5), "MDRP[00101]");
+    assert_eq!(mnemonic(0xA0), "INS_A0");
+    assert_eq!(opcode("MDRP"), None);
+    assert_eq!(opcode("SRP0[1]"), None);
+  }
+
+  #[test]
+  fn listing_names_functions_and_indents_blocks() {
+    let text = listing(&PROGRAM).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "    0  PUSHB[001] 1 0");
+    assert!(lines[1].starts_with("    3  FDEF") && lines[1].ends_with("; 関数 0 の始まり"));
+    assert_eq!(lines[3], "    7    IF");
+    assert_eq!(lines[4], "    8      DUP");
+    assert_eq!(lines[5], "    9    ELSE");
+    assert!(lines[8].ends_with("; 関数 0 の終わり"));
+    assert!(lines[9].ends_with("; 関数 1 の始まり"));
+    assert_eq!(lines[11], "   18    NPUSHW 256");
+    assert_eq!(&lines[13..], ["   23  MIAP[1]", "   24  MDRP[00101]", "   25  INS_A0", "   26  MDAP[0]", "   27  IUP[0]", "   28  IUP[1]"]);
+  }
+
+  #[test]
+  fn listing_assembles_back_to_the_same_bytes() {
+    assert_eq!(assemble(&listing(&PROGRAM).unwrap()).unwrap(), PROGRAM);
+    assert_eq!(assemble(&listing(&[]).unwrap()).unwrap(), []);
+  }
+
+  #[test]
+  fn push_picks_the_shortest_instruction() {
+    assert_eq!(assemble("PUSH 1 2").unwrap(), [0xB1, 1, 2]);
+    assert_eq!(assemble("PUSH 1 -1").unwrap(), [0xB9, 0, 1, 0xFF, 0xFF]);
+    let nine = assemble("PUSH 1 2 3 4 5 6 7 8 9").unwrap();
+    assert_eq!(nine[..2], [0x40, 9]);
+    let many = format!("PUSH {}", vec!["300"; 256].join(" "));
+    let bytecode = assemble(&many).unwrap();
+    assert_eq!(bytecode[..2], [0x41, 255]);
+    assert_eq!(bytecode[2 + 255 * 2], 0xB8);
+    assert_eq!(bytecode.len(), 2 + 255 * 2 + 1 + 2);
+  }
+
+  #[test]
+  fn assembler_errors_name_the_line() {
+    let error = |text: &str| assemble(text).unwrap_err();
+    assert_eq!(error("SVTCA[1]\nPUSHB[000] 256"), "2行目: バイトに収まりません: 256");
+    assert_eq!(error("PUSHB[000] -1"), "1行目: バイトに収まりません: -1");
+    assert_eq!(error("PUSHW[000] 40000"), "1行目: ワードに収まりません: 40000");
+    assert_eq!(error("PUSHB[001] 1"), "1行目: PUSHB[001] には値が2個必要です");
+    assert_eq!(error("PUSHW[010] 1 2 3 4"), "1行目: PUSHW[010] には値が3個必要です");
+    assert_eq!(error("SRP0 1"), "1行目: SRP0 には値が0個必要です");
+    assert_eq!(error(&format!("NPUSHB {}", vec!["1"; 256].join(" "))), "1行目: NPUSH で積める値は255個までです");
+    assert_eq!(error("PUSH 70000"), "1行目: ワードに収まりません: 70000");
+    assert_eq!(error("FOO"), "1行目: 不明な命令です: FOO");
+    assert_eq!(error("PUSHB[000] x"), "1行目: 値を解釈できません: x");
+  }
+
+  #[test]
+  fn truncated_push_data_is_an_error() {
+    assert!(disassemble(&[0xB1, 0x01]).is_err());
+    assert!(disassemble(&[0x40]).is_err());
+    assert!(disassemble(&[0x41, 0x01, 0x00]).is_err());
+  }
+}
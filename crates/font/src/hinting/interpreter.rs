@@ -0,0 +1,1534 @@
+use std::collections::HashMap;
+use super::state::{GraphicsState, RoundState, Vector, Zone, TOUCHED_X, TOUCHED_Y, X_AXIS, Y_AXIS};
+use super::trace::{PointMove, StateChange, TraceStep};
+use super::HintingTarget;
+
+// 関数呼び出しの深さの上限
+const MAX_CALL_DEPTH: usize = 32;
+// 1つのプログラムで実行する命令数の上限。壊れたフォントの無限ループを止める
+const MAX_INSTRUCTIONS: usize = 1_000_000;
+// GETINFO が返すインタプリタのバージョン（FreeType の v40 と同じ）
+const INTERPRETER_VERSION: i32 = 40;
+
+pub const TWILIGHT_ZONE: usize = 0;
+pub const GLYPH_ZONE: usize = 1;
+
+// 命令列の種類。関数はどの命令列の中で定義されたかを覚えておく
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeRange {
+  // fpgm
+  Font,
+  // prep
+  ControlValue,
+  // グリフの命令
+  Glyph,
+}
+
+// FDEF / IDEF で定義された命令列の範囲。end は ENDF の位置
+#[derive(Debug, Clone, Copy)]
+pub struct Definition {
+  pub range: CodeRange,
+  pub start: usize,
+  pub end: usize,
+}
+
+pub struct Code<'a> {
+  pub font: &'a [u8],
+  pub control_value: &'a [u8],
+  pub glyph: &'a [u8],
+}
+
+impl<'a> Code<'a> {
+  fn get(&self, range: CodeRange) -> &'a [u8] {
+    match range {
+      CodeRange::Font => self.font,
+      CodeRange::ControlValue => self.control_value,
+      CodeRange::Glyph => self.glyph,
+    }
+  }
+}
+
+// 呼び出し中の関数。remaining は LOOPCALL の残り回数
+struct Frame {
+  range: CodeRange,
+  return_ip: usize,
+  definition: Definition,
+  remaining: i32,
+}
+
+// a * b / c を四捨五入する（符号は絶対値で計算してから付ける）。c が 0 なら最大値
+pub(crate) fn mul_div(a: i32, b: i32, c: i32) -> i32 {
+  let negative = (a < 0) ^ (b < 0) ^ (c < 0);
+  let (a, b, c) = ((a as i64).abs(), (b as i64).abs(), (c as i64).abs());
+  let d = if c > 0 { ((a * b + c / 2) / c).min(i32::MAX as i64) as i32 } else { i32::MAX };
+  if negative { -d } else { d }
+}
+
+// a * b / c を切り捨てる
+fn mul_div_no_round(a: i32, b: i32, c: i32) -> i32 {
+  let negative = (a < 0) ^ (b < 0) ^ (c < 0);
+  let (a, b, c) = ((a as i64).abs(), (b as i64).abs(), (c as i64).abs());
+  let d = if c > 0 { (a * b / c).min(i32::MAX as i64) as i32 } else { i32::MAX };
+  if negative { -d } else { d }
+}
+
+// 16.16 固定小数点の掛け算と割り算
+pub(crate) fn mul_fix(a: i32, b: i32) -> i32 {
+  mul_div(a, b, 0x10000)
+}
+
+pub(crate) fn div_fix(a: i32, b: i32) -> i32 {
+  mul_div(a, 0x10000, b)
+}
+
+// 2.14 の値との掛け算
+fn mul14(a: i32, b: i32) -> i32 {
+  let ab = a as i64 * b as i64;
+  ((ab + 0x2000 + (ab >> 63)) >> 14) as i32
+}
+
+// 2.14 の単位ベクトルへの射影
+fn dot14(a: (i32, i32), b: Vector) -> i32 {
+  let t = a.0 as i64 * b.0 as i64 + a.1 as i64 * b.1 as i64;
+  ((t + 0x2000 + (t >> 63)) >> 14) as i32
+}
+
+fn difference(a: (i32, i32), b: (i32, i32)) -> (i32, i32) {
+  (a.0.wrapping_sub(b.0), a.1.wrapping_sub(b.1))
+}
+
+// (x, y) 方向の 2.14 の単位ベクトル。長さ 0 なら None
+// 浮動小数点で正規化すると FreeType と1単位ずれることがあるので、同じ整数のニュートン法で求める
+fn normalize(x: i32, y: i32) -> Option<Vector> {
+  if x == 0 && y == 0 {
+    return None;
+  }
+  let (sx, sy) = (x.signum(), y.signum());
+  let (mut ux, mut uy) = (x.unsigned_abs(), y.unsigned_abs());
+  if ux == 0 {
+    return Some((0, sy * 0x4000));
+  }
+  if uy == 0 {
+    return Some((sx * 0x4000, 0));
+  }
+
+  // 長さがおよそ 2/3〜4/3（16.16）になるようにずらしてから近似する
+  let estimate = |x: u32, y: u32| if x > y { x + (y >> 1) } else { y + (x >> 1) };
+  let mut length = estimate(ux, uy);
+  let msb = 31 - length.leading_zeros() as i32;
+  let mut shift = 31 - msb;
+  shift -= 15 + (length >= (0xAAAA_AAAAu32 >> shift)) as i32;
+  if shift > 0 {
+    ux <<= shift;
+    uy <<= shift;
+    length = estimate(ux, uy);
+  } else {
+    ux >>= -shift;
+    uy >>= -shift;
+    length >>= -shift;
+  }
+
+  // 長さの逆数から 1 を引いた値をニュートン法で詰める
+  let mut b = 0x10000 - length as i32;
+  let (x, y) = (ux as i32, uy as i32);
+  let (mut u, mut v);
+  loop {
+    u = x.wrapping_add(x.wrapping_mul(b) >> 16) as u32;
+    v = y.wrapping_add(y.wrapping_mul(b) >> 16) as u32;
+    let z = (u.wrapping_mul(u).wrapping_add(v.wrapping_mul(v)) as i32).wrapping_neg() / 0x200;
+    let z = z.wrapping_mul((0x10000 + b) >> 8) / 0x10000;
+    b += z;
+    if z <= 0 {
+      break;
+    }
+  }
+  Some((sx * (u as i32) / 4, sy * (v as i32) / 4))
+}
+
+// 2点を結ぶ直線の向き。perpendicular なら反時計回りに90度回す。2点が重なっていれば x 軸
+fn line_vector(a: (i32, i32), b: (i32, i32), perpendicular: bool) -> Vector {
+  let (x, y) = difference(a, b);
+  if x == 0 && y == 0 {
+    return X_AXIS;
+  }
+  let (x, y) = if perpendicular { (y.wrapping_neg(), x) } else { (x, y) };
+  normalize(x, y).unwrap_or(X_AXIS)
+}
+
+// 命令の長さ。プッシュ命令は後に続くデータを含める
+fn instruction_length(bytecode: &[u8], ip: usize) -> Result<usize, String> {
+  let opcode = bytecode[ip];
+  let count = || bytecode.get(ip + 1).map(|&n| n as usize).ok_or("NPUSH の個数がありません".to_string());
+  Ok(match opcode {
+    0x40 => 2 + count()?,
+    0x41 => 2 + 2 * count()?,
+    0xB0..=0xB7 => 2 + (opcode - 0xB0) as usize,
+    0xB8..=0xBF => 3 + 2 * (opcode - 0xB8) as usize,
+    _ => 1,
+  })
+}
+
+// IF の偽の枝や ELSE の後ろを読み飛ばし、対応する ELSE（stop_at_else のとき）か EIF の次の位置を返す
+fn skip_branch(bytecode: &[u8], mut ip: usize, stop_at_else: bool) -> Result<usize, String> {
+  let mut depth = 0;
+  while ip < bytecode.len() {
+    match bytecode[ip] {
+      0x58 => depth += 1,
+      0x1B if depth == 0 && stop_at_else => return Ok(ip + 1),
+      0x59 => {
+        if depth == 0 {
+          return Ok(ip + 1);
+        }
+        depth -= 1;
+      }
+      _ => {}
+    }
+    ip += instruction_length(bytecode, ip)?;
+  }
+  Err("IF に対応する EIF がありません".to_string())
+}
+
+// 関数定義の終わり（ENDF）の位置
+fn find_end_of_function(bytecode: &[u8], mut ip: usize) -> Result<usize, String> {
+  while ip < bytecode.len() {
+    match bytecode[ip] {
+      0x2D => return Ok(ip),
+      0x2C | 0x89 => return Err("関数定義が入れ子になっています".to_string()),
+      _ => {}
+    }
+    ip += instruction_length(bytecode, ip)?;
+  }
+  Err("FDEF に対応する ENDF がありません".to_string())
+}
+
+// 相対ジャンプの行き先。offset はジャンプ命令の位置からの距離
+fn jump(ip: usize, offset: i32, length: usize) -> Result<usize, String> {
+  let target = ip as i64 + offset as i64;
+  if target < 0 || target > length as i64 {
+    return Err(format!("ジャンプ先 {} が命令列の範囲外です", target));
+  }
+  Ok(target as usize)
+}
+
+// 1つの IUP の区間 p1..=p2 を、参照点 ref1, ref2 の動きに合わせて補間する
+fn interpolate(current: &mut [i32], original: &[i32], unscaled: &[i32], p1: usize, p2: usize, ref1: usize, ref2: usize) {
+  if p1 > p2 {
+    return;
+  }
+  let (ref1, ref2) = if unscaled[ref1] > unscaled[ref2] { (ref2, ref1) } else { (ref1, ref2) };
+  let (orus1, orus2) = (unscaled[ref1], unscaled[ref2]);
+  let (org1, org2) = (original[ref1], original[ref2]);
+  let (cur1, cur2) = (current[ref1], current[ref2]);
+  let (delta1, delta2) = (cur1.wrapping_sub(org1), cur2.wrapping_sub(org2));
+  // 参照点が重なっているときは間の点を片方にそろえる
+  let trivial = cur1 == cur2 || orus1 == orus2;
+  let scale = if trivial { 0 } else { div_fix(cur2.wrapping_sub(cur1), orus2 - orus1) };
+  for i in p1..=p2 {
+    let x = original[i];
+    current[i] = if x <= org1 {
+      x.wrapping_add(delta1)
+    } else if x >= org2 {
+      x.wrapping_add(delta2)
+    } else if trivial {
+      cur1
+    } else {
+      cur1.wrapping_add(mul_fix(unscaled[i] - orus1, scale))
+    };
+  }
+}
+
+// 触れられた点が1つだけの輪郭は、全体をその点と同じだけずらす
+fn shift(current: &mut [i32], original: &[i32], first: usize, last: usize, reference: usize) {
+  let delta = current[reference].wrapping_sub(original[reference]);
+  for (i, value) in current.iter_mut().enumerate().take(last + 1).skip(first) {
+    if i != reference {
+      *value = value.wrapping_add(delta);
+    }
+  }
+}
+
+// TrueType の命令を実行する仮想機械。fpgm と prep を実行した後の状態がサイズごとのインスタンスになる
+#[derive(Debug, Clone)]
+pub struct Machine {
+  pub ppem: i32,
+  // フォント単位から 26.6 への倍率（16.16）
+  pub scale: i32,
+  pub target: HintingTarget,
+  // 正規化したバリアブルフォントの座標（2.14）
+  pub coords: Vec<i32>,
+  pub functions: HashMap<i32, Definition>,
+  pub instructions: HashMap<u8, Definition>,
+  // 拡大縮小した制御値（26.6）
+  pub cvt: Vec<i32>,
+  pub storage: Vec<i32>,
+  pub zones: [Zone; 2],
+  pub state: GraphicsState,
+  pub max_stack: usize,
+  pub backward_compatibility: bool,
+  // Some なら実行した命令をここに記録する
+  pub trace: Option<Vec<TraceStep>>,
+  stack: Vec<i32>,
+  program: CodeRange,
+  // IUP[x], IUP[y] が呼ばれたか
+  iup_called: [bool; 2],
+}
+
+impl Machine {
+  pub fn new(ppem: i32, scale: i32, target: HintingTarget, cvt: Vec<i32>, storage: usize, twilight: usize, max_stack: usize) -> Self {
+    Machine {
+      ppem,
+      scale,
+      target,
+      coords: Vec::new(),
+      functions: HashMap::new(),
+      instructions: HashMap::new(),
+      cvt,
+      storage: vec![0; storage],
+      zones: [Zone::twilight(twilight), Zone::default()],
+      state: GraphicsState::default(),
+      max_stack,
+      backward_compatibility: false,
+      trace: None,
+      stack: Vec::new(),
+      program: CodeRange::Font,
+      iup_called: [false; 2],
+    }
+  }
+
+  // program の命令列を最初から実行する
+  pub fn execute(&mut self, code: &Code, program: CodeRange) -> Result<(), String> {
+    self.program = program;
+    self.stack.clear();
+    // FreeType の v40 と同じく、白黒以外では ClearType 向けと宣言していないフォントの x 方向の動きを止める
+    self.backward_compatibility = self.target != HintingTarget::Mono && self.state.instruct_control & 4 == 0;
+    self.iup_called = [false; 2];
+
+    let mut range = program;
+    let mut ip = 0;
+    let mut calls: Vec<Frame> = Vec::new();
+    let mut executed = 0;
+    loop {
+      let bytecode = code.get(range);
+      if ip >= bytecode.len() {
+        if calls.is_empty() {
+          return Ok(());
+        }
+        return Err("関数が ENDF で終わっていません".to_string());
+      }
+      executed += 1;
+      if executed > MAX_INSTRUCTIONS {
+        return Err("実行した命令の数が上限を超えました".to_string());
+      }
+
+      let opcode = bytecode[ip];
+      let mut next = ip + 1;
+      // 実行前の状態と点を覚えておき、実行後に比べる。失敗しても命令は記録に残す
+      let before = self.trace.is_some().then(|| (self.state, [self.zones[0].current.clone(), self.zones[1].current.clone()]));
+      if let Some(trace) = &mut self.trace {
+        trace.push(TraceStep {
+          program: range,
+          offset: ip,
+          opcode,
+          depth: calls.len(),
+          stack: self.stack.clone(),
+          state_changes: Vec::new(),
+          moves: Vec::new(),
+        });
+      }
+      match opcode {
+        // NPUSHB, NPUSHW, PUSHB, PUSHW
+        0x40 | 0x41 | 0xB0..=0xBF => next = self.push_data(bytecode, ip)?,
+        // IF
+        0x58 => {
+          if self.pop()? == 0 {
+            next = skip_branch(bytecode, next, true)?;
+          }
+        }
+        // ELSE（真の枝を実行し終えた）
+        0x1B => next = skip_branch(bytecode, next, false)?,
+        // EIF
+        0x59 => {}
+        // JMPR
+        0x1C => {
+          let offset = self.pop()?;
+          next = jump(ip, offset, bytecode.len())?;
+        }
+        // JROT, JROF
+        0x78 | 0x79 => {
+          let condition = self.pop()?;
+          let offset = self.pop()?;
+          if (condition != 0) == (opcode == 0x78) {
+            next = jump(ip, offset, bytecode.len())?;
+          }
+        }
+        // FDEF, IDEF
+        0x2C | 0x89 => {
+          if range == CodeRange::Glyph {
+            return Err("グリフの命令の中で関数は定義できません".to_string());
+          }
+          let id = self.pop()?;
+          let end = find_end_of_function(bytecode, next)?;
+          let definition = Definition { range, start: next, end };
+          if opcode == 0x2C {
+            self.functions.insert(id, definition);
+          } else {
+            self.instructions.insert(id as u8, definition);
+          }
+          next = end + 1;
+        }
+        // CALL, LOOPCALL
+        0x2B | 0x2A => {
+          let id = self.pop()?;
+          let count = if opcode == 0x2A { self.pop()? } else { 1 };
+          let definition = *self.functions.get(&id).ok_or(format!("関数{}は定義されていません", id))?;
+          if count > 0 {
+            if calls.len() >= MAX_CALL_DEPTH {
+              return Err("関数の呼び出しが深すぎます".to_string());
+            }
+            calls.push(Frame { range, return_ip: next, definition, remaining: count });
+            range = definition.range;
+            next = definition.start;
+          }
+        }
+        // ENDF
+        0x2D => {
+          let frame = calls.last_mut().ok_or("呼び出されていない ENDF があります")?;
+          frame.remaining -= 1;
+          if frame.remaining > 0 {
+            next = frame.definition.start;
+          } else {
+            range = frame.range;
+            next = frame.return_ip;
+            calls.pop();
+          }
+        }
+        _ => {
+          if !self.instruction(opcode)? {
+            // IDEF で定義された命令は引数なしの関数として呼ぶ
+            let definition = *self.instructions.get(&opcode).ok_or(format!("不明な命令です: 0x{:02X}", opcode))?;
+            if calls.len() >= MAX_CALL_DEPTH {
+              return Err("関数の呼び出しが深すぎます".to_string());
+            }
+            calls.push(Frame { range, return_ip: next, definition, remaining: 1 });
+            range = definition.range;
+            next = definition.start;
+          }
+        }
+      }
+      if let Some((state, points)) = before {
+        self.record_changes(&state, &points);
+      }
+      ip = next;
+    }
+  }
+
+  // 最後に記録した命令に、実行後のスタックと状態・点の変化を書き込む
+  fn record_changes(&mut self, state: &GraphicsState, points: &[Vec<(i32, i32)>; 2]) {
+    let state_changes = state.fields().into_iter().zip(self.state.fields())
+      .filter(|(before, after)| before.1 != after.1)
+      .map(|((field, before), (_, after))| StateChange { field, before, after })
+      .collect();
+    let mut moves = Vec::new();
+    for (zone, points) in points.iter().enumerate() {
+      for (point, (&before, &after)) in points.iter().zip(&self.zones[zone].current).enumerate() {
+        if before != after {
+          moves.push(PointMove { zone, point, before, after });
+        }
+      }
+    }
+    let stack = self.stack.clone();
+    if let Some(step) = self.trace.as_mut().and_then(|trace| trace.last_mut()) {
+      step.stack = stack;
+      step.state_changes = state_changes;
+      step.moves = moves;
+    }
+  }
+
+  fn pop(&mut self) -> Result<i32, String> {
+    self.stack.pop().ok_or("スタックが空です".to_string())
+  }
+
+  fn push(&mut self, value: i32) -> Result<(), String> {
+    if self.stack.len() >= self.max_stack {
+      return Err("スタックがあふれました".to_string());
+    }
+    self.stack.push(value);
+    Ok(())
+  }
+
+  fn push_data(&mut self, bytecode: &[u8], ip: usize) -> Result<usize, String> {
+    let opcode = bytecode[ip];
+    let (count, words, start) = match opcode {
+      0x40 | 0x41 => {
+        let count = *bytecode.get(ip + 1).ok_or("NPUSH の個数がありません")? as usize;
+        (count, opcode == 0x41, ip + 2)
+      }
+      0xB0..=0xB7 => ((opcode - 0xB0) as usize + 1, false, ip + 1),
+      _ => ((opcode - 0xB8) as usize + 1, true, ip + 1),
+    };
+    let size = if words { 2 } else { 1 };
+    let end = start + count * size;
+    let data = bytecode.get(start..end).ok_or("プッシュするデータが命令列の外にあります")?;
+    for chunk in data.chunks(size) {
+      let value = if words { i16::from_be_bytes([chunk[0], chunk[1]]) as i32 } else { chunk[0] as i32 };
+      self.push(value)?;
+    }
+    Ok(end)
+  }
+
+  // SLOOP で指定された数の点番号を取り出す
+  fn pop_loop(&mut self) -> Result<Vec<i32>, String> {
+    let count = self.state.loop_count as usize;
+    self.state.loop_count = 1;
+    (0..count).map(|_| self.pop()).collect()
+  }
+
+  // zp0, zp1, zp2 (pointer = 0, 1, 2) が指すゾーン
+  fn zone(&self, pointer: usize) -> &Zone {
+    &self.zones[self.state.zone_pointers[pointer]]
+  }
+
+  fn zone_mut(&mut self, pointer: usize) -> &mut Zone {
+    &mut self.zones[self.state.zone_pointers[pointer]]
+  }
+
+  fn point(&self, pointer: usize, point: usize) -> Result<usize, String> {
+    if point < self.zone(pointer).len() {
+      Ok(point)
+    } else {
+      Err(format!("点{}がゾーンの範囲外です", point as isize))
+    }
+  }
+
+  fn pop_point(&mut self, pointer: usize) -> Result<usize, String> {
+    let point = self.pop()?;
+    self.point(pointer, point as usize)
+  }
+
+  fn read_cvt(&self, index: i32) -> Result<i32, String> {
+    self.cvt.get(index as usize).copied().ok_or(format!("制御値{}が範囲外です", index))
+  }
+
+  fn project(&self, v: (i32, i32)) -> i32 {
+    dot14(v, self.state.projection_vector)
+  }
+
+  fn dual_project(&self, v: (i32, i32)) -> i32 {
+    dot14(v, self.state.dual_vector)
+  }
+
+  // 射影ベクトルと自由ベクトルの内積。直交に近いと移動量が発散するので、そのときは 1 とみなす
+  fn f_dot_p(&self) -> i32 {
+    let (p, f) = (self.state.projection_vector, self.state.freedom_vector);
+    let value = if f.0 == 0x4000 {
+      p.0
+    } else if f.1 == 0x4000 {
+      p.1
+    } else {
+      ((p.0 as i64 * f.0 as i64 + p.1 as i64 * f.1 as i64) >> 14) as i32
+    };
+    if value.abs() < 0x400 { 0x4000 } else { value }
+  }
+
+  // 互換モードでは x 方向の移動と、IUP を両軸とも済ませた後の y 方向の移動を捨てる（触れた印は付ける）
+  fn blocked(&self) -> (bool, bool) {
+    (self.backward_compatibility, self.backward_compatibility && self.iup_called == [true, true])
+  }
+
+  // 射影ベクトル方向の距離が distance だけ変わるように、点を自由ベクトルに沿って動かす
+  fn move_point(&mut self, pointer: usize, point: usize, distance: i32) {
+    let (freedom, f_dot_p) = (self.state.freedom_vector, self.f_dot_p());
+    let (block_x, block_y) = self.blocked();
+    let zone = self.zone_mut(pointer);
+    if freedom.0 != 0 {
+      if !block_x {
+        zone.current[point].0 = zone.current[point].0.wrapping_add(mul_div(distance, freedom.0, f_dot_p));
+      }
+      zone.touched[point] |= TOUCHED_X;
+    }
+    if freedom.1 != 0 {
+      if !block_y {
+        zone.current[point].1 = zone.current[point].1.wrapping_add(mul_div(distance, freedom.1, f_dot_p));
+      }
+      zone.touched[point] |= TOUCHED_Y;
+    }
+  }
+
+  // 元の座標を動かす（トワイライトゾーンの点を作るとき）
+  fn move_original(&mut self, pointer: usize, point: usize, distance: i32) {
+    let (freedom, f_dot_p) = (self.state.freedom_vector, self.f_dot_p());
+    let zone = self.zone_mut(pointer);
+    if freedom.0 != 0 {
+      zone.original[point].0 = zone.original[point].0.wrapping_add(mul_div(distance, freedom.0, f_dot_p));
+    }
+    if freedom.1 != 0 {
+      zone.original[point].1 = zone.original[point].1.wrapping_add(mul_div(distance, freedom.1, f_dot_p));
+    }
+  }
+
+  // zp2 の点を (dx, dy) ずらす
+  fn shift_point(&mut self, point: usize, dx: i32, dy: i32, touch: bool) {
+    let freedom = self.state.freedom_vector;
+    let (block_x, block_y) = self.blocked();
+    let zone = self.zone_mut(2);
+    if freedom.0 != 0 {
+      if !block_x {
+        zone.current[point].0 = zone.current[point].0.wrapping_add(dx);
+      }
+      if touch {
+        zone.touched[point] |= TOUCHED_X;
+      }
+    }
+    if freedom.1 != 0 {
+      if !block_y {
+        zone.current[point].1 = zone.current[point].1.wrapping_add(dy);
+      }
+      if touch {
+        zone.touched[point] |= TOUCHED_Y;
+      }
+    }
+  }
+
+  // SHP / SHC / SHZ の基準点と、その点が動いた量。opcode の最下位ビットで rp1 (zp0) か rp2 (zp1) を選ぶ
+  fn displacement(&self, opcode: u8) -> Result<(usize, usize, i32, i32), String> {
+    let (pointer, reference) = if opcode & 1 != 0 {
+      (0, self.state.reference_points[1])
+    } else {
+      (1, self.state.reference_points[2])
+    };
+    let reference = self.point(pointer, reference)?;
+    let zone = self.zone(pointer);
+    let distance = self.project(difference(zone.current[reference], zone.original[reference]));
+    let (freedom, f_dot_p) = (self.state.freedom_vector, self.f_dot_p());
+    Ok((
+      self.state.zone_pointers[pointer],
+      reference,
+      mul_div(distance, freedom.0, f_dot_p),
+      mul_div(distance, freedom.1, f_dot_p),
+    ))
+  }
+
+  // 元の輪郭での2点間の距離。トワイライトゾーンでなければフォント単位で測ってから拡大する
+  fn original_distance(&self, a: (usize, usize), b: (usize, usize)) -> i32 {
+    let (zone_a, zone_b) = (self.zone(a.0), self.zone(b.0));
+    if self.state.zone_pointers[a.0] == TWILIGHT_ZONE || self.state.zone_pointers[b.0] == TWILIGHT_ZONE {
+      self.dual_project(difference(zone_a.original[a.1], zone_b.original[b.1]))
+    } else {
+      mul_fix(self.dual_project(difference(zone_a.unscaled[a.1], zone_b.unscaled[b.1])), self.scale)
+    }
+  }
+
+  fn apply_minimum_distance(&self, distance: i32, original: i32) -> i32 {
+    let minimum = self.state.minimum_distance;
+    if original >= 0 { distance.max(minimum) } else { distance.min(minimum.wrapping_neg()) }
+  }
+
+  // DELTAP / DELTAC の引数から、この ppem での移動量を求める。対象外なら None
+  fn delta(&self, argument: i32, base: i32) -> Option<i32> {
+    if self.state.delta_base + base + ((argument & 0xF0) >> 4) != self.ppem {
+      return None;
+    }
+    let step = (argument & 0xF) - 8;
+    let step = if step >= 0 { step + 1 } else { step };
+    Some(step * (1 << (6 - self.state.delta_shift)))
+  }
+
+  // グリフの輪郭で、触れられていない点を触れられた点の間で補間する（IUP）
+  fn interpolate_untouched(&mut self, x_axis: bool) {
+    let zone = &mut self.zones[GLYPH_ZONE];
+    if zone.contour_ends.is_empty() {
+      return;
+    }
+    let mask = if x_axis { TOUCHED_X } else { TOUCHED_Y };
+    let pick = |p: &(i32, i32)| if x_axis { p.0 } else { p.1 };
+    let original: Vec<i32> = zone.original.iter().map(pick).collect();
+    let unscaled: Vec<i32> = zone.unscaled.iter().map(pick).collect();
+    let mut current: Vec<i32> = zone.current.iter().map(pick).collect();
+
+    let mut first = 0;
+    for &end in &zone.contour_ends {
+      let last = end.min(zone.len() - 1);
+      let touched: Vec<usize> = (first..=last).filter(|&i| zone.touched[i] & mask != 0).collect();
+      match touched.as_slice() {
+        [] => {}
+        &[only] => shift(&mut current, &original, first, last, only),
+        _ => {
+          for pair in touched.windows(2) {
+            interpolate(&mut current, &original, &unscaled, pair[0] + 1, pair[1] - 1, pair[0], pair[1]);
+          }
+          // 最後に触れた点から輪郭を一周して最初に触れた点まで
+          let (first_touched, last_touched) = (touched[0], touched[touched.len() - 1]);
+          interpolate(&mut current, &original, &unscaled, last_touched + 1, last, last_touched, first_touched);
+          if first_touched > first {
+            interpolate(&mut current, &original, &unscaled, first, first_touched - 1, last_touched, first_touched);
+          }
+        }
+      }
+      first = last + 1;
+    }
+
+    for (point, value) in zone.current.iter_mut().zip(current) {
+      if x_axis {
+        point.0 = value;
+      } else {
+        point.1 = value;
+      }
+    }
+  }
+
+  fn get_info(&self, selector: i32) -> i32 {
+    let mut result = 0;
+    if selector & 0x0001 != 0 {
+      result = INTERPRETER_VERSION;
+    }
+    // 回転・変形（ビット 8, 9）はしない
+    if selector & 0x0008 != 0 && !self.coords.is_empty() {
+      result |= 1 << 10;
+    }
+    // ClearType 向けのフラグは白黒以外で立てる
+    if self.target != HintingTarget::Mono {
+      if selector & 0x0040 != 0 {
+        result |= 1 << 13;
+      }
+      if selector & 0x0100 != 0 && self.target == HintingTarget::LcdVertical {
+        result |= 1 << 15;
+      }
+      if selector & 0x0400 != 0 {
+        result |= 1 << 17;
+      }
+      if selector & 0x0800 != 0 {
+        result |= 1 << 18;
+      }
+      if selector & 0x1000 != 0 && self.target == HintingTarget::Grayscale {
+        result |= 1 << 19;
+      }
+    }
+    result
+  }
+
+  // 制御の流れ以外の命令を1つ実行する。知らない命令なら false
+  fn instruction(&mut self, opcode: u8) -> Result<bool, String> {
+    match opcode {
+      // SVTCA, SPVTCA, SFVTCA
+      0x00..=0x05 => {
+        let axis = if opcode & 1 == 0 { Y_AXIS } else { X_AXIS };
+        if opcode < 0x04 {
+          self.state.projection_vector = axis;
+          self.state.dual_vector = axis;
+        }
+        if !(0x02..0x04).contains(&opcode) {
+          self.state.freedom_vector = axis;
+        }
+      }
+      // SPVTL, SFVTL
+      0x06..=0x09 => {
+        let p2 = self.pop_point(2)?;
+        let p1 = self.pop_point(1)?;
+        let vector = line_vector(self.zone(1).current[p1], self.zone(2).current[p2], opcode & 1 != 0);
+        if opcode < 0x08 {
+          self.state.projection_vector = vector;
+          self.state.dual_vector = vector;
+        } else {
+          self.state.freedom_vector = vector;
+        }
+      }
+      // SPVFS, SFVFS
+      0x0A | 0x0B => {
+        let y = self.pop()? as i16 as i32;
+        let x = self.pop()? as i16 as i32;
+        if let Some(vector) = normalize(x, y) {
+          if opcode == 0x0A {
+            self.state.projection_vector = vector;
+            self.state.dual_vector = vector;
+          } else {
+            self.state.freedom_vector = vector;
+          }
+        }
+      }
+      // GPV, GFV
+      0x0C | 0x0D => {
+        let vector = if opcode == 0x0C { self.state.projection_vector } else { self.state.freedom_vector };
+        self.push(vector.0)?;
+        self.push(vector.1)?;
+      }
+      // SFVTPV
+      0x0E => self.state.freedom_vector = self.state.projection_vector,
+      // ISECT: 直線 a0-a1 (zp1) と b0-b1 (zp0) の交点に点を置く
+      0x0F => {
+        let b1 = self.pop_point(0)?;
+        let b0 = self.pop_point(0)?;
+        let a1 = self.pop_point(1)?;
+        let a0 = self.pop_point(1)?;
+        let point = self.pop_point(2)?;
+        let (pa0, pa1) = (self.zone(1).current[a0], self.zone(1).current[a1]);
+        let (pb0, pb1) = (self.zone(0).current[b0], self.zone(0).current[b1]);
+        let (dbx, dby) = difference(pb1, pb0);
+        let (dax, day) = difference(pa1, pa0);
+        let (dx, dy) = difference(pb0, pa0);
+        let discriminant = mul_div(dax, -dby, 0x40).wrapping_add(mul_div(day, dbx, 0x40));
+        let dot_product = mul_div(dax, dbx, 0x40).wrapping_add(mul_div(day, dby, 0x40));
+        // ほぼ平行（3度未満）なら2本の中点の中点に置く
+        let position = if 19 * (discriminant as i64).abs() > (dot_product as i64).abs() {
+          let v = mul_div(dx, -dby, 0x40).wrapping_add(mul_div(dy, dbx, 0x40));
+          (pa0.0.wrapping_add(mul_div(v, dax, discriminant)), pa0.1.wrapping_add(mul_div(v, day, discriminant)))
+        } else {
+          (
+            ((pa0.0 as i64 + pa1.0 as i64 + pb0.0 as i64 + pb1.0 as i64) / 4) as i32,
+            ((pa0.1 as i64 + pa1.1 as i64 + pb0.1 as i64 + pb1.1 as i64) / 4) as i32,
+          )
+        };
+        let zone = self.zone_mut(2);
+        zone.current[point] = position;
+        zone.touched[point] |= TOUCHED_X | TOUCHED_Y;
+      }
+      // SRP0, SRP1, SRP2
+      0x10..=0x12 => self.state.reference_points[(opcode - 0x10) as usize] = self.pop()? as usize,
+      // SZP0, SZP1, SZP2, SZPS
+      0x13..=0x16 => {
+        let zone = self.pop()?;
+        if zone != 0 && zone != 1 {
+          return Err(format!("ゾーン{}は存在しません", zone));
+        }
+        match opcode {
+          0x16 => self.state.zone_pointers = [zone as usize; 3],
+          _ => self.state.zone_pointers[(opcode - 0x13) as usize] = zone as usize,
+        }
+      }
+      // SLOOP
+      0x17 => {
+        let count = self.pop()?;
+        if count < 0 {
+          return Err("SLOOP の回数が負です".to_string());
+        }
+        self.state.loop_count = count.min(0xFFFF);
+      }
+      // RTG, RTHG, RTDG, ROFF, RUTG, RDTG
+      0x18 => self.state.round_state = RoundState::Grid,
+      0x19 => self.state.round_state = RoundState::HalfGrid,
+      0x3D => self.state.round_state = RoundState::DoubleGrid,
+      0x7A => self.state.round_state = RoundState::Off,
+      0x7C => self.state.round_state = RoundState::UpToGrid,
+      0x7D => self.state.round_state = RoundState::DownToGrid,
+      // SMD, SCVTCI, SSWCI, SSW
+      0x1A => self.state.minimum_distance = self.pop()?,
+      0x1D => self.state.control_value_cutin = self.pop()?,
+      0x1E => self.state.single_width_cutin = self.pop()?,
+      0x1F => self.state.single_width_value = mul_fix(self.pop()?, self.scale),
+      // DUP
+      0x20 => {
+        let value = self.pop()?;
+        self.push(value)?;
+        self.push(value)?;
+      }
+      // POP
+      0x21 => {
+        self.pop()?;
+      }
+      // CLEAR
+      0x22 => self.stack.clear(),
+      // SWAP
+      0x23 => {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        self.push(a)?;
+        self.push(b)?;
+      }
+      // DEPTH
+      0x24 => self.push(self.stack.len() as i32)?,
+      // CINDEX, MINDEX
+      0x25 | 0x26 => {
+        let k = self.pop()?;
+        if k <= 0 || k as usize > self.stack.len() {
+          return Err(format!("スタックの{}番目の要素はありません", k));
+        }
+        let index = self.stack.len() - k as usize;
+        let value = if opcode == 0x25 { self.stack[index] } else { self.stack.remove(index) };
+        self.push(value)?;
+      }
+      // ALIGNPTS
+      0x27 => {
+        let p2 = self.pop_point(0)?;
+        let p1 = self.pop_point(1)?;
+        let distance = self.project(difference(self.zone(0).current[p2], self.zone(1).current[p1])) / 2;
+        self.move_point(1, p1, distance);
+        self.move_point(0, p2, distance.wrapping_neg());
+      }
+      // UTP
+      0x29 => {
+        let point = self.pop_point(0)?;
+        let freedom = self.state.freedom_vector;
+        let zone = self.zone_mut(0);
+        if freedom.0 != 0 {
+          zone.touched[point] &= !TOUCHED_X;
+        }
+        if freedom.1 != 0 {
+          zone.touched[point] &= !TOUCHED_Y;
+        }
+      }
+      // MDAP
+      0x2E | 0x2F => {
+        let point = self.pop_point(0)?;
+        let distance = if opcode & 1 != 0 {
+          let current = self.project(self.zone(0).current[point]);
+          self.state.round(current).wrapping_sub(current)
+        } else {
+          0
+        };
+        self.move_point(0, point, distance);
+        self.state.reference_points[0] = point;
+        self.state.reference_points[1] = point;
+      }
+      // IUP
+      0x30 | 0x31 => {
+        let x_axis = opcode & 1 != 0;
+        if self.backward_compatibility {
+          if self.iup_called == [true, true] {
+            return Ok(true);
+          }
+          self.iup_called[if x_axis { 0 } else { 1 }] = true;
+        }
+        self.interpolate_untouched(x_axis);
+      }
+      // SHP
+      0x32 | 0x33 => {
+        let points = self.pop_loop()?;
+        let (_, _, dx, dy) = self.displacement(opcode)?;
+        for point in points {
+          let point = self.point(2, point as usize)?;
+          self.shift_point(point, dx, dy, true);
+        }
+      }
+      // SHC
+      0x34 | 0x35 => {
+        let contour = self.pop()?;
+        let (zone_index, reference, dx, dy) = self.displacement(opcode)?;
+        let zp2 = self.state.zone_pointers[2];
+        let zone = self.zone(2);
+        let (start, limit) = if zp2 == TWILIGHT_ZONE {
+          // トワイライトゾーンは全体を1つの輪郭とみなす
+          if contour != 0 {
+            return Err(format!("輪郭{}は存在しません", contour));
+          }
+          (0, zone.len())
+        } else {
+          let ends = &zone.contour_ends;
+          let c = contour as usize;
+          if c >= ends.len() {
+            return Err(format!("輪郭{}は存在しません", contour));
+          }
+          (if c == 0 { 0 } else { ends[c - 1] + 1 }, ends[c] + 1)
+        };
+        for i in start..limit.min(zone.len()) {
+          if zone_index != zp2 || reference != i {
+            self.shift_point(i, dx, dy, true);
+          }
+        }
+      }
+      // SHZ。FreeType と同じく、引数のゾーンではなく zp2 を動かす。ファントム点は動かさず、触れた印も付けない
+      0x36 | 0x37 => {
+        let zone = self.pop()?;
+        if zone != 0 && zone != 1 {
+          return Err(format!("ゾーン{}は存在しません", zone));
+        }
+        let (zone_index, reference, dx, dy) = self.displacement(opcode)?;
+        let zp2 = self.state.zone_pointers[2];
+        let limit = if zp2 == TWILIGHT_ZONE {
+          self.zone(2).len()
+        } else {
+          self.zone(2).contour_ends.last().map_or(0, |&end| end + 1)
+        };
+        for i in 0..limit {
+          if zone_index != zp2 || reference != i {
+            self.shift_point(i, dx, dy, false);
+          }
+        }
+      }
+      // SHPIX
+      0x38 => {
+        let amount = self.pop()?;
+        let points = self.pop_loop()?;
+        let freedom = self.state.freedom_vector;
+        let (dx, dy) = (mul14(amount, freedom.0), mul14(amount, freedom.1));
+        for point in points {
+          let point = self.point(2, point as usize)?;
+          if self.backward_compatibility {
+            // 互換モードでは DELTAP と同じく、IUP 前に y 方向に触れた点だけを動かす（トワイライトゾーンは常に動かす）
+            let twilight = self.state.zone_pointers[2] == TWILIGHT_ZONE;
+            let touched_y = self.zone(2).touched[point] & TOUCHED_Y != 0;
+            if twilight || (self.iup_called != [true, true] && touched_y) {
+              self.shift_point(point, 0, dy, true);
+            }
+          } else {
+            self.shift_point(point, dx, dy, true);
+          }
+        }
+      }
+      // IP: rp1 と rp2 の間の位置関係を保つように点を動かす
+      0x39 => {
+        let points = self.pop_loop()?;
+        let twilight = self.state.zone_pointers.contains(&TWILIGHT_ZONE);
+        let rp1 = self.point(0, self.state.reference_points[1])?;
+        let base_original = if twilight { self.zone(0).original[rp1] } else { self.zone(0).unscaled[rp1] };
+        let base_current = self.zone(0).current[rp1];
+        let rp2 = self.state.reference_points[2];
+        let (old_range, current_range) = if rp2 < self.zone(1).len() {
+          let other = if twilight { self.zone(1).original[rp2] } else { self.zone(1).unscaled[rp2] };
+          (
+            self.dual_project(difference(other, base_original)),
+            self.project(difference(self.zone(1).current[rp2], base_current)),
+          )
+        } else {
+          (0, 0)
+        };
+        for point in points {
+          let point = self.point(2, point as usize)?;
+          let zone = self.zone(2);
+          let original = if twilight { zone.original[point] } else { zone.unscaled[point] };
+          let original_distance = self.dual_project(difference(original, base_original));
+          let current_distance = self.project(difference(zone.current[point], base_current));
+          let new_distance = if original_distance == 0 {
+            0
+          } else if old_range != 0 {
+            mul_div(original_distance, current_range, old_range)
+          } else {
+            original_distance
+          };
+          self.move_point(2, point, new_distance.wrapping_sub(current_distance));
+        }
+      }
+      // MSIRP
+      0x3A | 0x3B => {
+        let distance = self.pop()?;
+        let point = self.pop_point(1)?;
+        let rp0 = self.point(0, self.state.reference_points[0])?;
+        // トワイライトゾーンの点は rp0 から distance 離れた位置に作る
+        if self.state.zone_pointers[1] == TWILIGHT_ZONE {
+          let origin = self.zone(0).original[rp0];
+          self.zone_mut(1).original[point] = origin;
+          self.move_original(1, point, distance);
+          let zone = self.zone_mut(1);
+          zone.current[point] = zone.original[point];
+        }
+        let current = self.project(difference(self.zone(1).current[point], self.zone(0).current[rp0]));
+        self.move_point(1, point, distance.wrapping_sub(current));
+        self.state.reference_points[1] = rp0;
+        self.state.reference_points[2] = point;
+        if opcode & 1 != 0 {
+          self.state.reference_points[0] = point;
+        }
+      }
+      // ALIGNRP
+      0x3C => {
+        let points = self.pop_loop()?;
+        let rp0 = self.point(0, self.state.reference_points[0])?;
+        for point in points {
+          let point = self.point(1, point as usize)?;
+          let distance = self.project(difference(self.zone(1).current[point], self.zone(0).current[rp0]));
+          self.move_point(1, point, distance.wrapping_neg());
+        }
+      }
+      // MIAP
+      0x3E | 0x3F => {
+        let entry = self.pop()?;
+        let point = self.pop_point(0)?;
+        let mut distance = self.read_cvt(entry)?;
+        // トワイライトゾーンでは制御値の位置に点を作る
+        if self.state.zone_pointers[0] == TWILIGHT_ZONE {
+          let freedom = self.state.freedom_vector;
+          let position = (mul14(distance, freedom.0), mul14(distance, freedom.1));
+          let zone = self.zone_mut(0);
+          zone.original[point] = position;
+          zone.current[point] = position;
+        }
+        let original = self.project(self.zone(0).current[point]);
+        if opcode & 1 != 0 {
+          if distance.wrapping_sub(original).wrapping_abs() > self.state.control_value_cutin {
+            distance = original;
+          }
+          distance = self.state.round(distance);
+        }
+        self.move_point(0, point, distance.wrapping_sub(original));
+        self.state.reference_points[0] = point;
+        self.state.reference_points[1] = point;
+      }
+      // WS
+      0x42 => {
+        let value = self.pop()?;
+        let index = self.pop()?;
+        if let Some(slot) = self.storage.get_mut(index as usize) {
+          *slot = value;
+        }
+      }
+      // RS。範囲外は 0 を返す
+      0x43 => {
+        let index = self.pop()?;
+        let value = self.storage.get(index as usize).copied().unwrap_or(0);
+        self.push(value)?;
+      }
+      // WCVTP, WCVTF
+      0x44 | 0x70 => {
+        let value = self.pop()?;
+        let index = self.pop()?;
+        let value = if opcode == 0x70 { mul_fix(value, self.scale) } else { value };
+        if let Some(slot) = self.cvt.get_mut(index as usize) {
+          *slot = value;
+        }
+      }
+      // RCVT
+      0x45 => {
+        let index = self.pop()?;
+        let value = self.cvt.get(index as usize).copied().unwrap_or(0);
+        self.push(value)?;
+      }
+      // GC
+      0x46 | 0x47 => {
+        let point = self.pop_point(2)?;
+        let value = if opcode & 1 != 0 {
+          self.dual_project(self.zone(2).original[point])
+        } else {
+          self.project(self.zone(2).current[point])
+        };
+        self.push(value)?;
+      }
+      // SCFS
+      0x48 => {
+        let value = self.pop()?;
+        let point = self.pop_point(2)?;
+        let current = self.project(self.zone(2).current[point]);
+        self.move_point(2, point, value.wrapping_sub(current));
+        if self.state.zone_pointers[2] == TWILIGHT_ZONE {
+          let zone = self.zone_mut(2);
+          zone.original[point] = zone.current[point];
+        }
+      }
+      // MD。0x49 は現在の輪郭、0x4A は元の輪郭で測る
+      0x49 | 0x4A => {
+        let k = self.pop_point(1)?;
+        let l = self.pop_point(0)?;
+        let distance = if opcode & 1 != 0 {
+          self.project(difference(self.zone(0).current[l], self.zone(1).current[k]))
+        } else {
+          self.original_distance((0, l), (1, k))
+        };
+        self.push(distance)?;
+      }
+      // MPPEM
+      0x4B => self.push(self.ppem)?,
+      // MPS（72dpi とみなし、ポイント数を 26.6 で返す）
+      0x4C => self.push(self.ppem * 64)?,
+      // FLIPON, FLIPOFF
+      0x4D => self.state.auto_flip = true,
+      0x4E => self.state.auto_flip = false,
+      // DEBUG
+      0x4F => {
+        self.pop()?;
+      }
+      // LT, LTEQ, GT, GTEQ, EQ, NEQ
+      0x50..=0x55 => {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        let result = match opcode {
+          0x50 => a < b,
+          0x51 => a <= b,
+          0x52 => a > b,
+          0x53 => a >= b,
+          0x54 => a == b,
+          _ => a != b,
+        };
+        self.push(result as i32)?;
+      }
+      // ODD, EVEN
+      0x56 | 0x57 => {
+        let value = self.pop()?;
+        let value = self.state.round(value) & 127;
+        self.push((value == if opcode == 0x56 { 64 } else { 0 }) as i32)?;
+      }
+      // AND, OR
+      0x5A | 0x5B => {
+        let b = self.pop()? != 0;
+        let a = self.pop()? != 0;
+        self.push(if opcode == 0x5A { a && b } else { a || b } as i32)?;
+      }
+      // NOT
+      0x5C => {
+        let value = self.pop()?;
+        self.push((value == 0) as i32)?;
+      }
+      // DELTAP1, DELTAP2, DELTAP3
+      0x5D | 0x71 | 0x72 => {
+        let count = self.pop()?;
+        let base = match opcode {
+          0x71 => 16,
+          0x72 => 32,
+          _ => 0,
+        };
+        for _ in 0..count.max(0) {
+          let point = self.pop()?;
+          let argument = self.pop()?;
+          // 壊れた DELTAP が多いので、範囲外の点は FreeType と同じく無視する
+          let Ok(point) = self.point(0, point as usize) else {
+            continue;
+          };
+          let Some(distance) = self.delta(argument, base) else {
+            continue;
+          };
+          if self.backward_compatibility {
+            let touched_y = self.zone(0).touched[point] & TOUCHED_Y != 0;
+            if self.iup_called != [true, true] && touched_y {
+              self.move_point(0, point, distance);
+            }
+          } else {
+            self.move_point(0, point, distance);
+          }
+        }
+      }
+      // SDB, SDS
+      0x5E => self.state.delta_base = self.pop()? as u16 as i32,
+      0x5F => {
+        let shift = self.pop()?;
+        if !(0..=6).contains(&shift) {
+          return Err(format!("SDS の値 {} は範囲外です", shift));
+        }
+        self.state.delta_shift = shift;
+      }
+      // ADD, SUB, DIV, MUL
+      0x60..=0x63 => {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        let result = match opcode {
+          0x60 => a.wrapping_add(b),
+          0x61 => a.wrapping_sub(b),
+          0x62 => {
+            if b == 0 {
+              return Err("0 で割りました".to_string());
+            }
+            mul_div_no_round(a, 64, b)
+          }
+          _ => mul_div(a, b, 64),
+        };
+        self.push(result)?;
+      }
+      // ABS, NEG, FLOOR, CEILING
+      0x64..=0x67 => {
+        let value = self.pop()?;
+        let result = match opcode {
+          0x64 => value.wrapping_abs(),
+          0x65 => value.wrapping_neg(),
+          0x66 => value & !63,
+          _ => value.wrapping_add(63) & !63,
+        };
+        self.push(result)?;
+      }
+      // ROUND。エンジンの補正は 0 なので、種類（下位2ビット）は区別しない
+      0x68..=0x6B => {
+        let value = self.pop()?;
+        self.push(self.state.round(value))?;
+      }
+      // NROUND
+      0x6C..=0x6F => {}
+      // DELTAC1, DELTAC2, DELTAC3
+      0x73..=0x75 => {
+        let count = self.pop()?;
+        let base = (opcode - 0x73) as i32 * 16;
+        for _ in 0..count.max(0) {
+          let index = self.pop()?;
+          let argument = self.pop()?;
+          if let (Some(distance), true) = (self.delta(argument, base), (index as usize) < self.cvt.len()) {
+            self.cvt[index as usize] = self.cvt[index as usize].wrapping_add(distance);
+          }
+        }
+      }
+      // SROUND, S45ROUND
+      0x76 => {
+        let selector = self.pop()?;
+        self.state.set_super_round(0x4000, selector);
+        self.state.round_state = RoundState::Super;
+      }
+      0x77 => {
+        let selector = self.pop()?;
+        self.state.set_super_round(0x2D41, selector);
+        self.state.round_state = RoundState::Super45;
+      }
+      // SANGW, AA（廃止された命令。引数を捨てる）
+      0x7E | 0x7F => {
+        self.pop()?;
+      }
+      // FLIPPT
+      0x80 => {
+        let points = self.pop_loop()?;
+        if self.blocked().1 {
+          return Ok(true);
+        }
+        for point in points {
+          let zone = &mut self.zones[GLYPH_ZONE];
+          let point = point as usize;
+          if point >= zone.len() {
+            return Err(format!("点{}がゾーンの範囲外です", point as isize));
+          }
+          zone.on_curve[point] = !zone.on_curve[point];
+        }
+      }
+      // FLIPRGON, FLIPRGOFF
+      0x81 | 0x82 => {
+        let high = self.pop()? as usize;
+        let low = self.pop()? as usize;
+        let zone = &mut self.zones[GLYPH_ZONE];
+        if high >= zone.len() || low >= zone.len() {
+          return Err("FLIPRG の点がゾーンの範囲外です".to_string());
+        }
+        if !(self.backward_compatibility && self.iup_called == [true, true]) {
+          for on_curve in zone.on_curve.iter_mut().take(high + 1).skip(low) {
+            *on_curve = opcode == 0x81;
+          }
+        }
+      }
+      // SCANCTRL
+      0x85 => self.state.scan_control = self.pop()? as u16,
+      // SDPVTL
+      0x86 | 0x87 => {
+        let p2 = self.pop_point(2)?;
+        let p1 = self.pop_point(1)?;
+        let perpendicular = opcode & 1 != 0;
+        self.state.dual_vector = line_vector(self.zone(1).original[p1], self.zone(2).original[p2], perpendicular);
+        self.state.projection_vector = line_vector(self.zone(1).current[p1], self.zone(2).current[p2], perpendicular);
+      }
+      // GETINFO
+      0x88 => {
+        let selector = self.pop()?;
+        self.push(self.get_info(selector))?;
+      }
+      // ROLL
+      0x8A => {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        let c = self.pop()?;
+        self.push(b)?;
+        self.push(a)?;
+        self.push(c)?;
+      }
+      // MAX, MIN
+      0x8B | 0x8C => {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        self.push(if opcode == 0x8B { a.max(b) } else { a.min(b) })?;
+      }
+      // SCANTYPE
+      0x8D => {
+        let scan_type = self.pop()?;
+        if scan_type >= 0 {
+          self.state.scan_type = scan_type as u16;
+        }
+      }
+      // INSTCTRL。prep の中でだけ有効。ただしフラグ 3（ClearType 向け）はグリフの命令からも互換モードを切り替えられる
+      0x8E => {
+        let selector = self.pop()?;
+        let value = self.pop()?;
+        if !(1..=3).contains(&selector) {
+          return Ok(true);
+        }
+        // FreeType と同じく 0 以外の値はそのフラグとみなす
+        let flag = 1 << (selector - 1);
+        let value = if value != 0 { flag } else { 0 };
+        if self.program == CodeRange::ControlValue {
+          self.state.instruct_control = (self.state.instruct_control & !(flag as u8)) | value as u8;
+        } else if self.program == CodeRange::Glyph && selector == 3 && self.target != HintingTarget::Mono {
+          self.backward_compatibility = value != 4;
+        }
+      }
+      // GETVARIATION, GETDATA（バリアブルフォントのときだけある）
+      0x91 if !self.coords.is_empty() => {
+        for i in 0..self.coords.len() {
+          self.push(self.coords[i])?;
+        }
+      }
+      0x92 if !self.coords.is_empty() => self.push(17)?,
+      // MDRP
+      0xC0..=0xDF => {
+        let point = self.pop_point(1)?;
+        let rp0 = self.point(0, self.state.reference_points[0])?;
+        let mut original = self.original_distance((1, point), (0, rp0));
+        // 単一幅のカットインは距離の大きさで比べ、符号は元の距離に合わせる
+        let (cutin, width) = (self.state.single_width_cutin, self.state.single_width_value);
+        let magnitude = original.wrapping_abs();
+        if cutin > 0 && magnitude < width.wrapping_add(cutin) && magnitude > width.wrapping_sub(cutin) {
+          original = if original >= 0 { width } else { -width };
+        }
+        let mut distance = if opcode & 4 != 0 { self.state.round(original) } else { original };
+        if opcode & 8 != 0 {
+          distance = self.apply_minimum_distance(distance, original);
+        }
+        let current = self.project(difference(self.zone(1).current[point], self.zone(0).current[rp0]));
+        self.move_point(1, point, distance.wrapping_sub(current));
+        self.state.reference_points[1] = rp0;
+        self.state.reference_points[2] = point;
+        if opcode & 16 != 0 {
+          self.state.reference_points[0] = point;
+        }
+      }
+      // MIRP
+      0xE0..=0xFF => {
+        let entry = self.pop()?;
+        let point = self.pop_point(1)?;
+        let rp0 = self.point(0, self.state.reference_points[0])?;
+        // cvt[-1] は 0 として扱う
+        let mut cvt_distance = if entry == -1 { 0 } else { self.read_cvt(entry)? };
+        let (cutin, width) = (self.state.single_width_cutin, self.state.single_width_value);
+        if cvt_distance.wrapping_sub(width).wrapping_abs() < cutin {
+          cvt_distance = if cvt_distance >= 0 { width } else { -width };
+        }
+        if self.state.zone_pointers[1] == TWILIGHT_ZONE {
+          let (origin, freedom) = (self.zone(0).original[rp0], self.state.freedom_vector);
+          let position = (
+            origin.0.wrapping_add(mul14(cvt_distance, freedom.0)),
+            origin.1.wrapping_add(mul14(cvt_distance, freedom.1)),
+          );
+          let zone = self.zone_mut(1);
+          zone.original[point] = position;
+          zone.current[point] = position;
+        }
+        let original = self.dual_project(difference(self.zone(1).original[point], self.zone(0).original[rp0]));
+        let current = self.project(difference(self.zone(1).current[point], self.zone(0).current[rp0]));
+        if self.state.auto_flip && (original ^ cvt_distance) < 0 {
+          cvt_distance = cvt_distance.wrapping_neg();
+        }
+        let mut distance = if opcode & 4 != 0 {
+          // 制御値のカットインは2点が同じゾーンにあるときだけ
+          if self.state.zone_pointers[0] == self.state.zone_pointers[1]
+            && cvt_distance.wrapping_sub(original).wrapping_abs() > self.state.control_value_cutin
+          {
+            cvt_distance = original;
+          }
+          self.state.round(cvt_distance)
+        } else {
+          cvt_distance
+        };
+        if opcode & 8 != 0 {
+          distance = self.apply_minimum_distance(distance, original);
+        }
+        self.move_point(1, point, distance.wrapping_sub(current));
+        self.state.reference_points[1] = rp0;
+        self.state.reference_points[2] = point;
+        if opcode & 16 != 0 {
+          self.state.reference_points[0] = point;
+        }
+      }
+      _ => return Ok(false),
+    }
+    Ok(true)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // x 座標だけを持つ1つの輪郭。倍率 1.0 なので座標はそのまま 26.6 になる
+  fn machine(target: HintingTarget, cvt: Vec<i32>, xs: &[i32]) -> Machine {
+    let mut machine = Machine::new(12, 0x10000, target, cvt, 0, 0, 32);
+    let points: Vec<(i32, i32)> = xs.iter().map(|&x| (x, 0)).collect();
+    machine.zones[GLYPH_ZONE] = Zone {
+      original: points.clone(),
+      current: points.clone(),
+      unscaled: points,
+      on_curve: vec![true; xs.len()],
+      touched: vec![0; xs.len()],
+      contour_ends: vec![xs.len() - 1],
+    };
+    machine
+  }
+
+  fn run(machine: &mut Machine, glyph: &[u8]) {
+    let code = Code { font: &[], control_value: &[], glyph };
+    machine.execute(&code, CodeRange::Glyph).unwrap();
+  }
+
+  fn x(machine: &Machine, point: usize) -> i32 {
+    machine.zones[GLYPH_ZONE].current[point].0
+  }
+
+  #[test]
+  fn stack_operations() {
+    let mut m = machine(HintingTarget::Mono, vec![], &[0]);
+    // PUSHB[2] 3 5, SWAP, DUP, ADD, SUB, DEPTH
+    run(&mut m, &[0xB1, 3, 5, 0x23, 0x20, 0x60, 0x61, 0x24]);
+    assert_eq!(m.stack, vec![-1, 1]);
+  }
+
+  #[test]
+  fn mdrp_rounds_and_keeps_the_minimum_distance() {
+    let mut m = machine(HintingTarget::Mono, vec![], &[0, 100, -100, 10]);
+    // MDRP[min, rnd] を点 1, 2, 3 に
+    run(&mut m, &[0xB2, 3, 2, 1, 0xCC, 0xCC, 0xCC]);
+    assert_eq!((x(&m, 1), x(&m, 2), x(&m, 3)), (128, -128, 64));
+  }
+
+  #[test]
+  fn mdrp_single_width_cut_in_compares_magnitudes() {
+    let mut m = machine(HintingTarget::Mono, vec![], &[0, -100, 100]);
+    // SSW 64, SSWCI 40 の後で MDRP[min, rnd]。-100 も 100 も幅 64 に吸い寄せられる
+    run(&mut m, &[0xB3, 2, 1, 64, 40, 0x1E, 0x1F, 0xCC, 0xCC]);
+    assert_eq!((x(&m, 1), x(&m, 2)), (-64, 64));
+  }
+
+  #[test]
+  fn mirp_uses_the_cvt_within_the_cut_in() {
+    // 元の距離 100 と cvt 160 の差は 68 以内なので cvt を丸めて 192
+    let mut m = machine(HintingTarget::Mono, vec![160], &[0, 100]);
+    run(&mut m, &[0xB1, 1, 0, 0xEC]);
+    assert_eq!(x(&m, 1), 192);
+    // 差が大きければ元の距離を丸めて 128
+    let mut m = machine(HintingTarget::Mono, vec![300], &[0, 100]);
+    run(&mut m, &[0xB1, 1, 0, 0xEC]);
+    assert_eq!(x(&m, 1), 128);
+  }
+
+  #[test]
+  fn iup_interpolates_and_shifts() {
+    let mut m = machine(HintingTarget::Mono, vec![], &[0, 50, 100, 150]);
+    let zone = &mut m.zones[GLYPH_ZONE];
+    zone.current[2].0 = 200;
+    zone.touched[0] = TOUCHED_X;
+    zone.touched[2] = TOUCHED_X;
+    run(&mut m, &[0x31]);
+    // 間の点は比例して、範囲外の点は近い方の端と同じだけ動く
+    assert_eq!((x(&m, 0), x(&m, 1), x(&m, 2), x(&m, 3)), (0, 100, 200, 250));
+  }
+
+  #[test]
+  fn deltap_moves_only_at_its_ppem() {
+    // 引数 0x3F は ppem 9 + 3 = 12 で +8 段（delta_shift 3 なので 1 ピクセル）
+    let glyph = [0xB2, 0x3F, 1, 1, 0x5D];
+    let mut m = machine(HintingTarget::Mono, vec![], &[0, 100]);
+    run(&mut m, &glyph);
+    assert_eq!(x(&m, 1), 164);
+    let mut m = machine(HintingTarget::Mono, vec![], &[0, 100]);
+    m.ppem = 13;
+    run(&mut m, &glyph);
+    assert_eq!(x(&m, 1), 100);
+  }
+
+  #[test]
+  fn backward_compatibility_blocks_x_moves_until_instctrl() {
+    let mdrp = [0xB0, 1, 0xCC];
+    let mut m = machine(HintingTarget::Grayscale, vec![], &[0, 100]);
+    run(&mut m, &mdrp);
+    assert_eq!(x(&m, 1), 100);
+    assert_eq!(m.zones[GLYPH_ZONE].touched[1], TOUCHED_X);
+
+    // prep で INSTCTRL[3] に 0 以外の値を渡すとフラグ 4 になり、x 方向にも動かせる
+    let mut m = machine(HintingTarget::Grayscale, vec![], &[0, 100]);
+    let code = Code { font: &[], control_value: &[0xB1, 1, 3, 0x8E], glyph: &mdrp };
+    m.execute(&code, CodeRange::ControlValue).unwrap();
+    assert_eq!(m.state.instruct_control, 4);
+    m.execute(&code, CodeRange::Glyph).unwrap();
+    assert_eq!(x(&m, 1), 128);
+  }
+
+  #[test]
+  fn getvariation_pushes_the_coordinates() {
+    let mut m = machine(HintingTarget::Mono, vec![], &[0]);
+    m.coords = vec![0x2000, -0x4000];
+    run(&mut m, &[0x91]);
+    assert_eq!(m.stack, vec![0x2000, -0x4000]);
+  }
+}
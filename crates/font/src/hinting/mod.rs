@@ -0,0 +1,239 @@
+pub mod disasm;
+pub mod interpreter;
+pub mod state;
+pub mod trace;
+
+use std::collections::HashMap;
+use crate::sink::OutlineSink;
+use crate::tables::cvar::CvarTable;
+use crate::tables::cvt::CvtTable;
+use crate::tables::maxp::MaxpTable;
+use crate::truetype::outline::{draw_contour, PathSegment};
+use crate::truetype::SimpleGlyph;
+use interpreter::{div_fix, mul_fix, Code, CodeRange, Machine, GLYPH_ZONE};
+use state::{GraphicsState, RoundState, Zone};
+use trace::Trace;
+
+// ヒンティングの対象。GETINFO の答えと、x 方向の互換モードを使うかが変わる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HintingTarget {
+  Mono,
+  Grayscale,
+  Lcd,
+  LcdVertical,
+}
+
+// ファントム点を置くのに必要なグリフのメトリクス（フォント単位）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GlyphMetrics {
+  pub x_min: i16,
+  pub left_side_bearing: i16,
+  pub advance_width: u16,
+  pub ascender: i16,
+  pub descender: i16,
+}
+
+// ヒンティングした輪郭。座標はピクセル単位で、原点はペンの位置
+#[derive(Debug, Clone)]
+pub struct HintedGlyph {
+  pub points: Vec<(f32, f32, bool)>,
+  pub contour_ends: Vec<usize>,
+  // グリッドに合わせた送り幅（ピクセル）
+  pub advance_width: f32,
+  // 命令が SCANCTRL / SCANTYPE で指定したドロップアウト制御
+  pub scan_control: u16,
+  pub scan_type: u16,
+}
+
+impl HintedGlyph {
+  pub fn segments(&self) -> Vec<PathSegment> {
+    let mut segments = Vec::new();
+    self.draw(&mut segments);
+    segments
+  }
+
+  pub fn draw<S: OutlineSink + ?Sized>(&self, sink: &mut S) {
+    let mut start = 0;
+    for &end in &self.contour_ends {
+      draw_contour(&self.points[start..=end], sink);
+      start = end + 1;
+    }
+  }
+}
+
+// フォントのヒンティング命令を実行する。fpgm と prep を実行した状態を ppem と対象ごとに覚えておく
+pub struct Hinter {
+  font_program: Vec<u8>,
+  control_value_program: Vec<u8>,
+  base_cvt: Vec<i16>,
+  cvar: Option<CvarTable>,
+  // バリエーションを適用した制御値（フォント単位）
+  cvt: Vec<i32>,
+  // 正規化したバリアブルフォントの座標（2.14）。軸ごとに1つで、バリアブルフォントでなければ空
+  coords: Vec<i32>,
+  units_per_em: u16,
+  max_storage: usize,
+  max_twilight_points: usize,
+  max_stack_elements: usize,
+  instances: HashMap<(u16, HintingTarget), Machine>,
+}
+
+impl Hinter {
+  // axis_count は fvar の軸の数。座標を設定するまではどの軸も既定値（0）
+  pub fn new(font_program: Vec<u8>, control_value_program: Vec<u8>, cvt: CvtTable, cvar: Option<CvarTable>, axis_count: usize, maxp: &MaxpTable, units_per_em: u16) -> Self {
+    Hinter {
+      font_program,
+      control_value_program,
+      cvt: cvt.values.iter().map(|&v| v as i32).collect(),
+      base_cvt: cvt.values,
+      cvar,
+      coords: vec![0; axis_count],
+      units_per_em,
+      max_storage: maxp.max_storage.unwrap_or(0) as usize,
+      max_twilight_points: maxp.max_twilight_points.unwrap_or(0) as usize,
+      max_stack_elements: maxp.max_stack_elements.unwrap_or(0) as usize,
+      instances: HashMap::new(),
+    }
+  }
+
+  // バリアブルフォントの正規化座標を設定する。制御値が変わるので作ったインスタンスは捨てる
+  // 足りない軸は既定値、余分な値は捨てる
+  pub fn set_coords(&mut self, coords: &[f32]) {
+    for (i, c) in self.coords.iter_mut().enumerate() {
+      *c = coords.get(i).map_or(0, |&c| (c.clamp(-1.0, 1.0) * 16384.0).round() as i32);
+    }
+    let deltas = match &self.cvar {
+      Some(cvar) => cvar.deltas(coords, self.base_cvt.len()),
+      None => vec![0.0; self.base_cvt.len()],
+    };
+    // FreeType と同じく差分を足した値を整数に切り捨てる
+    self.cvt = self.base_cvt.iter().zip(deltas).map(|(&v, d)| (v as f32 + d) as i32).collect();
+    self.instances.clear();
+  }
+
+  fn code<'a>(&'a self, glyph: &'a [u8]) -> Code<'a> {
+    Code { font: &self.font_program, control_value: &self.control_value_program, glyph }
+  }
+
+  fn create_instance(&self, ppem: u16, target: HintingTarget) -> Result<Machine, String> {
+    let scale = div_fix(ppem as i32 * 64, self.units_per_em.max(1) as i32);
+    let cvt = self.cvt.iter().map(|&v| mul_fix(v, scale)).collect();
+    // スタックとトワイライトゾーンは maxp の値より少し余裕を持たせる
+    let mut machine = Machine::new(
+      ppem as i32,
+      scale,
+      target,
+      cvt,
+      self.max_storage,
+      self.max_twilight_points + 4,
+      self.max_stack_elements + 32,
+    );
+    // GETVARIATION は cvar がなくてもバリアブルフォントなら座標を返す
+    machine.coords = self.coords.clone();
+    let code = self.code(&[]);
+    machine.execute(&code, CodeRange::Font).map_err(|e| format!("fpgm: {}", e))?;
+    machine.state = GraphicsState::default();
+    machine.execute(&code, CodeRange::ControlValue).map_err(|e| format!("prep: {}", e))?;
+    machine.state.reset_for_glyph();
+    Ok(machine)
+  }
+
+  // ppem と対象に対応するインスタンス。なければ fpgm と prep を実行して作る
+  pub fn instance(&mut self, ppem: u16, target: HintingTarget) -> Result<&Machine, String> {
+    let key = (ppem, target);
+    if !self.instances.contains_key(&key) {
+      let machine = self.create_instance(ppem, target)?;
+      self.instances.insert(key, machine);
+    }
+    Ok(&self.instances[&key])
+  }
+
+  pub fn hint_glyph(&mut self, glyph: &SimpleGlyph, metrics: &GlyphMetrics, ppem: u16, target: HintingTarget) -> Result<HintedGlyph, String> {
+    Ok(self.hint(glyph, metrics, ppem, target, false)?.0)
+  }
+
+  // hint_glyph と同じくヒンティングし、グリフの命令（そこから呼んだ関数を含む）の実行記録も返す
+  pub fn trace_glyph(&mut self, glyph: &SimpleGlyph, metrics: &GlyphMetrics, ppem: u16, target: HintingTarget) -> Result<(HintedGlyph, Trace), String> {
+    let (hinted, trace) = self.hint(glyph, metrics, ppem, target, true)?;
+    Ok((hinted, trace.unwrap_or_default()))
+  }
+
+  fn hint(&mut self, glyph: &SimpleGlyph, metrics: &GlyphMetrics, ppem: u16, target: HintingTarget, trace: bool) -> Result<(HintedGlyph, Option<Trace>), String> {
+    let mut machine = self.instance(ppem, target)?.clone();
+    let instruct_control = machine.state.instruct_control;
+    if instruct_control & 2 != 0 {
+      machine.state = GraphicsState { instruct_control, ..GraphicsState::default() };
+    }
+
+    // 輪郭の点の後ろに4つのファントム点（左右のサイドベアリングと上下）を足す
+    let n = glyph.points.len();
+    let origin = metrics.x_min as i32 - metrics.left_side_bearing as i32;
+    let mut unscaled: Vec<(i32, i32)> = glyph.points.iter().map(|p| (p.x as i32, p.y as i32)).collect();
+    unscaled.extend([
+      (origin, 0),
+      (origin + metrics.advance_width as i32, 0),
+      (0, metrics.ascender as i32),
+      (0, metrics.descender as i32),
+    ]);
+    let original: Vec<(i32, i32)> = unscaled.iter().map(|&(x, y)| (mul_fix(x, machine.scale), mul_fix(y, machine.scale))).collect();
+    let mut current = original.clone();
+    let grid = GraphicsState { round_state: RoundState::Grid, ..GraphicsState::default() };
+    current[n].0 = grid.round(current[n].0);
+    current[n + 1].0 = grid.round(current[n + 1].0);
+    current[n + 2].1 = grid.round(current[n + 2].1);
+    current[n + 3].1 = grid.round(current[n + 3].1);
+    let mut on_curve: Vec<bool> = glyph.points.iter().map(|p| p.on_curve).collect();
+    on_curve.extend([true; 4]);
+    machine.zones[GLYPH_ZONE] = Zone {
+      original,
+      current,
+      unscaled,
+      on_curve,
+      touched: vec![0; n + 4],
+      contour_ends: glyph.end_pts_of_contours.iter().map(|&end| end as usize).collect(),
+    };
+
+    let mut trace = trace.then(|| Trace {
+      initial: machine.zones[GLYPH_ZONE].current.clone(),
+      on_curve: glyph.points.iter().map(|p| p.on_curve).collect(),
+      contour_ends: machine.zones[GLYPH_ZONE].contour_ends.clone(),
+      ..Trace::default()
+    });
+    if trace.is_some() {
+      machine.trace = Some(Vec::new());
+    }
+
+    let mut backward_compatibility = target != HintingTarget::Mono && instruct_control & 4 == 0;
+    if instruct_control & 1 == 0 && !glyph.instructions.is_empty() {
+      // 壊れた命令を持つグリフは多いので、FreeType と同じくエラーまでに動かした点をそのまま使う
+      let result = machine.execute(&self.code(&glyph.instructions), CodeRange::Glyph);
+      backward_compatibility = machine.backward_compatibility;
+      if let Some(trace) = &mut trace {
+        trace.error = result.err();
+      }
+    }
+    if let Some(trace) = &mut trace {
+      trace.steps = machine.trace.take().unwrap_or_default();
+    }
+
+    // 互換モードでは横方向のメトリクスを動かさない。送り幅はどちらもグリッドに合わせる
+    let zone = &machine.zones[GLYPH_ZONE];
+    let (origin, advance) = if backward_compatibility {
+      (zone.original[n].0, zone.original[n + 1].0 - zone.original[n].0)
+    } else {
+      (zone.current[n].0, zone.current[n + 1].0 - zone.current[n].0)
+    };
+    let advance = grid.round(advance);
+    let points = zone.current[..n].iter().zip(&zone.on_curve)
+      .map(|(&(x, y), &on_curve)| ((x - origin) as f32 / 64.0, y as f32 / 64.0, on_curve))
+      .collect();
+    let hinted = HintedGlyph {
+      points,
+      contour_ends: zone.contour_ends.clone(),
+      advance_width: advance as f32 / 64.0,
+      scan_control: machine.state.scan_control,
+      scan_type: machine.state.scan_type,
+    };
+    Ok((hinted, trace))
+  }
+}
@@ -0,0 +1,217 @@
+// 点が動かされた軸の印。IUP はこれを手がかりに残りの点を補間する
+pub const TOUCHED_X: u8 = 0x01;
+pub const TOUCHED_Y: u8 = 0x02;
+
+// F2Dot14 の単位ベクトル（0x4000 が 1.0）
+pub type Vector = (i32, i32);
+
+pub const X_AXIS: Vector = (0x4000, 0);
+pub const Y_AXIS: Vector = (0, 0x4000);
+
+// 距離の丸め方（RTG, RTHG, RTDG, RDTG, RUTG, ROFF, SROUND, S45ROUND）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundState {
+  HalfGrid,
+  Grid,
+  DoubleGrid,
+  DownToGrid,
+  UpToGrid,
+  Off,
+  Super,
+  Super45,
+}
+
+// 命令から変更できるグラフィックス状態。距離はすべて 26.6 固定小数点
+#[derive(Debug, Clone, Copy)]
+pub struct GraphicsState {
+  // 参照点 rp0, rp1, rp2
+  pub reference_points: [usize; 3],
+  pub dual_vector: Vector,
+  pub projection_vector: Vector,
+  pub freedom_vector: Vector,
+  pub loop_count: i32,
+  pub minimum_distance: i32,
+  pub round_state: RoundState,
+  // SROUND / S45ROUND の周期・位相・しきい値
+  pub period: i32,
+  pub phase: i32,
+  pub threshold: i32,
+  pub auto_flip: bool,
+  pub control_value_cutin: i32,
+  pub single_width_cutin: i32,
+  pub single_width_value: i32,
+  pub delta_base: i32,
+  pub delta_shift: i32,
+  // INSTCTRL のフラグ。1: グリフの命令を実行しない、2: グリフごとに既定の状態から始める、4: ClearType 向けの命令
+  pub instruct_control: u8,
+  // SCANCTRL と SCANTYPE の引数。ドロップアウト制御の判定はラスタライザに任せる
+  pub scan_control: u16,
+  pub scan_type: u16,
+  // zp0, zp1, zp2 が指すゾーン。0 がトワイライトゾーン、1 がグリフ
+  pub zone_pointers: [usize; 3],
+}
+
+impl Default for GraphicsState {
+  fn default() -> Self {
+    GraphicsState {
+      reference_points: [0; 3],
+      dual_vector: X_AXIS,
+      projection_vector: X_AXIS,
+      freedom_vector: X_AXIS,
+      loop_count: 1,
+      minimum_distance: 64,
+      round_state: RoundState::Grid,
+      period: 64,
+      phase: 0,
+      threshold: 32,
+      auto_flip: true,
+      // 17/16 ピクセル
+      control_value_cutin: 68,
+      single_width_cutin: 0,
+      single_width_value: 0,
+      delta_base: 9,
+      delta_shift: 3,
+      instruct_control: 0,
+      scan_control: 0,
+      scan_type: 0,
+      zone_pointers: [1; 3],
+    }
+  }
+}
+
+fn floor(distance: i32) -> i32 {
+  distance & !63
+}
+
+fn ceil(distance: i32) -> i32 {
+  floor(distance.wrapping_add(63))
+}
+
+impl GraphicsState {
+  // トレースで変化を比べるための項目名と値
+  pub fn fields(&self) -> Vec<(&'static str, String)> {
+    vec![
+      ("rp0", self.reference_points[0].to_string()),
+      ("rp1", self.reference_points[1].to_string()),
+      ("rp2", self.reference_points[2].to_string()),
+      ("dual_vector", format!("{:?}", self.dual_vector)),
+      ("projection_vector", format!("{:?}", self.projection_vector)),
+      ("freedom_vector", format!("{:?}", self.freedom_vector)),
+      ("loop", self.loop_count.to_string()),
+      ("minimum_distance", self.minimum_distance.to_string()),
+      ("round_state", format!("{:?}", self.round_state)),
+      ("period", self.period.to_string()),
+      ("phase", self.phase.to_string()),
+      ("threshold", self.threshold.to_string()),
+      ("auto_flip", self.auto_flip.to_string()),
+      ("control_value_cutin", self.control_value_cutin.to_string()),
+      ("single_width_cutin", self.single_width_cutin.to_string()),
+      ("single_width_value", self.single_width_value.to_string()),
+      ("delta_base", self.delta_base.to_string()),
+      ("delta_shift", self.delta_shift.to_string()),
+      ("instruct_control", self.instruct_control.to_string()),
+      ("scan_control", self.scan_control.to_string()),
+      ("scan_type", self.scan_type.to_string()),
+      ("zp0", self.zone_pointers[0].to_string()),
+      ("zp1", self.zone_pointers[1].to_string()),
+      ("zp2", self.zone_pointers[2].to_string()),
+    ]
+  }
+
+  // 丸めの状態に従って距離を丸める。丸めても符号は変えない
+  pub fn round(&self, distance: i32) -> i32 {
+    // 正の距離の丸めを負の距離には符号を反転して使う。0 をまたいだら下限の値にそろえる
+    let symmetric = |f: &dyn Fn(i32) -> i32, minimum: i32| {
+      if distance >= 0 {
+        let value = f(distance);
+        if value < 0 { minimum } else { value }
+      } else {
+        let value = -f(distance.wrapping_neg());
+        if value > 0 { -minimum } else { value }
+      }
+    };
+    match self.round_state {
+      RoundState::Off => distance,
+      RoundState::Grid => symmetric(&|d| floor(d.wrapping_add(32)), 0),
+      RoundState::HalfGrid => symmetric(&|d| floor(d) + 32, 32),
+      RoundState::DoubleGrid => symmetric(&|d| d.wrapping_add(16) & !31, 0),
+      RoundState::DownToGrid => symmetric(&floor, 0),
+      RoundState::UpToGrid => symmetric(&ceil, 0),
+      RoundState::Super => symmetric(
+        &|d| (d.wrapping_add(self.threshold - self.phase) & -self.period).wrapping_add(self.phase),
+        self.phase,
+      ),
+      RoundState::Super45 => symmetric(
+        &|d| (d.wrapping_add(self.threshold - self.phase) / self.period * self.period).wrapping_add(self.phase),
+        self.phase,
+      ),
+    }
+  }
+
+  // SROUND / S45ROUND の引数を周期・位相・しきい値に分解する。grid_period は 1 ピクセルまたは √2/2 ピクセル（2.14）
+  pub fn set_super_round(&mut self, grid_period: i32, selector: i32) {
+    let period = match selector & 0xC0 {
+      0x00 => grid_period / 2,
+      0x80 => grid_period * 2,
+      _ => grid_period,
+    };
+    let phase = match selector & 0x30 {
+      0x00 => 0,
+      0x10 => period / 4,
+      0x20 => period / 2,
+      _ => period * 3 / 4,
+    };
+    let threshold = if selector & 0x0F == 0 { period - 1 } else { ((selector & 0x0F) - 4) * period / 8 };
+    // 2.14 から 26.6 に直す
+    self.period = (period >> 8).max(1);
+    self.phase = phase >> 8;
+    self.threshold = threshold >> 8;
+  }
+
+  // 制御値プログラムの後、グリフごとの既定値にするときにベクトルや参照点を戻す
+  pub fn reset_for_glyph(&mut self) {
+    self.dual_vector = X_AXIS;
+    self.projection_vector = X_AXIS;
+    self.freedom_vector = X_AXIS;
+    self.reference_points = [0; 3];
+    self.zone_pointers = [1; 3];
+    self.loop_count = 1;
+  }
+}
+
+// 命令が操作する点の集まり。座標は 26.6 で、unscaled だけフォント単位
+#[derive(Debug, Clone, Default)]
+pub struct Zone {
+  // 拡大縮小しただけの座標
+  pub original: Vec<(i32, i32)>,
+  // 命令で動かした後の座標
+  pub current: Vec<(i32, i32)>,
+  // フォント単位の座標。IP や MDRP が丸め誤差なしに距離を測るのに使う
+  pub unscaled: Vec<(i32, i32)>,
+  pub on_curve: Vec<bool>,
+  pub touched: Vec<u8>,
+  // 各輪郭の最後の点の番号
+  pub contour_ends: Vec<usize>,
+}
+
+impl Zone {
+  // 輪郭を持たないトワイライトゾーン。すべての点が原点にある
+  pub fn twilight(points: usize) -> Self {
+    Zone {
+      original: vec![(0, 0); points],
+      current: vec![(0, 0); points],
+      unscaled: vec![(0, 0); points],
+      on_curve: vec![false; points],
+      touched: vec![0; points],
+      contour_ends: Vec::new(),
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.current.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.current.is_empty()
+  }
+}
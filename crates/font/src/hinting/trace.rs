@@ -0,0 +1,168 @@
+use std::fmt::Write;
+use super::disasm::mnemonic;
+use super::interpreter::{CodeRange, GLYPH_ZONE};
+use crate::json::escape_json;
+use crate::truetype::outline::{draw_contour, PathSegment};
+
+// 命令で変わったグラフィックス状態の項目。値は Debug 表記
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateChange {
+  pub field: &'static str,
+  pub before: String,
+  pub after: String,
+}
+
+// 命令で動いた点。座標は 26.6
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointMove {
+  pub zone: usize,
+  pub point: usize,
+  pub before: (i32, i32),
+  pub after: (i32, i32),
+}
+
+// 実行した1つの命令の記録
+#[derive(Debug, Clone)]
+pub struct TraceStep {
+  pub program: CodeRange,
+  pub offset: usize,
+  pub opcode: u8,
+  // 関数呼び出しの深さ
+  pub depth: usize,
+  // 実行後のスタック（下から順）。エラーで止まった命令では実行前のまま
+  pub stack: Vec<i32>,
+  pub state_changes: Vec<StateChange>,
+  pub moves: Vec<PointMove>,
+}
+
+// グリフの命令の実行記録。点の動きを最初の位置から順に当てはめれば各命令の後の輪郭が分かる
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+  pub steps: Vec<TraceStep>,
+  // 命令が失敗して止まったときのエラー。止まった命令は steps の最後
+  pub error: Option<String>,
+  // グリフのゾーンの実行前の位置（ファントム点を含む、26.6）
+  pub initial: Vec<(i32, i32)>,
+  pub on_curve: Vec<bool>,
+  pub contour_ends: Vec<usize>,
+}
+
+fn program_name(program: CodeRange) -> &'static str {
+  match program {
+    CodeRange::Font => "fpgm",
+    CodeRange::ControlValue => "prep",
+    CodeRange::Glyph => "glyf",
+  }
+}
+
+impl Trace {
+  pub fn to_json(&self) -> String {
+    let steps: Vec<String> = self.steps.iter()
+      .map(|step| {
+        let stack: Vec<String> = step.stack.iter().map(|v| v.to_string()).collect();
+        let changes: Vec<String> = step.state_changes.iter()
+          .map(|c| format!("{{\"field\": \"{}\", \"before\": \"{}\", \"after\": \"{}\"}}", c.field, escape_json(&c.before), escape_json(&c.after)))
+          .collect();
+        let moves: Vec<String> = step.moves.iter()
+          .map(|m| format!(
+            "{{\"zone\": {}, \"point\": {}, \"before\": [{}, {}], \"after\": [{}, {}]}}",
+            m.zone, m.point, m.before.0, m.before.1, m.after.0, m.after.1
+          ))
+          .collect();
+        format!(
+          "    {{\"program\": \"{}\", \"offset\": {}, \"instruction\": \"{}\", \"depth\": {}, \"stack\": [{}], \"state\": [{}], \"moves\": [{}]}}",
+          program_name(step.program), step.offset, mnemonic(step.opcode), step.depth,
+          stack.join(", "), changes.join(", "), moves.join(", ")
+        )
+      })
+      .collect();
+    let error = match &self.error {
+      Some(e) => format!("\"{}\"", escape_json(e)),
+      None => "null".to_string(),
+    };
+    format!(
+      "{{\n  \"units\": \"26.6\",\n  \"error\": {},\n  \"steps\": [\n{}\n  ]\n}}\n",
+      error, steps.join(",\n")
+    )
+  }
+
+  // 命令を1つ実行するごとに、その命令の番号とグリフの点の位置を f に渡す
+  pub fn replay<F: FnMut(usize, &[(i32, i32)])>(&self, mut f: F) {
+    let mut points = self.initial.clone();
+    for (index, step) in self.steps.iter().enumerate() {
+      for m in step.moves.iter().filter(|m| m.zone == GLYPH_ZONE) {
+        if let Some(point) = points.get_mut(m.point) {
+          *point = m.after;
+        }
+      }
+      f(index, &points);
+    }
+  }
+
+  // ある時点の点の位置を、ピクセル格子と輪郭つきの SVG にする。step を渡すとその命令で動いた点を赤く塗る
+  pub fn frame_svg(&self, points: &[(i32, i32)], step: Option<usize>) -> String {
+    let n = self.on_curve.len().min(points.len());
+    let pixels: Vec<(f32, f32, bool)> = (0..n)
+      .map(|i| (points[i].0 as f32 / 64.0, points[i].1 as f32 / 64.0, self.on_curve[i]))
+      .collect();
+    let mut segments: Vec<PathSegment> = Vec::new();
+    let mut start = 0;
+    for &end in &self.contour_ends {
+      if end >= n {
+        break;
+      }
+      draw_contour(&pixels[start..=end], &mut segments);
+      start = end + 1;
+    }
+
+    // 点とファントム点が入る範囲を1ピクセルの余白つきで整数に広げる
+    let xs = points.iter().map(|p| p.0 as f32 / 64.0);
+    let ys = points.iter().map(|p| p.1 as f32 / 64.0);
+    let x_min = xs.clone().fold(0.0f32, f32::min).floor() - 1.0;
+    let x_max = xs.fold(0.0f32, f32::max).ceil() + 1.0;
+    let y_min = ys.clone().fold(0.0f32, f32::min).floor() - 1.0;
+    let y_max = ys.fold(0.0f32, f32::max).ceil() + 1.0;
+
+    let mut svg = format!(
+      "<svg viewBox='{} {} {} {}' xmlns='http://www.w3.org/2000/svg'>",
+      x_min, -y_max, x_max - x_min, y_max - y_min
+    );
+    for x in x_min as i32..=x_max as i32 {
+      write!(svg, "<line x1='{}' y1='{}' x2='{}' y2='{}' stroke='#ccc' stroke-width='0.02' />", x, -y_max, x, -y_min).unwrap();
+    }
+    for y in y_min as i32..=y_max as i32 {
+      let color = if y == 0 { "#88f" } else { "#ccc" };
+      write!(svg, "<line x1='{}' y1='{}' x2='{}' y2='{}' stroke='{}' stroke-width='0.02' />", x_min, -y, x_max, -y, color).unwrap();
+    }
+    let mut data = String::new();
+    for segment in &segments {
+      match *segment {
+        PathSegment::MoveTo(x, y) => write!(data, "M{} {} ", x, -y),
+        PathSegment::LineTo(x, y) => write!(data, "L{} {} ", x, -y),
+        PathSegment::QuadTo(cx, cy, x, y) => write!(data, "Q{} {} {} {} ", cx, -cy, x, -y),
+        PathSegment::CubicTo(c1x, c1y, c2x, c2y, x, y) => write!(data, "C{} {} {} {} {} {} ", c1x, -c1y, c2x, -c2y, x, -y),
+        PathSegment::Close => write!(data, "Z "),
+      }
+      .unwrap();
+    }
+    write!(svg, "<path d='{}' fill='#0002' stroke='black' stroke-width='0.04' />", data.trim_end()).unwrap();
+
+    let moved: Vec<usize> = step
+      .and_then(|index| self.steps.get(index))
+      .map(|step| step.moves.iter().filter(|m| m.zone == GLYPH_ZONE).map(|m| m.point).collect())
+      .unwrap_or_default();
+    for (i, &(x, y, on_curve)) in pixels.iter().enumerate() {
+      let color = if moved.contains(&i) { "red" } else if on_curve { "black" } else { "gray" };
+      write!(svg, "<circle cx='{}' cy='{}' r='0.08' fill='{}'><title>{}</title></circle>", x, -y, color, i).unwrap();
+    }
+    if let Some(step) = step.and_then(|index| self.steps.get(index)) {
+      write!(
+        svg, "<text x='{}' y='{}' font-size='0.6'>{} {}: {}</text>",
+        x_min + 0.2, -y_max + 0.7, program_name(step.program), step.offset, mnemonic(step.opcode)
+      )
+      .unwrap();
+    }
+    svg.push_str("</svg>");
+    svg
+  }
+}
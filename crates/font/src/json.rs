@@ -0,0 +1,23 @@
+// JSON の文字列リテラルの中身としてエスケープする（引用符・バックスラッシュ・制御文字）
+pub fn escape_json(s: &str) -> String {
+  let mut escaped = String::new();
+  for c in s.chars() {
+    match c {
+      '"' => escaped.push_str("\\\""),
+      '\\' => escaped.push_str("\\\\"),
+      c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn escapes_quotes_backslashes_and_control_characters() {
+    assert_eq!(escape_json("a\"b\\c\nd\u{1}é"), "a\\\"b\\\\c\\u000ad\\u0001é");
+  }
+}
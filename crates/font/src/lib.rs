@@ -1,4 +1,20 @@
-mod tables;
+pub mod analysis;
+pub mod autohint;
+pub mod bbox;
+pub mod bitmap;
+pub mod colr_svg;
+pub mod convert;
+pub mod geometry;
+pub mod measure;
+pub mod ot_svg;
+pub mod boolean;
+pub mod hinting;
+pub mod json;
+pub mod sink;
+pub mod stroke;
+pub mod svg;
+pub mod tables;
+pub mod transform;
 pub mod truetype;
 
 use std::io::{Cursor, Read};
@@ -8,6 +24,29 @@ use tables::glyf::GlyfTable;
 use tables::loca::LocaTable;
 use tables::head::HeadTable;
 use tables::maxp::MaxpTable;
+use tables::hhea::HheaTable;
+use tables::hmtx::HmtxTable;
+use tables::os2::Os2Table;
+use tables::cvt::CvtTable;
+use tables::cvar::CvarTable;
+use tables::gasp::GaspTable;
+use tables::colr::{ColorLayer, ColrTable, FOREGROUND_PALETTE_INDEX};
+use tables::cbdt::CbdtGlyph;
+use tables::cblc::CblcTable;
+use tables::cpal::{Color, CpalTable};
+use tables::sbix::SbixTable;
+use tables::svg::SvgTable;
+use tables::paint::{ClipBox, Paint};
+use tables::name::NameTable;
+use analysis::OutlineReport;
+use autohint::{blue_zone, glyph_extremum, AutohintMetrics, Script};
+use bbox::{BoundingBox, BoundsCheck};
+use bitmap::{image_size, BitmapGlyph, ImageFormat};
+use hinting::trace::Trace;
+use hinting::{GlyphMetrics, HintedGlyph, Hinter, HintingTarget};
+use measure::OutlineMetrics;
+use sink::OutlineSink;
+use svg::{SvgMetrics, SvgOptions};
 pub trait Parse {
   fn from_bytes(data: &[u8]) -> Result<Self, String> where Self: Sized;
 }
@@ -74,38 +113,216 @@ impl Parse for Sfnt {
 }
 
 impl Sfnt {
-  pub fn get_glyph_data(&self, glyph_name: &str, data: &[u8]) -> Result<SimpleGlyph, String> {
-    let mut cursor = Cursor::new(data);
-    let saved_position = cursor.position();
+  pub fn has_table(&self, tag: &str) -> bool {
+    self.records.iter().any(|record| record.tag == tag)
+  }
 
-    let head_table = self.records.iter().find(|record| record.tag == "head")
-        .ok_or("headテーブルが見つかりません")?;
-    
-    // CBDTテーブルの存在チェック
-    let is_color_emoji = self.records.iter().any(|record| record.tag == "CBDT");
-    
-    if is_color_emoji {
-        return Err("色絵文字フォントは現在サポートされていません".to_string());
+  // タグで指定したテーブルのバイト列を切り出す
+  pub fn read_table(&self, tag: &str, data: &[u8]) -> Result<Vec<u8>, String> {
+    let record = self.records.iter().find(|record| record.tag == tag)
+        .ok_or(format!("{}テーブルが見つかりません", tag.trim_end()))?;
+    let start = record.offset as usize;
+    let end = start + record.length as usize;
+    data.get(start..end)
+        .map(|table| table.to_vec())
+        .ok_or(format!("{}テーブルがファイルの範囲外を指しています", tag.trim_end()))
+  }
+
+  pub fn get_head_table(&self, data: &[u8]) -> Result<HeadTable, String> {
+    HeadTable::parse(&self.read_table("head", data)?)
+  }
+
+  pub fn get_hhea_table(&self, data: &[u8]) -> Result<HheaTable, String> {
+    HheaTable::parse(&self.read_table("hhea", data)?)
+  }
+
+  pub fn get_maxp_table(&self, data: &[u8]) -> Result<MaxpTable, String> {
+    MaxpTable::parse(&self.read_table("maxp", data)?)
+  }
+
+  pub fn get_os2_table(&self, data: &[u8]) -> Result<Os2Table, String> {
+    Os2Table::parse(&self.read_table("OS/2", data)?)
+  }
+
+  pub fn get_gasp_table(&self, data: &[u8]) -> Result<GaspTable, String> {
+    GaspTable::parse(&self.read_table("gasp", data)?)
+  }
+
+  pub fn get_name_table(&self, data: &[u8]) -> Result<NameTable, String> {
+    NameTable::parse(&self.read_table("name", data)?)
+  }
+
+  pub fn get_colr_table(&self, data: &[u8]) -> Result<ColrTable, String> {
+    ColrTable::parse(&self.read_table("COLR", data)?)
+  }
+
+  pub fn get_cpal_table(&self, data: &[u8]) -> Result<CpalTable, String> {
+    CpalTable::parse(&self.read_table("CPAL", data)?)
+  }
+
+  // CPAL の palette 番目のパレットの色
+  pub fn get_palette(&self, palette: usize, data: &[u8]) -> Result<Vec<Color>, String> {
+    let cpal_table = self.get_cpal_table(data)?;
+    let palettes = cpal_table.palettes.len();
+    cpal_table.palettes.into_iter().nth(palette)
+      .map(|palette| palette.colors)
+      .ok_or(format!("パレット{}はありません（パレットは{}個です）", palette, palettes))
+  }
+
+  // COLR のレイヤーに CPAL の palette 番目のパレットの色を当てる。カラーグリフでなければ None
+  pub fn get_color_layers(&self, glyph_id: u16, palette: usize, data: &[u8]) -> Result<Option<Vec<ColorLayer>>, String> {
+    if !self.has_table("COLR") {
+      return Ok(None);
     }
-    
-    let loca_table = self.records.iter().find(|record| record.tag == "loca")
-        .ok_or("locaテーブルが見つかりません（色絵文字フォントの可能性があります）")?;
-    
-    let glyf_table = self.records.iter().find(|record| record.tag == "glyf")
-        .ok_or("glyfテーブルが見つかりません（色絵文字フォントの可能性があります）")?;
-    
-    let Some(cmap_table) = self.records.iter().find(|record| record.tag == "cmap") else {
-      return Err("cmapテーブルが見つかりません".to_string());
+    let colr_table = self.get_colr_table(data)?;
+    let Some(records) = colr_table.layers(glyph_id) else {
+      return Ok(None);
+    };
+    let colors = self.get_palette(palette, data)?;
+    records.iter()
+      .map(|record| {
+        let color = match record.palette_index {
+          FOREGROUND_PALETTE_INDEX => None,
+          index => Some(*colors.get(index as usize).ok_or(format!("色{}はパレットにありません", index))?),
+        };
+        Ok(ColorLayer { glyph_id: record.glyph_id, color })
+      })
+      .collect::<Result<_, String>>()
+      .map(Some)
+  }
+
+  // COLR バージョン 1 の Paint と描画範囲。coords は正規化したバリアブルフォントの座標
+  // バージョン 1 のカラーグリフでなければ None
+  pub fn get_color_paint(&self, glyph_id: u16, coords: &[f32], data: &[u8]) -> Result<Option<(Paint, Option<ClipBox>)>, String> {
+    if !self.has_table("COLR") {
+      return Ok(None);
+    }
+    let colr_table = self.get_colr_table(data)?;
+    let Some(paint) = colr_table.paint(glyph_id, coords)? else {
+      return Ok(None);
+    };
+    Ok(Some((paint, colr_table.clip_box(glyph_id, coords))))
+  }
+
+  pub fn get_cblc_table(&self, data: &[u8]) -> Result<CblcTable, String> {
+    CblcTable::parse(&self.read_table("CBLC", data)?)
+  }
+
+  pub fn get_svg_table(&self, data: &[u8]) -> Result<SvgTable, String> {
+    SvgTable::parse(&self.read_table("SVG ", data)?)
+  }
+
+  // SVG テーブルのうち glyph_id を含む文書（展開済み）。なければ None
+  pub fn get_svg_document(&self, glyph_id: u16, data: &[u8]) -> Result<Option<String>, String> {
+    if !self.has_table("SVG ") {
+      return Ok(None);
+    }
+    self.get_svg_table(data)?.document(glyph_id)
+  }
+
+  pub fn get_sbix_table(&self, data: &[u8]) -> Result<SbixTable, String> {
+    let maxp_table = self.get_maxp_table(data)?;
+    SbixTable::parse(&self.read_table("sbix", data)?, maxp_table.num_glyphs)
+  }
+
+  // 埋め込みビットマップのグリフ。sbix があればそれを、なければ CBLC/CBDT を使う。ビットマップがなければ None
+  pub fn get_bitmap_glyph(&self, glyph_id: u16, ppem: u16, data: &[u8]) -> Result<Option<BitmapGlyph>, String> {
+    if self.has_table("sbix") {
+      return self.get_sbix_glyph(glyph_id, ppem, data);
+    }
+    if self.has_table("CBLC") && self.has_table("CBDT") {
+      return self.get_cbdt_glyph(glyph_id, ppem, data);
+    }
+    Ok(None)
+  }
+
+  // sbix のうち ppem が最も近いストライクの画像
+  fn get_sbix_glyph(&self, glyph_id: u16, ppem: u16, data: &[u8]) -> Result<Option<BitmapGlyph>, String> {
+    let sbix_table = self.get_sbix_table(data)?;
+    let Some(strike) = sbix_table.nearest_strike(glyph_id, ppem)? else {
+      return Ok(None);
     };
-    let Some(maxp_table) = self.records.iter().find(|record| record.tag == "maxp") else {
-      return Err("maxpテーブルが見つかりません".to_string());
+    let Some(glyph) = sbix_table.glyph(strike, glyph_id)? else {
+      return Ok(None);
     };
-    
-    let mut cmap_data = vec![0; cmap_table.length as usize];
-    cursor.set_position(cmap_table.offset as u64);
-    cursor.read_exact(&mut cmap_data).unwrap();
+    let (width, height) = image_size(glyph.format, &glyph.data)
+      .ok_or(format!("sbix のグリフ {} の {} 画像の大きさを読めません", glyph_id, glyph.format.name()))?;
+    // 送り幅は hmtx をストライクの ppem に合わせたもの
+    let units_per_em = self.get_head_table(data)?.units_per_em as f32;
+    let advance = self.get_advance_width(glyph_id, data)? as f32 * strike.ppem as f32 / units_per_em;
+    Ok(Some(BitmapGlyph {
+      ppem: strike.ppem,
+      format: glyph.format,
+      data: glyph.data,
+      width,
+      height,
+      bearing_x: glyph.origin_offset_x as i32,
+      bearing_y: glyph.origin_offset_y as i32 + height as i32,
+      advance: advance.round() as u32,
+    }))
+  }
+
+  // CBDT のうち ppem に最も合うストライクの PNG
+  fn get_cbdt_glyph(&self, glyph_id: u16, ppem: u16, data: &[u8]) -> Result<Option<BitmapGlyph>, String> {
+    let cblc_table = self.get_cblc_table(data)?;
+    let Some(strike) = cblc_table.best_strike(glyph_id, ppem) else {
+      return Ok(None);
+    };
+    let Some(location) = strike.locate(glyph_id) else {
+      return Ok(None);
+    };
+    let glyph = CbdtGlyph::parse(&self.read_table("CBDT", data)?, &location)?;
+    let m = glyph.metrics;
+    // メトリクスの大きさが 0 なら PNG の大きさを使う
+    let (width, height) = match (m.width, m.height, image_size(ImageFormat::Png, &glyph.png)) {
+      (0, _, Some(size)) | (_, 0, Some(size)) => size,
+      (width, height, _) => (width as u32, height as u32),
+    };
+    Ok(Some(BitmapGlyph {
+      ppem: strike.ppem_y as u16,
+      format: ImageFormat::Png,
+      data: glyph.png,
+      width,
+      height,
+      bearing_x: m.hori_bearing_x as i32,
+      bearing_y: m.hori_bearing_y as i32,
+      advance: m.hori_advance as u32,
+    }))
+  }
+
+  pub fn get_hmtx_table(&self, data: &[u8]) -> Result<HmtxTable, String> {
+    let hhea_table = self.get_hhea_table(data)?;
+    let maxp_table = self.get_maxp_table(data)?;
+    HmtxTable::parse(&self.read_table("hmtx", data)?, hhea_table.number_of_h_metrics, maxp_table.num_glyphs)
+  }
+
+  pub fn get_advance_width(&self, glyph_id: u16, data: &[u8]) -> Result<u16, String> {
+    let hmtx_table = self.get_hmtx_table(data)?;
+    hmtx_table.get_metric(glyph_id)
+        .map(|metric| metric.advance_width)
+        .ok_or(format!("hmtxにグリフ{}のメトリクスがありません", glyph_id))
+  }
+
+  // 文字列の先頭の文字に対応するグリフIDをcmapから引く
+  pub fn get_glyph_id(&self, glyph_name: &str, data: &[u8]) -> Result<u16, String> {
+    let cmap_data = self.read_table("cmap", data)?;
     
     let cmap_table = CmapTable::parse(&cmap_data)?;
+    let c = glyph_name.chars().next().ok_or("Invalid glyph name")?;
+    let code_point = c as u32;
+
+    // BMP の外の文字も引けるフォーマット12（Windows の UCS-4 か Unicode の完全なレパートリー）があればそれを使う
+    let format12 = cmap_table.encoding_records.iter()
+      .filter(|record| matches!((record.platform_id, record.encoding_id), (3, 10) | (0, 4) | (0, 6)))
+      .filter_map(|record| cmap_data.get(record.offset as usize..))
+      .find(|subtable| subtable.starts_with(&12u16.to_be_bytes()));
+    if let Some(subtable) = format12 {
+      return Ok(CmapTable::parse_format12(subtable)?.get_glyph_id(code_point).unwrap_or(0));
+    }
+    if code_point > 0xFFFF {
+      return Err(format!("U+{:04X} は BMP の外の文字ですが、cmap にフォーマット12のサブテーブルがありません", code_point));
+    }
+
     let platform_id = 3;  // Windows
     let encoding_id = 1;  // Unicode BMP (Basic Multilingual Plane)
     
@@ -131,42 +348,226 @@ impl Sfnt {
     cmap_cursor.read_exact(&mut subtable_data).unwrap();
     let subtable = CmapTable::parse_format4(&subtable_data, subtable_length as u16)?;
     
-    let c = glyph_name.chars().next().ok_or("Invalid glyph name")?;
-    let code_point = c as u32;
-    let glyph_id = CmapTable::get_glyph_id(code_point, &subtable).unwrap_or(0);
+    Ok(CmapTable::get_glyph_id(code_point, &subtable).unwrap_or(0))
+  }
 
-    cursor.set_position(saved_position);
-    let mut head_data = vec![0; head_table.length as usize];
-    cursor.set_position(head_table.offset as u64);
-    cursor.read_exact(&mut head_data).unwrap();
+  pub fn get_glyph_data(&self, glyph_name: &str, data: &[u8]) -> Result<SimpleGlyph, String> {
+    let glyph_id = self.get_glyph_id(glyph_name, data)?;
+    self.get_glyph_data_by_id(glyph_id, data)
+  }
 
-    let head_table = HeadTable::parse(&head_data)?;
-    let index_to_loc_format = head_table.index_to_loc_format;
+  // グリフの輪郭を sink に流し込む（座標はフォント単位）
+  pub fn draw_glyph<S: OutlineSink + ?Sized>(&self, glyph_name: &str, data: &[u8], sink: &mut S) -> Result<(), String> {
+    let glyph_id = self.get_glyph_id(glyph_name, data)?;
+    self.draw_glyph_by_id(glyph_id, data, sink)
+  }
+
+  // 複合グリフは成分ごとに変換を適用しながら描く
+  pub fn draw_glyph_by_id<S: OutlineSink + ?Sized>(&self, glyph_id: u16, data: &[u8], sink: &mut S) -> Result<(), String> {
+    self.get_glyf_table(data)?.draw_glyph(glyph_id as usize, sink)
+  }
+
+  // viewBox の計算に使うメトリクスを集める
+  pub fn get_svg_metrics(&self, glyph_id: u16, data: &[u8]) -> Result<SvgMetrics, String> {
+    let head_table = self.get_head_table(data)?;
+    let hhea_table = self.get_hhea_table(data)?;
+    Ok(SvgMetrics {
+      units_per_em: head_table.units_per_em,
+      ascender: hhea_table.ascender,
+      descender: hhea_table.descender,
+      advance_width: self.get_advance_width(glyph_id, data)?,
+    })
+  }
+
+  pub fn glyph_to_svg(&self, glyph_name: &str, data: &[u8], options: &SvgOptions) -> Result<String, String> {
+    let glyph_id = self.get_glyph_id(glyph_name, data)?;
+    let mut segments = Vec::new();
+    self.draw_glyph_by_id(glyph_id, data, &mut segments)?;
+    let metrics = self.get_svg_metrics(glyph_id, data)?;
+    Ok(svg::outline_to_svg(&segments, &metrics, options))
+  }
+
+  // カラーグリフを色付きの SVG にする。COLR バージョン 1 の Paint があればそれを、なければバージョン 0 のレイヤーを使う
+  // 文字色は黒。カラーグリフでなければ None
+  pub fn color_glyph_to_svg(&self, glyph_name: &str, palette: usize, coords: &[f32], data: &[u8], options: &SvgOptions) -> Result<Option<String>, String> {
+    let glyph_id = self.get_glyph_id(glyph_name, data)?;
+    if let Some((paint, clip)) = self.get_color_paint(glyph_id, coords, data)? {
+      let colors = self.get_palette(palette, data)?;
+      let foreground = Color { r: 0, g: 0, b: 0, a: 255 };
+      // glyf は一度だけ読み、すべてのペイントの輪郭で使い回す
+      let glyf_table = self.get_glyf_table(data)?;
+      let outline = |glyph_id: u16| {
+        let mut segments = Vec::new();
+        glyf_table.draw_glyph(glyph_id as usize, &mut segments)?;
+        Ok(segments)
+      };
+      let metrics = self.get_svg_metrics(glyph_id, data)?;
+      return colr_svg::paint_to_svg(&paint, outline, &colors, foreground, clip, &metrics, options).map(Some);
+    }
+    let Some(layers) = self.get_color_layers(glyph_id, palette, data)? else {
+      return Ok(None);
+    };
+    let glyf_table = self.get_glyf_table(data)?;
+    let layers = layers.iter()
+      .map(|layer| {
+        let mut segments = Vec::new();
+        glyf_table.draw_glyph(layer.glyph_id as usize, &mut segments)?;
+        Ok((segments, layer.color))
+      })
+      .collect::<Result<Vec<_>, String>>()?;
+    let metrics = self.get_svg_metrics(glyph_id, data)?;
+    Ok(Some(svg::layers_to_svg(&layers, &metrics, options)))
+  }
+
+  // SVG テーブルの文書を glyph_to_svg と同じ座標系の単独のグリフの SVG にする。文書がなければ None
+  pub fn svg_document_to_svg(&self, glyph_name: &str, data: &[u8], options: &SvgOptions) -> Result<Option<String>, String> {
+    let glyph_id = self.get_glyph_id(glyph_name, data)?;
+    let Some(document) = self.get_svg_document(glyph_id, data)? else {
+      return Ok(None);
+    };
+    let metrics = self.get_svg_metrics(glyph_id, data)?;
+    ot_svg::svg_document_to_svg(&document, glyph_id, &metrics, options).map(Some)
+  }
+
+  pub fn get_glyph_data_by_id(&self, glyph_id: u16, data: &[u8]) -> Result<SimpleGlyph, String> {
+    let glyf_table = self.get_glyf_table(data)?;
+    glyf_table.get_glyph_data(glyph_id as usize)
+  }
+
+  fn check_glyf_tables(&self) -> Result<(), String> {
+    if !self.has_table("loca") {
+      return Err("locaテーブルが見つかりません（色絵文字フォントの可能性があります）".to_string());
+    }
+    if !self.has_table("glyf") {
+      return Err("glyfテーブルが見つかりません（色絵文字フォントの可能性があります）".to_string());
+    }
+    Ok(())
+  }
 
-    cursor.set_position(saved_position);
-    let mut maxp_data = vec![0; maxp_table.length as usize];
-    cursor.set_position(maxp_table.offset as u64);
-    cursor.read_exact(&mut maxp_data).unwrap();
+  pub fn get_glyf_table(&self, data: &[u8]) -> Result<GlyfTable, String> {
+    self.check_glyf_tables()?;
+    let head_table = self.get_head_table(data)?;
+    let index_to_loc_format = head_table.index_to_loc_format;
 
-    let maxp_table = MaxpTable::parse(&maxp_data)?;
+    let maxp_table = self.get_maxp_table(data)?;
     let num_glyphs = maxp_table.num_glyphs;
     
-    cursor.set_position(saved_position);
-    let mut loca_data = vec![0; loca_table.length as usize];
-    cursor.set_position(loca_table.offset as u64);
-    cursor.read_exact(&mut loca_data).unwrap();
-
+    let loca_data = self.read_table("loca", data)?;
     let loca_table = LocaTable::parse(&loca_data, num_glyphs, index_to_loc_format)?;
 
-    cursor.set_position(saved_position);
-    let mut glyf_data = vec![0; glyf_table.length as usize];
-    cursor.set_position(glyf_table.offset as u64);
-    cursor.read_exact(&mut glyf_data).unwrap();
-    
-    let glyf_table = GlyfTable::parse(&glyf_data, &loca_table)?;
-    let _glyph_offset = LocaTable::get_glyph_offset(&loca_data, glyph_id, index_to_loc_format);
-    
-    glyf_table.get_glyph_data(glyph_id as usize)
+    let glyf_data = self.read_table("glyf", data)?;
+    GlyfTable::parse(&glyf_data, &loca_table)
+  }
+
+  // glyf ヘッダの外接矩形を、輪郭から計算したコントロールボックス・正確な外接矩形と比べる
+  pub fn check_glyph_bounds(&self, glyph_id: u16, data: &[u8]) -> Result<BoundsCheck, String> {
+    let glyf_table = self.get_glyf_table(data)?;
+    let glyph = glyf_table.glyphs.get(glyph_id as usize).ok_or(format!("Glyph not found: {}", glyph_id))?;
+    let header = BoundingBox {
+      x_min: glyph.x_min as f32,
+      y_min: glyph.y_min as f32,
+      x_max: glyph.x_max as f32,
+      y_max: glyph.y_max as f32,
+    };
+    let outline = truetype::outline::Outline::from_simple_glyph(&glyf_table.get_glyph_data(glyph_id as usize)?);
+    Ok(BoundsCheck { header, control: outline.control_box(), exact: outline.bounding_box() })
+  }
+
+  // 輪郭の向き・入れ子・自己交差などを調べる
+  pub fn check_glyph(&self, glyph_id: u16, data: &[u8]) -> Result<OutlineReport, String> {
+    let glyph_data = self.get_glyph_data_by_id(glyph_id, data)?;
+    Ok(truetype::outline::Outline::from_simple_glyph(&glyph_data).analyze())
+  }
+
+  // 面積・周長・重心・ステム幅を測る
+  pub fn get_outline_metrics(&self, glyph_id: u16, data: &[u8]) -> Result<OutlineMetrics, String> {
+    let mut segments = Vec::new();
+    self.draw_glyph_by_id(glyph_id, data, &mut segments)?;
+    Ok(OutlineMetrics::measure(&segments))
+  }
+
+  // ヒンティング命令（fpgm, prep, cvt, cvar）を読み込む。命令のないフォントでは空のプログラムになる
+  pub fn get_hinter(&self, data: &[u8]) -> Result<Hinter, String> {
+    let read_optional = |tag: &str| if self.has_table(tag) { self.read_table(tag, data) } else { Ok(Vec::new()) };
+    let head_table = self.get_head_table(data)?;
+    let maxp_table = self.get_maxp_table(data)?;
+    let cvt_table = CvtTable::parse(&read_optional("cvt ")?)?;
+    let axis_count = if self.has_table("fvar") {
+      // fvar ヘッダの8バイト目が軸の数
+      let fvar_data = self.read_table("fvar", data)?;
+      fvar_data.get(8..10).map(|b| u16::from_be_bytes([b[0], b[1]])).ok_or("fvarテーブルが短すぎます")? as usize
+    } else {
+      0
+    };
+    let cvar_table = if self.has_table("cvar") && axis_count > 0 {
+      Some(CvarTable::parse(&self.read_table("cvar", data)?, axis_count, cvt_table.values.len())?)
+    } else {
+      None
+    };
+    Ok(Hinter::new(read_optional("fpgm")?, read_optional("prep")?, cvt_table, cvar_table, axis_count, &maxp_table, head_table.units_per_em))
+  }
+
+  // グリフの命令を実行して、ppem に合わせた輪郭を得る
+  pub fn hint_glyph(&self, hinter: &mut Hinter, glyph_id: u16, ppem: u16, target: HintingTarget, data: &[u8]) -> Result<HintedGlyph, String> {
+    let (glyph, metrics) = self.get_hinting_input(glyph_id, data)?;
+    hinter.hint_glyph(&glyph, &metrics, ppem, target)
+  }
+
+  // hint_glyph と同じく命令を実行し、命令ごとのスタックや点の動きの記録も返す
+  pub fn trace_glyph(&self, hinter: &mut Hinter, glyph_id: u16, ppem: u16, target: HintingTarget, data: &[u8]) -> Result<(HintedGlyph, Trace), String> {
+    let (glyph, metrics) = self.get_hinting_input(glyph_id, data)?;
+    hinter.trace_glyph(&glyph, &metrics, ppem, target)
+  }
+
+  // ヒンティングするグリフと、ファントム点を置くためのメトリクス
+  fn get_hinting_input(&self, glyph_id: u16, data: &[u8]) -> Result<(SimpleGlyph, GlyphMetrics), String> {
+    let glyf_table = self.get_glyf_table(data)?;
+    let glyph = glyf_table.glyphs.get(glyph_id as usize).ok_or(format!("Glyph not found: {}", glyph_id))?;
+    let glyph_data = glyf_table.get_glyph_data(glyph_id as usize)?;
+    let metric = self.get_hmtx_table(data)?.get_metric(glyph_id)
+        .ok_or(format!("hmtxにグリフ{}のメトリクスがありません", glyph_id))?;
+    // 上下のファントム点は FreeType と同じく OS/2 の typo メトリクス、なければ hhea から置く
+    let (ascender, descender) = match self.get_os2_table(data) {
+      Ok(os2_table) => (os2_table.typo_ascender, os2_table.typo_descender),
+      _ => {
+        let hhea_table = self.get_hhea_table(data)?;
+        (hhea_table.ascender, hhea_table.descender)
+      }
+    };
+    let metrics = GlyphMetrics {
+      x_min: glyph.x_min,
+      left_side_bearing: metric.left_side_bearing,
+      advance_width: metric.advance_width,
+      ascender,
+      descender,
+    };
+    Ok((glyph_data, metrics))
+  }
+
+  // 自動ヒンティングのブルーゾーンと標準のステム幅を参照文字から求める。文字体系は参照文字があるかで決める
+  pub fn get_autohint_metrics(&self, data: &[u8]) -> Result<AutohintMetrics, String> {
+    let head_table = self.get_head_table(data)?;
+    let glyph_id = |c: char| self.get_glyph_id(&c.to_string(), data).ok().filter(|&id| id != 0);
+    let glyph = |c: char| self.get_glyph_data_by_id(glyph_id(c)?, data).ok();
+    let script = if "ox".chars().all(|c| glyph_id(c).is_some()) {
+      Script::Latin
+    } else if "口国".chars().any(|c| glyph_id(c).is_some()) {
+      Script::Cjk
+    } else {
+      Script::Latin
+    };
+    let blue_zones = script.blues().iter()
+      .filter_map(|&(kind, characters)| {
+        let extrema: Vec<(f64, bool)> = characters.chars()
+          .filter_map(|c| glyph_extremum(&glyph(c)?, kind.is_top()))
+          .collect();
+        blue_zone(kind, &extrema)
+      })
+      .collect();
+    let standard_width = script.standard_character().chars().next()
+      .and_then(glyph)
+      .and_then(|g| truetype::outline::Outline::from_simple_glyph(&g).metrics().horizontal_stem);
+    Ok(AutohintMetrics { script, units_per_em: head_table.units_per_em, blue_zones, standard_width })
   }
 }
 
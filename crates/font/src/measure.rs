@@ -0,0 +1,300 @@
+use crate::boolean::remove_overlaps;
+use crate::convert::flatten;
+use crate::geometry::{bezier_point, hodograph, Point};
+use crate::sink::OutlineSink;
+use crate::truetype::outline::{Outline, PathSegment};
+
+// 周長を測るときの折れ線近似の許容誤差（フォント単位）
+const FLATTEN_TOLERANCE: f32 = 0.01;
+// ステム幅を測る走査線の本数
+const SCANLINES: usize = 64;
+
+// [0, 1] 上の5点ガウス・ルジャンドル求積の (t, 重み)。9次までの多項式を厳密に積分できる
+const GAUSS_LEGENDRE: [(f64, f64); 5] = [
+  (0.046910077030668, 0.118463442528095),
+  (0.230765344947158, 0.239314335249683),
+  (0.5, 0.284444444444444),
+  (0.769234655052842, 0.239314335249683),
+  (0.953089922969332, 0.118463442528095),
+];
+
+// 面積と1次モーメントをグリーンの定理で曲線ごとに積分する
+// 面積 = ∮ (x dy - y dx) / 2、∬ x dA = ∮ x^2 / 2 dy、∬ y dA = -∮ y^2 / 2 dx
+#[derive(Debug, Default)]
+struct MomentSink {
+  area: f64,
+  moment_x: f64,
+  moment_y: f64,
+  start: (f32, f32),
+  current: (f32, f32),
+}
+
+impl MomentSink {
+  fn segment(&mut self, points: &[(f32, f32)]) {
+    self.current = points[points.len() - 1];
+    let points: Vec<Point> = points.iter().map(|&(x, y)| Point::new(x as f64, y as f64)).collect();
+    let derivative = hodograph(&points);
+    for &(t, weight) in &GAUSS_LEGENDRE {
+      let Point { x, y } = bezier_point(&points, t);
+      let Point { x: dx, y: dy } = bezier_point(&derivative, t);
+      self.area += weight * (x * dy - y * dx) / 2.0;
+      self.moment_x += weight * x * x * dy / 2.0;
+      self.moment_y -= weight * y * y * dx / 2.0;
+    }
+  }
+}
+
+impl OutlineSink for MomentSink {
+  fn move_to(&mut self, x: f32, y: f32) {
+    self.start = (x, y);
+    self.current = (x, y);
+  }
+
+  fn line_to(&mut self, x: f32, y: f32) {
+    self.segment(&[self.current, (x, y)]);
+  }
+
+  fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+    self.segment(&[self.current, (x1, y1), (x, y)]);
+  }
+
+  fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+    self.segment(&[self.current, (x1, y1), (x2, y2), (x, y)]);
+  }
+
+  fn close(&mut self) {
+    if self.current != self.start {
+      self.line_to(self.start.0, self.start.1);
+    }
+  }
+}
+
+// 折れ線にした輪郭。各輪郭は始点に戻って閉じている
+fn polylines(segments: &[PathSegment]) -> Vec<Vec<(f64, f64)>> {
+  let mut polylines: Vec<Vec<(f64, f64)>> = Vec::new();
+  for segment in flatten(segments, FLATTEN_TOLERANCE) {
+    match segment {
+      PathSegment::MoveTo(x, y) => polylines.push(vec![(x as f64, y as f64)]),
+      PathSegment::LineTo(x, y) => polylines.last_mut().into_iter().for_each(|p| p.push((x as f64, y as f64))),
+      PathSegment::Close => {
+        if let Some(polyline) = polylines.last_mut() {
+          if polyline.first() != polyline.last() {
+            polyline.push(polyline[0]);
+          }
+        }
+      }
+      PathSegment::QuadTo(..) | PathSegment::CubicTo(..) => unreachable!(),
+    }
+  }
+  polylines
+}
+
+// 高さ y の水平線が塗られている区間の長さ（非ゼロ規則）
+fn runs(polylines: &[Vec<(f64, f64)>], y: f64) -> Vec<f64> {
+  let mut crossings: Vec<(f64, i32)> = Vec::new();
+  for polyline in polylines {
+    for w in polyline.windows(2) {
+      let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+      if (y0 <= y) != (y1 <= y) {
+        let x = x0 + (y - y0) / (y1 - y0) * (x1 - x0);
+        crossings.push((x, if y1 > y0 { 1 } else { -1 }));
+      }
+    }
+  }
+  crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+  let mut result = Vec::new();
+  let mut winding = 0;
+  let mut start = 0.0;
+  for (x, direction) in crossings {
+    let previous = winding;
+    winding += direction;
+    if previous == 0 && winding != 0 {
+      start = x;
+    } else if previous != 0 && winding == 0 {
+      result.push(x - start);
+    }
+  }
+  result
+}
+
+// 走査線ごとの塗りの区間のうち、最も多く現れる幅をステム幅とみなす
+// 幅が tolerance 以内の区間の数が最大になるものを選び、その近くの区間の平均を返す
+fn dominant_width(widths: &[f64], tolerance: f64) -> Option<f64> {
+  let neighbours = |w: f64| widths.iter().filter(move |&&other| (other - w).abs() <= tolerance);
+  let &mode = widths.iter().max_by_key(|&&w| neighbours(w).count())?;
+  let close: Vec<f64> = neighbours(mode).copied().collect();
+  Some(close.iter().sum::<f64>() / close.len() as f64)
+}
+
+// グリフの輪郭から測った形の量（フォント単位）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlineMetrics {
+  // 塗られる面積。重なりは1度だけ数える
+  pub area: f64,
+  // 重なりを除いた輪郭の長さ
+  pub perimeter: f64,
+  // 塗られる部分の重心
+  pub centroid: Option<(f64, f64)>,
+  // 縦のステムの太さ（水平に測った幅）
+  pub vertical_stem: Option<f64>,
+  // 横のステムの太さ（垂直に測った幅）
+  pub horizontal_stem: Option<f64>,
+}
+
+impl OutlineMetrics {
+  pub fn measure(segments: &[PathSegment]) -> Self {
+    // 重なりを除くと外側の輪郭が時計回りにそろうので、面積は符号を反転させた和になる
+    let outline = remove_overlaps(segments);
+    let mut sink = MomentSink::default();
+    PathSegment::replay(&outline, &mut sink);
+    let area = -sink.area;
+    let centroid = (area > 0.0).then(|| (-sink.moment_x / area, -sink.moment_y / area));
+
+    let polylines = polylines(&outline);
+    let perimeter = polylines.iter()
+      .flat_map(|polyline| polyline.windows(2))
+      .map(|w| ((w[1].0 - w[0].0).powi(2) + (w[1].1 - w[0].1).powi(2)).sqrt())
+      .sum();
+
+    let (mut x_min, mut y_min, mut x_max, mut y_max) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for &(x, y) in polylines.iter().flatten() {
+      x_min = x_min.min(x);
+      y_min = y_min.min(y);
+      x_max = x_max.max(x);
+      y_max = y_max.max(y);
+    }
+    let (vertical_stem, horizontal_stem) = if area > 0.0 {
+      let tolerance = ((x_max - x_min).max(y_max - y_min) * 0.01).max(1.0);
+      // ステムは太さより長いはずなので、走査線と直交する方向の大きさの半分より広い区間は数えない（"l" の縦の区間など）
+      let sample = |polylines: &[Vec<(f64, f64)>], low: f64, high: f64| {
+        let widths: Vec<f64> = (0..SCANLINES)
+          .flat_map(|i| runs(polylines, low + (high - low) * (i as f64 + 0.5) / SCANLINES as f64))
+          .filter(|&width| width < (high - low) / 2.0)
+          .collect();
+        dominant_width(&widths, tolerance)
+      };
+      // 縦のステムは水平な走査線で、横のステムは x と y を入れ替えて測る
+      let swapped: Vec<Vec<(f64, f64)>> = polylines.iter()
+        .map(|polyline| polyline.iter().map(|&(x, y)| (y, x)).collect())
+        .collect();
+      (sample(&polylines, y_min, y_max), sample(&swapped, x_min, x_max))
+    } else {
+      (None, None)
+    };
+
+    OutlineMetrics { area, perimeter, centroid, vertical_stem, horizontal_stem }
+  }
+
+  // 送り幅 × 高さの枠のうち塗られている割合
+  pub fn darkness(&self, advance: f64, height: f64) -> f64 {
+    let frame = advance * height;
+    if frame > 0.0 { self.area / frame } else { 0.0 }
+  }
+}
+
+impl Outline {
+  pub fn metrics(&self) -> OutlineMetrics {
+    OutlineMetrics::measure(&self.segments())
+  }
+}
+
+// 文字集合全体での濃さ（組版したときの「色」）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontColour {
+  pub glyphs: usize,
+  // グリフごとの濃さの平均
+  pub average_darkness: f64,
+  // 全グリフの面積の和を枠の面積の和で割ったもの。送り幅の広いグリフほど重みが大きい
+  pub colour: f64,
+  pub average_vertical_stem: Option<f64>,
+  pub average_horizontal_stem: Option<f64>,
+}
+
+impl FontColour {
+  // glyphs は (形の量, 送り幅)。height は枠の高さ（ふつうは ascender - descender）
+  pub fn new(glyphs: &[(OutlineMetrics, f64)], height: f64) -> Self {
+    let count = glyphs.len();
+    let average = |values: Vec<f64>| (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64);
+    let total_area: f64 = glyphs.iter().map(|(metrics, _)| metrics.area).sum();
+    let total_frame: f64 = glyphs.iter().map(|&(_, advance)| advance * height).sum();
+    FontColour {
+      glyphs: count,
+      average_darkness: average(glyphs.iter().map(|(metrics, advance)| metrics.darkness(*advance, height)).collect())
+        .unwrap_or(0.0),
+      colour: if total_frame > 0.0 { total_area / total_frame } else { 0.0 },
+      average_vertical_stem: average(glyphs.iter().filter_map(|(metrics, _)| metrics.vertical_stem).collect()),
+      average_horizontal_stem: average(glyphs.iter().filter_map(|(metrics, _)| metrics.horizontal_stem).collect()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // 反時計回りの長方形。reverse なら時計回り
+  fn rectangle(x0: f32, y0: f32, x1: f32, y1: f32, reverse: bool) -> Vec<PathSegment> {
+    let mut corners = [(x0, y0), (x1, y0), (x1, y1), (x0, y1)];
+    if reverse {
+      corners.reverse();
+    }
+    let mut segments = vec![PathSegment::MoveTo(corners[0].0, corners[0].1)];
+    segments.extend(corners[1..].iter().map(|&(x, y)| PathSegment::LineTo(x, y)));
+    segments.push(PathSegment::Close);
+    segments
+  }
+
+  fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-3
+  }
+
+  #[test]
+  fn rectangle_area_perimeter_and_centroid() {
+    let metrics = OutlineMetrics::measure(&rectangle(10.0, 20.0, 30.0, 220.0, false));
+    assert!(close(metrics.area, 4000.0));
+    assert!(close(metrics.perimeter, 440.0));
+    let (x, y) = metrics.centroid.unwrap();
+    assert!(close(x, 20.0) && close(y, 120.0));
+    // 縦長なので縦のステムの幅だけが測れる
+    assert!(close(metrics.vertical_stem.unwrap(), 20.0));
+    assert_eq!(metrics.horizontal_stem, None);
+    // 向きが逆でも同じ
+    let reversed = OutlineMetrics::measure(&rectangle(10.0, 20.0, 30.0, 220.0, true));
+    assert!(close(reversed.area, metrics.area) && reversed.vertical_stem == metrics.vertical_stem);
+  }
+
+  #[test]
+  fn annulus_subtracts_the_hole() {
+    let mut segments = rectangle(0.0, 0.0, 100.0, 100.0, false);
+    segments.extend(rectangle(25.0, 40.0, 75.0, 90.0, true));
+    let metrics = OutlineMetrics::measure(&segments);
+    assert!(close(metrics.area, 7500.0));
+    assert!(close(metrics.perimeter, 600.0));
+    // 穴が上に寄っているので重心は下に下がる：(50 * 10000 - 65 * 2500) / 7500
+    let (x, y) = metrics.centroid.unwrap();
+    assert!(close(x, 50.0) && close(y, 45.0));
+    assert!(close(metrics.vertical_stem.unwrap(), 25.0));
+  }
+
+  #[test]
+  fn stems_of_a_synthetic_h() {
+    // 幅30の縦棒2本と、重なって置かれた太さ20の横棒
+    let mut segments = rectangle(0.0, 0.0, 30.0, 300.0, false);
+    segments.extend(rectangle(170.0, 0.0, 200.0, 300.0, false));
+    segments.extend(rectangle(0.0, 140.0, 200.0, 160.0, false));
+    let metrics = OutlineMetrics::measure(&segments);
+    assert!(close(metrics.area, 2.0 * 30.0 * 300.0 + 140.0 * 20.0));
+    assert!(close(metrics.vertical_stem.unwrap(), 30.0));
+    assert!(close(metrics.horizontal_stem.unwrap(), 20.0));
+  }
+
+  #[test]
+  fn empty_outline_has_no_centroid_or_stems() {
+    let metrics = OutlineMetrics::measure(&[]);
+    assert_eq!(metrics.area, 0.0);
+    assert_eq!(metrics.centroid, None);
+    assert_eq!(metrics.vertical_stem, None);
+    assert_eq!(metrics.horizontal_stem, None);
+    assert_eq!(metrics.darkness(0.0, 100.0), 0.0);
+  }
+}
@@ -0,0 +1,219 @@
+use crate::svg::{guides, metrics_box, symbol_id, view_box, SvgKind, SvgMetrics, SvgOptions};
+
+// ルート要素から外す属性。大きさは出力側で決め直し、viewBox は包む <g> の transform にする
+const GEOMETRY_ATTRIBUTES: [&str; 8] = ["width", "height", "viewBox", "x", "y", "preserveAspectRatio", "version", "baseProfile"];
+
+// ルートの viewBox は em の正方形 (0, 0)-(units_per_em, units_per_em) に写る。その拡大と平行移動の行列。viewBox がなければ None
+fn view_box_transform(attributes: &[(&str, &str)], units_per_em: u16) -> Result<Option<String>, String> {
+  let Some(&(_, value)) = attributes.iter().find(|(name, _)| *name == "viewBox") else {
+    return Ok(None);
+  };
+  let numbers = value.split(|c: char| c == ',' || c.is_whitespace())
+    .filter(|s| !s.is_empty())
+    .map(|s| s.parse::<f32>())
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|_| format!("SVG 文書の viewBox '{}' を読めません", value))?;
+  let &[x, y, width, height] = numbers.as_slice() else {
+    return Err(format!("SVG 文書の viewBox '{}' を読めません", value));
+  };
+  if width <= 0.0 || height <= 0.0 {
+    return Err(format!("SVG 文書の viewBox '{}' の幅か高さが 0 以下です", value));
+  }
+  let em = units_per_em as f32;
+  let (sx, sy) = (em / width, em / height);
+  // preserveAspectRatio の既定は xMidYMid meet。none なら縦横を別々に伸ばす
+  let aspect = attributes.iter().find(|(name, _)| *name == "preserveAspectRatio").map_or("", |&(_, value)| value);
+  let mut tokens = aspect.split_whitespace();
+  let align = tokens.next().unwrap_or("xMidYMid");
+  let (sx, sy, dx, dy) = if align == "none" {
+    (sx, sy, 0.0, 0.0)
+  } else {
+    let scale = if tokens.next() == Some("slice") { sx.max(sy) } else { sx.min(sy) };
+    let fraction = |part: Option<&str>| match part {
+      Some("Min") => 0.0,
+      Some("Max") => 1.0,
+      _ => 0.5,
+    };
+    let (fx, fy) = (fraction(align.get(1..4)), fraction(align.get(5..8)));
+    (scale, scale, (em - width * scale) * fx, (em - height * scale) * fy)
+  };
+  Ok(Some(format!("matrix({} 0 0 {} {} {})", sx, sy, dx - x * sx, dy - y * sy)))
+}
+
+// ルートの <svg> 要素。attributes は開始タグの中身、content は子要素
+struct RootElement<'a> {
+  attributes: Vec<(&'a str, &'a str)>,
+  content: &'a str,
+}
+
+// 開始タグの属性を（名前, 値）で並べる。値は引用符を外したもの
+fn parse_attributes(tag: &str) -> Vec<(&str, &str)> {
+  let mut attributes = Vec::new();
+  let mut rest = tag.trim_start();
+  while let Some(eq) = rest.find('=') {
+    let name = rest[..eq].trim();
+    let value = rest[eq + 1..].trim_start();
+    let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+      break;
+    };
+    let Some(end) = value[1..].find(quote) else {
+      break;
+    };
+    attributes.push((name, &value[1..end + 1]));
+    rest = value[end + 2..].trim_start();
+  }
+  attributes
+}
+
+// XML 宣言、コメント、DOCTYPE を読み飛ばしてルート要素を探す
+fn root_element(document: &str) -> Result<RootElement<'_>, String> {
+  let mut at = 0;
+  loop {
+    let start = at + document[at..].find('<').ok_or("SVG 文書にルート要素がありません")?;
+    let rest = &document[start..];
+    let skip = if rest.starts_with("<?") {
+      rest.find("?>").map(|end| end + 2)
+    } else if rest.starts_with("<!--") {
+      rest.find("-->").map(|end| end + 3)
+    } else if rest.starts_with("<!") {
+      // DOCTYPE の内部サブセットは [ ] で囲まれている
+      match (rest.find('['), rest.find('>')) {
+        (Some(open), Some(close)) if open < close => rest.find("]>").map(|end| end + 2),
+        (_, close) => close.map(|end| end + 1),
+      }
+    } else {
+      None
+    };
+    if let Some(skip) = skip {
+      at = start + skip;
+      continue;
+    }
+    if !rest.starts_with("<svg") {
+      return Err("SVG 文書のルート要素が <svg> ではありません".to_string());
+    }
+
+    // 属性値の中の > を閉じ括弧と間違えないように引用符を追う
+    let mut quote = None;
+    let mut tag_end = None;
+    for (i, c) in rest.char_indices().skip(4) {
+      match (quote, c) {
+        (None, '"' | '\'') => quote = Some(c),
+        (Some(q), _) if q == c => quote = None,
+        (None, '>') => {
+          tag_end = Some(i);
+          break;
+        }
+        _ => {}
+      }
+    }
+    let tag_end = tag_end.ok_or("SVG 文書の <svg> タグが閉じていません")?;
+    let self_closing = rest[..tag_end].ends_with('/');
+    let attributes = parse_attributes(&rest[4..if self_closing { tag_end - 1 } else { tag_end }]);
+    let content = if self_closing {
+      ""
+    } else {
+      let close = rest.rfind("</svg>").ok_or("SVG 文書の </svg> がありません")?;
+      &rest[tag_end + 1..close]
+    };
+    return Ok(RootElement { attributes, content });
+  }
+}
+
+// id とその参照に接頭辞を付ける。スプライトに複数のグリフを並べても id がぶつからないようにする
+fn prefix_ids(svg: &str, prefix: &str) -> String {
+  let mut result = svg.to_string();
+  for quote in ['"', '\''] {
+    for space in [' ', '\t', '\n', '\r'] {
+      let pattern = format!("{}id={}", space, quote);
+      result = result.replace(&pattern, &format!("{}{}", pattern, prefix));
+    }
+    let pattern = format!("href={}#", quote);
+    result = result.replace(&pattern, &format!("{}{}", pattern, prefix));
+    let pattern = format!("url({}#", quote);
+    result = result.replace(&pattern, &format!("{}{}", pattern, prefix));
+  }
+  result.replace("url(#", &format!("url(#{}", prefix))
+}
+
+// SVG テーブルの文書を、glyph<ID> の要素だけが見える単独のグリフの SVG にする
+// SVG テーブルの座標系は y軸下向きでベースラインが y = 0 なので、glyph_to_svg の viewBox にそのまま収まる
+// 文書には輪郭がないので、viewBox は --view-box tight でもメトリクスで決める
+pub fn svg_document_to_svg(document: &str, glyph_id: u16, metrics: &SvgMetrics, options: &SvgOptions) -> Result<String, String> {
+  let root = root_element(document)?;
+  let glyph = format!("glyph{}", glyph_id);
+  let is_root = root.attributes.iter().any(|&(name, value)| name == "id" && value == glyph);
+  if !is_root && !root.content.contains(&format!("id=\"{}\"", glyph)) && !root.content.contains(&format!("id='{}'", glyph)) {
+    return Err(format!("SVG 文書に {} の要素がありません", glyph));
+  }
+
+  // ルートの属性（名前空間の宣言や継承されるスタイル）は包む <g> に移す
+  let mut body = String::from("<g");
+  for (name, value) in root.attributes.iter().filter(|(name, _)| !GEOMETRY_ATTRIBUTES.contains(name) && *name != "transform") {
+    let quote = if value.contains('"') { '\'' } else { '"' };
+    body.push_str(&format!(" {}={}{}{}", name, quote, value, quote));
+  }
+  // viewBox の写像はルート自身の transform の内側にかかる
+  let transforms: Vec<String> = root.attributes.iter()
+    .filter(|(name, _)| *name == "transform")
+    .map(|(_, value)| value.to_string())
+    .chain(view_box_transform(&root.attributes, metrics.units_per_em)?)
+    .collect();
+  if !transforms.is_empty() {
+    let transform = transforms.join(" ");
+    let quote = if transform.contains('"') { '\'' } else { '"' };
+    body.push_str(&format!(" transform={}{}{}", quote, transform, quote));
+  }
+  body.push('>');
+  if is_root {
+    body.push_str(root.content);
+  } else {
+    // 文書全体を defs に入れて、グリフの要素だけを <use> で描く
+    body.push_str(&format!("<defs>{}</defs><use href='#{}' />", root.content, glyph));
+  }
+  body.push_str("</g>");
+
+  let view = metrics_box(metrics, None);
+  let mut content = String::new();
+  if options.guides {
+    content.push_str(&format!("<g transform='scale(1 -1)'>{}</g>", guides(view, None, metrics)));
+  }
+  match &options.kind {
+    SvgKind::Document => {
+      content.push_str(&body);
+      Ok(format!("<svg viewBox='{}' xmlns='http://www.w3.org/2000/svg'>{}</svg>", view_box(&view), content))
+    }
+    SvgKind::Symbol { id } => {
+      let id = symbol_id(id);
+      content.push_str(&prefix_ids(&body, &format!("{}-", id)));
+      Ok(format!("<symbol id='{}' viewBox='{}'>{}</symbol>", id, view_box(&view), content))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn transform(root: &str) -> Option<String> {
+    view_box_transform(&parse_attributes(root), 1000).unwrap()
+  }
+
+  #[test]
+  fn view_box_maps_onto_the_em_square() {
+    assert_eq!(transform(" viewBox='0 0 100 100'"), Some("matrix(10 0 0 10 0 0)".to_string()));
+    assert_eq!(transform(" viewBox='-50,0,100,100'"), Some("matrix(10 0 0 10 500 0)".to_string()));
+    // 縦長の viewBox は既定では縦に合わせて中央に寄せる
+    assert_eq!(transform(" viewBox='0 0 50 100'"), Some("matrix(10 0 0 10 250 0)".to_string()));
+    assert_eq!(transform(" viewBox='0 0 50 100' preserveAspectRatio='xMinYMin'"), Some("matrix(10 0 0 10 0 0)".to_string()));
+    assert_eq!(transform(" viewBox='0 0 50 100' preserveAspectRatio='none'"), Some("matrix(20 0 0 10 0 0)".to_string()));
+    assert_eq!(transform(" width='100'"), None);
+  }
+
+  #[test]
+  fn root_transform_wraps_the_view_box() {
+    let metrics = SvgMetrics { units_per_em: 1000, ascender: 800, descender: -200, advance_width: 500 };
+    let document = "<svg id='glyph3' viewBox='0 0 100 100' width='100' transform='scale(2)'><path d='M0 0'/></svg>";
+    let svg = svg_document_to_svg(document, 3, &metrics, &SvgOptions::default()).unwrap();
+    assert!(svg.contains("<g id=\"glyph3\" transform=\"scale(2) matrix(10 0 0 10 0 0)\"><path"), "{}", svg);
+  }
+}
@@ -0,0 +1,84 @@
+use crate::truetype::outline::PathSegment;
+
+// アウトラインを受け取る側が実装するトレイト
+// グリフの出どころ（TrueType、複合グリフ、CFF、バリエーション）によらず、この呼び出し列で輪郭を流し込む
+// 各輪郭は move_to で始まり close で終わる
+pub trait OutlineSink {
+  fn move_to(&mut self, x: f32, y: f32);
+  fn line_to(&mut self, x: f32, y: f32);
+  fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32);
+  fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32);
+  fn close(&mut self);
+}
+
+// 呼び出しをそのまま記録する
+impl OutlineSink for Vec<PathSegment> {
+  fn move_to(&mut self, x: f32, y: f32) {
+    self.push(PathSegment::MoveTo(x, y));
+  }
+
+  fn line_to(&mut self, x: f32, y: f32) {
+    self.push(PathSegment::LineTo(x, y));
+  }
+
+  fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+    self.push(PathSegment::QuadTo(x1, y1, x, y));
+  }
+
+  fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+    self.push(PathSegment::CubicTo(x1, y1, x2, y2, x, y));
+  }
+
+  fn close(&mut self) {
+    self.push(PathSegment::Close);
+  }
+}
+
+impl PathSegment {
+  // 記録した要素を別の受け手に流し直す
+  pub fn replay<S: OutlineSink + ?Sized>(segments: &[PathSegment], sink: &mut S) {
+    for segment in segments {
+      match *segment {
+        PathSegment::MoveTo(x, y) => sink.move_to(x, y),
+        PathSegment::LineTo(x, y) => sink.line_to(x, y),
+        PathSegment::QuadTo(x1, y1, x, y) => sink.quad_to(x1, y1, x, y),
+        PathSegment::CubicTo(x1, y1, x2, y2, x, y) => sink.cubic_to(x1, y1, x2, y2, x, y),
+        PathSegment::Close => sink.close(),
+      }
+    }
+  }
+}
+
+// SVGの path 要素の d 属性を組み立てる
+#[derive(Debug, Default)]
+pub struct SvgPathBuilder {
+  pub data: String,
+}
+
+impl SvgPathBuilder {
+  pub fn new() -> Self {
+    SvgPathBuilder::default()
+  }
+}
+
+impl OutlineSink for SvgPathBuilder {
+  fn move_to(&mut self, x: f32, y: f32) {
+    self.data.push_str(&format!("M{} {} ", x, y));
+  }
+
+  fn line_to(&mut self, x: f32, y: f32) {
+    self.data.push_str(&format!("L {} {} ", x, y));
+  }
+
+  fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+    self.data.push_str(&format!("Q {} {}, {} {} ", x1, y1, x, y));
+  }
+
+  fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+    self.data.push_str(&format!("C {} {}, {} {}, {} {} ", x1, y1, x2, y2, x, y));
+  }
+
+  fn close(&mut self) {
+    self.data.push('Z');
+  }
+}
@@ -0,0 +1,393 @@
+use crate::convert::cubic_to_quads;
+use crate::geometry::{Point, Segment};
+use crate::truetype::outline::PathSegment;
+use std::f64::consts::{FRAC_PI_4, PI};
+
+// 線の継ぎ目の形
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineJoin {
+  Miter,
+  Round,
+  Bevel,
+}
+
+// 開いた輪郭の端の形
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineCap {
+  Butt,
+  Round,
+  Square,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StrokeOptions {
+  // 線幅（フォント単位）
+  pub width: f32,
+  pub join: LineJoin,
+  // マイター長と線幅の比の上限。超えたらベベルにする（SVG の stroke-miterlimit と同じ）
+  pub miter_limit: f32,
+  pub cap: LineCap,
+}
+
+impl Default for StrokeOptions {
+  fn default() -> Self {
+    StrokeOptions {
+      width: 1.0,
+      join: LineJoin::Miter,
+      miter_limit: 4.0,
+      cap: LineCap::Butt,
+    }
+  }
+}
+
+// 輪郭。closed なら最後の点から始点へ戻る
+struct Contour {
+  pieces: Vec<Segment>,
+  start: Point,
+  closed: bool,
+}
+
+// 3次ベジェは2次ベジェに近似して、直線と2次ベジェだけのセグメントにする
+fn split_contours(segments: &[PathSegment], tolerance: f64) -> Vec<Contour> {
+  let mut contours: Vec<Contour> = Vec::new();
+  let mut current = Point::new(0.0, 0.0);
+  let point = |x: f32, y: f32| Point::new(x as f64, y as f64);
+  for segment in segments {
+    if let PathSegment::MoveTo(x, y) = *segment {
+      current = point(x, y);
+      contours.push(Contour { pieces: Vec::new(), start: current, closed: false });
+      continue;
+    }
+    let Some(contour) = contours.last_mut() else {
+      continue;
+    };
+    match *segment {
+      PathSegment::LineTo(x, y) => {
+        let p = point(x, y);
+        contour.pieces.push(Segment::Line(current, p));
+        current = p;
+      }
+      PathSegment::QuadTo(x1, y1, x, y) => {
+        let p = point(x, y);
+        contour.pieces.push(Segment::Quad(current, point(x1, y1), p));
+        current = p;
+      }
+      PathSegment::CubicTo(x1, y1, x2, y2, x, y) => {
+        let p = point(x, y);
+        let mut start = current;
+        for (control, end) in cubic_to_quads(current, point(x1, y1), point(x2, y2), p, tolerance) {
+          contour.pieces.push(Segment::Quad(start, control, end));
+          start = end;
+        }
+        current = p;
+      }
+      PathSegment::Close => {
+        if current != contour.start {
+          contour.pieces.push(Segment::Line(current, contour.start));
+        }
+        contour.closed = true;
+        current = contour.start;
+      }
+      PathSegment::MoveTo(..) => unreachable!(),
+    }
+  }
+  for contour in &mut contours {
+    contour.pieces.retain(|piece| !piece.is_degenerate());
+  }
+  contours
+}
+
+// 出力するパスを組み立てる
+struct Builder {
+  segments: Vec<PathSegment>,
+  current: Point,
+}
+
+impl Builder {
+  fn move_to(&mut self, p: Point) {
+    self.segments.push(PathSegment::MoveTo(p.x as f32, p.y as f32));
+    self.current = p;
+  }
+
+  fn line_to(&mut self, p: Point) {
+    if p != self.current {
+      self.segments.push(PathSegment::LineTo(p.x as f32, p.y as f32));
+      self.current = p;
+    }
+  }
+
+  fn quad_to(&mut self, c: Point, p: Point) {
+    self.segments.push(PathSegment::QuadTo(c.x as f32, c.y as f32, p.x as f32, p.y as f32));
+    self.current = p;
+  }
+
+  fn close(&mut self) {
+    self.segments.push(PathSegment::Close);
+  }
+
+  // center を中心に現在の点から sweep ラジアン回る円弧（45度ごとの2次ベジェで近似）
+  fn arc(&mut self, center: Point, sweep: f64) {
+    let from = self.current - center;
+    let radius = from.length();
+    let start = from.y.atan2(from.x);
+    let count = (sweep.abs() / FRAC_PI_4).ceil().max(1.0) as usize;
+    let step = sweep / count as f64;
+    for i in 0..count {
+      let a0 = start + step * i as f64;
+      let a1 = a0 + step;
+      let middle = (a0 + a1) / 2.0;
+      let control_radius = radius / (step / 2.0).cos();
+      let c = center + Point::new(middle.cos(), middle.sin()) * control_radius;
+      let p = center + Point::new(a1.cos(), a1.sin()) * radius;
+      self.quad_to(c, p);
+    }
+  }
+}
+
+struct Stroker {
+  options: StrokeOptions,
+  half: f64,
+  tolerance: f64,
+  out: Builder,
+}
+
+impl Stroker {
+  // 2次ベジェの左側のオフセット曲線。誤差が大きければ分割する
+  fn offset_quad(&mut self, piece: Segment, depth: u32) {
+    let (p0, p1) = (piece.start(), piece.end());
+    let (d0, d1) = (piece.direction(0.0), piece.direction(1.0));
+    let s = p0 + d0.left_normal() * self.half;
+    let e = p1 + d1.left_normal() * self.half;
+
+    // 両端の接線の交点を制御点にする
+    let denominator = d0.cross(d1);
+    let control = if denominator.abs() < 1e-6 * d0.length() * d1.length() {
+      None
+    } else {
+      let t = (e - s).cross(d1) / denominator;
+      Some(s + d0 * t)
+    };
+
+    // 中点でのずれを見て分割するか決める
+    let target = piece.point(0.5) + piece.direction(0.5).left_normal() * self.half;
+    let approximate = match control {
+      Some(control) => Segment::Quad(s, control, e).point(0.5),
+      None => s.lerp(e, 0.5),
+    };
+    let error = (approximate - target).length();
+    let turn = d0.normalize().dot(d1.normalize());
+    if depth < 8 && (error > self.tolerance || turn < FRAC_PI_4.cos()) {
+      let (head, tail) = piece.split(0.5);
+      self.offset_quad(head, depth + 1);
+      self.offset_quad(tail, depth + 1);
+      return;
+    }
+
+    self.out.line_to(s);
+    match control {
+      Some(control) => self.out.quad_to(control, e),
+      None => self.out.line_to(e),
+    }
+  }
+
+  fn offset(&mut self, piece: &Segment) {
+    match *piece {
+      Segment::Line(p0, p1) => {
+        let n = (p1 - p0).left_normal() * self.half;
+        self.out.line_to(p0 + n);
+        self.out.line_to(p1 + n);
+      }
+      Segment::Quad(..) => self.offset_quad(*piece, 0),
+      // split_contours で2次ベジェにしてある
+      Segment::Cubic(..) => unreachable!(),
+    }
+  }
+
+  // 頂点 p で方向 d0 から d1 へ曲がるときの左側の継ぎ目。現在の点は p の左側のオフセット
+  fn join(&mut self, p: Point, d0: Point, d1: Point) {
+    let (n0, n1) = (d0.left_normal(), d1.left_normal());
+    let b = p + n1 * self.half;
+    let cross = d0.cross(d1);
+    let cos = n0.dot(n1);
+    // 滑らかにつながる点は直線でつなぐだけでよい
+    if cos > 0.9999 && cross.abs() <= 1e-4 * d0.length() * d1.length() {
+      self.out.line_to(b);
+      return;
+    }
+    if cross > 0.0 {
+      // 左に曲がるとき左側は内側。中心を通ってつなぐと非ゼロ規則で塗りが欠けない
+      self.out.line_to(p);
+      self.out.line_to(b);
+      return;
+    }
+    match self.options.join {
+      LineJoin::Bevel => self.out.line_to(b),
+      LineJoin::Miter => {
+        // マイター長と線幅の比は 1 / sin(θ/2) = sqrt(2 / (1 + cos))
+        if 1.0 + cos > 1e-6 && (2.0 / (1.0 + cos)).sqrt() <= self.options.miter_limit as f64 {
+          let miter = p + (n0 + n1) * (self.half / (1.0 + cos));
+          self.out.line_to(miter);
+        }
+        self.out.line_to(b);
+      }
+      LineJoin::Round => {
+        let sweep = cross.atan2(d0.dot(d1));
+        self.out.arc(p, sweep);
+      }
+    }
+  }
+
+  // 端点 p（進行方向 d）での端の形。現在の点は左側のオフセットで、右側のオフセットまで回る
+  fn cap(&mut self, p: Point, d: Point) {
+    let n = d.left_normal() * self.half;
+    let forward = d.normalize() * self.half;
+    match self.options.cap {
+      LineCap::Butt => self.out.line_to(p - n),
+      LineCap::Square => {
+        self.out.line_to(p + n + forward);
+        self.out.line_to(p - n + forward);
+        self.out.line_to(p - n);
+      }
+      LineCap::Round => self.out.arc(p, -PI),
+    }
+  }
+
+  // pieces の左側を、継ぎ目を挟みながらたどる
+  fn side(&mut self, pieces: &[Segment], closed: bool) {
+    for (i, piece) in pieces.iter().enumerate() {
+      if i > 0 {
+        self.join(piece.start(), pieces[i - 1].direction(1.0), piece.direction(0.0));
+      }
+      self.offset(piece);
+    }
+    if closed {
+      let (last, first) = (&pieces[pieces.len() - 1], &pieces[0]);
+      self.join(first.start(), last.direction(1.0), first.direction(0.0));
+    }
+  }
+
+  // 長さのない開いた輪郭は、丸と四角の端なら点として描く
+  fn point(&mut self, p: Point) {
+    let h = self.half;
+    match self.options.cap {
+      LineCap::Butt => {}
+      LineCap::Round => {
+        self.out.move_to(p + Point::new(h, 0.0));
+        self.out.arc(p, 2.0 * PI);
+        self.out.close();
+      }
+      LineCap::Square => {
+        self.out.move_to(p + Point::new(-h, -h));
+        self.out.line_to(p + Point::new(h, -h));
+        self.out.line_to(p + Point::new(h, h));
+        self.out.line_to(p + Point::new(-h, h));
+        self.out.close();
+      }
+    }
+  }
+
+  fn contour(&mut self, contour: &Contour) {
+    let pieces = &contour.pieces;
+    if pieces.is_empty() {
+      if !contour.closed {
+        self.point(contour.start);
+      }
+      return;
+    }
+    let reversed: Vec<Segment> = pieces.iter().rev().map(|piece| piece.reverse()).collect();
+    let half = self.half;
+    let start_of = |pieces: &[Segment]| pieces[0].start() + pieces[0].direction(0.0).left_normal() * half;
+
+    if contour.closed {
+      // 左側と右側（逆向きにたどった左側）の2つの閉じた輪郭になる
+      self.out.move_to(start_of(pieces));
+      self.side(pieces, true);
+      self.out.close();
+      self.out.move_to(start_of(&reversed));
+      self.side(&reversed, true);
+      self.out.close();
+    } else {
+      let (first, last) = (&pieces[0], &pieces[pieces.len() - 1]);
+      self.out.move_to(start_of(pieces));
+      self.side(pieces, false);
+      self.cap(last.end(), last.direction(1.0));
+      self.side(&reversed, false);
+      self.cap(first.start(), first.direction(0.0) * -1.0);
+      self.out.close();
+    }
+  }
+}
+
+// 輪郭の線を太さ options.width で塗りつぶす輪郭に変換する（非ゼロ規則で塗る前提）
+// MoveTo から Close までを閉じた輪郭、Close のないものを開いた輪郭として扱う
+pub fn stroke(segments: &[PathSegment], options: &StrokeOptions) -> Vec<PathSegment> {
+  let half = options.width.abs() as f64 / 2.0;
+  let tolerance = (options.width.abs() as f64 * 0.005).max(0.05);
+  let mut stroker = Stroker {
+    options: *options,
+    half,
+    tolerance,
+    out: Builder { segments: Vec::new(), current: Point::new(0.0, 0.0) },
+  };
+  if half == 0.0 {
+    return Vec::new();
+  }
+  for contour in split_contours(segments, tolerance) {
+    stroker.contour(&contour);
+  }
+  stroker.out.segments
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::measure::OutlineMetrics;
+  use PathSegment::*;
+
+  fn square() -> Vec<PathSegment> {
+    vec![MoveTo(0.0, 0.0), LineTo(0.0, 100.0), LineTo(100.0, 100.0), LineTo(100.0, 0.0), Close]
+  }
+
+  fn area(segments: &[PathSegment], options: StrokeOptions) -> f64 {
+    OutlineMetrics::measure(&stroke(segments, &options)).area
+  }
+
+  fn joined(join: LineJoin) -> StrokeOptions {
+    StrokeOptions { width: 10.0, join, ..Default::default() }
+  }
+
+  fn capped(cap: LineCap) -> StrokeOptions {
+    StrokeOptions { width: 10.0, cap, ..Default::default() }
+  }
+
+  fn close(actual: f64, expected: f64, tolerance: f64) -> bool {
+    (actual - expected).abs() <= tolerance
+  }
+
+  #[test]
+  fn closed_square_joins() {
+    // 外側 110 × 110 から内側 90 × 90 を除くと 4000。角を丸めると 4 隅で 25 - 25π/4 ずつ、斜めに切ると 12.5 ずつ減る
+    assert!(close(area(&square(), joined(LineJoin::Miter)), 4000.0, 1e-6));
+    assert!(close(area(&square(), joined(LineJoin::Round)), 3900.0 + 25.0 * PI, 0.5));
+    assert!(close(area(&square(), joined(LineJoin::Bevel)), 3950.0, 1e-6));
+  }
+
+  #[test]
+  fn open_line_caps() {
+    // 長さ 100、幅 10 の線。丸い端は両端で半径 5 の円1つ分、四角い端は 5 ずつ伸びる
+    let line = [MoveTo(0.0, 0.0), LineTo(100.0, 0.0)];
+    assert!(close(area(&line, capped(LineCap::Butt)), 1000.0, 1e-6));
+    assert!(close(area(&line, capped(LineCap::Round)), 1000.0 + 25.0 * PI, 0.5));
+    assert!(close(area(&line, capped(LineCap::Square)), 1100.0, 1e-6));
+  }
+
+  #[test]
+  fn sharp_angle_beyond_the_miter_limit_falls_back_to_bevel() {
+    // 約 11 度で折り返す線。マイター長の比は約 10 で、既定の上限 4 を超える
+    let v = [MoveTo(0.0, 0.0), LineTo(100.0, 10.0), LineTo(0.0, 20.0)];
+    let bevel = area(&v, joined(LineJoin::Bevel));
+    assert!(close(area(&v, joined(LineJoin::Miter)), bevel, 1e-6));
+    let unlimited = area(&v, StrokeOptions { miter_limit: 100.0, ..joined(LineJoin::Miter) });
+    assert!(unlimited > bevel + 100.0, "{} {}", unlimited, bevel);
+  }
+}
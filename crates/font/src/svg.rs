@@ -0,0 +1,190 @@
+use crate::bbox::{BoundingBox, BoundsSink};
+use crate::sink::SvgPathBuilder;
+use crate::tables::cpal::Color;
+use crate::truetype::outline::{Outline, PathSegment};
+use crate::truetype::SimpleGlyph;
+
+// viewBox の決め方
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewBoxMode {
+  // 原点から送り幅まで、ディセンダからアセンダまで（はみ出すグリフは含める）
+  Metrics,
+  // グリフの外接矩形（曲線の極値まで含めた正確なもの）ぴったり
+  Tight,
+}
+
+// 出力の形
+#[derive(Debug, Clone, PartialEq)]
+pub enum SvgKind {
+  // 単独のSVG文書
+  Document,
+  // スプライトに埋め込む <symbol>
+  Symbol { id: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct SvgOptions {
+  pub view_box: ViewBoxMode,
+  // ベースライン、送り幅、外接矩形の補助線を描く
+  pub guides: bool,
+  pub kind: SvgKind,
+}
+
+impl Default for SvgOptions {
+  fn default() -> Self {
+    SvgOptions {
+      view_box: ViewBoxMode::Metrics,
+      guides: false,
+      kind: SvgKind::Document,
+    }
+  }
+}
+
+// viewBox の計算に使うメトリクス（フォント単位）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SvgMetrics {
+  pub units_per_em: u16,
+  pub ascender: i16,
+  pub descender: i16,
+  pub advance_width: u16,
+}
+
+pub(crate) fn metrics_box(metrics: &SvgMetrics, glyph: Option<BoundingBox>) -> BoundingBox {
+  let (mut ascender, mut descender) = (metrics.ascender as f32, metrics.descender as f32);
+  if ascender <= descender {
+    // hhea が空のフォントでは em の8割をアセンダとみなす
+    ascender = metrics.units_per_em as f32 * 0.8;
+    descender = ascender - metrics.units_per_em as f32;
+  }
+  let rect = BoundingBox { x_min: 0.0, y_min: descender, x_max: metrics.advance_width as f32, y_max: ascender };
+  match glyph {
+    Some(glyph) => rect.union(&glyph),
+    None => rect,
+  }
+}
+
+pub(crate) fn guides(view: BoundingBox, glyph: Option<BoundingBox>, metrics: &SvgMetrics) -> String {
+  let line = |x1: f32, y1: f32, x2: f32, y2: f32, color: &str| {
+    format!("<line x1='{}' y1='{}' x2='{}' y2='{}' stroke='{}' vector-effect='non-scaling-stroke' />", x1, y1, x2, y2, color)
+  };
+  let mut svg = String::from("<g fill='none'>");
+  svg.push_str(&line(view.x_min, 0.0, view.x_max, 0.0, "#1e90ff"));
+  svg.push_str(&line(0.0, view.y_min, 0.0, view.y_max, "#2e8b57"));
+  let advance = metrics.advance_width as f32;
+  svg.push_str(&line(advance, view.y_min, advance, view.y_max, "#2e8b57"));
+  if let Some(glyph) = glyph {
+    svg.push_str(&format!(
+      "<rect x='{}' y='{}' width='{}' height='{}' stroke='#ff4500' vector-effect='non-scaling-stroke' />",
+      glyph.x_min, glyph.y_min, glyph.width(), glyph.height()
+    ));
+  }
+  svg.push_str("</g>");
+  svg
+}
+
+// グリフをSVGにする。y軸はフォント座標（上向き）から反転する
+pub fn glyph_to_svg(glyph: &SimpleGlyph, metrics: &SvgMetrics, options: &SvgOptions) -> String {
+  outline_to_svg(&Outline::from_simple_glyph(glyph).segments(), metrics, options)
+}
+
+// 変換や合成スタイルを適用した後の輪郭をSVGにする
+pub fn outline_to_svg(segments: &[PathSegment], metrics: &SvgMetrics, options: &SvgOptions) -> String {
+  layers_to_svg(&[(segments.to_vec(), None)], metrics, options)
+}
+
+// <symbol> の id に使える名前（XML の NCName）にする
+// 使えない文字は _ に置き換え、先頭が名前の開始文字でなければ _ を付ける。url(#...) の参照にもそのまま使える
+pub fn symbol_id(id: &str) -> String {
+  let mut name: String = id.chars()
+    .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '.' | '_') { c } else { '_' })
+    .collect();
+  if !name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+    name.insert(0, '_');
+  }
+  name
+}
+
+// 色を塗る属性。先頭に空白がつく
+pub(crate) fn fill_attributes(color: &Color) -> String {
+  if color.a == 255 {
+    format!(" fill='{}'", color.to_hex())
+  } else {
+    format!(" fill='{}' fill-opacity='{:.3}'", color.to_hex(), color.a as f32 / 255.0)
+  }
+}
+
+// viewBox の文字列
+pub(crate) fn view_box(view: &BoundingBox) -> String {
+  format!("{} {} {} {}", view.x_min, -view.y_max, view.x_max - view.x_min, view.y_max - view.y_min)
+}
+
+// 色を付けたレイヤーを下から順に1つずつの <path> にする。色が None のレイヤーは文字色（fill を指定しない）
+pub fn layers_to_svg(layers: &[(Vec<PathSegment>, Option<Color>)], metrics: &SvgMetrics, options: &SvgOptions) -> String {
+  let mut bounds = BoundsSink::new();
+  for (segments, _) in layers {
+    PathSegment::replay(segments, &mut bounds);
+  }
+
+  let glyph_rect = bounds.exact;
+  let view = match (options.view_box, glyph_rect) {
+    (ViewBoxMode::Tight, Some(rect)) => rect,
+    // 空のグリフはメトリクスで枠を取る
+    _ => metrics_box(metrics, glyph_rect),
+  };
+  let view_box = view_box(&view);
+
+  let mut content = String::from("<g transform='scale(1 -1)'>");
+  if options.guides {
+    content.push_str(&guides(view, glyph_rect, metrics));
+  }
+  for (segments, color) in layers {
+    let mut builder = SvgPathBuilder::new();
+    PathSegment::replay(segments, &mut builder);
+    let fill = color.as_ref().map(fill_attributes).unwrap_or_default();
+    content.push_str(&format!("<path d='{}'{} />", builder.data, fill));
+  }
+  content.push_str("</g>");
+
+  match &options.kind {
+    SvgKind::Document => format!("<svg viewBox='{}' xmlns='http://www.w3.org/2000/svg'>{}</svg>", view_box, content),
+    SvgKind::Symbol { id } => format!("<symbol id='{}' viewBox='{}'>{}</symbol>", symbol_id(id), view_box, content),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn symbol_id_is_an_ncname() {
+    assert_eq!(symbol_id("glyph-a.1"), "glyph-a.1");
+    assert_eq!(symbol_id("あ"), "あ");
+    assert_eq!(symbol_id("a'><script>"), "a___script_");
+    assert_eq!(symbol_id("1st"), "_1st");
+    assert_eq!(symbol_id("-x"), "_-x");
+    assert_eq!(symbol_id(""), "_");
+  }
+
+  #[test]
+  fn symbol_output_uses_sanitised_id() {
+    let options = SvgOptions { kind: SvgKind::Symbol { id: "a\"b".to_string() }, ..Default::default() };
+    let metrics = SvgMetrics { units_per_em: 1000, ascender: 800, descender: -200, advance_width: 500 };
+    let svg = outline_to_svg(&[], &metrics, &options);
+    assert!(svg.starts_with("<symbol id='a_b' viewBox='0 -800 500 1000'>"), "{}", svg);
+  }
+
+  #[test]
+  fn layers_fill_with_opacity_only_when_translucent() {
+    let square = vec![PathSegment::MoveTo(0.0, 0.0), PathSegment::LineTo(0.0, 10.0), PathSegment::LineTo(10.0, 0.0), PathSegment::Close];
+    let layers = [
+      (square.clone(), Some(Color { r: 0xFF, g: 0x80, b: 0, a: 0xFF })),
+      (square.clone(), Some(Color { r: 0, g: 0, b: 0, a: 0x40 })),
+      // 文字色のレイヤーは fill を指定しない
+      (square, None),
+    ];
+    let metrics = SvgMetrics { units_per_em: 1000, ascender: 800, descender: -200, advance_width: 500 };
+    let svg = layers_to_svg(&layers, &metrics, &SvgOptions::default());
+    let paths: Vec<&str> = svg.split("<path").skip(1).map(|path| &path[path.find("Z'").unwrap() + 2..path.find("/>").unwrap()]).collect();
+    assert_eq!(paths, [" fill='#ff8000' ", " fill='#000000' fill-opacity='0.251' ", " "]);
+  }
+}
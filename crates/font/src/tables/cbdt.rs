@@ -0,0 +1,93 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+use super::cblc::{BigGlyphMetrics, BitmapLocation};
+
+// 'CBDT' の PNG のグリフ。形式 17 は SmallGlyphMetrics、18 は BigGlyphMetrics を持ち、19 は CBLC のメトリクスを使う
+#[derive(Debug, Clone)]
+pub struct CbdtGlyph {
+  pub metrics: BigGlyphMetrics,
+  pub png: Vec<u8>,
+}
+
+impl CbdtGlyph {
+  // CBDT テーブル全体の data から location のグリフを読む
+  pub fn parse(data: &[u8], location: &BitmapLocation) -> Result<Self, String> {
+    let end = location.offset as usize + location.length as usize;
+    let record = data.get(location.offset as usize..end).ok_or("CBDT のグリフがテーブルの外を指しています")?;
+    let mut cursor = Cursor::new(record);
+    let metrics = match (location.image_format, location.metrics) {
+      (17, _) => BigGlyphMetrics::read_small(&mut cursor)?,
+      (18, _) => BigGlyphMetrics::read(&mut cursor)?,
+      (19, Some(metrics)) => metrics,
+      (19, None) => return Err("CBDT の形式 19 のグリフに CBLC のメトリクスがありません".to_string()),
+      (other, _) => return Err(format!("CBDT の画像の形式{}には対応していません（PNG の 17〜19 のみ）", other)),
+    };
+    let length = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())? as usize;
+    let start = cursor.position() as usize;
+    let png = record.get(start..start + length).ok_or("CBDT の PNG がグリフの外にはみ出しています")?.to_vec();
+    Ok(CbdtGlyph { metrics, png })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const PNG: [u8; 4] = [0x89, b'P', b'N', b'G'];
+
+  fn location(image_format: u16, offset: u32, length: u32, metrics: Option<BigGlyphMetrics>) -> BitmapLocation {
+    BitmapLocation { image_format, offset, length, metrics }
+  }
+
+  // 画像の前に4バイトの余白を置いた CBDT
+  fn table(record: &[u8]) -> Vec<u8> {
+    let mut data = vec![0, 3, 0, 0];
+    data.extend(record);
+    data
+  }
+
+  fn png_record(metrics: &[u8]) -> Vec<u8> {
+    let mut record = metrics.to_vec();
+    record.extend((PNG.len() as u32).to_be_bytes());
+    record.extend(PNG);
+    record
+  }
+
+  #[test]
+  fn format_17_reads_small_metrics() {
+    let record = png_record(&[10, 8, 0xFF, 9, 11]);
+    let glyph = CbdtGlyph::parse(&table(&record), &location(17, 4, record.len() as u32, None)).unwrap();
+    assert_eq!(glyph.metrics, BigGlyphMetrics { height: 10, width: 8, hori_bearing_x: -1, hori_bearing_y: 9, hori_advance: 11, ..Default::default() });
+    assert_eq!(glyph.png, PNG);
+  }
+
+  #[test]
+  fn format_18_reads_big_metrics() {
+    let record = png_record(&[10, 8, 1, 9, 11, 0xFC, 2, 12]);
+    let glyph = CbdtGlyph::parse(&table(&record), &location(18, 4, record.len() as u32, None)).unwrap();
+    assert_eq!((glyph.metrics.vert_bearing_x, glyph.metrics.vert_bearing_y, glyph.metrics.vert_advance), (-4, 2, 12));
+    assert_eq!(glyph.png, PNG);
+  }
+
+  #[test]
+  fn format_19_takes_metrics_from_cblc() {
+    let record = png_record(&[]);
+    let metrics = BigGlyphMetrics { height: 5, width: 6, hori_advance: 7, ..Default::default() };
+    let glyph = CbdtGlyph::parse(&table(&record), &location(19, 4, record.len() as u32, Some(metrics))).unwrap();
+    assert_eq!(glyph.metrics, metrics);
+    assert_eq!(glyph.png, PNG);
+    // CBLC の索引がメトリクスを持たない（形式 1、3、4）ときは読めない
+    let error = CbdtGlyph::parse(&table(&record), &location(19, 4, record.len() as u32, None)).unwrap_err();
+    assert_eq!(error, "CBDT の形式 19 のグリフに CBLC のメトリクスがありません");
+  }
+
+  #[test]
+  fn unsupported_formats_and_overruns_are_errors() {
+    let record = png_record(&[]);
+    assert!(CbdtGlyph::parse(&table(&record), &location(1, 4, record.len() as u32, None)).is_err());
+    // グリフがテーブルの外を指す
+    assert!(CbdtGlyph::parse(&table(&record), &location(19, 4, 100, Some(BigGlyphMetrics::default()))).is_err());
+    // PNG の長さがグリフの長さを超える
+    assert!(CbdtGlyph::parse(&table(&record), &location(19, 4, record.len() as u32 - 1, Some(BigGlyphMetrics::default()))).is_err());
+  }
+}
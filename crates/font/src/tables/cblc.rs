@@ -0,0 +1,375 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+
+// BigGlyphMetrics（ピクセル単位）。SmallGlyphMetrics は横書きの値だけを埋めてこの形にそろえる
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BigGlyphMetrics {
+  pub height: u8,
+  pub width: u8,
+  pub hori_bearing_x: i8,
+  pub hori_bearing_y: i8,
+  pub hori_advance: u8,
+  pub vert_bearing_x: i8,
+  pub vert_bearing_y: i8,
+  pub vert_advance: u8,
+}
+
+impl BigGlyphMetrics {
+  pub fn read(cursor: &mut Cursor<&[u8]>) -> Result<Self, String> {
+    Ok(BigGlyphMetrics {
+      height: cursor.read_u8().map_err(|e| e.to_string())?,
+      width: cursor.read_u8().map_err(|e| e.to_string())?,
+      hori_bearing_x: cursor.read_i8().map_err(|e| e.to_string())?,
+      hori_bearing_y: cursor.read_i8().map_err(|e| e.to_string())?,
+      hori_advance: cursor.read_u8().map_err(|e| e.to_string())?,
+      vert_bearing_x: cursor.read_i8().map_err(|e| e.to_string())?,
+      vert_bearing_y: cursor.read_i8().map_err(|e| e.to_string())?,
+      vert_advance: cursor.read_u8().map_err(|e| e.to_string())?,
+    })
+  }
+
+  pub fn read_small(cursor: &mut Cursor<&[u8]>) -> Result<Self, String> {
+    Ok(BigGlyphMetrics {
+      height: cursor.read_u8().map_err(|e| e.to_string())?,
+      width: cursor.read_u8().map_err(|e| e.to_string())?,
+      hori_bearing_x: cursor.read_i8().map_err(|e| e.to_string())?,
+      hori_bearing_y: cursor.read_i8().map_err(|e| e.to_string())?,
+      hori_advance: cursor.read_u8().map_err(|e| e.to_string())?,
+      ..Default::default()
+    })
+  }
+}
+
+// グリフの範囲ごとの索引。オフセットはすべて CBDT の先頭から
+#[derive(Debug, Clone)]
+pub enum IndexSubtable {
+  // 形式 1、3: グリフごとのオフセット（範囲の長さ + 1 個）
+  Offsets { offsets: Vec<u32> },
+  // 形式 2: offset から同じ大きさの画像が並ぶ
+  Constant { offset: u32, image_size: u32, metrics: BigGlyphMetrics },
+  // 形式 4: 画像のあるグリフだけのオフセット（最後の1つは終端）
+  Sparse { glyphs: Vec<(u16, u32)> },
+  // 形式 5: 画像のあるグリフだけの、同じ大きさの画像が glyph_ids の順に並ぶ
+  SparseConstant { offset: u32, image_size: u32, metrics: BigGlyphMetrics, glyph_ids: Vec<u16> },
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexSubtableRecord {
+  pub first_glyph_id: u16,
+  pub last_glyph_id: u16,
+  pub image_format: u16,
+  pub subtable: IndexSubtable,
+}
+
+// CBDT の中でのグリフの画像の位置。metrics は索引が持っているとき（形式 2、5）
+#[derive(Debug, Clone, Copy)]
+pub struct BitmapLocation {
+  pub image_format: u16,
+  pub offset: u32,
+  pub length: u32,
+  pub metrics: Option<BigGlyphMetrics>,
+}
+
+// ある ppem のビットマップの集まり（BitmapSize）
+#[derive(Debug, Clone)]
+pub struct Strike {
+  pub start_glyph_id: u16,
+  pub end_glyph_id: u16,
+  pub ppem_x: u8,
+  pub ppem_y: u8,
+  pub bit_depth: u8,
+  pub index_subtables: Vec<IndexSubtableRecord>,
+}
+
+// 'CBLC' テーブル（EBLC と同じ形）
+#[derive(Debug, Clone)]
+pub struct CblcTable {
+  pub major_version: u16,
+  pub minor_version: u16,
+  pub strikes: Vec<Strike>,
+}
+
+// 基準の位置からの相対オフセットを足す。u32 をあふれたら壊れたテーブル
+fn add_offset(base: u32, offset: u32) -> Result<u32, String> {
+  base.checked_add(offset).ok_or("CBLC のオフセットが範囲外です".to_string())
+}
+
+fn read_index_subtable(cursor: &mut Cursor<&[u8]>, first: u16, last: u16) -> Result<(u16, IndexSubtable), String> {
+  let index_format = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+  let image_format = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+  let image_data_offset = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+  let count = last.checked_sub(first).ok_or("CBLC のグリフの範囲が逆転しています")? as usize + 1;
+  let subtable = match index_format {
+    1 => IndexSubtable::Offsets {
+      offsets: (0..=count)
+        .map(|_| add_offset(image_data_offset, cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?))
+        .collect::<Result<_, String>>()?,
+    },
+    2 => IndexSubtable::Constant {
+      offset: image_data_offset,
+      image_size: cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?,
+      metrics: BigGlyphMetrics::read(cursor)?,
+    },
+    3 => IndexSubtable::Offsets {
+      offsets: (0..=count)
+        .map(|_| add_offset(image_data_offset, cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())? as u32))
+        .collect::<Result<_, String>>()?,
+    },
+    4 => {
+      let num_glyphs = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+      IndexSubtable::Sparse {
+        glyphs: (0..=num_glyphs)
+          .map(|_| {
+            let glyph_id = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+            let offset = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())? as u32;
+            Ok((glyph_id, add_offset(image_data_offset, offset)?))
+          })
+          .collect::<Result<_, String>>()?,
+      }
+    }
+    5 => {
+      let image_size = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+      let metrics = BigGlyphMetrics::read(cursor)?;
+      let num_glyphs = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+      let glyph_ids = (0..num_glyphs)
+        .map(|_| cursor.read_u16::<BigEndian>().map_err(|e| e.to_string()))
+        .collect::<Result<_, String>>()?;
+      IndexSubtable::SparseConstant { offset: image_data_offset, image_size, metrics, glyph_ids }
+    }
+    other => return Err(format!("CBLC の索引の形式{}には対応していません", other)),
+  };
+  Ok((image_format, subtable))
+}
+
+impl IndexSubtableRecord {
+  // glyph_id の画像の位置。この範囲に画像がなければ None
+  pub fn locate(&self, glyph_id: u16) -> Option<BitmapLocation> {
+    if glyph_id < self.first_glyph_id || glyph_id > self.last_glyph_id {
+      return None;
+    }
+    let index = (glyph_id - self.first_glyph_id) as usize;
+    let location = |offset: u32, length: u32, metrics: Option<BigGlyphMetrics>| {
+      // 長さ 0 は画像のないグリフ
+      (length > 0).then_some(BitmapLocation { image_format: self.image_format, offset, length, metrics })
+    };
+    match &self.subtable {
+      IndexSubtable::Offsets { offsets } => {
+        let (start, end) = (*offsets.get(index)?, *offsets.get(index + 1)?);
+        location(start, end.checked_sub(start)?, None)
+      }
+      IndexSubtable::Constant { offset, image_size, metrics } => {
+        let start = image_size.checked_mul(index as u32)?.checked_add(*offset)?;
+        location(start, *image_size, Some(*metrics))
+      }
+      IndexSubtable::Sparse { glyphs } => {
+        let i = glyphs[..glyphs.len().saturating_sub(1)].iter().position(|&(id, _)| id == glyph_id)?;
+        let (start, end) = (glyphs[i].1, glyphs[i + 1].1);
+        location(start, end.checked_sub(start)?, None)
+      }
+      IndexSubtable::SparseConstant { offset, image_size, metrics, glyph_ids } => {
+        let i = glyph_ids.binary_search(&glyph_id).ok()?;
+        let start = image_size.checked_mul(i as u32)?.checked_add(*offset)?;
+        location(start, *image_size, Some(*metrics))
+      }
+    }
+  }
+}
+
+impl Strike {
+  pub fn locate(&self, glyph_id: u16) -> Option<BitmapLocation> {
+    self.index_subtables.iter().find_map(|record| record.locate(glyph_id))
+  }
+}
+
+impl CblcTable {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+    let major_version = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let minor_version = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let num_sizes = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+
+    // BitmapSize は48バイト。行のメトリクス（hori、vert）は読み飛ばす
+    let sizes = (0..num_sizes)
+      .map(|_| {
+        let array_offset = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+        let _index_tables_size = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+        let count = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+        let _color_ref = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+        cursor.set_position(cursor.position() + 24);
+        let start_glyph_id = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+        let end_glyph_id = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+        let ppem_x = cursor.read_u8().map_err(|e| e.to_string())?;
+        let ppem_y = cursor.read_u8().map_err(|e| e.to_string())?;
+        let bit_depth = cursor.read_u8().map_err(|e| e.to_string())?;
+        let _flags = cursor.read_i8().map_err(|e| e.to_string())?;
+        Ok((array_offset, count, Strike { start_glyph_id, end_glyph_id, ppem_x, ppem_y, bit_depth, index_subtables: Vec::new() }))
+      })
+      .collect::<Result<Vec<_>, String>>()?;
+
+    let strikes = sizes.into_iter()
+      .map(|(array_offset, count, mut strike)| {
+        cursor.set_position(array_offset as u64);
+        let ranges = (0..count)
+          .map(|_| {
+            Ok((
+              cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?,
+              cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?,
+              cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?,
+            ))
+          })
+          .collect::<Result<Vec<_>, String>>()?;
+        strike.index_subtables = ranges.into_iter()
+          .map(|(first_glyph_id, last_glyph_id, offset)| {
+            cursor.set_position(add_offset(array_offset, offset)? as u64);
+            let (image_format, subtable) = read_index_subtable(&mut cursor, first_glyph_id, last_glyph_id)?;
+            Ok(IndexSubtableRecord { first_glyph_id, last_glyph_id, image_format, subtable })
+          })
+          .collect::<Result<_, String>>()?;
+        Ok(strike)
+      })
+      .collect::<Result<_, String>>()?;
+    Ok(CblcTable { major_version, minor_version, strikes })
+  }
+
+  // ppem に最も合うストライク。glyph_id の画像を持つもののうち、ppem 以上で最小のもの、なければ最大のもの
+  pub fn best_strike(&self, glyph_id: u16, ppem: u16) -> Option<&Strike> {
+    let candidates = self.strikes.iter().filter(|strike| strike.locate(glyph_id).is_some());
+    let (larger, smaller): (Vec<&Strike>, Vec<&Strike>) = candidates.partition(|strike| strike.ppem_y as u16 >= ppem);
+    larger.into_iter().min_by_key(|strike| strike.ppem_y)
+      .or_else(|| smaller.into_iter().max_by_key(|strike| strike.ppem_y))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const METRICS: [u8; 8] = [16, 12, 1, 14, 13, 0xFA, 2, 18];
+
+  // IndexSubHeader（索引の形式、画像の形式、画像の位置）
+  fn header(index_format: u16, image_format: u16, image_data_offset: u32) -> Vec<u8> {
+    let mut data = vec![];
+    data.extend(index_format.to_be_bytes());
+    data.extend(image_format.to_be_bytes());
+    data.extend(image_data_offset.to_be_bytes());
+    data
+  }
+
+  fn record(data: &[u8], first_glyph_id: u16, last_glyph_id: u16) -> IndexSubtableRecord {
+    let (image_format, subtable) = read_index_subtable(&mut Cursor::new(data), first_glyph_id, last_glyph_id).unwrap();
+    IndexSubtableRecord { first_glyph_id, last_glyph_id, image_format, subtable }
+  }
+
+  fn span(location: Option<BitmapLocation>) -> Option<(u16, u32, u32, bool)> {
+    location.map(|l| (l.image_format, l.offset, l.length, l.metrics.is_some()))
+  }
+
+  #[test]
+  fn index_format_1_has_a_u32_offset_per_glyph() {
+    let mut data = header(1, 17, 100);
+    [0u32, 10, 10, 30].iter().for_each(|offset| data.extend(offset.to_be_bytes()));
+    let record = record(&data, 5, 7);
+    assert_eq!(span(record.locate(5)), Some((17, 100, 10, false)));
+    // 長さ 0 は画像なし
+    assert_eq!(span(record.locate(6)), None);
+    assert_eq!(span(record.locate(7)), Some((17, 110, 20, false)));
+    assert_eq!(span(record.locate(4)), None);
+    assert_eq!(span(record.locate(8)), None);
+  }
+
+  #[test]
+  fn index_format_2_has_constant_sized_images_and_metrics() {
+    let mut data = header(2, 19, 200);
+    data.extend(16u32.to_be_bytes());
+    data.extend(METRICS);
+    let record = record(&data, 10, 12);
+    assert_eq!(span(record.locate(11)), Some((19, 216, 16, true)));
+    let metrics = record.locate(12).unwrap().metrics.unwrap();
+    assert_eq!((metrics.height, metrics.width, metrics.hori_bearing_y, metrics.vert_bearing_x), (16, 12, 14, -6));
+  }
+
+  #[test]
+  fn index_format_3_has_a_u16_offset_per_glyph() {
+    let mut data = header(3, 18, 300);
+    [0u16, 4, 4].iter().for_each(|offset| data.extend(offset.to_be_bytes()));
+    let record = record(&data, 20, 21);
+    assert_eq!(span(record.locate(20)), Some((18, 300, 4, false)));
+    assert_eq!(span(record.locate(21)), None);
+  }
+
+  #[test]
+  fn index_format_4_lists_only_glyphs_with_images() {
+    let mut data = header(4, 17, 400);
+    data.extend(2u32.to_be_bytes());
+    [30u16, 0, 33, 8, 0, 20].iter().for_each(|value| data.extend(value.to_be_bytes()));
+    let record = record(&data, 30, 40);
+    assert_eq!(span(record.locate(30)), Some((17, 400, 8, false)));
+    assert_eq!(span(record.locate(33)), Some((17, 408, 12, false)));
+    assert_eq!(span(record.locate(31)), None);
+    // 終端の項目はグリフではない
+    assert_eq!(span(record.locate(0)), None);
+  }
+
+  #[test]
+  fn index_format_5_lists_glyphs_with_constant_sized_images() {
+    let mut data = header(5, 19, 500);
+    data.extend(6u32.to_be_bytes());
+    data.extend(METRICS);
+    data.extend(3u32.to_be_bytes());
+    [40u16, 42, 45].iter().for_each(|id| data.extend(id.to_be_bytes()));
+    let record = record(&data, 40, 45);
+    assert_eq!(span(record.locate(40)), Some((19, 500, 6, true)));
+    assert_eq!(span(record.locate(42)), Some((19, 506, 6, true)));
+    assert_eq!(span(record.locate(45)), Some((19, 512, 6, true)));
+    assert_eq!(span(record.locate(41)), None);
+  }
+
+  #[test]
+  fn unknown_index_formats_and_reversed_ranges_are_errors() {
+    let data = header(6, 17, 0);
+    assert!(read_index_subtable(&mut Cursor::new(&data[..]), 0, 0).is_err());
+    let data = header(1, 17, 0);
+    assert!(read_index_subtable(&mut Cursor::new(&data[..]), 2, 1).is_err());
+    // オフセットを足して u32 をあふれる
+    let mut data = header(1, 17, u32::MAX);
+    [0u32, 1].iter().for_each(|offset| data.extend(offset.to_be_bytes()));
+    assert!(read_index_subtable(&mut Cursor::new(&data[..]), 0, 0).is_err());
+  }
+
+  // ppem ごとに1つの範囲（形式 2、グリフ 1〜3）を持つ CBLC
+  fn table(ppems: &[u8]) -> Vec<u8> {
+    let mut data = vec![0, 3, 0, 0];
+    data.extend((ppems.len() as u32).to_be_bytes());
+    let arrays_start = 8 + 48 * ppems.len() as u32;
+    let array_size = 8 + 8 + 4 + 8;
+    for (i, &ppem) in ppems.iter().enumerate() {
+      data.extend((arrays_start + i as u32 * array_size).to_be_bytes());
+      data.extend(array_size.to_be_bytes());
+      data.extend(1u32.to_be_bytes());
+      data.extend([0; 4 + 24]);
+      data.extend([0, 1, 0, 3, ppem, ppem, 32, 1]);
+    }
+    for i in 0..ppems.len() as u32 {
+      data.extend([0, 1, 0, 3, 0, 0, 0, 8]);
+      data.extend(header(2, 17, 1000 * i));
+      data.extend(10u32.to_be_bytes());
+      data.extend(METRICS);
+    }
+    data
+  }
+
+  #[test]
+  fn strikes_and_best_strike() {
+    let cblc = CblcTable::parse(&table(&[20, 64, 136])).unwrap();
+    assert_eq!((cblc.major_version, cblc.minor_version), (3, 0));
+    assert_eq!(cblc.strikes.len(), 3);
+    assert_eq!(cblc.strikes[1].locate(2).unwrap().offset, 1010);
+    let best = |ppem| cblc.best_strike(2, ppem).map(|strike| strike.ppem_y);
+    // ppem 以上で一番小さいもの、なければ一番大きいもの
+    assert_eq!(best(12), Some(20));
+    assert_eq!(best(64), Some(64));
+    assert_eq!(best(65), Some(136));
+    assert_eq!(best(200), Some(136));
+    assert_eq!(cblc.best_strike(4, 20).map(|strike| strike.ppem_y), None);
+  }
+}
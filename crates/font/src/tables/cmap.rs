@@ -51,6 +51,27 @@ pub struct CmapFormat4 {
   glyph_id_array: Vec<u16>,
 }
 
+// フォーマット12の1つの範囲。start_char_code から end_char_code までの文字に start_glyph_id から順にグリフを割り当てる
+#[derive(Debug, Clone, Copy)]
+pub struct SequentialMapGroup {
+  pub start_char_code: u32,
+  pub end_char_code: u32,
+  pub start_glyph_id: u32,
+}
+
+// BMP の外の文字も引けるフォーマット12のサブテーブル
+#[derive(Debug)]
+pub struct CmapFormat12 {
+  pub groups: Vec<SequentialMapGroup>,
+}
+
+impl CmapFormat12 {
+  pub fn get_glyph_id(&self, char_code: u32) -> Option<u16> {
+    let group = self.groups.iter().find(|group| group.start_char_code <= char_code && char_code <= group.end_char_code)?;
+    u16::try_from(group.start_glyph_id + (char_code - group.start_char_code)).ok()
+  }
+}
+
 impl CmapTable {
   pub fn parse(data: &[u8]) -> Result<Self, String> {
     let mut cursor = Cursor::new(data);
@@ -137,17 +158,41 @@ impl CmapTable {
     Ok(cmap_format4)
   }
 
+  // data はサブテーブルの先頭（format から）
+  pub fn parse_format12(data: &[u8]) -> Result<CmapFormat12, String> {
+    let mut cursor = Cursor::new(data);
+    let format = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    if format != 12 {
+      return Err(format!("cmap のサブテーブルがフォーマット12ではありません: {}", format));
+    }
+    // reserved, length, language
+    cursor.set_position(12);
+    let num_groups = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+    let groups = (0..num_groups)
+      .map(|_| {
+        Ok(SequentialMapGroup {
+          start_char_code: cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?,
+          end_char_code: cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?,
+          start_glyph_id: cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?,
+        })
+      })
+      .collect::<Result<_, String>>()?;
+    Ok(CmapFormat12 { groups })
+  }
+
+// フォーマット4は BMP の文字だけを引ける。U+FFFF を超える文字は None
 pub fn get_glyph_id(char_code: u32, cmap_format4: &CmapFormat4) -> Option<u16> {
   let seg_count = cmap_format4.seg_count_x2 / 2;
+  let char_code = u16::try_from(char_code).ok()?;
   
   for i in 0..seg_count as usize {
-    if (char_code as u16) >= cmap_format4.start_code[i] && 
-       (char_code as u16) <= cmap_format4.end_code[i] {
+    if char_code >= cmap_format4.start_code[i] && 
+       char_code <= cmap_format4.end_code[i] {
       if cmap_format4.id_range_offset[i] == 0 {
         return Some(((char_code as i32 + cmap_format4.id_delta[i] as i32) % 65536) as u16);
       } else {
         let index = (cmap_format4.id_range_offset[i] as usize) / 2
-        + ((char_code as u16 - cmap_format4.start_code[i]) as usize)
+        + ((char_code - cmap_format4.start_code[i]) as usize)
         + i;
         
         if index < cmap_format4.glyph_id_array.len() {
@@ -163,3 +208,58 @@ pub fn get_glyph_id(char_code: u32, cmap_format4: &CmapFormat4) -> Option<u16> {
 }
 
 
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // 1つの区間 start..=end を id_delta で写すフォーマット4（終端の 0xFFFF の区間つき）
+  fn format4(start: u16, end: u16, id_delta: i16) -> CmapFormat4 {
+    CmapFormat4 {
+      format: 4,
+      length: 32,
+      language: 0,
+      seg_count_x2: 4,
+      search_range: 4,
+      entry_selector: 1,
+      range_shift: 0,
+      end_code: vec![end, 0xFFFF],
+      reserved_pad: 0,
+      start_code: vec![start, 0xFFFF],
+      id_delta: vec![id_delta, 1],
+      id_range_offset: vec![0, 0],
+      glyph_id_array: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn format4_does_not_truncate_supplementary_code_points() {
+    let cmap = format4(0xD400, 0xD4FF, (0x1_0000 - 0xD400 + 10) as i16);
+    assert_eq!(CmapTable::get_glyph_id(0xD400, &cmap), Some(10));
+    // U+1D400 を u16 に切り詰めると U+D400 になってしまう
+    assert_eq!(CmapTable::get_glyph_id(0x1D400, &cmap), None);
+  }
+
+  #[test]
+  fn format12_maps_sequential_groups() {
+    let mut data = vec![];
+    data.extend(12u16.to_be_bytes());
+    data.extend(0u16.to_be_bytes());
+    data.extend((16u32 + 2 * 12).to_be_bytes());
+    data.extend(0u32.to_be_bytes());
+    data.extend(2u32.to_be_bytes());
+    for value in [0x41, 0x5A, 3, 0x1D400, 0x1D419, 100] {
+      data.extend((value as u32).to_be_bytes());
+    }
+    let cmap = CmapTable::parse_format12(&data).unwrap();
+    assert_eq!(cmap.get_glyph_id(0x41), Some(3));
+    assert_eq!(cmap.get_glyph_id(0x5A), Some(28));
+    assert_eq!(cmap.get_glyph_id(0x1D400), Some(100));
+    assert_eq!(cmap.get_glyph_id(0x1D401), Some(101));
+    assert_eq!(cmap.get_glyph_id(0xD400), None);
+    assert_eq!(cmap.get_glyph_id(0x1D41A), None);
+    assert!(CmapTable::parse_format12(&data[..data.len() - 1]).is_err());
+    data[1] = 4;
+    assert!(CmapTable::parse_format12(&data).is_err());
+  }
+}
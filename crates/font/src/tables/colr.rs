@@ -0,0 +1,296 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+use super::cpal::Color;
+use super::variation_store::{DeltaSetIndexMap, ItemVariationStore};
+
+// 色の番号がこの値のレイヤーは文字色で塗る
+pub const FOREGROUND_PALETTE_INDEX: u16 = 0xFFFF;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BaseGlyphRecord {
+  pub glyph_id: u16,
+  pub first_layer_index: u16,
+  pub num_layers: u16,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LayerRecord {
+  pub glyph_id: u16,
+  pub palette_index: u16,
+}
+
+// 色を決めたレイヤー。color が None なら文字色
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorLayer {
+  pub glyph_id: u16,
+  pub color: Option<Color>,
+}
+
+// バージョン 1 のグリフの Paint。offset はテーブルの先頭から
+#[derive(Debug, Clone, Copy)]
+pub struct BaseGlyphPaintRecord {
+  pub glyph_id: u16,
+  pub paint_offset: u32,
+}
+
+// 描画範囲を切り抜く矩形。var_index_base は ClipBox の形式 2 のとき
+#[derive(Debug, Clone, Copy)]
+pub struct ClipRecord {
+  pub start_glyph_id: u16,
+  pub end_glyph_id: u16,
+  pub bounds: [i16; 4],
+  pub var_index_base: Option<u32>,
+}
+
+// 'COLR' テーブル。バージョン 0 は下から順に重ねる単色のレイヤー、バージョン 1 は Paint のグラフ
+#[derive(Debug, Clone)]
+pub struct ColrTable {
+  pub version: u16,
+  // グリフ ID の昇順
+  pub base_glyphs: Vec<BaseGlyphRecord>,
+  pub layers: Vec<LayerRecord>,
+  // 以下はバージョン 1 だけ。グリフ ID の昇順
+  pub base_glyph_paints: Vec<BaseGlyphPaintRecord>,
+  // PaintColrLayers が参照する Paint のオフセット（テーブルの先頭から）
+  pub layer_paints: Vec<u32>,
+  pub clips: Vec<ClipRecord>,
+  pub var_index_map: Option<DeltaSetIndexMap>,
+  pub variation_store: Option<ItemVariationStore>,
+  // Paint は参照されたときに読む
+  pub(crate) data: Vec<u8>,
+}
+
+// オフセットが 0 でなければ、その位置から読む
+fn optional<T>(data: &[u8], offset: u32, parse: impl Fn(&[u8]) -> Result<T, String>) -> Result<Option<T>, String> {
+  if offset == 0 {
+    return Ok(None);
+  }
+  let table = data.get(offset as usize..).ok_or("COLR のオフセットがテーブルの外を指しています")?;
+  parse(table).map(Some)
+}
+
+// base からの相対オフセットを COLR の先頭からの位置にする。u32 をあふれたら壊れたテーブル
+pub(crate) fn add_offset(base: u32, offset: u32) -> Result<u32, String> {
+  base.checked_add(offset).ok_or("COLR のオフセットが範囲外です".to_string())
+}
+
+fn read_base_glyph_list(data: &[u8], offset: u32) -> Result<Vec<BaseGlyphPaintRecord>, String> {
+  let mut cursor = Cursor::new(data);
+  cursor.set_position(offset as u64);
+  let count = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+  (0..count)
+    .map(|_| {
+      Ok(BaseGlyphPaintRecord {
+        glyph_id: cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?,
+        paint_offset: add_offset(offset, cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?)?,
+      })
+    })
+    .collect()
+}
+
+fn read_layer_list(data: &[u8], offset: u32) -> Result<Vec<u32>, String> {
+  let mut cursor = Cursor::new(data);
+  cursor.set_position(offset as u64);
+  let count = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+  (0..count)
+    .map(|_| add_offset(offset, cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?))
+    .collect()
+}
+
+fn read_clip_list(data: &[u8], offset: u32) -> Result<Vec<ClipRecord>, String> {
+  let mut cursor = Cursor::new(data);
+  cursor.set_position(offset as u64 + 1);
+  let count = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+  let ranges = (0..count)
+    .map(|_| {
+      Ok((
+        cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?,
+        cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?,
+        cursor.read_u24::<BigEndian>().map_err(|e| e.to_string())?,
+      ))
+    })
+    .collect::<Result<Vec<_>, String>>()?;
+  ranges.into_iter()
+    .map(|(start_glyph_id, end_glyph_id, box_offset)| {
+      cursor.set_position(add_offset(offset, box_offset)? as u64);
+      let format = cursor.read_u8().map_err(|e| e.to_string())?;
+      let mut bounds = [0; 4];
+      for value in &mut bounds {
+        *value = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+      }
+      let var_index_base = match format {
+        2 => Some(cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?),
+        _ => None,
+      };
+      Ok(ClipRecord { start_glyph_id, end_glyph_id, bounds, var_index_base })
+    })
+    .collect()
+}
+
+impl ColrTable {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+    let version = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let num_base_glyphs = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let base_glyphs_offset = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+    let layers_offset = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+    let num_layers = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+
+    cursor.set_position(base_glyphs_offset as u64);
+    let base_glyphs = (0..num_base_glyphs)
+      .map(|_| {
+        Ok(BaseGlyphRecord {
+          glyph_id: cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?,
+          first_layer_index: cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?,
+          num_layers: cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?,
+        })
+      })
+      .collect::<Result<_, String>>()?;
+    cursor.set_position(layers_offset as u64);
+    let layers = (0..num_layers)
+      .map(|_| {
+        Ok(LayerRecord {
+          glyph_id: cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?,
+          palette_index: cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?,
+        })
+      })
+      .collect::<Result<_, String>>()?;
+
+    let mut table = ColrTable {
+      version,
+      base_glyphs,
+      layers,
+      base_glyph_paints: Vec::new(),
+      layer_paints: Vec::new(),
+      clips: Vec::new(),
+      var_index_map: None,
+      variation_store: None,
+      data: Vec::new(),
+    };
+    if version == 0 {
+      return Ok(table);
+    }
+    // バージョン 1 のヘッダーはバージョン 0 のヘッダーの後に続く
+    cursor.set_position(14);
+    let mut offsets = [0u32; 5];
+    for offset in &mut offsets {
+      *offset = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+    }
+    let [base_glyph_list, layer_list, clip_list, var_index_map, variation_store] = offsets;
+    if base_glyph_list != 0 {
+      table.base_glyph_paints = read_base_glyph_list(data, base_glyph_list)?;
+    }
+    if layer_list != 0 {
+      table.layer_paints = read_layer_list(data, layer_list)?;
+    }
+    if clip_list != 0 {
+      table.clips = read_clip_list(data, clip_list)?;
+    }
+    table.var_index_map = optional(data, var_index_map, DeltaSetIndexMap::parse)?;
+    table.variation_store = optional(data, variation_store, ItemVariationStore::parse)?;
+    table.data = data.to_vec();
+    Ok(table)
+  }
+
+  // glyph_id のレイヤー（下から順）。カラーグリフでなければ None
+  pub fn layers(&self, glyph_id: u16) -> Option<&[LayerRecord]> {
+    let index = self.base_glyphs.binary_search_by_key(&glyph_id, |record| record.glyph_id).ok()?;
+    let record = self.base_glyphs[index];
+    let start = record.first_layer_index as usize;
+    self.layers.get(start..start + record.num_layers as usize)
+  }
+
+  // バージョン 1 の Paint のオフセット。なければ None
+  pub fn paint_offset(&self, glyph_id: u16) -> Option<u32> {
+    let index = self.base_glyph_paints.binary_search_by_key(&glyph_id, |record| record.glyph_id).ok()?;
+    Some(self.base_glyph_paints[index].paint_offset)
+  }
+
+  // 変化インデックスの座標での差分
+  pub fn delta(&self, var_index: u32, coords: &[f32]) -> f32 {
+    let Some(store) = &self.variation_store else {
+      return 0.0;
+    };
+    let (outer, inner) = match &self.var_index_map {
+      Some(map) => match map.get(var_index) {
+        Some(entry) => entry,
+        None => return 0.0,
+      },
+      // 対応表がなければ上位 16 ビットが外側、下位 16 ビットが内側
+      None => ((var_index >> 16) as u16, var_index as u16),
+    };
+    store.delta(outer, inner, coords)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // バージョン 0 のヘッダーの後に BaseGlyphRecord と LayerRecord を並べる
+  fn version_0(base_glyphs: &[(u16, u16, u16)], layers: &[(u16, u16)]) -> Vec<u8> {
+    let layers_offset = 14 + base_glyphs.len() as u32 * 6;
+    let mut data = vec![];
+    data.extend(0u16.to_be_bytes());
+    data.extend((base_glyphs.len() as u16).to_be_bytes());
+    data.extend(14u32.to_be_bytes());
+    data.extend(layers_offset.to_be_bytes());
+    data.extend((layers.len() as u16).to_be_bytes());
+    for &(glyph_id, first, count) in base_glyphs {
+      data.extend([glyph_id, first, count].iter().flat_map(|v| v.to_be_bytes()));
+    }
+    for &(glyph_id, palette_index) in layers {
+      data.extend([glyph_id, palette_index].iter().flat_map(|v| v.to_be_bytes()));
+    }
+    data
+  }
+
+  #[test]
+  fn version_0_layers_by_base_glyph() {
+    let data = version_0(&[(3, 0, 2), (7, 2, 1)], &[(10, 0), (11, FOREGROUND_PALETTE_INDEX), (12, 1)]);
+    let colr = ColrTable::parse(&data).unwrap();
+    assert_eq!(colr.version, 0);
+    let layers: Vec<(u16, u16)> = colr.layers(3).unwrap().iter().map(|l| (l.glyph_id, l.palette_index)).collect();
+    // 下から順。0xFFFF は文字色のまま残す
+    assert_eq!(layers, [(10, 0), (11, FOREGROUND_PALETTE_INDEX)]);
+    assert_eq!(colr.layers(7).unwrap()[0].glyph_id, 12);
+    assert!(colr.layers(5).is_none());
+    // バージョン 0 には Paint がない
+    assert!(colr.paint_offset(3).is_none());
+    assert_eq!(colr.delta(0, &[0.5]), 0.0);
+  }
+
+  #[test]
+  fn layers_past_the_layer_records_are_none() {
+    let data = version_0(&[(3, 1, 4)], &[(10, 0), (11, 1)]);
+    assert!(ColrTable::parse(&data).unwrap().layers(3).is_none());
+    // レコードがテーブルの外に出れば読み込めない
+    assert!(ColrTable::parse(&data[..data.len() - 2]).is_err());
+  }
+
+  #[test]
+  fn version_1_without_lists() {
+    let mut data = version_0(&[], &[]);
+    data[1] = 1;
+    data.extend([0; 20]);
+    let colr = ColrTable::parse(&data).unwrap();
+    assert_eq!(colr.version, 1);
+    assert!(colr.base_glyph_paints.is_empty() && colr.layer_paints.is_empty() && colr.clips.is_empty());
+    assert!(colr.var_index_map.is_none() && colr.variation_store.is_none());
+  }
+
+  #[test]
+  fn version_1_base_glyph_list_offsets_are_from_the_table_start() {
+    let mut data = version_0(&[], &[]);
+    data[1] = 1;
+    // BaseGlyphList を 34 に置く
+    data.extend(34u32.to_be_bytes());
+    data.extend([0; 16]);
+    data.extend(2u32.to_be_bytes());
+    data.extend([0, 4, 0, 0, 0, 16, 0, 9, 0, 0, 0, 20]);
+    let colr = ColrTable::parse(&data).unwrap();
+    assert_eq!(colr.paint_offset(4), Some(50));
+    assert_eq!(colr.paint_offset(9), Some(54));
+    assert_eq!(colr.paint_offset(5), None);
+  }
+}
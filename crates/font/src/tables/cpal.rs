@@ -0,0 +1,177 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+
+// パレットの種類（CPAL バージョン 1 の paletteTypes）
+pub const USABLE_WITH_LIGHT_BACKGROUND: u32 = 0x0001;
+pub const USABLE_WITH_DARK_BACKGROUND: u32 = 0x0002;
+
+// ラベルがないことを表す name ID
+const NO_LABEL: u16 = 0xFFFF;
+
+// sRGB の色。アルファはストレート（乗算済みでない）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+  pub r: u8,
+  pub g: u8,
+  pub b: u8,
+  pub a: u8,
+}
+
+impl Color {
+  // #rrggbb 形式。アルファは含めない
+  pub fn to_hex(&self) -> String {
+    format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+  }
+
+  pub fn to_rgba(&self) -> [u8; 4] {
+    [self.r, self.g, self.b, self.a]
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Palette {
+  pub colors: Vec<Color>,
+  // USABLE_WITH_* のフラグ。バージョン 0 では 0
+  pub palette_type: u32,
+  // name テーブルでのパレット名の ID
+  pub label: Option<u16>,
+}
+
+// 'CPAL' テーブル
+#[derive(Debug, Clone)]
+pub struct CpalTable {
+  pub version: u16,
+  pub palettes: Vec<Palette>,
+  // パレットの各項目（色の番号ごと）の name ID
+  pub entry_labels: Vec<Option<u16>>,
+}
+
+fn label(id: u16) -> Option<u16> {
+  (id != NO_LABEL).then_some(id)
+}
+
+impl CpalTable {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+    let version = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let num_entries = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())? as usize;
+    let num_palettes = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())? as usize;
+    let num_color_records = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())? as usize;
+    let color_records_offset = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())? as usize;
+    let first_indices = (0..num_palettes)
+      .map(|_| cursor.read_u16::<BigEndian>().map_err(|e| e.to_string()).map(|i| i as usize))
+      .collect::<Result<Vec<_>, _>>()?;
+    let (types_offset, labels_offset, entry_labels_offset) = if version >= 1 {
+      (
+        cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())? as usize,
+        cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())? as usize,
+        cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())? as usize,
+      )
+    } else {
+      (0, 0, 0)
+    };
+
+    // 色は BGRA の順
+    let records = data.get(color_records_offset..color_records_offset + num_color_records * 4)
+      .ok_or("CPAL の色レコードが範囲外です")?;
+    let colors: Vec<Color> = records.chunks_exact(4).map(|c| Color { r: c[2], g: c[1], b: c[0], a: c[3] }).collect();
+    // オフセットが 0 の配列はない
+    let read_u16s = |offset: usize, count: usize| -> Result<Vec<u16>, String> {
+      if offset == 0 {
+        return Ok(vec![NO_LABEL; count]);
+      }
+      let bytes = data.get(offset..offset + count * 2).ok_or("CPAL の配列が範囲外です")?;
+      Ok(bytes.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect())
+    };
+    let types: Vec<u32> = if types_offset == 0 {
+      vec![0; num_palettes]
+    } else {
+      let bytes = data.get(types_offset..types_offset + num_palettes * 4).ok_or("CPAL のパレットの種類が範囲外です")?;
+      bytes.chunks_exact(4).map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]])).collect()
+    };
+    let labels = read_u16s(labels_offset, num_palettes)?;
+    let entry_labels = read_u16s(entry_labels_offset, num_entries)?.into_iter().map(label).collect();
+
+    let palettes = first_indices.iter().enumerate()
+      .map(|(i, &first)| {
+        let colors = colors.get(first..first + num_entries).ok_or(format!("パレット{}の色が範囲外です", i))?.to_vec();
+        Ok(Palette { colors, palette_type: types[i], label: label(labels[i]) })
+      })
+      .collect::<Result<_, String>>()?;
+    Ok(CpalTable { version, palettes, entry_labels })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // 2色のパレット2つ。色レコードは BGRA の順で、2つ目のパレットは1色目を1つ目と共有する
+  fn table(version: u16) -> Vec<u8> {
+    let header_size = if version == 0 { 12 + 4 } else { 12 + 4 + 12 };
+    let records_offset = header_size as u32;
+    let types_offset = records_offset + 3 * 4;
+    let labels_offset = types_offset + 2 * 4;
+    let entry_labels_offset = labels_offset + 2 * 2;
+    let mut data = vec![];
+    data.extend(version.to_be_bytes());
+    data.extend(2u16.to_be_bytes()); // numPaletteEntries
+    data.extend(2u16.to_be_bytes()); // numPalettes
+    data.extend(3u16.to_be_bytes()); // numColorRecords
+    data.extend(records_offset.to_be_bytes());
+    data.extend([0, 0, 0, 1]); // colorRecordIndices: 0, 1
+    if version >= 1 {
+      data.extend(types_offset.to_be_bytes());
+      data.extend(labels_offset.to_be_bytes());
+      data.extend(entry_labels_offset.to_be_bytes());
+    }
+    data.extend([0x30, 0x20, 0x10, 0xFF, 0x60, 0x50, 0x40, 0x80, 0x90, 0x80, 0x70, 0x00]);
+    if version >= 1 {
+      data.extend(USABLE_WITH_LIGHT_BACKGROUND.to_be_bytes());
+      data.extend(USABLE_WITH_DARK_BACKGROUND.to_be_bytes());
+      data.extend([0x01, 0x00, 0xFF, 0xFF]); // パレット名: 256, なし
+      data.extend([0xFF, 0xFF, 0x01, 0x02]); // 色の名前: なし, 258
+    }
+    data
+  }
+
+  #[test]
+  fn color_records_are_read_as_bgra() {
+    let cpal = CpalTable::parse(&table(0)).unwrap();
+    assert_eq!(cpal.version, 0);
+    let colors: Vec<[u8; 4]> = cpal.palettes[0].colors.iter().map(Color::to_rgba).collect();
+    assert_eq!(colors, [[0x10, 0x20, 0x30, 0xFF], [0x40, 0x50, 0x60, 0x80]]);
+    assert_eq!(cpal.palettes[0].colors[0].to_hex(), "#102030");
+    // 2つ目のパレットは色レコード1から始まる
+    assert_eq!(cpal.palettes[1].colors[0], cpal.palettes[0].colors[1]);
+    assert_eq!(cpal.palettes[1].colors[1], Color { r: 0x70, g: 0x80, b: 0x90, a: 0 });
+  }
+
+  #[test]
+  fn version_0_has_no_types_or_labels() {
+    let cpal = CpalTable::parse(&table(0)).unwrap();
+    assert!(cpal.palettes.iter().all(|palette| palette.palette_type == 0 && palette.label.is_none()));
+    assert_eq!(cpal.entry_labels, [None, None]);
+  }
+
+  #[test]
+  fn version_1_types_and_labels() {
+    let cpal = CpalTable::parse(&table(1)).unwrap();
+    assert_eq!(cpal.palettes[0].palette_type, USABLE_WITH_LIGHT_BACKGROUND);
+    assert_eq!(cpal.palettes[1].palette_type, USABLE_WITH_DARK_BACKGROUND);
+    // 0xFFFF はラベルなし
+    assert_eq!(cpal.palettes[0].label, Some(256));
+    assert_eq!(cpal.palettes[1].label, None);
+    assert_eq!(cpal.entry_labels, [None, Some(258)]);
+    assert_eq!(cpal.palettes[1].colors, CpalTable::parse(&table(0)).unwrap().palettes[1].colors);
+  }
+
+  #[test]
+  fn palettes_past_the_color_records_are_errors() {
+    let mut data = table(0);
+    // 2つ目のパレットを色レコード2から始めると2色目が足りない
+    data[15] = 2;
+    assert_eq!(CpalTable::parse(&data).unwrap_err(), "パレット1の色が範囲外です");
+    assert!(CpalTable::parse(&table(1)[..30]).is_err());
+  }
+}
@@ -0,0 +1,192 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+
+const SHARED_POINT_NUMBERS: u16 = 0x8000;
+const COUNT_MASK: u16 = 0x0FFF;
+const EMBEDDED_PEAK_TUPLE: u16 = 0x8000;
+const INTERMEDIATE_REGION: u16 = 0x4000;
+const PRIVATE_POINT_NUMBERS: u16 = 0x2000;
+
+// 1つの変化の組（タプル）。points が None なら全ての制御値に差分がある
+#[derive(Debug, Clone)]
+pub struct CvarTuple {
+  pub peak: Vec<f32>,
+  pub intermediate: Option<(Vec<f32>, Vec<f32>)>,
+  pub points: Option<Vec<u16>>,
+  pub deltas: Vec<i16>,
+}
+
+// 'cvar' テーブル。バリアブルフォントの座標に応じた制御値の差分
+#[derive(Debug, Clone, Default)]
+pub struct CvarTable {
+  pub tuples: Vec<CvarTuple>,
+}
+
+fn read_f2dot14(cursor: &mut Cursor<&[u8]>) -> Result<f32, String> {
+  Ok(cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())? as f32 / 16384.0)
+}
+
+fn read_tuple(cursor: &mut Cursor<&[u8]>, axis_count: usize) -> Result<Vec<f32>, String> {
+  (0..axis_count).map(|_| read_f2dot14(cursor)).collect()
+}
+
+// 圧縮された点番号の列。None は「全ての点」
+fn read_points(cursor: &mut Cursor<&[u8]>) -> Result<Option<Vec<u16>>, String> {
+  let first = cursor.read_u8().map_err(|e| e.to_string())? as usize;
+  let count = if first & 0x80 != 0 {
+    ((first & 0x7F) << 8) | cursor.read_u8().map_err(|e| e.to_string())? as usize
+  } else {
+    first
+  };
+  if count == 0 {
+    return Ok(None);
+  }
+  let mut points = Vec::with_capacity(count);
+  let mut last = 0u16;
+  while points.len() < count {
+    let control = cursor.read_u8().map_err(|e| e.to_string())?;
+    let run = (control & 0x7F) as usize + 1;
+    for _ in 0..run.min(count - points.len()) {
+      let delta = if control & 0x80 != 0 {
+        cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?
+      } else {
+        cursor.read_u8().map_err(|e| e.to_string())? as u16
+      };
+      last = last.wrapping_add(delta);
+      points.push(last);
+    }
+  }
+  Ok(Some(points))
+}
+
+// 圧縮された差分の列を count 個読む
+fn read_deltas(cursor: &mut Cursor<&[u8]>, count: usize) -> Result<Vec<i16>, String> {
+  let mut deltas = Vec::with_capacity(count);
+  while deltas.len() < count {
+    let control = cursor.read_u8().map_err(|e| e.to_string())?;
+    let run = (control & 0x3F) as usize + 1;
+    for _ in 0..run.min(count - deltas.len()) {
+      let delta = if control & 0x80 != 0 {
+        0
+      } else if control & 0x40 != 0 {
+        cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?
+      } else {
+        cursor.read_i8().map_err(|e| e.to_string())? as i16
+      };
+      deltas.push(delta);
+    }
+  }
+  Ok(deltas)
+}
+
+impl CvarTable {
+  // axis_count は fvar の軸の数。cvt_len は全ての点を対象とする組の差分の数になる
+  pub fn parse(data: &[u8], axis_count: usize, cvt_len: usize) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+    let _major_version = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let _minor_version = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let tuple_variation_count = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let data_offset = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())? as u64;
+
+    // (データの大きさ, ピーク, 中間領域, 固有の点番号を持つか)
+    let mut headers = Vec::new();
+    for _ in 0..tuple_variation_count & COUNT_MASK {
+      let size = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())? as u64;
+      let tuple_index = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+      // cvar には共有タプルがないので、ピークは必ず埋め込まれている
+      if tuple_index & EMBEDDED_PEAK_TUPLE == 0 {
+        return Err("cvarのタプルにピークの座標がありません".to_string());
+      }
+      let peak = read_tuple(&mut cursor, axis_count)?;
+      let intermediate = if tuple_index & INTERMEDIATE_REGION != 0 {
+        Some((read_tuple(&mut cursor, axis_count)?, read_tuple(&mut cursor, axis_count)?))
+      } else {
+        None
+      };
+      headers.push((size, peak, intermediate, tuple_index & PRIVATE_POINT_NUMBERS != 0));
+    }
+
+    cursor.set_position(data_offset);
+    let shared_points = if tuple_variation_count & SHARED_POINT_NUMBERS != 0 {
+      read_points(&mut cursor)?
+    } else {
+      None
+    };
+    let mut tuples = Vec::with_capacity(headers.len());
+    for (size, peak, intermediate, private_points) in headers {
+      let start = cursor.position();
+      let points = if private_points { read_points(&mut cursor)? } else { shared_points.clone() };
+      let count = points.as_ref().map_or(cvt_len, |points| points.len());
+      let deltas = read_deltas(&mut cursor, count)?;
+      tuples.push(CvarTuple { peak, intermediate, points, deltas });
+      cursor.set_position(start + size);
+    }
+    Ok(CvarTable { tuples })
+  }
+
+  // 正規化された座標 coords での制御値の差分（フォント単位）
+  pub fn deltas(&self, coords: &[f32], cvt_len: usize) -> Vec<f32> {
+    let mut result = vec![0.0; cvt_len];
+    for tuple in &self.tuples {
+      let scalar = tuple.scalar(coords);
+      if scalar == 0.0 {
+        continue;
+      }
+      match &tuple.points {
+        Some(points) => {
+          for (&point, &delta) in points.iter().zip(&tuple.deltas) {
+            if let Some(value) = result.get_mut(point as usize) {
+              *value += delta as f32 * scalar;
+            }
+          }
+        }
+        None => {
+          for (value, &delta) in result.iter_mut().zip(&tuple.deltas) {
+            *value += delta as f32 * scalar;
+          }
+        }
+      }
+    }
+    result
+  }
+}
+
+impl CvarTuple {
+  // 座標に対するこの組の重み
+  pub fn scalar(&self, coords: &[f32]) -> f32 {
+    let mut scalar = 1.0;
+    for (axis, &peak) in self.peak.iter().enumerate() {
+      if peak == 0.0 {
+        continue;
+      }
+      let v = coords.get(axis).copied().unwrap_or(0.0);
+      if v == 0.0 {
+        return 0.0;
+      }
+      match &self.intermediate {
+        Some((start, end)) => {
+          let (start, end) = (start[axis], end[axis]);
+          // 不正な領域や 0 をまたぐ領域はこの軸を無視する
+          if start > peak || peak > end || (start < 0.0 && end > 0.0) {
+            continue;
+          }
+          if v < start || v > end {
+            return 0.0;
+          }
+          if v < peak {
+            scalar *= (v - start) / (peak - start);
+          } else if v > peak {
+            scalar *= (end - v) / (end - peak);
+          }
+        }
+        None => {
+          if v < peak.min(0.0) || v > peak.max(0.0) {
+            return 0.0;
+          }
+          scalar *= v / peak;
+        }
+      }
+    }
+    scalar
+  }
+}
@@ -0,0 +1,19 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+
+// 'cvt ' テーブル。ヒンティング命令が参照する制御値（フォント単位）の配列
+#[derive(Debug, Clone, Default)]
+pub struct CvtTable {
+  pub values: Vec<i16>,
+}
+
+impl CvtTable {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+    let mut values = Vec::with_capacity(data.len() / 2);
+    for _ in 0..data.len() / 2 {
+      values.push(cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?);
+    }
+    Ok(CvtTable { values })
+  }
+}
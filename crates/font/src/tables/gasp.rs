@@ -0,0 +1,142 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+
+// rangeGaspBehavior のビット
+pub const GASP_GRIDFIT: u16 = 0x0001;
+pub const GASP_DOGRAY: u16 = 0x0002;
+pub const GASP_SYMMETRIC_GRIDFIT: u16 = 0x0004;
+pub const GASP_SYMMETRIC_SMOOTHING: u16 = 0x0008;
+
+// ある ppem でのヒンティングとアンチエイリアスの指定
+// symmetric_* は ClearType（LCD）向けで、バージョン 0 のテーブルでは grid_fit / do_gray と同じとみなす
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GaspBehavior {
+  pub grid_fit: bool,
+  pub do_gray: bool,
+  pub symmetric_grid_fit: bool,
+  pub symmetric_smoothing: bool,
+}
+
+impl GaspBehavior {
+  pub fn from_flags(flags: u16, version: u16) -> Self {
+    let grid_fit = flags & GASP_GRIDFIT != 0;
+    let do_gray = flags & GASP_DOGRAY != 0;
+    if version == 0 {
+      return GaspBehavior { grid_fit, do_gray, symmetric_grid_fit: grid_fit, symmetric_smoothing: do_gray };
+    }
+    GaspBehavior {
+      grid_fit,
+      do_gray,
+      symmetric_grid_fit: flags & GASP_SYMMETRIC_GRIDFIT != 0,
+      symmetric_smoothing: flags & GASP_SYMMETRIC_SMOOTHING != 0,
+    }
+  }
+}
+
+// gasp テーブルがないときや範囲から外れたときはヒンティングもアンチエイリアスもする
+impl Default for GaspBehavior {
+  fn default() -> Self {
+    GaspBehavior { grid_fit: true, do_gray: true, symmetric_grid_fit: true, symmetric_smoothing: true }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GaspRange {
+  // この範囲に含まれる最大の ppem
+  pub max_ppem: u16,
+  pub flags: u16,
+}
+
+// 'gasp' テーブル。範囲は max_ppem の昇順に並ぶ
+#[derive(Debug, Clone)]
+pub struct GaspTable {
+  pub version: u16,
+  pub ranges: Vec<GaspRange>,
+}
+
+impl GaspTable {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+    let version = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    if version > 1 {
+      return Err(format!("未対応のgaspテーブルのバージョンです: {}", version));
+    }
+    let num_ranges = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let ranges = (0..num_ranges)
+      .map(|_| {
+        let max_ppem = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+        let flags = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+        Ok(GaspRange { max_ppem, flags })
+      })
+      .collect::<Result<_, String>>()?;
+    Ok(GaspTable { version, ranges })
+  }
+
+  // ppem を含む最初の範囲の指定
+  pub fn behavior(&self, ppem: u16) -> GaspBehavior {
+    self.ranges.iter()
+      .find(|range| ppem <= range.max_ppem)
+      .map(|range| GaspBehavior::from_flags(range.flags, self.version))
+      .unwrap_or_default()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn table(version: u16, ranges: &[(u16, u16)]) -> GaspTable {
+    let mut data = vec![];
+    data.extend(version.to_be_bytes());
+    data.extend((ranges.len() as u16).to_be_bytes());
+    for &(max_ppem, flags) in ranges {
+      data.extend(max_ppem.to_be_bytes());
+      data.extend(flags.to_be_bytes());
+    }
+    GaspTable::parse(&data).unwrap()
+  }
+
+  #[test]
+  fn behavior_uses_the_first_range_containing_the_ppem() {
+    let gasp = table(1, &[
+      (8, GASP_DOGRAY),
+      (16, GASP_GRIDFIT | GASP_SYMMETRIC_GRIDFIT),
+      (0xFFFF, GASP_GRIDFIT | GASP_DOGRAY | GASP_SYMMETRIC_SMOOTHING),
+    ]);
+    let behavior = |grid_fit, do_gray, symmetric_grid_fit, symmetric_smoothing| {
+      GaspBehavior { grid_fit, do_gray, symmetric_grid_fit, symmetric_smoothing }
+    };
+    assert_eq!(gasp.behavior(6), behavior(false, true, false, false));
+    // max_ppem 自身はその範囲に含まれる
+    assert_eq!(gasp.behavior(8), behavior(false, true, false, false));
+    assert_eq!(gasp.behavior(9), behavior(true, false, true, false));
+    assert_eq!(gasp.behavior(16), behavior(true, false, true, false));
+    assert_eq!(gasp.behavior(100), behavior(true, true, false, true));
+  }
+
+  #[test]
+  fn behavior_past_the_last_range_is_the_default() {
+    let gasp = table(1, &[(8, 0), (16, GASP_GRIDFIT)]);
+    assert_eq!(gasp.behavior(8), GaspBehavior::from_flags(0, 1));
+    assert_eq!(gasp.behavior(17), GaspBehavior::default());
+    assert_eq!(table(1, &[]).behavior(12), GaspBehavior::default());
+  }
+
+  #[test]
+  fn version_0_copies_gridfit_and_dogray_to_the_symmetric_flags() {
+    // バージョン 0 では SYMMETRIC_* のビットは無視する
+    let gasp = table(0, &[(8, GASP_GRIDFIT | GASP_SYMMETRIC_SMOOTHING), (0xFFFF, GASP_DOGRAY | GASP_SYMMETRIC_GRIDFIT)]);
+    let small = gasp.behavior(8);
+    assert!(small.grid_fit && small.symmetric_grid_fit);
+    assert!(!small.do_gray && !small.symmetric_smoothing);
+    let large = gasp.behavior(20);
+    assert!(!large.grid_fit && !large.symmetric_grid_fit);
+    assert!(large.do_gray && large.symmetric_smoothing);
+  }
+
+  #[test]
+  fn unknown_version_and_truncated_tables_are_errors() {
+    assert!(GaspTable::parse(&[0, 2, 0, 0]).is_err());
+    assert!(GaspTable::parse(&[0, 1, 0, 1, 0, 8]).is_err());
+  }
+}
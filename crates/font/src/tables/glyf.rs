@@ -1,4 +1,7 @@
-use crate::truetype::{SimpleGlyph, GlyphType};
+use crate::sink::OutlineSink;
+use crate::transform::TransformSink;
+use crate::truetype::outline::{Outline, Point};
+use crate::truetype::{CompositeGlyph, ComponentArguments, SimpleGlyph, GlyphType, Transform, ROUND_XY_TO_GRID};
 use byteorder::{BigEndian, ReadBytesExt};
 use std::io::{Cursor, Read, Seek, SeekFrom};
 use crate::tables::loca::{LocaTable, LocaFormat};
@@ -9,6 +12,15 @@ pub struct GlyfTable {
   pub glyphs: Vec<Glyph>,
 }
 
+// 複合グリフの入れ子の上限。自分自身を参照するグリフで止まらなくならないようにする
+const MAX_COMPONENT_DEPTH: usize = 8;
+
+// 成分を合成した輪郭。点はフォント単位の (x, y, オンカーブか)
+struct ResolvedGlyph {
+  end_pts_of_contours: Vec<u16>,
+  points: Vec<(f32, f32, bool)>,
+}
+
 #[derive(Debug)]
 pub struct Glyph {
   #[allow(dead_code)]
@@ -86,11 +98,26 @@ impl GlyfTable {
     Ok(GlyfTable { glyphs })
   }
 
+  // 複合グリフは成分を合成し、座標を丸めた単純グリフとして返す（命令は持たない）
   pub fn get_glyph_data(&self, glyph_id: usize) -> Result<SimpleGlyph, String> {
     if let Some(glyph) = self.glyphs.get(glyph_id) {
       match Glyph::get_glyph_type(glyph) {
         GlyphType::Simple => SimpleGlyph::parse(glyph),
-        GlyphType::Composite => Err("Composite glyphs are not supported yet".to_string()),
+        GlyphType::Composite => {
+          let resolved = self.resolve(glyph_id, 0)?;
+          let points: Vec<Point> = resolved.points.iter()
+            .map(|&(x, y, on_curve)| Point { x: x.round() as i16, y: y.round() as i16, on_curve })
+            .collect();
+          Ok(SimpleGlyph {
+            end_pts_of_contours: resolved.end_pts_of_contours,
+            instruction_length: 0,
+            instructions: vec![],
+            flags: points.iter().map(|p| p.on_curve as u8).collect(),
+            x_coordinates: points.iter().map(|p| p.x).collect(),
+            y_coordinates: points.iter().map(|p| p.y).collect(),
+            points,
+          })
+        }
         GlyphType::Empty => Ok(SimpleGlyph {
           end_pts_of_contours: vec![],
           instruction_length: 0,
@@ -105,6 +132,96 @@ impl GlyfTable {
       Err(format!("Glyph not found: {}", glyph_id))
     }
   }
+
+  // グリフの輪郭を sink に流す。複合グリフの成分は TransformSink で変換しながら描くので座標は丸めない
+  pub fn draw_glyph<S: OutlineSink + ?Sized>(&self, glyph_id: usize, sink: &mut S) -> Result<(), String> {
+    self.draw_component(glyph_id, &mut TransformSink::new(sink, Transform::identity()), 0)
+  }
+
+  // 入れ子ごとに TransformSink の型が増えないよう、sink は dyn で受ける
+  fn draw_component(&self, glyph_id: usize, sink: &mut dyn OutlineSink, depth: usize) -> Result<(), String> {
+    let glyph = self.glyphs.get(glyph_id).ok_or(format!("Glyph not found: {}", glyph_id))?;
+    match Glyph::get_glyph_type(glyph) {
+      GlyphType::Simple => Outline::from_simple_glyph(&SimpleGlyph::parse(glyph)?).draw(sink),
+      GlyphType::Composite => {
+        for (component, transform) in self.component_transforms(glyph, depth)? {
+          self.draw_component(component, &mut TransformSink::new(sink, transform), depth + 1)?;
+        }
+      }
+      GlyphType::Empty => {}
+    }
+    Ok(())
+  }
+
+  // 成分ごとのグリフ ID と変換。点合わせの成分があるときだけ、それまでの成分を合成した点を追う
+  fn component_transforms(&self, glyph: &Glyph, depth: usize) -> Result<Vec<(usize, Transform)>, String> {
+    if depth >= MAX_COMPONENT_DEPTH {
+      return Err("複合グリフの入れ子が深すぎます".to_string());
+    }
+    let composite = CompositeGlyph::parse(glyph)?;
+    let matches_points = composite.components.iter().any(|c| matches!(c.arguments, ComponentArguments::Points(..)));
+    let mut points: Vec<(f32, f32, bool)> = Vec::new();
+    let mut transforms = Vec::new();
+    for component in &composite.components {
+      let linear = component.transform.unwrap_or_else(Transform::identity);
+      let (x, y) = match component.arguments {
+        ComponentArguments::Offset(dx, dy) => {
+          let (dx, dy) = (dx as f32, dy as f32);
+          let (x, y) = if component.scales_offset() { linear.apply(dx, dy) } else { (dx, dy) };
+          if component.flags & ROUND_XY_TO_GRID != 0 { (x.round(), y.round()) } else { (x, y) }
+        }
+        ComponentArguments::Points(parent, child) => {
+          // 親の点（合成済み）と成分の点（行列をかけたもの）が重なるように動かす
+          let child_points = self.resolve(component.glyph_index as usize, depth + 1)?.points;
+          let &(px, py, _) = points.get(parent as usize).ok_or("複合グリフの点合わせの親の点がありません")?;
+          let &(cx, cy, _) = child_points.get(child as usize).ok_or("複合グリフの点合わせの成分の点がありません")?;
+          let (cx, cy) = linear.apply(cx, cy);
+          (px - cx, py - cy)
+        }
+      };
+      let transform = Transform { x, y, ..linear };
+      if matches_points {
+        let child = self.resolve(component.glyph_index as usize, depth + 1)?;
+        points.extend(child.points.iter().map(|&(cx, cy, on_curve)| {
+          let (cx, cy) = transform.apply(cx, cy);
+          (cx, cy, on_curve)
+        }));
+      }
+      transforms.push((component.glyph_index as usize, transform));
+    }
+    Ok(transforms)
+  }
+
+  // 成分を合成して点と輪郭の終わりを並べる。点の並びは成分の順（点合わせの番号と同じ）
+  fn resolve(&self, glyph_id: usize, depth: usize) -> Result<ResolvedGlyph, String> {
+    let glyph = self.glyphs.get(glyph_id).ok_or(format!("Glyph not found: {}", glyph_id))?;
+    match Glyph::get_glyph_type(glyph) {
+      GlyphType::Simple => {
+        let simple = SimpleGlyph::parse(glyph)?;
+        Ok(ResolvedGlyph {
+          end_pts_of_contours: simple.end_pts_of_contours,
+          points: simple.points.iter().map(|p| (p.x as f32, p.y as f32, p.on_curve)).collect(),
+        })
+      }
+      GlyphType::Composite => {
+        let mut resolved = ResolvedGlyph { end_pts_of_contours: Vec::new(), points: Vec::new() };
+        for (component, transform) in self.component_transforms(glyph, depth)? {
+          let child = self.resolve(component, depth + 1)?;
+          let base = resolved.points.len();
+          let ends = child.end_pts_of_contours.iter()
+            .map(|&end| u16::try_from(base + end as usize).map_err(|_| "複合グリフの点が多すぎます".to_string()))
+            .collect::<Result<Vec<_>, String>>()?;
+          resolved.end_pts_of_contours.extend(ends);
+          resolved.points.extend(child.points.iter().map(|&(x, y, on_curve)| {
+            let (x, y) = transform.apply(x, y);
+            (x, y, on_curve)
+          }));
+        }
+        Ok(resolved)
+      }
+      GlyphType::Empty => Ok(ResolvedGlyph { end_pts_of_contours: Vec::new(), points: Vec::new() }),
+    }
+  }
 }
 
 #[allow(dead_code)]
@@ -118,4 +235,101 @@ impl Glyph {
       GlyphType::Empty
     }
   }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::truetype::outline::PathSegment;
+  use crate::truetype::{ARG_1_AND_2_ARE_WORDS, ARGS_ARE_XY_VALUES, MORE_COMPONENTS, SCALED_COMPONENT_OFFSET, WE_HAVE_A_SCALE, WE_HAVE_A_TWO_BY_TWO};
+
+  // (0, 0) から (100, 100) までの正方形
+  fn square() -> Glyph {
+    let mut data = vec![0, 3, 0, 0, 1, 1, 1, 1];
+    for dx in [0i16, 0, 100, 0] {
+      data.extend(dx.to_be_bytes());
+    }
+    for dy in [0i16, 100, 0, -100] {
+      data.extend(dy.to_be_bytes());
+    }
+    Glyph { number_of_contours: 1, x_min: 0, y_min: 0, x_max: 100, y_max: 100, data }
+  }
+
+  // 成分は (flags, グリフ ID, 引数, F2DOT14 の値)。MORE_COMPONENTS は最後以外に付ける
+  fn composite(components: &[(u16, u16, [i16; 2], &[f32])]) -> Glyph {
+    let mut data = Vec::new();
+    for (i, &(flags, glyph_index, args, scale)) in components.iter().enumerate() {
+      let more = if i + 1 < components.len() { MORE_COMPONENTS } else { 0 };
+      data.extend((flags | more | ARG_1_AND_2_ARE_WORDS).to_be_bytes());
+      data.extend(glyph_index.to_be_bytes());
+      data.extend(args[0].to_be_bytes());
+      data.extend(args[1].to_be_bytes());
+      for value in scale {
+        data.extend(((value * 16384.0) as i16).to_be_bytes());
+      }
+    }
+    Glyph { number_of_contours: -1, x_min: 0, y_min: 0, x_max: 0, y_max: 0, data }
+  }
+
+  fn points(table: &GlyfTable, glyph_id: usize) -> Vec<(i16, i16)> {
+    table.get_glyph_data(glyph_id).unwrap().points.iter().map(|p| (p.x, p.y)).collect()
+  }
+
+  #[test]
+  fn composite_offsets_and_scales_components() {
+    let table = GlyfTable {
+      glyphs: vec![
+        square(),
+        composite(&[
+          (ARGS_ARE_XY_VALUES, 0, [10, 20], &[]),
+          (ARGS_ARE_XY_VALUES | WE_HAVE_A_SCALE, 0, [200, 0], &[0.5]),
+        ]),
+      ],
+    };
+    assert_eq!(points(&table, 1), vec![(10, 20), (10, 120), (110, 120), (110, 20), (200, 0), (200, 50), (250, 50), (250, 0)]);
+    assert_eq!(table.get_glyph_data(1).unwrap().end_pts_of_contours, vec![3, 7]);
+  }
+
+  #[test]
+  fn scaled_component_offset_goes_through_the_matrix() {
+    let table = GlyfTable {
+      glyphs: vec![square(), composite(&[(ARGS_ARE_XY_VALUES | WE_HAVE_A_SCALE | SCALED_COMPONENT_OFFSET, 0, [100, 0], &[0.5])])],
+    };
+    assert_eq!(points(&table, 1)[0], (50, 0));
+  }
+
+  #[test]
+  fn two_by_two_rotates_a_component() {
+    // 90 度の回転: x' = -y, y' = x
+    let table = GlyfTable {
+      glyphs: vec![square(), composite(&[(ARGS_ARE_XY_VALUES | WE_HAVE_A_TWO_BY_TWO, 0, [0, 0], &[0.0, 1.0, -1.0, 0.0])])],
+    };
+    assert_eq!(points(&table, 1), vec![(0, 0), (-100, 0), (-100, 100), (0, 100)]);
+  }
+
+  #[test]
+  fn matched_points_are_placed_on_top_of_each_other() {
+    // 2 つ目の成分の点 0 (0, 0) を、1 つ目の成分の点 2 (100, 100) に重ねる
+    let table = GlyfTable {
+      glyphs: vec![square(), composite(&[(ARGS_ARE_XY_VALUES, 0, [0, 0], &[]), (0, 0, [2, 0], &[])])],
+    };
+    assert_eq!(points(&table, 1)[4], (100, 100));
+  }
+
+  #[test]
+  fn draw_glyph_keeps_fractional_coordinates() {
+    let table = GlyfTable {
+      glyphs: vec![square(), composite(&[(ARGS_ARE_XY_VALUES | WE_HAVE_A_SCALE, 0, [0, 0], &[0.375])])],
+    };
+    let mut segments = Vec::new();
+    table.draw_glyph(1, &mut segments).unwrap();
+    assert_eq!(segments[1], PathSegment::LineTo(0.0, 37.5));
+    assert_eq!(points(&table, 1)[1], (0, 38));
+  }
+
+  #[test]
+  fn self_referencing_composite_is_an_error() {
+    let table = GlyfTable { glyphs: vec![composite(&[(ARGS_ARE_XY_VALUES, 0, [0, 0], &[])])] };
+    assert!(table.get_glyph_data(0).is_err());
+    assert!(table.draw_glyph(0, &mut Vec::new()).is_err());
+  }
+}
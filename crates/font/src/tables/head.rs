@@ -42,7 +42,6 @@ pub struct HeadTable {
 // locaテーブルの構造
 #[derive(Debug)]
 #[allow(dead_code)]
-
 pub struct LocaTable {
   #[allow(dead_code)]
   pub offsets: Vec<u32>,
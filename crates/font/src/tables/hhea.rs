@@ -0,0 +1,72 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+
+#[derive(Debug)]
+pub struct HheaTable {
+  #[allow(dead_code)]
+  pub version: u32,
+  #[allow(dead_code)]
+  pub ascender: i16,
+  #[allow(dead_code)]
+  pub descender: i16,
+  #[allow(dead_code)]
+  pub line_gap: i16,
+  #[allow(dead_code)]
+  pub advance_width_max: u16,
+  #[allow(dead_code)]
+  pub min_left_side_bearing: i16,
+  #[allow(dead_code)]
+  pub min_right_side_bearing: i16,
+  #[allow(dead_code)]
+  pub x_max_extent: i16,
+  #[allow(dead_code)]
+  pub caret_slope_rise: i16,
+  #[allow(dead_code)]
+  pub caret_slope_run: i16,
+  #[allow(dead_code)]
+  pub caret_offset: i16,
+  #[allow(dead_code)]
+  pub metric_data_format: i16,
+  #[allow(dead_code)]
+  pub number_of_h_metrics: u16,
+}
+
+impl HheaTable {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+
+    let version = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+    let ascender = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    let descender = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    let line_gap = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    let advance_width_max = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let min_left_side_bearing = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    let min_right_side_bearing = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    let x_max_extent = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    let caret_slope_rise = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    let caret_slope_run = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    let caret_offset = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    // 予約領域 (int16 x 4)
+    for _ in 0..4 {
+      cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    }
+    let metric_data_format = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    let number_of_h_metrics = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+
+    Ok(HheaTable {
+      version,
+      ascender,
+      descender,
+      line_gap,
+      advance_width_max,
+      min_left_side_bearing,
+      min_right_side_bearing,
+      x_max_extent,
+      caret_slope_rise,
+      caret_slope_run,
+      caret_offset,
+      metric_data_format,
+      number_of_h_metrics,
+    })
+  }
+}
@@ -0,0 +1,49 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+
+#[derive(Debug, Clone, Copy)]
+pub struct LongHorMetric {
+  pub advance_width: u16,
+  pub left_side_bearing: i16,
+}
+
+#[derive(Debug)]
+pub struct HmtxTable {
+  #[allow(dead_code)]
+  pub h_metrics: Vec<LongHorMetric>,
+  // numberOfHMetrics 以降のグリフは左サイドベアリングだけを持つ
+  #[allow(dead_code)]
+  pub left_side_bearings: Vec<i16>,
+}
+
+impl HmtxTable {
+  pub fn parse(data: &[u8], number_of_h_metrics: u16, num_glyphs: u16) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+
+    let mut h_metrics = Vec::with_capacity(number_of_h_metrics as usize);
+    for _ in 0..number_of_h_metrics {
+      let advance_width = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+      let left_side_bearing = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+      h_metrics.push(LongHorMetric { advance_width, left_side_bearing });
+    }
+
+    let mut left_side_bearings = Vec::new();
+    for _ in number_of_h_metrics..num_glyphs {
+      left_side_bearings.push(cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?);
+    }
+
+    Ok(HmtxTable { h_metrics, left_side_bearings })
+  }
+
+  pub fn get_metric(&self, glyph_id: u16) -> Option<LongHorMetric> {
+    let glyph_id = glyph_id as usize;
+    let last = *self.h_metrics.last()?;
+    if glyph_id < self.h_metrics.len() {
+      Some(self.h_metrics[glyph_id])
+    } else {
+      // 最後のadvanceWidthを引き継ぐ
+      let left_side_bearing = *self.left_side_bearings.get(glyph_id - self.h_metrics.len())?;
+      Some(LongHorMetric { advance_width: last.advance_width, left_side_bearing })
+    }
+  }
+}
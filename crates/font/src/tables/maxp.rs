@@ -40,7 +40,9 @@ impl MaxpTable {
   pub fn parse(data: &[u8]) -> Result<Self, String> {
     let mut cursor = Cursor::new(data);
     
-    let version = cursor.read_f32::<BigEndian>().map_err(|e| e.to_string())?;
+    // バージョンは Version16Dot16（小数部の上位4ビットが小数第1位。0.5 は 0x00005000、1.0 は 0x00010000）
+    let raw_version = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+    let version = (raw_version >> 16) as f32 + ((raw_version >> 12) & 0xF) as f32 / 10.0;
     let num_glyphs = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
     
     // バージョンが1.0の場合、追加のフィールドを読み込む
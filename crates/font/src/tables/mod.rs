@@ -1,5 +1,20 @@
+pub mod cbdt;
+pub mod cblc;
 pub mod cmap; 
+pub mod colr;
+pub mod cpal;
+pub mod cvar;
+pub mod cvt;
+pub mod gasp;
 pub mod head;
+pub mod hhea;
+pub mod hmtx;
 pub mod glyf;
 pub mod loca;
-pub mod maxp;
\ No newline at end of file
+pub mod maxp;
+pub mod name;
+pub mod os2;
+pub mod paint;
+pub mod sbix;
+pub mod svg;
+pub mod variation_store;
@@ -0,0 +1,59 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+
+#[derive(Debug, Clone)]
+pub struct NameRecord {
+  pub platform_id: u16,
+  pub encoding_id: u16,
+  pub language_id: u16,
+  pub name_id: u16,
+  pub value: String,
+}
+
+// 'name' テーブル。文字列は読み込むときに UTF-8 にしておく
+#[derive(Debug, Clone)]
+pub struct NameTable {
+  pub records: Vec<NameRecord>,
+}
+
+// Unicode と Windows は UTF-16BE、Macintosh は1バイト文字（ASCII の範囲だけ正しく読める）
+fn decode(platform_id: u16, bytes: &[u8]) -> String {
+  match platform_id {
+    0 | 3 => {
+      let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+      String::from_utf16_lossy(&units)
+    }
+    _ => bytes.iter().map(|&b| b as char).collect(),
+  }
+}
+
+impl NameTable {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+    let _format = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let count = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let storage_offset = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())? as usize;
+    let mut records = Vec::new();
+    for _ in 0..count {
+      let platform_id = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+      let encoding_id = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+      let language_id = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+      let name_id = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+      let length = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())? as usize;
+      let offset = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())? as usize;
+      let start = storage_offset + offset;
+      let bytes = data.get(start..start + length).ok_or(format!("name レコード{}の文字列が範囲外です", name_id))?;
+      records.push(NameRecord { platform_id, encoding_id, language_id, name_id, value: decode(platform_id, bytes) });
+    }
+    Ok(NameTable { records })
+  }
+
+  // name_id の文字列。Windows の英語（米国）を優先し、なければ最初に見つかったもの
+  pub fn get(&self, name_id: u16) -> Option<&str> {
+    let candidates = || self.records.iter().filter(|r| r.name_id == name_id);
+    candidates()
+      .find(|r| r.platform_id == 3 && r.language_id == 0x409)
+      .or_else(|| candidates().next())
+      .map(|r| r.value.as_str())
+  }
+}
@@ -0,0 +1,28 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+
+// 'OS/2' テーブルのうち、縦方向のメトリクスに使う部分
+#[derive(Debug)]
+pub struct Os2Table {
+  pub version: u16,
+  pub typo_ascender: i16,
+  pub typo_descender: i16,
+  pub typo_line_gap: i16,
+  pub win_ascent: u16,
+  pub win_descent: u16,
+}
+
+impl Os2Table {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+    let version = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    // xAvgCharWidth から usLastCharIndex までは読み飛ばす
+    cursor.set_position(68);
+    let typo_ascender = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    let typo_descender = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    let typo_line_gap = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())?;
+    let win_ascent = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let win_descent = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    Ok(Os2Table { version, typo_ascender, typo_descender, typo_line_gap, win_ascent, win_descent })
+  }
+}
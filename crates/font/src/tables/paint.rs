@@ -0,0 +1,487 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+use super::colr::{add_offset, ColrTable, FOREGROUND_PALETTE_INDEX};
+use super::cpal::Color;
+use super::variation_store::NO_VARIATION_INDEX;
+use crate::truetype::Transform;
+
+// Paint のグラフをたどる深さの上限
+const MAX_DEPTH: usize = 64;
+// 1つのグリフで読む Paint の数の上限。部分グラフを共有する Paint は木に展開すると指数的に増える
+const MAX_NODES: usize = 100_000;
+
+// グラデーションの範囲の外の塗り方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extend {
+  Pad,
+  Repeat,
+  Reflect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorStop {
+  pub offset: f32,
+  pub palette_index: u16,
+  pub alpha: f32,
+}
+
+// 色の並び。stops は offset の昇順
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorLine {
+  pub extend: Extend,
+  pub stops: Vec<ColorStop>,
+}
+
+// PaintComposite の合成方法。Clear から Plus までは Porter-Duff、Screen 以降はブレンドモード
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompositeMode {
+  Clear,
+  Src,
+  Dest,
+  SrcOver,
+  DestOver,
+  SrcIn,
+  DestIn,
+  SrcOut,
+  DestOut,
+  SrcAtop,
+  DestAtop,
+  Xor,
+  Plus,
+  Screen,
+  Overlay,
+  Darken,
+  Lighten,
+  ColorDodge,
+  ColorBurn,
+  HardLight,
+  SoftLight,
+  Difference,
+  Exclusion,
+  Multiply,
+  HslHue,
+  HslSaturation,
+  HslColor,
+  HslLuminosity,
+}
+
+const COMPOSITE_MODES: [CompositeMode; 28] = [
+  CompositeMode::Clear,
+  CompositeMode::Src,
+  CompositeMode::Dest,
+  CompositeMode::SrcOver,
+  CompositeMode::DestOver,
+  CompositeMode::SrcIn,
+  CompositeMode::DestIn,
+  CompositeMode::SrcOut,
+  CompositeMode::DestOut,
+  CompositeMode::SrcAtop,
+  CompositeMode::DestAtop,
+  CompositeMode::Xor,
+  CompositeMode::Plus,
+  CompositeMode::Screen,
+  CompositeMode::Overlay,
+  CompositeMode::Darken,
+  CompositeMode::Lighten,
+  CompositeMode::ColorDodge,
+  CompositeMode::ColorBurn,
+  CompositeMode::HardLight,
+  CompositeMode::SoftLight,
+  CompositeMode::Difference,
+  CompositeMode::Exclusion,
+  CompositeMode::Multiply,
+  CompositeMode::HslHue,
+  CompositeMode::HslSaturation,
+  CompositeMode::HslColor,
+  CompositeMode::HslLuminosity,
+];
+
+// COLR バージョン 1 の Paint。可変の値は座標を当てはめ、変換はすべて行列にまとめてある
+// 座標はフォント単位、角度は反時計回りの度
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+  // 下から順に重ねる
+  Layers(Vec<Paint>),
+  Solid { palette_index: u16, alpha: f32 },
+  // p0 から p1 へのグラデーション。色の変わらない線は p0-p2 と平行
+  LinearGradient { line: ColorLine, p0: (f32, f32), p1: (f32, f32), p2: (f32, f32) },
+  // 円 (c0, r0) から円 (c1, r1) への2点円錐グラデーション
+  RadialGradient { line: ColorLine, c0: (f32, f32), r0: f32, c1: (f32, f32), r1: f32 },
+  SweepGradient { line: ColorLine, center: (f32, f32), start_angle: f32, end_angle: f32 },
+  // グリフの輪郭で paint を切り抜く
+  Glyph { glyph_id: u16, paint: Box<Paint> },
+  Transform { transform: Transform, paint: Box<Paint> },
+  // source を backdrop の上に mode で合成する
+  Composite { source: Box<Paint>, mode: CompositeMode, backdrop: Box<Paint> },
+}
+
+// 描画範囲を切り抜く矩形（フォント単位）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipBox {
+  pub x_min: f32,
+  pub y_min: f32,
+  pub x_max: f32,
+  pub y_max: f32,
+}
+
+// 値の型。可変の Paint の差分はこの単位で加える
+#[derive(Clone, Copy)]
+enum Field {
+  FWord,
+  UFWord,
+  F2Dot14,
+  Fixed,
+}
+
+// パレットの色に alpha を掛ける。パレットにない色は透明として扱う
+pub fn resolve_color(palette_index: u16, alpha: f32, palette: &[Color], foreground: Color) -> Color {
+  let color = match palette_index {
+    FOREGROUND_PALETTE_INDEX => foreground,
+    index => palette.get(index as usize).copied().unwrap_or(Color { r: 0, g: 0, b: 0, a: 0 }),
+  };
+  Color { a: (color.a as f32 * alpha.clamp(0.0, 1.0)).round() as u8, ..color }
+}
+
+// p0-p2 に垂直で p0 を通る直線へ p1 を射影した点。グラデーションは p0 からこの点へ向かう
+pub fn linear_gradient_end(p0: (f32, f32), p1: (f32, f32), p2: (f32, f32)) -> (f32, f32) {
+  let normal = (p2.1 - p0.1, p0.0 - p2.0);
+  let length = normal.0 * normal.0 + normal.1 * normal.1;
+  if length == 0.0 {
+    return p1;
+  }
+  let k = ((p1.0 - p0.0) * normal.0 + (p1.1 - p0.1) * normal.1) / length;
+  (p0.0 + normal.0 * k, p0.1 + normal.1 * k)
+}
+
+// 扇形グラデーションでの角度 degrees の位置（start_angle が 0、end_angle が 1）
+pub fn sweep_position(degrees: f32, start_angle: f32, end_angle: f32) -> f32 {
+  if end_angle == start_angle {
+    return if degrees < start_angle { 0.0 } else { 1.0 };
+  }
+  (degrees - start_angle) / (end_angle - start_angle)
+}
+
+impl Paint {
+  // 輪郭を使うグリフ ID（重複なし、出てきた順）
+  pub fn glyph_ids(&self) -> Vec<u16> {
+    let mut ids = Vec::new();
+    self.collect_glyph_ids(&mut ids);
+    ids
+  }
+
+  fn collect_glyph_ids(&self, ids: &mut Vec<u16>) {
+    match self {
+      Paint::Layers(layers) => layers.iter().for_each(|layer| layer.collect_glyph_ids(ids)),
+      Paint::Glyph { glyph_id, paint } => {
+        if !ids.contains(glyph_id) {
+          ids.push(*glyph_id);
+        }
+        paint.collect_glyph_ids(ids);
+      }
+      Paint::Transform { paint, .. } => paint.collect_glyph_ids(ids),
+      Paint::Composite { source, backdrop, .. } => {
+        source.collect_glyph_ids(ids);
+        backdrop.collect_glyph_ids(ids);
+      }
+      Paint::Solid { .. } | Paint::LinearGradient { .. } | Paint::RadialGradient { .. } | Paint::SweepGradient { .. } => {}
+    }
+  }
+}
+
+impl Extend {
+  // 範囲の外の t を [0, 1] に収める
+  pub fn apply(&self, t: f32) -> f32 {
+    match self {
+      Extend::Pad => t.clamp(0.0, 1.0),
+      Extend::Repeat => t - t.floor(),
+      Extend::Reflect => {
+        let t = t.rem_euclid(2.0);
+        if t > 1.0 { 2.0 - t } else { t }
+      }
+    }
+  }
+}
+
+impl ColorLine {
+  // 最初と最後の stop の offset。stop がなければ None
+  pub fn range(&self) -> Option<(f32, f32)> {
+    Some((self.stops.first()?.offset, self.stops.last()?.offset))
+  }
+
+  // 位置 t の色。t は extend を当てはめる前の値で、色は乗算済みのアルファで補間する
+  pub fn color_at(&self, t: f32, palette: &[Color], foreground: Color) -> [f32; 4] {
+    let Some((first, last)) = self.range() else {
+      return [0.0; 4];
+    };
+    let premultiplied = |stop: &ColorStop| {
+      let color = resolve_color(stop.palette_index, stop.alpha, palette, foreground);
+      let a = color.a as f32 / 255.0;
+      [color.r as f32 / 255.0 * a, color.g as f32 / 255.0 * a, color.b as f32 / 255.0 * a, a]
+    };
+    // stop の範囲を [0, 1] とみなして extend を当てはめる
+    let t = if last > first { first + self.extend.apply((t - first) / (last - first)) * (last - first) } else { t };
+    let after = self.stops.iter().position(|stop| stop.offset > t);
+    match after {
+      Some(0) => premultiplied(&self.stops[0]),
+      None => premultiplied(self.stops.last().unwrap()),
+      Some(i) => {
+        let (a, b) = (&self.stops[i - 1], &self.stops[i]);
+        let u = if b.offset > a.offset { (t - a.offset) / (b.offset - a.offset) } else { 1.0 };
+        let (ca, cb) = (premultiplied(a), premultiplied(b));
+        [0, 1, 2, 3].map(|c| ca[c] + (cb[c] - ca[c]) * u)
+      }
+    }
+  }
+}
+
+fn u8_at(cursor: &mut Cursor<&[u8]>) -> Result<u8, String> {
+  cursor.read_u8().map_err(|e| e.to_string())
+}
+
+fn u16_at(cursor: &mut Cursor<&[u8]>) -> Result<u16, String> {
+  cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())
+}
+
+fn u24_at(cursor: &mut Cursor<&[u8]>) -> Result<u32, String> {
+  cursor.read_u24::<BigEndian>().map_err(|e| e.to_string())
+}
+
+fn u32_at(cursor: &mut Cursor<&[u8]>) -> Result<u32, String> {
+  cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())
+}
+
+// Paint のグラフを座標を当てはめながら読む
+struct PaintParser<'a> {
+  colr: &'a ColrTable,
+  coords: &'a [f32],
+  // たどっている途中の Paint のオフセット（循環の検出に使う）
+  active: Vec<u32>,
+  // これまでに読んだ Paint の数
+  nodes: usize,
+}
+
+impl PaintParser<'_> {
+  // var_index_base から順に count 個の差分
+  fn deltas(&self, var_index_base: u32, count: usize) -> Vec<f32> {
+    if var_index_base == NO_VARIATION_INDEX || self.coords.iter().all(|&c| c == 0.0) {
+      return vec![0.0; count];
+    }
+    (0..count as u32).map(|i| self.colr.delta(var_index_base.wrapping_add(i), self.coords)).collect()
+  }
+
+  // fields の型の値を順に読み、variable なら続く varIndexBase の差分を加えて実際の値にする
+  fn read_values(&self, cursor: &mut Cursor<&[u8]>, fields: &[Field], variable: bool) -> Result<Vec<f32>, String> {
+    let raw = fields.iter()
+      .map(|field| {
+        Ok(match field {
+          Field::FWord | Field::F2Dot14 => cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())? as f32,
+          Field::UFWord => u16_at(cursor)? as f32,
+          Field::Fixed => cursor.read_i32::<BigEndian>().map_err(|e| e.to_string())? as f32,
+        })
+      })
+      .collect::<Result<Vec<f32>, String>>()?;
+    let deltas = if variable { self.deltas(u32_at(cursor)?, fields.len()) } else { vec![0.0; fields.len()] };
+    Ok(fields.iter().zip(raw).zip(deltas)
+      .map(|((field, value), delta)| match field {
+        Field::FWord | Field::UFWord => value + delta,
+        Field::F2Dot14 => (value + delta) / 16384.0,
+        Field::Fixed => (value + delta) / 65536.0,
+      })
+      .collect())
+  }
+
+  fn color_line(&self, offset: u32, variable: bool) -> Result<ColorLine, String> {
+    let mut cursor = Cursor::new(self.colr.data.as_slice());
+    cursor.set_position(offset as u64);
+    let extend = match u8_at(&mut cursor)? {
+      1 => Extend::Repeat,
+      2 => Extend::Reflect,
+      // 未知の値は Pad として扱う
+      _ => Extend::Pad,
+    };
+    let count = u16_at(&mut cursor)?;
+    let mut stops = (0..count)
+      .map(|_| {
+        let offset = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())? as f32;
+        let palette_index = u16_at(&mut cursor)?;
+        let alpha = cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())? as f32;
+        let deltas = if variable { self.deltas(u32_at(&mut cursor)?, 2) } else { vec![0.0; 2] };
+        Ok(ColorStop { offset: (offset + deltas[0]) / 16384.0, palette_index, alpha: (alpha + deltas[1]) / 16384.0 })
+      })
+      .collect::<Result<Vec<_>, String>>()?;
+    stops.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+    Ok(ColorLine { extend, stops })
+  }
+
+  fn paint(&mut self, offset: u32) -> Result<Paint, String> {
+    if self.active.contains(&offset) {
+      return Err("COLR の Paint が循環しています".to_string());
+    }
+    if self.active.len() >= MAX_DEPTH {
+      return Err("COLR の Paint の入れ子が深すぎます".to_string());
+    }
+    self.nodes += 1;
+    if self.nodes > MAX_NODES {
+      return Err("COLR の Paint が多すぎます".to_string());
+    }
+    self.active.push(offset);
+    let paint = self.read_paint(offset);
+    self.active.pop();
+    paint
+  }
+
+  // 子の Paint。オフセットは親の Paint の先頭から
+  fn child(&mut self, cursor: &mut Cursor<&[u8]>, base: u32) -> Result<Box<Paint>, String> {
+    let offset = u24_at(cursor)?;
+    Ok(Box::new(self.paint(add_offset(base, offset)?)?))
+  }
+
+  fn read_paint(&mut self, offset: u32) -> Result<Paint, String> {
+    let data = self.colr.data.as_slice();
+    let mut cursor = Cursor::new(data);
+    cursor.set_position(offset as u64);
+    let format = u8_at(&mut cursor)?;
+    // 奇数の形式は直前の形式の可変版（1 と 11 を除く）
+    let variable = format % 2 == 1 && format != 1 && format != 11;
+    use Field::*;
+    Ok(match format {
+      1 => {
+        let count = u8_at(&mut cursor)? as usize;
+        let first = u32_at(&mut cursor)? as usize;
+        let offsets = self.colr.layer_paints.get(first..first + count).ok_or("PaintColrLayers が LayerList の外を指しています")?;
+        Paint::Layers(offsets.iter().map(|&offset| self.paint(offset)).collect::<Result<_, String>>()?)
+      }
+      2 | 3 => {
+        let palette_index = u16_at(&mut cursor)?;
+        let values = self.read_values(&mut cursor, &[F2Dot14], variable)?;
+        Paint::Solid { palette_index, alpha: values[0] }
+      }
+      4 | 5 => {
+        let line = self.color_line(add_offset(offset, u24_at(&mut cursor)?)?, variable)?;
+        let v = self.read_values(&mut cursor, &[FWord; 6], variable)?;
+        Paint::LinearGradient { line, p0: (v[0], v[1]), p1: (v[2], v[3]), p2: (v[4], v[5]) }
+      }
+      6 | 7 => {
+        let line = self.color_line(add_offset(offset, u24_at(&mut cursor)?)?, variable)?;
+        let v = self.read_values(&mut cursor, &[FWord, FWord, UFWord, FWord, FWord, UFWord], variable)?;
+        Paint::RadialGradient { line, c0: (v[0], v[1]), r0: v[2], c1: (v[3], v[4]), r1: v[5] }
+      }
+      8 | 9 => {
+        let line = self.color_line(add_offset(offset, u24_at(&mut cursor)?)?, variable)?;
+        let v = self.read_values(&mut cursor, &[FWord, FWord, F2Dot14, F2Dot14], variable)?;
+        // 角度は 1.0 あたり 180 度で、1.0 だけ下駄を履かせてある
+        Paint::SweepGradient { line, center: (v[0], v[1]), start_angle: (v[2] + 1.0) * 180.0, end_angle: (v[3] + 1.0) * 180.0 }
+      }
+      10 => {
+        let paint = self.child(&mut cursor, offset)?;
+        Paint::Glyph { glyph_id: u16_at(&mut cursor)?, paint }
+      }
+      11 => {
+        // 別のカラーグリフをそのまま埋め込む
+        let glyph_id = u16_at(&mut cursor)?;
+        let target = self.colr.paint_offset(glyph_id).ok_or(format!("PaintColrGlyph のグリフ{}はカラーグリフではありません", glyph_id))?;
+        self.paint(target)?
+      }
+      12 | 13 => {
+        let paint = self.child(&mut cursor, offset)?;
+        let affine = add_offset(offset, u24_at(&mut cursor)?)?;
+        cursor.set_position(affine as u64);
+        let v = self.read_values(&mut cursor, &[Fixed; 6], variable)?;
+        Paint::Transform { transform: Transform::new(v[0], v[1], v[2], v[3], v[4], v[5]), paint }
+      }
+      14..=31 => {
+        let paint = self.child(&mut cursor, offset)?;
+        let transform = self.read_transform(&mut cursor, format, variable)?;
+        Paint::Transform { transform, paint }
+      }
+      32 => {
+        let source = self.child(&mut cursor, offset)?;
+        let mode = u8_at(&mut cursor)?;
+        let mode = *COMPOSITE_MODES.get(mode as usize).ok_or(format!("未知の合成方法です: {}", mode))?;
+        let backdrop = self.child(&mut cursor, offset)?;
+        Paint::Composite { source, mode, backdrop }
+      }
+      other => return Err(format!("未知の Paint の形式です: {}", other)),
+    })
+  }
+
+  // PaintTranslate から PaintVarSkewAroundCenter までを行列にする
+  fn read_transform(&self, cursor: &mut Cursor<&[u8]>, format: u8, variable: bool) -> Result<Transform, String> {
+    use Field::*;
+    let (fields, around): (&[Field], bool) = match format & !1 {
+      14 => (&[FWord, FWord], false),
+      16 => (&[F2Dot14, F2Dot14], false),
+      18 => (&[F2Dot14, F2Dot14, FWord, FWord], true),
+      20 | 24 => (&[F2Dot14], false),
+      22 | 26 => (&[F2Dot14, FWord, FWord], true),
+      28 => (&[F2Dot14, F2Dot14], false),
+      _ => (&[F2Dot14, F2Dot14, FWord, FWord], true),
+    };
+    let v = self.read_values(cursor, fields, variable)?;
+    let transform = match format & !1 {
+      14 => Transform::translate(v[0], v[1]),
+      16 | 18 => Transform::scale(v[0], v[1]),
+      20 | 22 => Transform::scale(v[0], v[0]),
+      24 | 26 => Transform::rotate(v[0] * 180.0),
+      _ => Transform::skew(v[0] * 180.0, v[1] * 180.0),
+    };
+    Ok(if around {
+      let n = v.len();
+      transform.around(v[n - 2], v[n - 1])
+    } else {
+      transform
+    })
+  }
+}
+
+impl ColrTable {
+  // glyph_id のバージョン 1 の Paint を、正規化した座標 coords で読む。なければ None
+  pub fn paint(&self, glyph_id: u16, coords: &[f32]) -> Result<Option<Paint>, String> {
+    let Some(offset) = self.paint_offset(glyph_id) else {
+      return Ok(None);
+    };
+    let mut parser = PaintParser { colr: self, coords, active: Vec::new(), nodes: 0 };
+    parser.paint(offset).map(Some)
+  }
+
+  // glyph_id の描画範囲。ClipList になければ None
+  pub fn clip_box(&self, glyph_id: u16, coords: &[f32]) -> Option<ClipBox> {
+    let clip = self.clips.iter().find(|clip| (clip.start_glyph_id..=clip.end_glyph_id).contains(&glyph_id))?;
+    let parser = PaintParser { colr: self, coords, active: Vec::new(), nodes: 0 };
+    let deltas = parser.deltas(clip.var_index_base.unwrap_or(NO_VARIATION_INDEX), 4);
+    let [x_min, y_min, x_max, y_max] = [0, 1, 2, 3].map(|i| clip.bounds[i] as f32 + deltas[i]);
+    Some(ClipBox { x_min, y_min, x_max, y_max })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tables::colr::BaseGlyphPaintRecord;
+
+  fn colr(data: Vec<u8>) -> ColrTable {
+    ColrTable {
+      version: 1,
+      base_glyphs: Vec::new(),
+      layers: Vec::new(),
+      base_glyph_paints: vec![BaseGlyphPaintRecord { glyph_id: 1, paint_offset: 0 }],
+      layer_paints: Vec::new(),
+      clips: Vec::new(),
+      var_index_map: None,
+      variation_store: None,
+      data,
+    }
+  }
+
+  #[test]
+  fn shared_subgraphs_hit_the_node_budget() {
+    // source と backdrop が同じ次の PaintComposite を指す 20 段の鎖。木に展開すると 2^20 個になる
+    let mut data = Vec::new();
+    for _ in 0..20 {
+      data.extend_from_slice(&[32, 0, 0, 8, 3, 0, 0, 8]);
+    }
+    // PaintSolid
+    data.extend_from_slice(&[2, 0, 0, 0x40, 0]);
+    assert_eq!(colr(data).paint(1, &[]).unwrap_err(), "COLR の Paint が多すぎます");
+  }
+}
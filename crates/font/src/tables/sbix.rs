@@ -0,0 +1,227 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+
+use crate::bitmap::ImageFormat;
+
+// dupe をたどる回数の上限（循環していても止まるように）
+const MAX_DUPE_DEPTH: usize = 8;
+
+// ある ppem のビットマップの集まり。offsets はテーブルの先頭から、グリフ数 + 1 個
+#[derive(Debug, Clone)]
+pub struct SbixStrike {
+  pub ppem: u16,
+  pub ppi: u16,
+  pub offsets: Vec<u32>,
+}
+
+// グリフの画像。origin は画像の左下の原点からの位置（ピクセル）
+#[derive(Debug, Clone)]
+pub struct SbixGlyph {
+  pub origin_offset_x: i16,
+  pub origin_offset_y: i16,
+  pub format: ImageFormat,
+  pub data: Vec<u8>,
+}
+
+// 'sbix' テーブル
+#[derive(Debug, Clone)]
+pub struct SbixTable {
+  pub version: u16,
+  pub flags: u16,
+  pub strikes: Vec<SbixStrike>,
+  pub(crate) data: Vec<u8>,
+}
+
+impl SbixTable {
+  pub fn parse(data: &[u8], num_glyphs: u16) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+    let version = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let flags = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let num_strikes = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+    let strike_offsets = (0..num_strikes)
+      .map(|_| cursor.read_u32::<BigEndian>().map_err(|e| e.to_string()))
+      .collect::<Result<Vec<_>, String>>()?;
+
+    let strikes = strike_offsets.into_iter()
+      .map(|strike_offset| {
+        cursor.set_position(strike_offset as u64);
+        let ppem = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+        let ppi = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+        let offsets = (0..=num_glyphs)
+          .map(|_| {
+            let offset = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+            strike_offset.checked_add(offset).ok_or("sbix のオフセットが範囲外です".to_string())
+          })
+          .collect::<Result<_, String>>()?;
+        Ok(SbixStrike { ppem, ppi, offsets })
+      })
+      .collect::<Result<_, String>>()?;
+    Ok(SbixTable { version, flags, strikes, data: data.to_vec() })
+  }
+
+  // 画像がないか対応していない形式なら None。dupe は参照先のグリフの画像にする
+  pub fn glyph(&self, strike: &SbixStrike, glyph_id: u16) -> Result<Option<SbixGlyph>, String> {
+    let mut glyph_id = glyph_id;
+    for _ in 0..MAX_DUPE_DEPTH {
+      let index = glyph_id as usize;
+      let (Some(&start), Some(&end)) = (strike.offsets.get(index), strike.offsets.get(index + 1)) else {
+        return Ok(None);
+      };
+      // 長さ 0 は画像のないグリフ
+      if end <= start {
+        return Ok(None);
+      }
+      let record = self.data.get(start as usize..end as usize).ok_or("sbix のグリフの位置がテーブルの外です")?;
+      if record.len() < 8 {
+        return Err(format!("sbix のグリフ {} のデータが短すぎます", glyph_id));
+      }
+      let origin_offset_x = i16::from_be_bytes([record[0], record[1]]);
+      let origin_offset_y = i16::from_be_bytes([record[2], record[3]]);
+      let data = &record[8..];
+      let format = match &record[4..8] {
+        b"png " => ImageFormat::Png,
+        b"jpg " => ImageFormat::Jpeg,
+        b"tiff" => ImageFormat::Tiff,
+        b"dupe" => {
+          glyph_id = u16::from_be_bytes(data.get(..2).ok_or("sbix の dupe に参照先がありません")?.try_into().unwrap());
+          continue;
+        }
+        // mask や pdf など描けない形式は画像がないものとして、輪郭で描かせる
+        _ => return Ok(None),
+      };
+      return Ok(Some(SbixGlyph { origin_offset_x, origin_offset_y, format, data: data.to_vec() }));
+    }
+    Err("sbix の dupe の参照が深すぎます".to_string())
+  }
+
+  // glyph_id の画像を持つストライクのうち ppem が最も近いもの。同じ近さなら大きい方
+  pub fn nearest_strike(&self, glyph_id: u16, ppem: u16) -> Result<Option<&SbixStrike>, String> {
+    let mut best: Option<&SbixStrike> = None;
+    for strike in &self.strikes {
+      if self.glyph(strike, glyph_id)?.is_none() {
+        continue;
+      }
+      let distance = |s: &SbixStrike| (s.ppem as i32 - ppem as i32).abs();
+      let better = best.is_none_or(|b| (distance(strike), -(strike.ppem as i32)) < (distance(b), -(b.ppem as i32)));
+      if better {
+        best = Some(strike);
+      }
+    }
+    Ok(best)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn png(data: &[u8]) -> Vec<u8> {
+    let mut record = vec![0, 1, 0xFF, 0xFE];
+    record.extend(b"png ");
+    record.extend(data);
+    record
+  }
+
+  fn dupe(glyph_id: u16) -> Vec<u8> {
+    let mut record = vec![0; 4];
+    record.extend(b"dupe");
+    record.extend(glyph_id.to_be_bytes());
+    record
+  }
+
+  fn graphic(graphic_type: &[u8; 4]) -> Vec<u8> {
+    let mut record = vec![0; 4];
+    record.extend(graphic_type);
+    record.extend([1, 2, 3]);
+    record
+  }
+
+  // ストライクごとの (ppem, グリフごとのレコード)。空のレコードは画像なし
+  fn table(strikes: &[(u16, Vec<Vec<u8>>)]) -> SbixTable {
+    let num_glyphs = strikes[0].1.len() as u16;
+    let mut data = vec![0, 1, 0, 1];
+    data.extend((strikes.len() as u32).to_be_bytes());
+    let mut strike_data = Vec::new();
+    let mut strike_offset = 8 + 4 * strikes.len() as u32;
+    for (ppem, records) in strikes {
+      data.extend(strike_offset.to_be_bytes());
+      let mut strike = vec![];
+      strike.extend(ppem.to_be_bytes());
+      strike.extend(72u16.to_be_bytes());
+      let mut offset = 4 + 4 * (records.len() as u32 + 1);
+      for record in records {
+        strike.extend(offset.to_be_bytes());
+        offset += record.len() as u32;
+      }
+      strike.extend(offset.to_be_bytes());
+      records.iter().for_each(|record| strike.extend(record));
+      strike_offset += strike.len() as u32;
+      strike_data.extend(strike);
+    }
+    data.extend(strike_data);
+    SbixTable::parse(&data, num_glyphs).unwrap()
+  }
+
+  #[test]
+  fn glyph_reads_the_origin_and_image() {
+    let sbix = table(&[(20, vec![png(b"abc"), vec![]])]);
+    let glyph = sbix.glyph(&sbix.strikes[0], 0).unwrap().unwrap();
+    assert_eq!((glyph.origin_offset_x, glyph.origin_offset_y), (1, -2));
+    assert_eq!((glyph.format, glyph.data.as_slice()), (ImageFormat::Png, &b"abc"[..]));
+    // 長さ 0 のレコードと範囲外のグリフは画像なし
+    assert!(sbix.glyph(&sbix.strikes[0], 1).unwrap().is_none());
+    assert!(sbix.glyph(&sbix.strikes[0], 2).unwrap().is_none());
+  }
+
+  #[test]
+  fn short_records_are_errors() {
+    let sbix = table(&[(20, vec![vec![0, 0, 0, 0, b'p', b'n']])]);
+    assert_eq!(sbix.glyph(&sbix.strikes[0], 0).unwrap_err(), "sbix のグリフ 0 のデータが短すぎます");
+  }
+
+  #[test]
+  fn unsupported_graphic_types_have_no_image() {
+    let sbix = table(&[(20, vec![graphic(b"mask"), graphic(b"pdf "), graphic(b"jpg ")])]);
+    assert!(sbix.glyph(&sbix.strikes[0], 0).unwrap().is_none());
+    assert!(sbix.glyph(&sbix.strikes[0], 1).unwrap().is_none());
+    assert_eq!(sbix.glyph(&sbix.strikes[0], 2).unwrap().unwrap().format, ImageFormat::Jpeg);
+  }
+
+  #[test]
+  fn dupes_follow_the_chain() {
+    let sbix = table(&[(20, vec![png(b"x"), dupe(0), dupe(1)])]);
+    let glyph = sbix.glyph(&sbix.strikes[0], 2).unwrap().unwrap();
+    assert_eq!(glyph.data, b"x");
+    // dupe の先に画像がなければ None
+    let sbix = table(&[(20, vec![vec![], dupe(0), dupe(5)])]);
+    assert!(sbix.glyph(&sbix.strikes[0], 1).unwrap().is_none());
+    assert!(sbix.glyph(&sbix.strikes[0], 2).unwrap().is_none());
+  }
+
+  #[test]
+  fn dupe_chains_are_limited() {
+    // グリフ i は i + 1 の dupe。グリフ 8 だけが画像を持つ
+    let mut records: Vec<Vec<u8>> = (1..=8).map(dupe).collect();
+    records.push(png(b"x"));
+    let sbix = table(&[(20, records)]);
+    // dupe を7回たどるのは許す
+    assert!(sbix.glyph(&sbix.strikes[0], 1).unwrap().is_some());
+    assert_eq!(sbix.glyph(&sbix.strikes[0], 0).unwrap_err(), "sbix の dupe の参照が深すぎます");
+    // 循環していても止まる
+    let sbix = table(&[(20, vec![dupe(1), dupe(0)])]);
+    assert!(sbix.glyph(&sbix.strikes[0], 0).is_err());
+  }
+
+  #[test]
+  fn nearest_strike_prefers_the_larger_on_a_tie() {
+    let sbix = table(&[(20, vec![png(b"a"), vec![]]), (40, vec![png(b"b"), png(b"c")]), (160, vec![png(b"d"), graphic(b"pdf ")])]);
+    let nearest = |glyph_id, ppem| sbix.nearest_strike(glyph_id, ppem).unwrap().map(|strike| strike.ppem);
+    assert_eq!(nearest(0, 29), Some(20));
+    assert_eq!(nearest(0, 30), Some(40));
+    assert_eq!(nearest(0, 100), Some(160));
+    // 画像のないストライクや対応していない形式のストライクは飛ばす
+    assert_eq!(nearest(1, 12), Some(40));
+    assert_eq!(nearest(1, 200), Some(40));
+    assert_eq!(nearest(2, 20), None);
+  }
+}
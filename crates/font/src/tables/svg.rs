@@ -0,0 +1,106 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use flate2::read::GzDecoder;
+use std::io::{Cursor, Read};
+
+// gzip の先頭のマジックナンバー
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+// 展開した SVG 文書の大きさの上限。小さな gzip が巨大に膨らむのを防ぐ
+const MAX_DOCUMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+// start_glyph_id から end_glyph_id までのグリフを含む SVG 文書。offset はテーブルの先頭から
+#[derive(Debug, Clone)]
+pub struct SvgDocumentRecord {
+  pub start_glyph_id: u16,
+  pub end_glyph_id: u16,
+  pub offset: u32,
+  pub length: u32,
+}
+
+// 'SVG ' テーブル
+#[derive(Debug, Clone)]
+pub struct SvgTable {
+  pub version: u16,
+  pub records: Vec<SvgDocumentRecord>,
+  pub(crate) data: Vec<u8>,
+}
+
+impl SvgTable {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+    let version = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let list_offset = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+
+    cursor.set_position(list_offset as u64);
+    let num_entries = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let records = (0..num_entries)
+      .map(|_| {
+        Ok(SvgDocumentRecord {
+          start_glyph_id: cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?,
+          end_glyph_id: cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?,
+          offset: list_offset
+            .checked_add(cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?)
+            .ok_or("SVG 文書のオフセットが範囲外です")?,
+          length: cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?,
+        })
+      })
+      .collect::<Result<_, String>>()?;
+    Ok(SvgTable { version, records, data: data.to_vec() })
+  }
+
+  // glyph_id を含む SVG 文書。gzip で圧縮されていれば展開する。なければ None
+  pub fn document(&self, glyph_id: u16) -> Result<Option<String>, String> {
+    // レコードは start_glyph_id の順に並んでいる
+    let index = self.records.partition_point(|record| record.end_glyph_id < glyph_id);
+    let Some(record) = self.records.get(index).filter(|record| record.start_glyph_id <= glyph_id) else {
+      return Ok(None);
+    };
+    let start = record.offset as usize;
+    let bytes = self.data.get(start..start + record.length as usize).ok_or("SVG 文書の位置がテーブルの外です")?;
+    let mut document = Vec::new();
+    if bytes.starts_with(&GZIP_MAGIC) {
+      // 上限を1バイト超えて読めたら大きすぎる
+      GzDecoder::new(bytes)
+        .take(MAX_DOCUMENT_SIZE + 1)
+        .read_to_end(&mut document)
+        .map_err(|e| format!("SVG 文書の展開に失敗しました: {}", e))?;
+      if document.len() as u64 > MAX_DOCUMENT_SIZE {
+        return Err(format!("展開した SVG 文書が {} バイトを超えています", MAX_DOCUMENT_SIZE));
+      }
+    } else {
+      document.extend_from_slice(bytes);
+    }
+    String::from_utf8(document).map(Some).map_err(|_| "SVG 文書が UTF-8 ではありません".to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use flate2::write::GzEncoder;
+  use flate2::Compression;
+  use std::io::Write;
+
+  fn table(document: &[u8]) -> SvgTable {
+    let record = SvgDocumentRecord { start_glyph_id: 1, end_glyph_id: 1, offset: 0, length: document.len() as u32 };
+    SvgTable { version: 0, records: vec![record], data: document.to_vec() }
+  }
+
+  fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+  }
+
+  #[test]
+  fn decompresses_gzip_documents() {
+    let svg = b"<svg id='glyph1'/>";
+    assert_eq!(table(&gzip(svg)).document(1).unwrap().as_deref(), Some("<svg id='glyph1'/>"));
+    assert_eq!(table(svg).document(2).unwrap(), None);
+  }
+
+  #[test]
+  fn rejects_documents_that_expand_past_the_limit() {
+    let bomb = gzip(&vec![b' '; MAX_DOCUMENT_SIZE as usize + 1]);
+    assert!(table(&bomb).document(1).is_err());
+  }
+}
@@ -0,0 +1,174 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+
+// 変化がないことを表す変化インデックス
+pub const NO_VARIATION_INDEX: u32 = 0xFFFF_FFFF;
+
+const WORD_DELTA_COUNT_MASK: u16 = 0x7FFF;
+const LONG_WORDS: u16 = 0x8000;
+const INNER_INDEX_BIT_COUNT_MASK: u8 = 0x0F;
+const MAP_ENTRY_SIZE_MASK: u8 = 0x30;
+
+// 1つの軸での領域（F2Dot14 の正規化座標）
+#[derive(Debug, Clone, Copy)]
+pub struct RegionAxis {
+  pub start: f32,
+  pub peak: f32,
+  pub end: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ItemVariationData {
+  pub region_indexes: Vec<u16>,
+  // 項目ごとに、region_indexes の並びの差分
+  pub delta_sets: Vec<Vec<i32>>,
+}
+
+// ItemVariationStore。(外側, 内側) の番号で引く差分の集まり
+#[derive(Debug, Clone, Default)]
+pub struct ItemVariationStore {
+  pub regions: Vec<Vec<RegionAxis>>,
+  pub data: Vec<ItemVariationData>,
+}
+
+// DeltaSetIndexMap。変化インデックスを (外側, 内側) の番号に対応づける
+#[derive(Debug, Clone, Default)]
+pub struct DeltaSetIndexMap {
+  pub entries: Vec<(u16, u16)>,
+}
+
+fn read_f2dot14(cursor: &mut Cursor<&[u8]>) -> Result<f32, String> {
+  Ok(cursor.read_i16::<BigEndian>().map_err(|e| e.to_string())? as f32 / 16384.0)
+}
+
+fn read_variation_data(cursor: &mut Cursor<&[u8]>) -> Result<ItemVariationData, String> {
+  let item_count = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+  let word_delta_count = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+  let region_index_count = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())? as usize;
+  let region_indexes = (0..region_index_count)
+    .map(|_| cursor.read_u16::<BigEndian>().map_err(|e| e.to_string()))
+    .collect::<Result<Vec<_>, String>>()?;
+  // 先頭の word_count 個は広い整数、残りは狭い整数
+  let word_count = (word_delta_count & WORD_DELTA_COUNT_MASK) as usize;
+  let long_words = word_delta_count & LONG_WORDS != 0;
+  let delta_sets = (0..item_count)
+    .map(|_| {
+      (0..region_index_count)
+        .map(|i| {
+          let value = match (i < word_count, long_words) {
+            (true, true) => cursor.read_i32::<BigEndian>(),
+            (true, false) | (false, true) => cursor.read_i16::<BigEndian>().map(i32::from),
+            (false, false) => cursor.read_i8().map(i32::from),
+          };
+          value.map_err(|e| e.to_string())
+        })
+        .collect::<Result<Vec<_>, String>>()
+    })
+    .collect::<Result<_, String>>()?;
+  Ok(ItemVariationData { region_indexes, delta_sets })
+}
+
+impl ItemVariationStore {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+    let format = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    if format != 1 {
+      return Err(format!("ItemVariationStore の形式{}には対応していません", format));
+    }
+    let region_list_offset = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+    let data_count = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let data_offsets = (0..data_count)
+      .map(|_| cursor.read_u32::<BigEndian>().map_err(|e| e.to_string()))
+      .collect::<Result<Vec<_>, String>>()?;
+
+    cursor.set_position(region_list_offset as u64);
+    let axis_count = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let region_count = cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+    let regions = (0..region_count)
+      .map(|_| {
+        (0..axis_count)
+          .map(|_| {
+            Ok(RegionAxis {
+              start: read_f2dot14(&mut cursor)?,
+              peak: read_f2dot14(&mut cursor)?,
+              end: read_f2dot14(&mut cursor)?,
+            })
+          })
+          .collect::<Result<Vec<_>, String>>()
+      })
+      .collect::<Result<_, String>>()?;
+
+    let data = data_offsets.iter()
+      .map(|&offset| {
+        cursor.set_position(offset as u64);
+        read_variation_data(&mut cursor)
+      })
+      .collect::<Result<_, String>>()?;
+    Ok(ItemVariationStore { regions, data })
+  }
+
+  // 座標に対する領域の重み
+  fn scalar(&self, region: usize, coords: &[f32]) -> f32 {
+    let Some(axes) = self.regions.get(region) else {
+      return 0.0;
+    };
+    let mut scalar = 1.0;
+    for (axis, region) in axes.iter().enumerate() {
+      let RegionAxis { start, peak, end } = *region;
+      // 不正な領域や 0 をまたぐ領域はこの軸を無視する
+      if peak == 0.0 || start > peak || peak > end || (start < 0.0 && end > 0.0) {
+        continue;
+      }
+      let v = coords.get(axis).copied().unwrap_or(0.0);
+      if v < start || v > end {
+        return 0.0;
+      }
+      if v < peak {
+        scalar *= (v - start) / (peak - start);
+      } else if v > peak {
+        scalar *= (end - v) / (end - peak);
+      }
+    }
+    scalar
+  }
+
+  // (outer, inner) の項目の座標での差分
+  pub fn delta(&self, outer: u16, inner: u16, coords: &[f32]) -> f32 {
+    let Some(data) = self.data.get(outer as usize) else {
+      return 0.0;
+    };
+    let Some(deltas) = data.delta_sets.get(inner as usize) else {
+      return 0.0;
+    };
+    data.region_indexes.iter().zip(deltas)
+      .map(|(&region, &delta)| delta as f32 * self.scalar(region as usize, coords))
+      .sum()
+  }
+}
+
+impl DeltaSetIndexMap {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    let mut cursor = Cursor::new(data);
+    let format = cursor.read_u8().map_err(|e| e.to_string())?;
+    let entry_format = cursor.read_u8().map_err(|e| e.to_string())?;
+    let map_count = match format {
+      0 => cursor.read_u16::<BigEndian>().map_err(|e| e.to_string())? as u32,
+      1 => cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?,
+      other => return Err(format!("DeltaSetIndexMap の形式{}には対応していません", other)),
+    };
+    let entry_size = ((entry_format & MAP_ENTRY_SIZE_MASK) >> 4) + 1;
+    let inner_bits = (entry_format & INNER_INDEX_BIT_COUNT_MASK) + 1;
+    let entries = (0..map_count)
+      .map(|_| {
+        let entry = cursor.read_uint::<BigEndian>(entry_size as usize).map_err(|e| e.to_string())? as u32;
+        Ok(((entry >> inner_bits) as u16, (entry & ((1 << inner_bits) - 1)) as u16))
+      })
+      .collect::<Result<_, String>>()?;
+    Ok(DeltaSetIndexMap { entries })
+  }
+
+  // 範囲を超えた番号は最後の項目を使う
+  pub fn get(&self, index: u32) -> Option<(u16, u16)> {
+    self.entries.get(index as usize).or(self.entries.last()).copied()
+  }
+}
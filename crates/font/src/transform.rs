@@ -0,0 +1,276 @@
+use crate::sink::OutlineSink;
+use crate::truetype::outline::PathSegment;
+use crate::truetype::Transform;
+
+// x' = a x + c y + x, y' = b x + d y + y（複合グリフの成分と同じ並び）
+impl Transform {
+  pub fn new(a: f32, b: f32, c: f32, d: f32, x: f32, y: f32) -> Self {
+    Transform { a, b, c, d, x, y }
+  }
+
+  pub fn identity() -> Self {
+    Transform::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+  }
+
+  pub fn translate(x: f32, y: f32) -> Self {
+    Transform::new(1.0, 0.0, 0.0, 1.0, x, y)
+  }
+
+  pub fn scale(sx: f32, sy: f32) -> Self {
+    Transform::new(sx, 0.0, 0.0, sy, 0.0, 0.0)
+  }
+
+  // 斜体化。slant は高さあたりの横ずれ（FreeType の FT_GlyphSlot_Oblique は約 0.2）
+  pub fn oblique(slant: f32) -> Self {
+    Transform::new(1.0, 0.0, slant, 1.0, 0.0, 0.0)
+  }
+
+  // 反時計回りの回転（度）
+  pub fn rotate(degrees: f32) -> Self {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    Transform::new(cos, sin, -sin, cos, 0.0, 0.0)
+  }
+
+  // x 方向・y 方向のせん断。角度は反時計回り（度）
+  pub fn skew(x_degrees: f32, y_degrees: f32) -> Self {
+    Transform::new(1.0, y_degrees.to_radians().tan(), -x_degrees.to_radians().tan(), 1.0, 0.0, 0.0)
+  }
+
+  // (cx, cy) を中心にして self を適用する変換
+  pub fn around(&self, cx: f32, cy: f32) -> Transform {
+    Transform::translate(-cx, -cy).then(self).then(&Transform::translate(cx, cy))
+  }
+
+  // 逆変換。潰れた変換なら None
+  pub fn invert(&self) -> Option<Transform> {
+    let det = self.a * self.d - self.b * self.c;
+    if det == 0.0 || !det.is_finite() {
+      return None;
+    }
+    let (a, b, c, d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+    Some(Transform::new(a, b, c, d, -(a * self.x + c * self.y), -(b * self.x + d * self.y)))
+  }
+
+  pub fn is_identity(&self) -> bool {
+    *self == Transform::identity()
+  }
+
+  pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+    (self.a * x + self.c * y + self.x, self.b * x + self.d * y + self.y)
+  }
+
+  // self を適用したあとに other を適用する変換
+  pub fn then(&self, other: &Transform) -> Transform {
+    let (x, y) = other.apply(self.x, self.y);
+    Transform {
+      a: other.a * self.a + other.c * self.b,
+      b: other.b * self.a + other.d * self.b,
+      c: other.a * self.c + other.c * self.d,
+      d: other.b * self.c + other.d * self.d,
+      x,
+      y,
+    }
+  }
+}
+
+// 座標を変換しながら別の sink に流す
+pub struct TransformSink<'a, S: OutlineSink + ?Sized> {
+  pub sink: &'a mut S,
+  pub transform: Transform,
+}
+
+impl<'a, S: OutlineSink + ?Sized> TransformSink<'a, S> {
+  pub fn new(sink: &'a mut S, transform: Transform) -> Self {
+    TransformSink { sink, transform }
+  }
+}
+
+impl<S: OutlineSink + ?Sized> OutlineSink for TransformSink<'_, S> {
+  fn move_to(&mut self, x: f32, y: f32) {
+    let (x, y) = self.transform.apply(x, y);
+    self.sink.move_to(x, y);
+  }
+
+  fn line_to(&mut self, x: f32, y: f32) {
+    let (x, y) = self.transform.apply(x, y);
+    self.sink.line_to(x, y);
+  }
+
+  fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+    let (x1, y1) = self.transform.apply(x1, y1);
+    let (x, y) = self.transform.apply(x, y);
+    self.sink.quad_to(x1, y1, x, y);
+  }
+
+  fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+    let (x1, y1) = self.transform.apply(x1, y1);
+    let (x2, y2) = self.transform.apply(x2, y2);
+    let (x, y) = self.transform.apply(x, y);
+    self.sink.cubic_to(x1, y1, x2, y2, x, y);
+  }
+
+  fn close(&mut self) {
+    self.sink.close();
+  }
+}
+
+// MoveTo から始まる輪郭ごとに、始点と各セグメントの点を並べる
+fn contour_points(segments: &[PathSegment]) -> Vec<Vec<(f32, f32)>> {
+  let mut contours: Vec<Vec<(f32, f32)>> = Vec::new();
+  for segment in segments {
+    match *segment {
+      PathSegment::MoveTo(x, y) => contours.push(vec![(x, y)]),
+      PathSegment::LineTo(x, y) => contours.last_mut().into_iter().for_each(|c| c.push((x, y))),
+      PathSegment::QuadTo(x1, y1, x, y) => contours.last_mut().into_iter().for_each(|c| c.extend([(x1, y1), (x, y)])),
+      PathSegment::CubicTo(x1, y1, x2, y2, x, y) => {
+        contours.last_mut().into_iter().for_each(|c| c.extend([(x1, y1), (x2, y2), (x, y)]))
+      }
+      PathSegment::Close => {}
+    }
+  }
+  contours
+}
+
+fn polygon_area(points: &[(f32, f32)]) -> f32 {
+  let n = points.len();
+  (0..n)
+    .map(|i| {
+      let (p, q) = (points[i], points[(i + 1) % n]);
+      p.0 * q.1 - q.0 * p.1
+    })
+    .sum::<f32>()
+    / 2.0
+}
+
+// 輪郭を外側へ strength / 2 ずつ膨らませる（FT_Outline_Embolden と同じく制御点ごとにずらす）
+// 輪郭の向きは制御点の多角形の符号付き面積の合計から判断する
+pub fn embolden(segments: &[PathSegment], strength: f32) -> Vec<PathSegment> {
+  let offset = strength / 2.0;
+  let contours = contour_points(segments);
+  // 時計回り（y軸上向き）が外側なら左手側が外向き法線になる
+  let area: f32 = contours.iter().map(|points| polygon_area(points)).sum();
+  let orientation = if area > 0.0 { -1.0 } else { 1.0 };
+
+  // 始点に戻って閉じている輪郭は、重複した終点を除いて環状に扱う
+  let moved: Vec<Vec<(f32, f32)>> = contours.iter()
+    .map(|points| {
+      let mut n = points.len();
+      if n > 1 && points[0] == points[n - 1] {
+        n -= 1;
+      }
+      let normal = |from: (f32, f32), to: (f32, f32)| {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0.0 { (0.0, 0.0) } else { (-dy / length * orientation, dx / length * orientation) }
+      };
+      let mut moved: Vec<(f32, f32)> = (0..n)
+        .map(|i| {
+          let (previous, point, next) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+          let n_in = normal(previous, point);
+          let n_out = normal(point, next);
+          let d = 1.0 + n_in.0 * n_out.0 + n_in.1 * n_out.1;
+          // 折り返しに近い角では移動量が発散するので抑える
+          let k = if d < 0.25 { offset } else { offset / d };
+          (point.0 + (n_in.0 + n_out.0) * k, point.1 + (n_in.1 + n_out.1) * k)
+        })
+        .collect();
+      if n < points.len() {
+        moved.push(moved[0]);
+      }
+      moved
+    })
+    .collect();
+
+  // 移動後の点で同じ形のセグメントを組み直す
+  let mut result = Vec::with_capacity(segments.len());
+  let mut contour: Option<usize> = None;
+  let mut index = 0;
+  for segment in segments {
+    if let PathSegment::MoveTo(..) = segment {
+      contour = Some(contour.map_or(0, |c| c + 1));
+      index = 0;
+    }
+    let Some(points) = contour.and_then(|c| moved.get(c)) else {
+      result.push(*segment);
+      continue;
+    };
+    let mut next = || {
+      let p = points[index.min(points.len() - 1)];
+      index += 1;
+      p
+    };
+    result.push(match segment {
+      PathSegment::MoveTo(..) => {
+        let p = next();
+        PathSegment::MoveTo(p.0, p.1)
+      }
+      PathSegment::LineTo(..) => {
+        let p = next();
+        PathSegment::LineTo(p.0, p.1)
+      }
+      PathSegment::QuadTo(..) => {
+        let (c, p) = (next(), next());
+        PathSegment::QuadTo(c.0, c.1, p.0, p.1)
+      }
+      PathSegment::CubicTo(..) => {
+        let (c1, c2, p) = (next(), next(), next());
+        PathSegment::CubicTo(c1.0, c1.1, c2.0, c2.1, p.0, p.1)
+      }
+      PathSegment::Close => PathSegment::Close,
+    });
+  }
+  result
+}
+
+// 太字・斜体のないフォントのための合成スタイル
+#[derive(Debug, Clone, Copy)]
+pub struct SyntheticStyle {
+  // 太らせる量（フォント単位）。左端は動かさず、送り幅を同じだけ広げる
+  pub embolden: f32,
+  // 斜体化の傾き（高さあたりの横ずれ）
+  pub oblique: f32,
+  // 最後に適用する任意の変換
+  pub transform: Transform,
+}
+
+impl Default for SyntheticStyle {
+  fn default() -> Self {
+    SyntheticStyle {
+      embolden: 0.0,
+      oblique: 0.0,
+      transform: Transform::identity(),
+    }
+  }
+}
+
+impl SyntheticStyle {
+  fn combined_transform(&self) -> Transform {
+    Transform::translate(self.embolden / 2.0, 0.0)
+      .then(&Transform::oblique(self.oblique))
+      .then(&self.transform)
+  }
+
+  // 太らせてから斜体化と変換を行う
+  pub fn apply(&self, segments: &[PathSegment]) -> Vec<PathSegment> {
+    let bold;
+    let segments = if self.embolden != 0.0 {
+      bold = embolden(segments, self.embolden);
+      &bold
+    } else {
+      segments
+    };
+    let transform = self.combined_transform();
+    if transform.is_identity() {
+      return segments.to_vec();
+    }
+    let mut result = Vec::with_capacity(segments.len());
+    PathSegment::replay(segments, &mut TransformSink::new(&mut result, transform));
+    result
+  }
+
+  // 変換後の送り幅
+  pub fn advance(&self, advance: f32) -> f32 {
+    let linear = Transform { x: 0.0, y: 0.0, ..Transform::oblique(self.oblique).then(&self.transform) };
+    linear.apply(advance + self.embolden, 0.0).0
+  }
+}
@@ -29,10 +29,20 @@ pub struct CompositeGlyph {
 #[derive(Debug)]
 pub struct Component {
   pub glyph_index: u16,
+  pub flags: u16,
+  pub arguments: ComponentArguments,
+  // 拡大縮小・2x2 の行列。x, y は使わず、位置は arguments で決める
   pub transform: Option<Transform>,
 }
 
-#[derive(Debug)]
+// 成分の位置。ARGS_ARE_XY_VALUES なら移動量、そうでなければ（親の点番号, 成分の点番号）を重ねる
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComponentArguments {
+  Offset(i16, i16),
+  Points(u16, u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Transform {
   pub a: f32,
   pub b: f32,
@@ -50,6 +60,18 @@ const REPEAT_FLAG: u8 = 0x08;
 const X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR: u8 = 0x10;
 const Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR: u8 = 0x20;
 
+// 複合グリフの成分のフラグ
+pub const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+pub const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+pub const ROUND_XY_TO_GRID: u16 = 0x0004;
+pub const WE_HAVE_A_SCALE: u16 = 0x0008;
+pub const MORE_COMPONENTS: u16 = 0x0020;
+pub const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+pub const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+pub const USE_MY_METRICS: u16 = 0x0200;
+pub const SCALED_COMPONENT_OFFSET: u16 = 0x0800;
+pub const UNSCALED_COMPONENT_OFFSET: u16 = 0x1000;
+
 impl SimpleGlyph {
   pub fn parse(glyph: &Glyph) -> Result<Self, String> {
     let mut reader = Cursor::new(&glyph.data);
@@ -90,8 +112,7 @@ impl SimpleGlyph {
     
     // X座標の読み込み
     let mut x = 0;
-    for i in 0..num_points {
-      let flag = flags[i];
+    for &flag in flags.iter().take(num_points) {
       let value: i16;
       if (flag & X_SHORT_VECTOR) != 0 {
         // X_SHORT_VECTORがセットされている場合
@@ -118,8 +139,7 @@ impl SimpleGlyph {
     
     // Y座標の読み込み
     let mut y = 0;
-    for i in 0..num_points {
-      let flag = flags[i];
+    for &flag in flags.iter().take(num_points) {
       let value: i16;
       if (flag & Y_SHORT_VECTOR) != 0 {
         // Y_SHORT_VECTORがセットされている場合
@@ -165,5 +185,70 @@ impl SimpleGlyph {
   }
 }
 
+// F2DOT14 の固定小数点数
+fn read_f2dot14(reader: &mut Cursor<&Vec<u8>>) -> Result<f32, String> {
+  Ok(reader.read_i16::<BigEndian>().map_err(|e| e.to_string())? as f32 / 16384.0)
+}
 
+impl CompositeGlyph {
+  // 成分の記録を MORE_COMPONENTS が立っている間読む。末尾の命令は使わない
+  pub fn parse(glyph: &Glyph) -> Result<Self, String> {
+    let mut reader = Cursor::new(&glyph.data);
+    let mut components = Vec::new();
+    loop {
+      let flags = reader.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+      let glyph_index = reader.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
 
+      // 移動量は符号付き、点番号は符号なし
+      let (arg1, arg2) = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+        let arg1 = reader.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+        let arg2 = reader.read_u16::<BigEndian>().map_err(|e| e.to_string())?;
+        (arg1, arg2)
+      } else {
+        let arg1 = reader.read_u8().map_err(|e| e.to_string())?;
+        let arg2 = reader.read_u8().map_err(|e| e.to_string())?;
+        if flags & ARGS_ARE_XY_VALUES != 0 {
+          (arg1 as i8 as i16 as u16, arg2 as i8 as i16 as u16)
+        } else {
+          (arg1 as u16, arg2 as u16)
+        }
+      };
+      let arguments = if flags & ARGS_ARE_XY_VALUES != 0 {
+        ComponentArguments::Offset(arg1 as i16, arg2 as i16)
+      } else {
+        ComponentArguments::Points(arg1, arg2)
+      };
+
+      let transform = if flags & WE_HAVE_A_SCALE != 0 {
+        let scale = read_f2dot14(&mut reader)?;
+        Some(Transform::scale(scale, scale))
+      } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+        let sx = read_f2dot14(&mut reader)?;
+        let sy = read_f2dot14(&mut reader)?;
+        Some(Transform::scale(sx, sy))
+      } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+        // 並びは xscale, scale01, scale10, yscale。x' = xscale x + scale10 y, y' = scale01 x + yscale y
+        let a = read_f2dot14(&mut reader)?;
+        let b = read_f2dot14(&mut reader)?;
+        let c = read_f2dot14(&mut reader)?;
+        let d = read_f2dot14(&mut reader)?;
+        Some(Transform::new(a, b, c, d, 0.0, 0.0))
+      } else {
+        None
+      };
+
+      components.push(Component { glyph_index, flags, arguments, transform });
+      if flags & MORE_COMPONENTS == 0 {
+        break;
+      }
+    }
+    Ok(CompositeGlyph { components })
+  }
+}
+
+impl Component {
+  // 移動量を成分の行列で変換するか。どちらのフラグもなければ FreeType と同じく変換しない
+  pub fn scales_offset(&self) -> bool {
+    self.flags & SCALED_COMPONENT_OFFSET != 0 && self.flags & UNSCALED_COMPONENT_OFFSET == 0
+  }
+}
@@ -1,3 +1,5 @@
+use crate::sink::OutlineSink;
+use crate::svg::{glyph_to_svg, SvgMetrics, SvgOptions, ViewBoxMode};
 use crate::truetype::SimpleGlyph;
 
 pub struct Outline {
@@ -15,6 +17,16 @@ pub struct Point {
   pub on_curve: bool,
 }
 
+// 輪郭をデコードしたパスの要素。暗黙のオンカーブ点は半端な座標になるので f32 で持つ
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+  MoveTo(f32, f32),
+  LineTo(f32, f32),
+  QuadTo(f32, f32, f32, f32),
+  CubicTo(f32, f32, f32, f32, f32, f32),
+  Close,
+}
+
 impl Outline {
   pub fn from_simple_glyph(glyph_data: &SimpleGlyph) -> Self {
     let mut contours = Vec::new();
@@ -28,65 +40,153 @@ impl Outline {
     
     Outline { contours }
   }
+
+  pub fn segments(&self) -> Vec<PathSegment> {
+    let mut segments = Vec::new();
+    self.draw(&mut segments);
+    segments
+  }
+
+  pub fn draw<S: OutlineSink + ?Sized>(&self, sink: &mut S) {
+    for contour in &self.contours {
+      contour.draw(sink);
+    }
+  }
 }
 
-fn points_to_svg_path(glyph: &SimpleGlyph) -> String {
-  let mut path_data = String::new();
-  let mut current_point: Option<Point> = None;
-  let mut contour_start_index = 0;
-
-  for contour_end_index in &glyph.end_pts_of_contours {
-      let mut i = contour_start_index;
-      while i <= *contour_end_index as usize {
-          let p = glyph.points[i];
-          if current_point.is_none() {
-              // Move to the first point of the contour
-              path_data.push_str(&format!("M{} {} ", p.x, p.y));
-              current_point = Some(p);
-              i += 1;
-              continue;
-          }
-
-          match (glyph.points.get(i), glyph.points.get(i + 1), glyph.points.get(i + 2)) {
-              (Some(&p1), Some(&p2), Some(&p3)) if !p1.on_curve && !p2.on_curve && p3.on_curve => {
-                  // Two off-curve points followed by an on-curve point, use a cubic Bezier curve
-                  // let last_point = current_point.unwrap();
-                  path_data.push_str(&format!("C {} {}, {} {}, {} {} ", p1.x, p1.y, p2.x, p2.y, p3.x, p3.y));
-                  current_point = Some(p3);
-                  i += 3;
-              }
-              (Some(&p1), Some(&p2), _) if !p1.on_curve && p2.on_curve => {
-                  // One off-curve point, use a quadratic Bezier curve
-                  //let last_point = current_point.unwrap();
-                  path_data.push_str(&format!("Q {} {}, {} {} ", p1.x, p1.y, p2.x, p2.y));
-                  current_point = Some(p2);
-                  i += 2;
-              }
-              (Some(&p1), _, _) if p1.on_curve => {
-                  // Line
-                  path_data.push_str(&format!("L {} {} ", p1.x, p1.y));
-                  current_point = Some(p1);
-                  i += 1;
-              }
-              _ => {
-                  i += 1;
-              }
-          }
-      }
-      // Close the path for the current contour
-      path_data.push_str("Z");
-      current_point = None; // Reset for the next contour
-      contour_start_index = *contour_end_index as usize + 1;
+impl Contour {
+  pub fn segments(&self) -> Vec<PathSegment> {
+    let mut segments = Vec::new();
+    self.draw(&mut segments);
+    segments
   }
 
-  return path_data;
-} 
+  pub fn draw<S: OutlineSink + ?Sized>(&self, sink: &mut S) {
+    let points: Vec<(f32, f32, bool)> = self.points.iter()
+      .map(|p| (p.x as f32, p.y as f32, p.on_curve))
+      .collect();
+    draw_contour(&points, sink);
+  }
+}
+
+// 2次ベジェの輪郭を move/line/quad の列として sink に流す
+// 連続するオフカーブ点の間には暗黙のオンカーブ点（中点）を補い、最後は始点に戻して閉じる
+pub fn draw_contour<S: OutlineSink + ?Sized>(points: &[(f32, f32, bool)], sink: &mut S) {
+  let n = points.len();
+  if n == 0 {
+    return;
+  }
+  let midpoint = |a: (f32, f32, bool), b: (f32, f32, bool)| ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0, true);
+
+  // 始点となるオンカーブ点を決める。全点がオフカーブなら最後と最初の点の中点を使う
+  let (origin, sequence) = match points.iter().position(|p| p.2) {
+    Some(i) => (points[i], (1..n).map(|k| points[(i + k) % n]).collect::<Vec<_>>()),
+    None => (midpoint(points[n - 1], points[0]), points.to_vec()),
+  };
+
+  sink.move_to(origin.0, origin.1);
+  let mut control: Option<(f32, f32, bool)> = None;
+  for p in sequence.into_iter().chain(std::iter::once(origin)) {
+    match (control, p.2) {
+      (None, true) => sink.line_to(p.0, p.1),
+      (None, false) => control = Some(p),
+      (Some(c), true) => {
+        sink.quad_to(c.0, c.1, p.0, p.1);
+        control = None;
+      }
+      (Some(c), false) => {
+        let mid = midpoint(c, p);
+        sink.quad_to(c.0, c.1, mid.0, mid.1);
+        control = Some(p);
+      }
+    }
+  }
+  sink.close();
+}
 
+// メトリクスがないので外接矩形ぴったりの viewBox で出力する
 pub fn simple_glyph_to_svg(glyph: &SimpleGlyph) -> String {
-  let path_data = points_to_svg_path(glyph);
+  let options = SvgOptions { view_box: ViewBoxMode::Tight, ..Default::default() };
+  glyph_to_svg(glyph, &SvgMetrics::default(), &options)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use PathSegment::*;
+
+  // (x, y, オンカーブか) の点を輪郭ごとに並べた SimpleGlyph
+  fn glyph(contours: &[&[(i16, i16, bool)]]) -> SimpleGlyph {
+    let points: Vec<Point> = contours.iter()
+      .flat_map(|contour| contour.iter().map(|&(x, y, on_curve)| Point { x, y, on_curve }))
+      .collect();
+    let end_pts_of_contours = contours.iter()
+      .scan(0, |count, contour| {
+        *count += contour.len();
+        Some(*count as u16 - 1)
+      })
+      .collect();
+    SimpleGlyph {
+      end_pts_of_contours,
+      instruction_length: 0,
+      instructions: Vec::new(),
+      flags: points.iter().map(|p| p.on_curve as u8).collect(),
+      x_coordinates: points.iter().map(|p| p.x).collect(),
+      y_coordinates: points.iter().map(|p| p.y).collect(),
+      points,
+    }
+  }
+
+  fn segments(contours: &[&[(i16, i16, bool)]]) -> Vec<PathSegment> {
+    let mut sink = Vec::new();
+    Outline::from_simple_glyph(&glyph(contours)).draw(&mut sink);
+    sink
+  }
 
-  format!(
-      "<svg viewBox='0 0 1468 1468' xmlns='http://www.w3.org/2000/svg'><path d='{}' /></svg>",
-      path_data
-  )
-}
\ No newline at end of file
+  #[test]
+  fn closing_segment_returns_to_the_first_point() {
+    let triangle: &[(i16, i16, bool)] = &[(0, 0, true), (50, 100, true), (100, 0, true)];
+    let square: &[(i16, i16, bool)] = &[(200, 0, true), (200, 10, true), (210, 10, true), (210, 0, true)];
+    assert_eq!(
+      segments(&[triangle, square]),
+      [
+        MoveTo(0.0, 0.0), LineTo(50.0, 100.0), LineTo(100.0, 0.0), LineTo(0.0, 0.0), Close,
+        MoveTo(200.0, 0.0), LineTo(200.0, 10.0), LineTo(210.0, 10.0), LineTo(210.0, 0.0), LineTo(200.0, 0.0), Close,
+      ]
+    );
+  }
+
+  #[test]
+  fn implied_on_curve_point_between_two_off_curve_points() {
+    let contour: &[(i16, i16, bool)] = &[(0, 0, true), (0, 100, false), (100, 100, false), (100, 0, true)];
+    assert_eq!(
+      segments(&[contour]),
+      [MoveTo(0.0, 0.0), QuadTo(0.0, 100.0, 50.0, 100.0), QuadTo(100.0, 100.0, 100.0, 0.0), LineTo(0.0, 0.0), Close]
+    );
+  }
+
+  #[test]
+  fn contour_starting_off_curve_starts_at_the_first_on_curve_point() {
+    let contour: &[(i16, i16, bool)] = &[(50, 100, false), (100, 0, true), (0, 0, true)];
+    assert_eq!(
+      segments(&[contour]),
+      [MoveTo(100.0, 0.0), LineTo(0.0, 0.0), QuadTo(50.0, 100.0, 100.0, 0.0), Close]
+    );
+  }
+
+  #[test]
+  fn all_off_curve_contour_starts_between_the_last_and_first_points() {
+    let contour: &[(i16, i16, bool)] = &[(0, 0, false), (0, 100, false), (100, 100, false), (100, 0, false)];
+    assert_eq!(
+      segments(&[contour]),
+      [
+        MoveTo(50.0, 0.0),
+        QuadTo(0.0, 0.0, 0.0, 50.0),
+        QuadTo(0.0, 100.0, 50.0, 100.0),
+        QuadTo(100.0, 100.0, 100.0, 50.0),
+        QuadTo(100.0, 0.0, 50.0, 0.0),
+        Close,
+      ]
+    );
+  }
+}
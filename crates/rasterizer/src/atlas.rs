@@ -0,0 +1,290 @@
+use crate::{GlyphBitmap, ImageBuffer, Pixel};
+use font::json::escape_json;
+
+// アトラスに詰めるグリフ
+pub struct AtlasEntry<P> {
+  pub codepoint: u32,
+  pub glyph_id: u16,
+  // 送り幅（ピクセル）
+  pub advance: f64,
+  pub bitmap: GlyphBitmap<P>,
+}
+
+// アトラス上のグリフの位置とメトリクス（ピクセル）
+#[derive(Debug, Clone)]
+pub struct AtlasGlyph {
+  pub codepoint: u32,
+  pub glyph_id: u16,
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+  // ビットマップ左端の原点からのオフセット
+  pub left: i32,
+  // ベースラインからビットマップ上端までの高さ
+  pub top: i32,
+  pub advance: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AtlasOptions {
+  pub width: u32,
+  // グリフ同士の間隔（ピクセル）
+  pub spacing: u32,
+  // 高さを2のべき乗に切り上げる
+  pub power_of_two: bool,
+}
+
+impl Default for AtlasOptions {
+  fn default() -> Self {
+    AtlasOptions {
+      width: 512,
+      spacing: 1,
+      power_of_two: true,
+    }
+  }
+}
+
+// メタデータの書き出しに使うフォント全体の情報（ピクセル）
+#[derive(Debug, Clone)]
+pub struct AtlasFontInfo {
+  pub face: String,
+  pub size: u32,
+  pub line_height: f64,
+  // 行の上端からベースラインまでの距離
+  pub base: f64,
+}
+
+pub struct Atlas<P> {
+  pub image: ImageBuffer<P>,
+  pub glyphs: Vec<AtlasGlyph>,
+  pub spacing: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SkylineNode {
+  x: u32,
+  y: u32,
+  width: u32,
+}
+
+// スカイライン法（bottom-left）による矩形パッキング
+struct Skyline {
+  width: u32,
+  nodes: Vec<SkylineNode>,
+}
+
+impl Skyline {
+  fn new(width: u32) -> Self {
+    Skyline { width, nodes: vec![SkylineNode { x: 0, y: 0, width }] }
+  }
+
+  // index のノードから幅 width の矩形を置いたときの下端の y
+  fn fit(&self, index: usize, width: u32) -> Option<u32> {
+    let x = self.nodes[index].x;
+    if x + width > self.width {
+      return None;
+    }
+    let mut remaining = width as i64;
+    let mut y = 0;
+    for node in &self.nodes[index..] {
+      if remaining <= 0 {
+        break;
+      }
+      y = y.max(node.y);
+      remaining -= node.width as i64;
+    }
+    Some(y)
+  }
+
+  fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+    // 置いた後の上端が最も低く、同じなら隙間の少ない位置を選ぶ
+    let mut best: Option<(usize, u32, u32)> = None;
+    for index in 0..self.nodes.len() {
+      if let Some(y) = self.fit(index, width) {
+        let node_width = self.nodes[index].width;
+        let better = match best {
+          None => true,
+          Some((best_index, best_y, _)) => {
+            y < best_y || (y == best_y && node_width < self.nodes[best_index].width)
+          }
+        };
+        if better {
+          best = Some((index, y, self.nodes[index].x));
+        }
+      }
+    }
+    let (index, y, x) = best?;
+
+    self.nodes.insert(index, SkylineNode { x, y: y + height, width });
+    // 新しいノードに覆われた部分を削る
+    let right = x + width;
+    let i = index + 1;
+    while i < self.nodes.len() {
+      let node = self.nodes[i];
+      if node.x >= right {
+        break;
+      }
+      let node_right = node.x + node.width;
+      if node_right <= right {
+        self.nodes.remove(i);
+      } else {
+        self.nodes[i].x = right;
+        self.nodes[i].width = node_right - right;
+        break;
+      }
+    }
+    // 同じ高さの隣接ノードを併合する
+    let mut i = 0;
+    while i + 1 < self.nodes.len() {
+      if self.nodes[i].y == self.nodes[i + 1].y {
+        self.nodes[i].width += self.nodes[i + 1].width;
+        self.nodes.remove(i + 1);
+      } else {
+        i += 1;
+      }
+    }
+    Some((x, y))
+  }
+}
+
+// グリフのビットマップを1枚の画像に詰める。背景は background で塗る
+pub fn pack<P: Pixel>(entries: Vec<AtlasEntry<P>>, options: &AtlasOptions, background: P) -> Result<Atlas<P>, String> {
+  let spacing = options.spacing;
+  // 間隔は各グリフの右と下に取る。右端の列に置くグリフの間隔ははみ出してよいので、その分だけ幅を広げておく
+  let mut skyline = Skyline::new(options.width + spacing);
+
+  // 背の高いものから詰めると隙間が減る
+  let mut order: Vec<usize> = (0..entries.len()).collect();
+  order.sort_by_key(|&i| std::cmp::Reverse(entries[i].bitmap.image.height()));
+
+  let mut positions = vec![(0, 0); entries.len()];
+  let mut height = 0;
+  for i in order {
+    let image = &entries[i].bitmap.image;
+    if image.width() == 0 || image.height() == 0 {
+      continue;
+    }
+    let (x, y) = skyline.insert(image.width() + spacing, image.height() + spacing)
+      .ok_or(format!("U+{:04X} のグリフがアトラスの幅 {} に収まりません", entries[i].codepoint, options.width))?;
+    positions[i] = (x, y);
+    height = height.max(y + image.height());
+  }
+  if options.power_of_two {
+    height = height.max(1).next_power_of_two();
+  }
+
+  let mut image = ImageBuffer::from_pixel(options.width, height, background);
+  let mut glyphs = Vec::with_capacity(entries.len());
+  for (entry, (x, y)) in entries.into_iter().zip(positions) {
+    let bitmap = entry.bitmap;
+    image.copy_from(&bitmap.image, x, y);
+    glyphs.push(AtlasGlyph {
+      codepoint: entry.codepoint,
+      glyph_id: entry.glyph_id,
+      x,
+      y,
+      width: bitmap.image.width(),
+      height: bitmap.image.height(),
+      left: bitmap.left,
+      top: bitmap.top,
+      advance: entry.advance,
+    });
+  }
+
+  Ok(Atlas { image, glyphs, spacing })
+}
+
+impl<P> Atlas<P> {
+  pub fn to_json(&self, info: &AtlasFontInfo, image_file: &str) -> String {
+    let glyphs: Vec<String> = self.glyphs.iter()
+      .map(|g| {
+        let c = char::from_u32(g.codepoint).map(|c| c.to_string()).unwrap_or_default();
+        format!(
+          "    {{\"char\": \"{}\", \"codepoint\": {}, \"glyph_id\": {}, \"x\": {}, \"y\": {}, \"width\": {}, \"height\": {}, \"bearing_x\": {}, \"bearing_y\": {}, \"advance\": {}}}",
+          escape_json(&c), g.codepoint, g.glyph_id, g.x, g.y, g.width, g.height, g.left, g.top, g.advance
+        )
+      })
+      .collect();
+    format!(
+      "{{\n  \"face\": \"{}\",\n  \"size\": {},\n  \"line_height\": {},\n  \"base\": {},\n  \"image\": \"{}\",\n  \"width\": {},\n  \"height\": {},\n  \"glyphs\": [\n{}\n  ]\n}}\n",
+      escape_json(&info.face), info.size, info.line_height, info.base, escape_json(image_file),
+      self.image.width(), self.image.height(), glyphs.join(",\n")
+    )
+  }
+
+  // AngelCode BMFont のテキスト形式 (.fnt)
+  pub fn to_bmfont(&self, info: &AtlasFontInfo, image_file: &str) -> String {
+    let base = info.base.round() as i32;
+    let mut fnt = String::new();
+    fnt.push_str(&format!(
+      "info face=\"{}\" size={} bold=0 italic=0 charset=\"\" unicode=1 stretchH=100 smooth=1 aa=1 padding=0,0,0,0 spacing={},{}\n",
+      bmfont_string(&info.face), info.size, self.spacing, self.spacing
+    ));
+    fnt.push_str(&format!(
+      "common lineHeight={} base={} scaleW={} scaleH={} pages=1 packed=0\n",
+      info.line_height.round() as i32, base, self.image.width(), self.image.height()
+    ));
+    fnt.push_str(&format!("page id=0 file=\"{}\"\n", bmfont_string(image_file)));
+    fnt.push_str(&format!("chars count={}\n", self.glyphs.len()));
+    for g in &self.glyphs {
+      fnt.push_str(&format!(
+        "char id={} x={} y={} width={} height={} xoffset={} yoffset={} xadvance={} page=0 chnl=15\n",
+        g.codepoint, g.x, g.y, g.width, g.height, g.left, base - g.top, g.advance.round() as i32
+      ));
+    }
+    fnt
+  }
+}
+
+// BMFont の引用符で囲んだ値。" は ' に、改行は空白にする
+fn bmfont_string(s: &str) -> String {
+  s.chars()
+    .map(|c| match c {
+      '"' => '\'',
+      '\n' | '\r' => ' ',
+      c => c,
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Luma;
+
+  fn entry(codepoint: u32, width: u32, height: u32) -> AtlasEntry<Luma<u8>> {
+    let image = ImageBuffer::from_pixel(width, height, Luma { data: [255] });
+    AtlasEntry { codepoint, glyph_id: 0, advance: width as f64, bitmap: GlyphBitmap { image, left: 0, top: height as i32 } }
+  }
+
+  fn options(width: u32, spacing: u32) -> AtlasOptions {
+    AtlasOptions { width, spacing, power_of_two: false }
+  }
+
+  #[test]
+  fn glyph_as_wide_as_the_atlas_fits() {
+    let atlas = pack(vec![entry(0x41, 16, 8), entry(0x42, 16, 8)], &options(16, 2), Luma { data: [0] }).unwrap();
+    let positions: Vec<(u32, u32)> = atlas.glyphs.iter().map(|g| (g.x, g.y)).collect();
+    assert_eq!(positions, [(0, 0), (0, 10)]);
+    assert_eq!(atlas.image.height(), 18);
+  }
+
+  #[test]
+  fn spacing_is_only_between_glyphs() {
+    // 5 + 1 + 5 = 11 で、右端の間隔がなければ1行に収まる
+    let atlas = pack(vec![entry(0x41, 5, 4), entry(0x42, 5, 4)], &options(11, 1), Luma { data: [0] }).unwrap();
+    let positions: Vec<(u32, u32)> = atlas.glyphs.iter().map(|g| (g.x, g.y)).collect();
+    assert_eq!(positions, [(0, 0), (6, 0)]);
+    assert!(pack(vec![entry(0x41, 12, 4)], &options(11, 1), Luma { data: [0] }).is_err());
+  }
+
+  #[test]
+  fn bmfont_replaces_quotes_and_keeps_backslashes() {
+    let atlas = pack(vec![entry(0x41, 4, 4)], &options(16, 1), Luma { data: [0] }).unwrap();
+    let info = AtlasFontInfo { face: "My \"Font\"\nBold".to_string(), size: 16, line_height: 20.0, base: 16.0 };
+    let fnt = atlas.to_bmfont(&info, "a\\b.png");
+    assert!(fnt.starts_with("info face=\"My 'Font' Bold\" size=16 "), "{}", fnt);
+    assert!(fnt.contains("page id=0 file=\"a\\b.png\"\n"), "{}", fnt);
+  }
+}
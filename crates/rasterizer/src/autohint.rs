@@ -0,0 +1,459 @@
+use std::collections::HashMap;
+use font::autohint::{AutohintMetrics, BlueKind};
+use crate::geometry::{Path, Point, Segment};
+
+// 横の長さが縦の長さのこの倍を超える区間を水平とみなす
+const HORIZONTAL_RATIO: f64 = 14.0;
+// 同じ辺にまとめる線分の高さの差（ピクセル）
+const EDGE_THRESHOLD: f64 = 0.25;
+// 標準のステム幅に寄せる差（ピクセル）
+const STANDARD_WIDTH_SNAP: f64 = 40.0 / 64.0;
+// x ハイトを切り上げる端数のしきい値（ピクセル）
+const X_HEIGHT_THRESHOLD: f64 = 40.0 / 64.0;
+// ブルーゾーンに吸着させる最大の距離（ピクセル）
+const MAX_BLUE_DISTANCE: f64 = 0.5;
+
+// 水平な線分。y はピクセル、x はフォント単位
+#[derive(Debug, Clone)]
+struct EdgeSegment {
+  y: f64,
+  min_x: f64,
+  max_x: f64,
+  // インクが下側にある（上端の）線分か
+  top: bool,
+  round: bool,
+  points: Vec<Point>,
+  link: Option<usize>,
+}
+
+// 高さのそろった線分の集まり。position がグリッドに合わせた位置
+#[derive(Debug, Clone)]
+struct Edge {
+  original: f64,
+  position: Option<f64>,
+  top: bool,
+  round: bool,
+  link: Option<usize>,
+}
+
+// ピクセルに合わせたブルーゾーン
+#[derive(Debug, Clone, Copy)]
+struct FittedZone {
+  top: bool,
+  reference: (f64, f64),
+  overshoot: (f64, f64),
+}
+
+// FreeType の autofit のように、ブルーゾーンとステムを手がかりに縦方向だけ輪郭をピクセル格子に合わせる
+// 命令を持たないフォント向け。横方向は動かさないので字幅や字間は変わらない
+#[derive(Debug, Clone)]
+pub struct AutoHinter {
+  pub metrics: AutohintMetrics,
+}
+
+impl AutoHinter {
+  pub fn new(metrics: AutohintMetrics) -> Self {
+    AutoHinter { metrics }
+  }
+
+  // x ハイトがピクセルにそろうように縦の倍率を少し変える
+  pub fn vertical_scale(&self, scale: f64) -> f64 {
+    let Some(zone) = self.metrics.blue_zone(BlueKind::SmallTop) else {
+      return scale;
+    };
+    let scaled = zone.overshoot * scale;
+    let fitted = (scaled + X_HEIGHT_THRESHOLD).floor();
+    if scaled > 0.0 && fitted > 0.0 { scale * fitted / scaled } else { scale }
+  }
+
+  fn fitted_zones(&self, scale: f64) -> Vec<FittedZone> {
+    self.metrics.blue_zones.iter()
+      .map(|zone| {
+        let reference = zone.reference * scale;
+        let delta = (zone.overshoot - zone.reference) * scale;
+        // オーバーシュートは半ピクセル未満なら消し、それ以上でも1ピクセルまでにする
+        let fitted_delta: f64 = match delta.abs() {
+          d if d < 0.5 => 0.0,
+          d if d < 0.75 => 0.5,
+          _ => 1.0,
+        };
+        let fitted = reference.round();
+        FittedZone {
+          top: zone.kind.is_top(),
+          reference: (reference, fitted),
+          overshoot: (zone.overshoot * scale, fitted + fitted_delta.copysign(delta)),
+        }
+      })
+      .collect()
+  }
+
+  // ステムの太さ（ピクセル）を標準の太さに寄せてから整数にする
+  fn stem_width(&self, width: f64, scale: f64) -> f64 {
+    let mut width = width;
+    if let Some(standard) = self.metrics.standard_width {
+      let standard = standard * scale;
+      if (width - standard).abs() < STANDARD_WIDTH_SNAP {
+        width = standard;
+      }
+    }
+    width.round().max(1.0)
+  }
+
+  // 輪郭を縦方向にグリッドに合わせる。path と戻り値はフォント単位で、scale はラスタライズに使う倍率
+  pub fn apply(&self, path: &Path, scale: f64) -> Path {
+    if scale <= 0.0 || path.contours.is_empty() {
+      return path.clone();
+    }
+    let scale_y = self.vertical_scale(scale);
+    let rings = rings(path);
+    let mut segments = find_segments(&rings, scale_y);
+    link_segments(&mut segments);
+    let (mut edges, membership) = build_edges(&segments);
+
+    self.align_blue_edges(&mut edges, scale_y);
+    self.align_stems(&mut edges, scale_y);
+    align_remaining(&mut edges);
+
+    // 辺に属する点は辺と同じだけ動かし、それ以外は辺の間で補間する
+    let mut moved: HashMap<(u64, u64), f64> = HashMap::new();
+    for (segment, &edge) in segments.iter().zip(&membership) {
+      let edge = &edges[edge];
+      let delta = edge.position.unwrap_or(edge.original) - edge.original;
+      for point in &segment.points {
+        moved.insert(key(*point), point.y * scale_y + delta);
+      }
+    }
+    let knots = knots(&edges);
+    path.transform(|p| {
+      let y = moved.get(&key(p)).copied().unwrap_or_else(|| interpolate(&knots, p.y * scale_y));
+      Point::new(p.x, y / scale)
+    })
+  }
+
+  fn align_blue_edges(&self, edges: &mut [Edge], scale: f64) {
+    let zones = self.fitted_zones(scale);
+    let threshold = (self.metrics.units_per_em as f64 / 40.0 * scale).min(MAX_BLUE_DISTANCE);
+    for edge in edges.iter_mut() {
+      let mut best: Option<(f64, f64)> = None;
+      for zone in zones.iter().filter(|zone| zone.top == edge.top) {
+        // 丸い辺はオーバーシュートの位置とも比べる
+        let candidates = if edge.round { vec![zone.reference, zone.overshoot] } else { vec![zone.reference] };
+        for (original, fitted) in candidates {
+          let distance = (edge.original - original).abs();
+          if distance < threshold && best.is_none_or(|(d, _)| distance < d) {
+            best = Some((distance, fitted));
+          }
+        }
+      }
+      if let Some((_, fitted)) = best {
+        edge.position = Some(fitted);
+      }
+    }
+  }
+
+  // 組になった辺をステムとして、太さを整数ピクセルにしたまま置く。片方がブルーゾーンに合っていればそれに合わせる
+  fn align_stems(&self, edges: &mut [Edge], scale: f64) {
+    let mut stems: Vec<(usize, usize)> = (0..edges.len())
+      .filter_map(|i| {
+        let j = edges[i].link?;
+        (i < j && edges[j].link == Some(i)).then(|| if edges[i].original < edges[j].original { (i, j) } else { (j, i) })
+      })
+      .collect();
+    stems.sort_by_key(|&(lower, upper)| edges[lower].position.is_none() && edges[upper].position.is_none());
+    for (lower, upper) in stems {
+      let width = self.stem_width(edges[upper].original - edges[lower].original, scale);
+      match (edges[lower].position, edges[upper].position) {
+        (Some(_), Some(_)) => {}
+        (Some(position), None) => edges[upper].position = Some(position + width),
+        (None, Some(position)) => edges[lower].position = Some(position - width),
+        (None, None) => {
+          let center = (edges[lower].original + edges[upper].original) / 2.0;
+          let bottom = (center - width / 2.0).round();
+          edges[lower].position = Some(bottom);
+          edges[upper].position = Some(bottom + width);
+        }
+      }
+    }
+  }
+}
+
+fn key(p: Point) -> (u64, u64) {
+  (p.x.to_bits(), p.y.to_bits())
+}
+
+// 輪郭ごとの点の列（オンカーブ点と制御点）
+fn rings(path: &Path) -> Vec<Vec<(Point, bool)>> {
+  path.contours.iter()
+    .map(|contour| {
+      let mut ring = Vec::new();
+      for segment in contour {
+        match *segment {
+          Segment::Line(p0, _) => ring.push((p0, true)),
+          Segment::Quad(p0, p1, _) => ring.extend([(p0, true), (p1, false)]),
+          Segment::Cubic(p0, p1, p2, _) => ring.extend([(p0, true), (p1, false), (p2, false)]),
+        }
+      }
+      ring
+    })
+    .collect()
+}
+
+// 輪郭が時計回り（TrueType の向き）なら true
+fn is_clockwise(rings: &[Vec<(Point, bool)>]) -> bool {
+  let area: f64 = rings.iter()
+    .map(|ring| {
+      (0..ring.len()).map(|i| ring[i].0.cross(ring[(i + 1) % ring.len()].0)).sum::<f64>()
+    })
+    .sum();
+  area <= 0.0
+}
+
+// 同じ向きに続く水平な区間を線分にまとめる
+fn find_segments(rings: &[Vec<(Point, bool)>], scale: f64) -> Vec<EdgeSegment> {
+  let clockwise = is_clockwise(rings);
+  let mut segments = Vec::new();
+  for ring in rings {
+    let n = ring.len();
+    if n < 2 {
+      continue;
+    }
+    // 各区間の向き。1: 右向き、-1: 左向き、0: 水平でない、None: 長さ 0（前後の向きに合わせる）
+    let directions: Vec<Option<i8>> = (0..n)
+      .map(|i| {
+        let d = ring[(i + 1) % n].0 - ring[i].0;
+        if d.x == 0.0 && d.y == 0.0 {
+          None
+        } else if d.x.abs() > HORIZONTAL_RATIO * d.y.abs() {
+          Some(d.x.signum() as i8)
+        } else {
+          Some(0)
+        }
+      })
+      .collect();
+    let Some(last) = directions.iter().rev().find_map(|&d| d) else {
+      continue;
+    };
+    let mut previous = last;
+    let directions: Vec<i8> = directions.iter()
+      .map(|&d| {
+        previous = d.unwrap_or(previous);
+        previous
+      })
+      .collect();
+    // 向きが変わる区間から一周する
+    let Some(start) = (0..n).find(|&i| directions[i] != directions[(i + n - 1) % n]) else {
+      continue;
+    };
+    let mut i = 0;
+    while i < n {
+      let direction = directions[(start + i) % n];
+      let mut j = i;
+      while j + 1 < n && directions[(start + j + 1) % n] == direction {
+        j += 1;
+      }
+      if direction != 0 {
+        let points: Vec<(Point, bool)> = (i..=j + 1).map(|k| ring[(start + k) % n]).collect();
+        let top = (direction > 0) == clockwise;
+        let round = points.iter().any(|p| !p.1);
+        let ys = points.iter().map(|p| p.0.y);
+        let y = if !round {
+          ys.sum::<f64>() / points.len() as f64
+        } else if top {
+          ys.fold(f64::MIN, f64::max)
+        } else {
+          ys.fold(f64::MAX, f64::min)
+        };
+        let min_x = points.iter().map(|p| p.0.x).fold(f64::MAX, f64::min);
+        let max_x = points.iter().map(|p| p.0.x).fold(f64::MIN, f64::max);
+        segments.push(EdgeSegment {
+          y: y * scale,
+          min_x,
+          max_x,
+          top,
+          round,
+          points: points.iter().map(|p| p.0).collect(),
+          link: None,
+        });
+      }
+      i = j + 1;
+    }
+  }
+  segments
+}
+
+// 横に重なり、間にインクを挟む一番近い線分どうしをステムの両端として結ぶ
+fn link_segments(segments: &mut [EdgeSegment]) {
+  let best: Vec<Option<usize>> = (0..segments.len())
+    .map(|i| {
+      let a = &segments[i];
+      (0..segments.len())
+        .filter(|&j| {
+          let b = &segments[j];
+          let inside = if a.top { b.y < a.y } else { b.y > a.y };
+          b.top != a.top && inside && a.max_x.min(b.max_x) > a.min_x.max(b.min_x)
+        })
+        .min_by(|&j, &k| (segments[j].y - a.y).abs().total_cmp(&(segments[k].y - a.y).abs()))
+    })
+    .collect();
+  for i in 0..segments.len() {
+    segments[i].link = best[i].filter(|&j| best[j] == Some(i));
+  }
+}
+
+// 高さの近い同じ種類の線分を辺にまとめる。戻り値の2つ目は線分ごとの辺の番号
+fn build_edges(segments: &[EdgeSegment]) -> (Vec<Edge>, Vec<usize>) {
+  let mut order: Vec<usize> = (0..segments.len()).collect();
+  order.sort_by(|&a, &b| segments[a].top.cmp(&segments[b].top).then(segments[a].y.total_cmp(&segments[b].y)));
+  let mut edges: Vec<Edge> = Vec::new();
+  let mut groups: Vec<Vec<usize>> = Vec::new();
+  let mut membership = vec![0; segments.len()];
+  for i in order {
+    let segment = &segments[i];
+    let same = groups.last().is_some_and(|group| {
+      let first = &segments[group[0]];
+      first.top == segment.top && segment.y - first.y <= EDGE_THRESHOLD
+    });
+    if !same {
+      groups.push(Vec::new());
+    }
+    groups.last_mut().unwrap().push(i);
+    membership[i] = groups.len() - 1;
+  }
+  for group in &groups {
+    let length = |s: &EdgeSegment| (s.max_x - s.min_x).max(1.0);
+    let total: f64 = group.iter().map(|&i| length(&segments[i])).sum();
+    let original = group.iter().map(|&i| segments[i].y * length(&segments[i])).sum::<f64>() / total;
+    let rounds = group.iter().filter(|&&i| segments[i].round).count();
+    edges.push(Edge {
+      original,
+      position: None,
+      top: segments[group[0]].top,
+      round: rounds * 2 > group.len(),
+      link: None,
+    });
+  }
+  for (i, segment) in segments.iter().enumerate() {
+    if let Some(other) = segment.link {
+      let edge = &mut edges[membership[i]];
+      edge.link = edge.link.or(Some(membership[other]));
+    }
+  }
+  (edges, membership)
+}
+
+// まだ置いていない辺を、置いた辺の間で補間する。片側にしかなければその辺と同じだけずらす
+fn align_remaining(edges: &mut [Edge]) {
+  let fitted: Vec<(f64, f64)> = edges.iter().filter_map(|e| Some((e.original, e.position?))).collect();
+  for edge in edges.iter_mut().filter(|e| e.position.is_none()) {
+    edge.position = Some(if fitted.is_empty() { edge.original.round() } else { interpolate_knots(&fitted, edge.original) });
+  }
+  // 辺の上下が入れ替わらないようにする
+  let mut order: Vec<usize> = (0..edges.len()).collect();
+  order.sort_by(|&a, &b| edges[a].original.total_cmp(&edges[b].original));
+  for pair in order.windows(2) {
+    let (below, above) = (edges[pair[0]].position.unwrap(), edges[pair[1]].position.unwrap());
+    if above < below {
+      edges[pair[1]].position = Some(below);
+    }
+  }
+}
+
+// 補間に使う (元の位置, 合わせた位置) を元の位置の順に並べる
+fn knots(edges: &[Edge]) -> Vec<(f64, f64)> {
+  let mut knots: Vec<(f64, f64)> = edges.iter().map(|e| (e.original, e.position.unwrap_or(e.original))).collect();
+  knots.sort_by(|a, b| a.0.total_cmp(&b.0));
+  knots.dedup_by(|a, b| a.0 == b.0);
+  knots
+}
+
+fn interpolate_knots(fitted: &[(f64, f64)], y: f64) -> f64 {
+  let mut knots = fitted.to_vec();
+  knots.sort_by(|a, b| a.0.total_cmp(&b.0));
+  knots.dedup_by(|a, b| a.0 == b.0);
+  interpolate(&knots, y)
+}
+
+// 区分線形に写す。両端の外側は端の辺と同じだけずらす
+fn interpolate(knots: &[(f64, f64)], y: f64) -> f64 {
+  let (Some(first), Some(last)) = (knots.first(), knots.last()) else {
+    return y;
+  };
+  if y <= first.0 {
+    return y + first.1 - first.0;
+  }
+  if y >= last.0 {
+    return y + last.1 - last.0;
+  }
+  let i = knots.partition_point(|k| k.0 <= y);
+  let (a, b) = (knots[i - 1], knots[i]);
+  a.1 + (b.1 - a.1) * (y - a.0) / (b.0 - a.0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use font::autohint::{BlueZone, Script};
+
+  fn metrics() -> AutohintMetrics {
+    let zone = |kind, reference, overshoot| BlueZone { kind, reference, overshoot };
+    AutohintMetrics {
+      script: Script::Latin,
+      units_per_em: 1000,
+      blue_zones: vec![
+        zone(BlueKind::CapitalTop, 700.0, 712.0),
+        zone(BlueKind::SmallTop, 500.0, 510.0),
+        zone(BlueKind::SmallBottom, 0.0, -10.0),
+      ],
+      standard_width: None,
+    }
+  }
+
+  // 時計回り（TrueType の向き）の長方形
+  fn rectangle(x0: f64, y0: f64, x1: f64, y1: f64) -> Path {
+    let corners = [Point::new(x0, y0), Point::new(x0, y1), Point::new(x1, y1), Point::new(x1, y0)];
+    let mut path = Path::new();
+    path.contours.push((0..4).map(|i| Segment::Line(corners[i], corners[(i + 1) % 4])).collect());
+    path
+  }
+
+  fn ys(path: &Path) -> Vec<f64> {
+    let mut ys: Vec<f64> = path.contours.iter().flatten().map(|segment| segment.start().y).collect();
+    ys.sort_by(f64::total_cmp);
+    ys.dedup();
+    ys
+  }
+
+  #[test]
+  fn x_height_is_rounded_to_a_whole_pixel() {
+    let hinter = AutoHinter::new(metrics());
+    let scale = 12.0 / 1000.0;
+    // オーバーシュートの 6.12 ピクセルが 6 ピクセルになる
+    assert!((hinter.vertical_scale(scale) * 510.0 - 6.0).abs() < 1e-9);
+  }
+
+  #[test]
+  fn edges_near_blue_zones_snap_to_pixel_rows() {
+    let hinter = AutoHinter::new(metrics());
+    let scale = 12.0 / 1000.0;
+    // 上端が大文字のゾーンの少し上、下端がベースラインの少し下にある縦棒
+    let hinted = hinter.apply(&rectangle(0.0, -3.0, 80.0, 705.0), scale);
+    let rows: Vec<f64> = ys(&hinted).iter().map(|y| y * scale).collect();
+    assert_eq!(rows.len(), 2);
+    for row in &rows {
+      assert!((row - row.round()).abs() < 1e-9, "{:?}", rows);
+    }
+    // ゾーンの位置 700 * 6 / 510 = 8.24 ピクセルは 8 に、ベースラインは 0 に合う
+    assert!((rows[0] - 0.0).abs() < 1e-9 && (rows[1] - 8.0).abs() < 1e-9, "{:?}", rows);
+    // 横方向は動かない
+    let xs: Vec<f64> = hinted.contours[0].iter().map(|segment| segment.start().x).collect();
+    assert_eq!(xs, [0.0, 0.0, 80.0, 80.0]);
+  }
+
+  #[test]
+  fn empty_path_and_zero_scale_are_unchanged() {
+    let hinter = AutoHinter::new(metrics());
+    let path = rectangle(0.0, 0.0, 80.0, 705.0);
+    assert_eq!(ys(&hinter.apply(&path, 0.0)), ys(&path));
+    assert!(hinter.apply(&Path::new(), 0.012).contours.is_empty());
+  }
+}
@@ -0,0 +1,102 @@
+use font::bitmap::{BitmapGlyph, ImageFormat};
+
+use crate::{GlyphBitmap, ImageBuffer, Rgba};
+
+// PNG を読み込んで RGBA（ストレートアルファ）にする。パレット・グレー・16ビットも8ビットの RGBA にそろえる
+pub fn decode_png(data: &[u8]) -> Result<ImageBuffer<Rgba<u8>>, String> {
+  let mut decoder = png::Decoder::new(data);
+  decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+  let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+  let mut buffer = vec![0; reader.output_buffer_size()];
+  let info = reader.next_frame(&mut buffer).map_err(|e| e.to_string())?;
+  let channels = match info.color_type {
+    png::ColorType::Grayscale => 1,
+    png::ColorType::GrayscaleAlpha => 2,
+    png::ColorType::Rgb => 3,
+    png::ColorType::Rgba => 4,
+    png::ColorType::Indexed => return Err("パレットのPNGを展開できませんでした".to_string()),
+  };
+
+  let mut image = ImageBuffer::from_pixel(info.width, info.height, Rgba { data: [0, 0, 0, 0] });
+  for (x, y, pixel) in image.enumerate_pixels_mut() {
+    let at = y as usize * info.line_size + x as usize * channels;
+    let p = &buffer[at..at + channels];
+    pixel.data = match channels {
+      1 => [p[0], p[0], p[0], 255],
+      2 => [p[0], p[0], p[0], p[1]],
+      3 => [p[0], p[1], p[2], 255],
+      _ => [p[0], p[1], p[2], p[3]],
+    };
+  }
+  Ok(image)
+}
+
+// 拡大縮小で出力の各画素に寄与する入力の画素と重み
+// 縮小では覆う面積の平均、拡大では双線形補間
+fn axis_weights(source: u32, target: u32) -> Vec<Vec<(u32, f32)>> {
+  let ratio = source as f32 / target as f32;
+  (0..target)
+    .map(|i| {
+      if ratio > 1.0 {
+        let (start, end) = (i as f32 * ratio, (i + 1) as f32 * ratio);
+        (start.floor() as u32..(end.ceil() as u32).min(source))
+          .map(|j| (j, ((j + 1) as f32).min(end) - (j as f32).max(start)))
+          .map(|(j, overlap)| (j, overlap / ratio))
+          .collect()
+      } else {
+        let center = ((i as f32 + 0.5) * ratio - 0.5).clamp(0.0, (source - 1) as f32);
+        let j = center.floor() as u32;
+        let t = center - j as f32;
+        if j + 1 < source { vec![(j, 1.0 - t), (j + 1, t)] } else { vec![(j, 1.0)] }
+      }
+    })
+    .collect()
+}
+
+// RGBA の画像を width × height に拡大縮小する。にじみを防ぐため乗算済みアルファで混ぜる
+pub fn resize(image: &ImageBuffer<Rgba<u8>>, width: u32, height: u32) -> ImageBuffer<Rgba<u8>> {
+  let mut result = ImageBuffer::from_pixel(width, height, Rgba { data: [0, 0, 0, 0] });
+  if image.width() == 0 || image.height() == 0 {
+    return result;
+  }
+  let columns = axis_weights(image.width(), width);
+  let rows = axis_weights(image.height(), height);
+  for (x, y, pixel) in result.enumerate_pixels_mut() {
+    let mut sum = [0.0f32; 4];
+    for &(sy, wy) in &rows[y as usize] {
+      for &(sx, wx) in &columns[x as usize] {
+        let p = image.get_pixel(sx, sy).data;
+        let w = wx * wy;
+        let a = p[3] as f32 / 255.0;
+        for c in 0..3 {
+          sum[c] += p[c] as f32 / 255.0 * a * w;
+        }
+        sum[3] += a * w;
+      }
+    }
+    let a = sum[3].clamp(0.0, 1.0);
+    let channel = |c: f32| if a > 0.0 { (c / a * 255.0).round().clamp(0.0, 255.0) as u8 } else { 0 };
+    pixel.data = [channel(sum[0]), channel(sum[1]), channel(sum[2]), (a * 255.0).round() as u8];
+  }
+  result
+}
+
+// 埋め込みビットマップを size ピクセル/em に拡大縮小して配置を合わせる。ストライクと同じ大きさならそのまま
+pub fn rasterize_bitmap(glyph: &BitmapGlyph, size: u32) -> Result<GlyphBitmap<Rgba<u8>>, String> {
+  if glyph.format != ImageFormat::Png {
+    return Err(format!("{} の画像は展開できません", glyph.format.name()));
+  }
+  let image = decode_png(&glyph.data)?;
+  if glyph.ppem == 0 {
+    return Err("ビットマップのストライクの ppem が 0 です".to_string());
+  }
+  let scale = size as f32 / glyph.ppem as f32;
+  let left = (glyph.bearing_x as f32 * scale).round() as i32;
+  let top = (glyph.bearing_y as f32 * scale).round() as i32;
+  if size == glyph.ppem as u32 {
+    return Ok(GlyphBitmap { image, left, top });
+  }
+  let width = ((image.width() as f32 * scale).round() as u32).max(1);
+  let height = ((image.height() as f32 * scale).round() as u32).max(1);
+  Ok(GlyphBitmap { image: resize(&image, width, height), left, top })
+}
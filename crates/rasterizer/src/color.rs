@@ -0,0 +1,52 @@
+use crate::geometry::Path;
+use crate::raster::Rasterizer;
+use crate::{Frame, GlyphBitmap, ImageBuffer, Rgba};
+
+const FLATTEN_TOLERANCE: f64 = 0.05;
+
+// 色付きのレイヤーを下から順に重ねて RGBA のビットマップにする。背景は透明で、色はストレートアルファ
+pub fn rasterize_layers(layers: &[(Path, [u8; 4])], scale: f64) -> GlyphBitmap<Rgba<u8>> {
+  // すべてのレイヤーが入る枠
+  let mut union = Path::new();
+  for (path, _) in layers {
+    union.contours.extend(path.scale(scale).contours);
+  }
+  let frame = Frame::new(&union, 1);
+  // 乗算済みの RGBA を重ねていく
+  let mut canvas = vec![[0.0f32; 4]; (frame.width * frame.height) as usize];
+  for (path, color) in layers {
+    let alpha = color[3] as f32 / 255.0;
+    for (pixel, coverage) in canvas.iter_mut().zip(coverage(&path.scale(scale), &frame)) {
+      let a = alpha * coverage;
+      for c in 0..3 {
+        pixel[c] = color[c] as f32 / 255.0 * a + pixel[c] * (1.0 - a);
+      }
+      pixel[3] = a + pixel[3] * (1.0 - a);
+    }
+  }
+
+  to_bitmap(&canvas, &frame)
+}
+
+// frame の各ピクセルを path が覆う割合
+pub(crate) fn coverage(path: &Path, frame: &Frame) -> Vec<f32> {
+  let mut rasterizer = Rasterizer::new(frame.width, frame.height);
+  for polyline in path.flatten(FLATTEN_TOLERANCE) {
+    for edge in polyline.windows(2) {
+      rasterizer.draw_line(frame.to_image(edge[0]), frame.to_image(edge[1]));
+    }
+  }
+  rasterizer.coverage()
+}
+
+// 乗算済みの RGBA をストレートアルファのビットマップにする
+pub(crate) fn to_bitmap(canvas: &[[f32; 4]], frame: &Frame) -> GlyphBitmap<Rgba<u8>> {
+  let mut image = ImageBuffer::from_pixel(frame.width, frame.height, Rgba { data: [0, 0, 0, 0] });
+  for ((_, _, pixel), value) in image.enumerate_pixels_mut().zip(canvas) {
+    let a = value[3].clamp(0.0, 1.0);
+    let channel = |c: f32| if a > 0.0 { (c / a * 255.0).round().clamp(0.0, 255.0) as u8 } else { 0 };
+    pixel.data = [channel(value[0]), channel(value[1]), channel(value[2]), (a * 255.0).round() as u8];
+  }
+  GlyphBitmap { image, left: frame.x0, top: frame.y1 }
+}
+
@@ -0,0 +1,186 @@
+use crate::autohint::AutoHinter;
+use crate::geometry::Path;
+use crate::{GlyphBitmap, ImageBuffer, Pixel};
+
+// 被覆率に掛けるトーンカーブ
+// 被覆率 a を a^(1/gamma) に写し、contrast で中間調を持ち上げる（負なら下げる）
+#[derive(Debug, Clone, Copy)]
+pub struct GammaOptions {
+  pub gamma: f64,
+  pub contrast: f64,
+}
+
+impl Default for GammaOptions {
+  fn default() -> Self {
+    GammaOptions { gamma: 1.0, contrast: 0.0 }
+  }
+}
+
+impl GammaOptions {
+  pub fn is_identity(&self) -> bool {
+    self.gamma == 1.0 && self.contrast == 0.0
+  }
+
+  // 0〜255 の被覆率を変換する参照表
+  pub fn coverage_table(&self) -> [u8; 256] {
+    let mut table = [0u8; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+      let a = (i as f64 / 255.0).powf(1.0 / self.gamma);
+      let a = (a + self.contrast * a * (1.0 - a)).clamp(0.0, 1.0);
+      *entry = (a * 255.0).round() as u8;
+    }
+    table
+  }
+
+  pub fn apply<P: Pixel>(&self, image: &mut ImageBuffer<P>) {
+    if self.is_identity() {
+      return;
+    }
+    let table = self.coverage_table();
+    for (_, _, pixel) in image.enumerate_pixels_mut() {
+      for channel in pixel.channels_mut() {
+        *channel = table[*channel as usize];
+      }
+    }
+  }
+}
+
+// FreeType の CFF ドライバと同じ既定の制御点
+// (ステム幅 [ピクセル×1000], 太らせる量 [ピクセル×1000])
+pub const DEFAULT_DARKENING_PARAMETERS: [(f64, f64); 4] = [
+  (500.0, 400.0),
+  (1000.0, 275.0),
+  (1667.0, 275.0),
+  (2333.0, 0.0),
+];
+
+// 小さいppemで細いステムを太らせる（stem darkening）
+#[derive(Debug, Clone, Copy)]
+pub struct StemDarkening {
+  // 基準となるステム幅（フォント単位）
+  pub stem_width: f64,
+  pub units_per_em: f64,
+  pub parameters: [(f64, f64); 4],
+}
+
+impl StemDarkening {
+  pub fn new(stem_width: f64, units_per_em: f64) -> Self {
+    StemDarkening { stem_width, units_per_em, parameters: DEFAULT_DARKENING_PARAMETERS }
+  }
+
+  // ppem での太らせる量（ステム全体の増分、フォント単位）
+  pub fn amount(&self, ppem: f64) -> f64 {
+    if ppem <= 0.0 {
+      return 0.0;
+    }
+    let scaled_stem = self.stem_width / self.units_per_em * ppem * 1000.0;
+    let p = &self.parameters;
+    let darkening = if scaled_stem < p[0].0 {
+      p[0].1
+    } else if scaled_stem >= p[3].0 {
+      p[3].1
+    } else {
+      // 区分線形補間
+      let i = (0..3).find(|&i| scaled_stem < p[i + 1].0).unwrap_or(2);
+      let (x0, y0) = p[i];
+      let (x1, y1) = p[i + 1];
+      y0 + (y1 - y0) * (scaled_stem - x0) / (x1 - x0)
+    };
+    darkening / 1000.0 * self.units_per_em / ppem
+  }
+
+  pub fn apply(&self, path: &Path, ppem: f64) -> Path {
+    let amount = self.amount(ppem);
+    if amount == 0.0 { path.clone() } else { path.embolden(amount) }
+  }
+}
+
+// rasterize_with の追加オプション
+#[derive(Debug, Clone, Default)]
+pub struct RasterOptions {
+  pub gamma: GammaOptions,
+  pub stem_darkening: Option<StemDarkening>,
+  // 命令を持たないフォントの輪郭を縦方向にグリッドに合わせる
+  pub autohint: Option<AutoHinter>,
+}
+
+// ステムを太らせ、自動ヒンティングしてからラスタライズし、被覆率にトーンカーブをかける
+pub fn rasterize_with<P: Pixel>(
+  path: &Path,
+  scale: f64,
+  options: &RasterOptions,
+  render: impl Fn(&Path, f64) -> GlyphBitmap<P>,
+) -> GlyphBitmap<P> {
+  let darkened;
+  let path = match &options.stem_darkening {
+    Some(darkening) => {
+      darkened = darkening.apply(path, scale * darkening.units_per_em);
+      &darkened
+    }
+    None => path,
+  };
+  let hinted;
+  let path = match &options.autohint {
+    Some(hinter) => {
+      hinted = hinter.apply(path, scale);
+      &hinted
+    }
+    None => path,
+  };
+  let mut bitmap = render(path, scale);
+  options.gamma.apply(&mut bitmap.image);
+  bitmap
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Luma;
+
+  #[test]
+  fn identity_table_and_image() {
+    let options = GammaOptions::default();
+    assert!(options.is_identity());
+    let table = options.coverage_table();
+    assert!(table.iter().enumerate().all(|(i, &v)| v as usize == i));
+
+    let mut image = ImageBuffer::from_pixel(3, 1, Luma { data: [0] });
+    for (x, _, pixel) in image.enumerate_pixels_mut() {
+      pixel.data[0] = [17, 128, 240][x as usize];
+    }
+    let before = image.as_bytes();
+    options.apply(&mut image);
+    assert_eq!(image.as_bytes(), before);
+    GammaOptions { gamma: 2.2, contrast: 0.0 }.apply(&mut image);
+    assert_ne!(image.as_bytes(), before);
+  }
+
+  #[test]
+  fn gamma_and_contrast_curves() {
+    let table = GammaOptions { gamma: 2.2, contrast: 0.0 }.coverage_table();
+    assert_eq!((table[0], table[64], table[128], table[255]), (0, 136, 186, 255));
+    assert!(table.windows(2).all(|w| w[0] <= w[1]));
+    // 被覆率 0.2 は contrast 1 で 0.2 + 0.2 * 0.8 = 0.36、-1 で 0.04 になる
+    assert_eq!(GammaOptions { gamma: 1.0, contrast: 1.0 }.coverage_table()[51], 92);
+    assert_eq!(GammaOptions { gamma: 1.0, contrast: -1.0 }.coverage_table()[51], 10);
+  }
+
+  #[test]
+  fn darkening_interpolates_between_the_control_points() {
+    // 1000 単位の em を 10 ppem で描くと、ステム幅 w の 1000 分の1ピクセル単位の幅は w * 10
+    let amount = |stem_width: f64| StemDarkening::new(stem_width, 1000.0).amount(10.0);
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+    // 最初の制御点より細ければ 0.4 ピクセル = 40 単位
+    assert!(close(amount(20.0), 40.0));
+    assert!(close(amount(50.0), 40.0));
+    // (500, 400) と (1000, 275) の中点
+    assert!(close(amount(75.0), 33.75));
+    assert!(close(amount(100.0), 27.5));
+    assert!(close(amount(150.0), 27.5));
+    // (1667, 275) と (2333, 0) の中点
+    assert!(close(amount(200.0), 13.75));
+    assert!(close(amount(233.3), 0.0));
+    assert!(close(amount(400.0), 0.0));
+    assert_eq!(StemDarkening::new(50.0, 1000.0).amount(0.0), 0.0);
+  }
+}
@@ -0,0 +1,203 @@
+use font::convert::FlattenSink;
+pub use font::geometry::{Point, Segment};
+use font::sink::OutlineSink;
+use font::transform::embolden;
+use font::truetype::outline::{Outline, PathSegment};
+use font::truetype::{SimpleGlyph, Transform};
+
+// 輪郭の集合。各輪郭は閉じたセグメント列
+#[derive(Debug, Clone, Default)]
+pub struct Path {
+  pub contours: Vec<Vec<Segment>>,
+  current: Point,
+  start: Point,
+}
+
+impl Path {
+  pub fn new() -> Self {
+    Path::default()
+  }
+
+  // 現在の輪郭を始点まで直線で閉じる
+  fn close_contour(&mut self) {
+    if self.current != self.start {
+      self.push(Segment::Line(self.current, self.start));
+    }
+  }
+
+  fn push(&mut self, segment: Segment) {
+    if self.contours.is_empty() {
+      self.contours.push(Vec::new());
+    }
+    self.current = segment.end();
+    self.contours.last_mut().unwrap().push(segment);
+  }
+
+  // TrueTypeの輪郭を font クレートのデコーダでパスに変換する
+  pub fn from_simple_glyph(glyph: &SimpleGlyph) -> Self {
+    let mut path = Path::new();
+    Outline::from_simple_glyph(glyph).draw(&mut path);
+    path
+  }
+
+  pub fn from_segments(segments: &[PathSegment]) -> Self {
+    let mut path = Path::new();
+    PathSegment::replay(segments, &mut path);
+    path
+  }
+
+  // 輪郭を sink に流す。各輪郭は最後のセグメントの終点で閉じる
+  pub fn draw<S: OutlineSink + ?Sized>(&self, sink: &mut S) {
+    for contour in self.contours.iter().filter(|contour| !contour.is_empty()) {
+      let start = contour[0].start();
+      sink.move_to(start.x as f32, start.y as f32);
+      for segment in contour {
+        match *segment {
+          Segment::Line(_, p) => sink.line_to(p.x as f32, p.y as f32),
+          Segment::Quad(_, c, p) => sink.quad_to(c.x as f32, c.y as f32, p.x as f32, p.y as f32),
+          Segment::Cubic(_, c1, c2, p) => {
+            sink.cubic_to(c1.x as f32, c1.y as f32, c2.x as f32, c2.y as f32, p.x as f32, p.y as f32)
+          }
+        }
+      }
+      sink.close();
+    }
+  }
+
+  pub fn apply(&self, transform: &Transform) -> Path {
+    let t = transform;
+    self.transform(|p| Point::new(
+      t.a as f64 * p.x + t.c as f64 * p.y + t.x as f64,
+      t.b as f64 * p.x + t.d as f64 * p.y + t.y as f64,
+    ))
+  }
+
+  pub fn transform<F: Fn(Point) -> Point>(&self, f: F) -> Path {
+    Path {
+      contours: self.contours.iter()
+        .map(|contour| contour.iter().map(|s| s.map(&f)).collect())
+        .collect(),
+      current: f(self.current),
+      start: f(self.start),
+    }
+  }
+
+  pub fn scale(&self, s: f64) -> Path {
+    self.transform(|p| p * s)
+  }
+
+  pub fn segments(&self) -> impl Iterator<Item = &Segment> {
+    self.contours.iter().flatten()
+  }
+
+  // 制御点を含む外接矩形 (x_min, y_min, x_max, y_max)
+  pub fn control_bounds(&self) -> Option<(f64, f64, f64, f64)> {
+    let mut bounds: Option<(f64, f64, f64, f64)> = None;
+    for segment in self.segments() {
+      let points: &[Point] = match segment {
+        Segment::Line(p0, p1) => &[*p0, *p1],
+        Segment::Quad(p0, p1, p2) => &[*p0, *p1, *p2],
+        Segment::Cubic(p0, p1, p2, p3) => &[*p0, *p1, *p2, *p3],
+      };
+      for p in points {
+        bounds = Some(match bounds {
+          None => (p.x, p.y, p.x, p.y),
+          Some((x0, y0, x1, y1)) => (x0.min(p.x), y0.min(p.y), x1.max(p.x), y1.max(p.y)),
+        });
+      }
+    }
+    bounds
+  }
+
+  // 曲線の極値まで含めた正確な外接矩形 (x_min, y_min, x_max, y_max)
+  pub fn bounds(&self) -> Option<(f64, f64, f64, f64)> {
+    let mut bounds: Option<(f64, f64, f64, f64)> = None;
+    for segment in self.segments() {
+      let points = [segment.start(), segment.end()].into_iter()
+        .chain(segment.extrema().into_iter().map(|t| segment.point(t)));
+      for p in points {
+        bounds = Some(match bounds {
+          None => (p.x, p.y, p.x, p.y),
+          Some((x0, y0, x1, y1)) => (x0.min(p.x), y0.min(p.y), x1.max(p.x), y1.max(p.y)),
+        });
+      }
+    }
+    bounds
+  }
+
+  // 輪郭ごとの折れ線近似。曲線は font クレートの FlattenSink で弦との距離が tolerance 以下になるまで分ける
+  pub fn flatten(&self, tolerance: f64) -> Vec<Vec<Point>> {
+    let mut segments = Vec::new();
+    self.draw(&mut FlattenSink::new(&mut segments, tolerance as f32));
+    let mut polylines: Vec<Vec<Point>> = Vec::new();
+    for segment in segments {
+      match segment {
+        PathSegment::MoveTo(x, y) => polylines.push(vec![Point::new(x as f64, y as f64)]),
+        PathSegment::LineTo(x, y) => polylines.last_mut().into_iter().for_each(|p| p.push(Point::new(x as f64, y as f64))),
+        // FlattenSink は曲線を流さないので、ここに来るのは直線と閉じる指示だけ
+        PathSegment::QuadTo(..) | PathSegment::CubicTo(..) | PathSegment::Close => {}
+      }
+    }
+    polylines
+  }
+
+  // 輪郭を外側へ strength / 2 ずつ膨らませる（font クレートの embolden を使う）
+  pub fn embolden(&self, strength: f64) -> Path {
+    let mut segments = Vec::new();
+    self.draw(&mut segments);
+    Path::from_segments(&embolden(&segments, strength as f32))
+  }
+}
+
+// font クレートのアウトラインを直接受け取れるようにする
+impl OutlineSink for Path {
+  fn move_to(&mut self, x: f32, y: f32) {
+    self.close_contour();
+    self.contours.push(Vec::new());
+    self.current = Point::new(x as f64, y as f64);
+    self.start = self.current;
+  }
+
+  fn line_to(&mut self, x: f32, y: f32) {
+    let p = Point::new(x as f64, y as f64);
+    self.push(Segment::Line(self.current, p));
+  }
+
+  fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+    let p = Point::new(x as f64, y as f64);
+    self.push(Segment::Quad(self.current, Point::new(x1 as f64, y1 as f64), p));
+  }
+
+  fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+    let p = Point::new(x as f64, y as f64);
+    self.push(Segment::Cubic(self.current, Point::new(x1 as f64, y1 as f64), Point::new(x2 as f64, y2 as f64), p));
+  }
+
+  fn close(&mut self) {
+    self.close_contour();
+  }
+}
+
+// 折れ線近似した輪郭に対する点 p の巻き数
+pub fn winding_number(polylines: &[Vec<Point>], p: Point) -> i32 {
+  let mut winding = 0;
+  for polyline in polylines {
+    for edge in polyline.windows(2) {
+      let (a, b) = (edge[0], edge[1]);
+      let side = (b - a).cross(p - a);
+      if a.y <= p.y && b.y > p.y && side > 0.0 {
+        winding += 1;
+      } else if b.y <= p.y && a.y > p.y && side < 0.0 {
+        winding -= 1;
+      }
+    }
+  }
+  winding
+}
+
+// 折れ線近似した輪郭の符号付き面積（y軸上向きで反時計回りが正）
+pub fn signed_area(polylines: &[Vec<Point>]) -> f64 {
+  polylines.iter()
+    .map(|polyline| polyline.windows(2).map(|e| e[0].cross(e[1])).sum::<f64>() / 2.0)
+    .sum()
+}
@@ -0,0 +1,214 @@
+use crate::geometry::{Path, Point};
+use crate::raster::Rasterizer;
+use crate::{Frame, GlyphBitmap, ImageBuffer, Rgb};
+
+// サブピクセルの並ぶ向き
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SubpixelLayout {
+  Horizontal,
+  Vertical,
+}
+
+// サブピクセルの色の順序（左から、または上から）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SubpixelOrder {
+  Rgb,
+  Bgr,
+}
+
+// FreeType の FT_LCD_FILTER_DEFAULT と同じ5タップ
+pub const DEFAULT_FILTER: [f32; 5] = [8.0, 77.0, 86.0, 77.0, 8.0];
+// FT_LCD_FILTER_LIGHT
+pub const LIGHT_FILTER: [f32; 5] = [0.0, 85.0, 86.0, 85.0, 0.0];
+// フィルタなし
+pub const NO_FILTER: [f32; 1] = [1.0];
+// サブピクセルと直交する向きになめらかにしないとき、その向きに1ピクセルを分ける数
+const SAMPLE_LINES: u32 = 16;
+
+#[derive(Debug, Clone)]
+pub struct LcdOptions {
+  pub layout: SubpixelLayout,
+  pub order: SubpixelOrder,
+  // サブピクセル単位のFIRフィルタ。奇数タップで中央が対象のサブピクセル。合計で正規化する
+  pub filter: Vec<f32>,
+  // サブピクセルと直交する向きにもアンチエイリアスするか（gasp の SYMMETRIC_SMOOTHING）
+  // false ならその向きはピクセル中心を通る線で切った被覆率になる
+  pub symmetric_smoothing: bool,
+}
+
+impl Default for LcdOptions {
+  fn default() -> Self {
+    LcdOptions {
+      layout: SubpixelLayout::Horizontal,
+      order: SubpixelOrder::Rgb,
+      filter: DEFAULT_FILTER.to_vec(),
+      symmetric_smoothing: true,
+    }
+  }
+}
+
+// 長さ count の列を stride 間隔で取り出してフィルタをかける
+fn apply_filter(values: &mut [f32], start: usize, stride: usize, count: usize, filter: &[f32]) {
+  let sum: f32 = filter.iter().sum();
+  if sum == 0.0 {
+    return;
+  }
+  let center = (filter.len() / 2) as isize;
+  let source: Vec<f32> = (0..count).map(|i| values[start + i * stride]).collect();
+  for i in 0..count {
+    let mut total = 0.0;
+    for (k, weight) in filter.iter().enumerate() {
+      let j = i as isize + k as isize - center;
+      if j >= 0 && (j as usize) < count {
+        total += weight * source[j as usize];
+      }
+    }
+    values[start + i * stride] = (total / sum).min(1.0);
+  }
+}
+
+// 3倍の解像度で被覆率を求め、FIRフィルタをかけてから3サブピクセルずつRGBにまとめる
+pub fn rasterize_lcd(path: &Path, scale: f64, options: &LcdOptions) -> GlyphBitmap<Rgb<u8>> {
+  // フィルタの広がり分を含めて1ピクセルの余白を取る
+  let padding = 1 + (options.filter.len() / 2 / 3) as i32;
+  let frame = Frame::new(&path.scale(scale), padding);
+  let (sx, sy) = match options.layout {
+    SubpixelLayout::Horizontal => (3, 1),
+    SubpixelLayout::Vertical => (1, 3),
+  };
+  let lines = if options.symmetric_smoothing { 1 } else { SAMPLE_LINES };
+  let (ox, oy) = match options.layout {
+    SubpixelLayout::Horizontal => (1, lines),
+    SubpixelLayout::Vertical => (lines, 1),
+  };
+  let (width, height) = (frame.width * sx, frame.height * sy);
+
+  let subpixel_path = path.transform(|p| Point::new(
+    (p.x * scale - frame.x0 as f64) * (sx * ox) as f64,
+    (frame.y1 as f64 - p.y * scale) * (sy * oy) as f64,
+  ));
+  let mut rasterizer = Rasterizer::new(width * ox, height * oy);
+  for polyline in subpixel_path.flatten(0.05) {
+    for edge in polyline.windows(2) {
+      rasterizer.draw_line(edge[0], edge[1]);
+    }
+  }
+  let mut coverage = rasterizer.coverage();
+
+  let (width, height) = (width as usize, height as usize);
+  if lines > 1 {
+    // 細かく分けた列のうち、ピクセル中心のすぐ下（右）の列だけを残す
+    let (ox, oy) = (ox as usize, oy as usize);
+    coverage = (0..width * height)
+      .map(|i| {
+        let (x, y) = (i % width, i / width);
+        coverage[(y * oy + oy / 2) * width * ox + x * ox + ox / 2]
+      })
+      .collect();
+  }
+  match options.layout {
+    SubpixelLayout::Horizontal => {
+      for y in 0..height {
+        apply_filter(&mut coverage, y * width, 1, width, &options.filter);
+      }
+    }
+    SubpixelLayout::Vertical => {
+      for x in 0..width {
+        apply_filter(&mut coverage, x, width, height, &options.filter);
+      }
+    }
+  }
+
+  let mut image = ImageBuffer::from_pixel(frame.width, frame.height, Rgb { data: [0, 0, 0] });
+  for (x, y, pixel) in image.enumerate_pixels_mut() {
+    let (x, y) = (x as usize, y as usize);
+    let subpixel = |i: usize| {
+      let index = match options.layout {
+        SubpixelLayout::Horizontal => y * width + x * 3 + i,
+        SubpixelLayout::Vertical => (y * 3 + i) * width + x,
+      };
+      (coverage[index] * 255.0).round() as u8
+    };
+    let (first, second, third) = (subpixel(0), subpixel(1), subpixel(2));
+    pixel.data = match options.order {
+      SubpixelOrder::Rgb => [first, second, third],
+      SubpixelOrder::Bgr => [third, second, first],
+    };
+  }
+
+  GlyphBitmap { image, left: frame.x0, top: frame.y1 }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::geometry::Segment;
+
+  fn rectangle(x_min: f64, y_min: f64, x_max: f64, y_max: f64) -> Path {
+    let corners = [Point::new(x_min, y_min), Point::new(x_min, y_max), Point::new(x_max, y_max), Point::new(x_max, y_min)];
+    let mut path = Path::new();
+    path.contours.push((0..4).map(|i| Segment::Line(corners[i], corners[(i + 1) % 4])).collect());
+    path
+  }
+
+  // 行（縦並びなら列）のサブピクセルの値を並び順に取り出し、0 を除く
+  fn nonzero(values: impl Iterator<Item = u8>) -> Vec<u8> {
+    values.filter(|&v| v != 0).collect()
+  }
+
+  #[test]
+  fn filter_is_normalised_by_its_sum() {
+    let mut values = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+    apply_filter(&mut values, 0, 1, 7, &DEFAULT_FILTER);
+    let expected = [0.0, 8.0, 77.0, 86.0, 77.0, 8.0, 0.0].map(|w| w / 256.0);
+    for (value, expected) in values.iter().zip(expected) {
+      assert!((value - expected).abs() < 1e-6, "{:?}", values);
+    }
+  }
+
+  #[test]
+  fn one_subpixel_bar_spreads_by_the_default_weights() {
+    let bar = rectangle(1.0, 0.0, 1.0 + 1.0 / 3.0, 4.0);
+    let bitmap = rasterize_lcd(&bar, 1.0, &LcdOptions::default());
+    let row = (0..bitmap.image.width()).flat_map(|x| bitmap.image.get_pixel(x, 2).data);
+    assert_eq!(nonzero(row), [8, 77, 86, 77, 8]);
+  }
+
+  #[test]
+  fn vertical_layout_spreads_down_the_column() {
+    let bar = rectangle(0.0, 1.0, 4.0, 1.0 + 1.0 / 3.0);
+    let options = LcdOptions { layout: SubpixelLayout::Vertical, ..Default::default() };
+    let bitmap = rasterize_lcd(&bar, 1.0, &options);
+    let column = (0..bitmap.image.height()).flat_map(|y| bitmap.image.get_pixel(2, y).data);
+    assert_eq!(nonzero(column), [8, 77, 86, 77, 8]);
+  }
+
+  #[test]
+  fn bgr_is_the_reverse_of_rgb() {
+    let shape = rectangle(0.3, 0.0, 2.6, 3.0);
+    let rgb = rasterize_lcd(&shape, 1.0, &LcdOptions::default());
+    let bgr = rasterize_lcd(&shape, 1.0, &LcdOptions { order: SubpixelOrder::Bgr, ..Default::default() });
+    assert_eq!((rgb.image.width(), rgb.image.height()), (bgr.image.width(), bgr.image.height()));
+    for y in 0..rgb.image.height() {
+      for x in 0..rgb.image.width() {
+        let [r, g, b] = rgb.image.get_pixel(x, y).data;
+        assert_eq!(bgr.image.get_pixel(x, y).data, [b, g, r]);
+      }
+    }
+  }
+
+  #[test]
+  fn without_symmetric_smoothing_rows_are_sampled_at_the_centre() {
+    let options = |symmetric_smoothing| LcdOptions { filter: NO_FILTER.to_vec(), symmetric_smoothing, ..Default::default() };
+    // 上端がピクセルの下 1/4 にある行は中心線に届かないので消え、3/4 なら塗りつぶされる
+    for (top, expected) in [(2.25, 0), (2.75, 255)] {
+      let shape = rectangle(0.0, 0.0, 3.0, top);
+      let smooth = rasterize_lcd(&shape, 1.0, &options(true));
+      let sampled = rasterize_lcd(&shape, 1.0, &options(false));
+      let row = (smooth.top - 3) as u32;
+      let partial = ((top - 2.0) * 255.0).round() as u8;
+      assert_eq!(smooth.image.get_pixel(1, row).data, [partial; 3]);
+      assert_eq!(sampled.image.get_pixel(1, row).data, [expected; 3]);
+    }
+  }
+}
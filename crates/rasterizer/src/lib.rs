@@ -1,8 +1,81 @@
+pub mod atlas;
+pub mod autohint;
+pub mod bitmap;
+pub mod color;
+pub mod gamma;
+pub mod geometry;
+pub mod lcd;
+pub mod mono;
+pub mod paint;
+pub mod raster;
+pub mod sdf;
+
+use std::fs::File;
+use std::io::BufWriter;
+
+pub use autohint::AutoHinter;
+pub use bitmap::{decode_png, rasterize_bitmap};
+pub use color::rasterize_layers;
+pub use gamma::{rasterize_with, GammaOptions, RasterOptions, StemDarkening};
+pub use geometry::{Path, Point, Segment};
+pub use lcd::{rasterize_lcd, LcdOptions, SubpixelLayout, SubpixelOrder};
+pub use mono::{rasterize_mono, DropoutMode, MonoBitmap, MonoOptions};
+pub use paint::rasterize_paint;
+pub use raster::rasterize;
+pub use sdf::{generate_msdf, generate_sdf, SdfOptions};
+
+// PNGに書き出せるピクセル型
+pub trait Pixel: Clone {
+  const COLOR_TYPE: png::ColorType;
+  fn channels(&self) -> &[u8];
+  fn channels_mut(&mut self) -> &mut [u8];
+}
+
 #[derive(Clone)]
 pub struct Luma<T: Clone> {
   pub data: [T; 1]
 }
 
+#[derive(Clone)]
+pub struct Rgb<T: Clone> {
+  pub data: [T; 3]
+}
+
+#[derive(Clone)]
+pub struct Rgba<T: Clone> {
+  pub data: [T; 4]
+}
+
+impl Pixel for Luma<u8> {
+  const COLOR_TYPE: png::ColorType = png::ColorType::Grayscale;
+  fn channels(&self) -> &[u8] {
+    &self.data
+  }
+  fn channels_mut(&mut self) -> &mut [u8] {
+    &mut self.data
+  }
+}
+
+impl Pixel for Rgb<u8> {
+  const COLOR_TYPE: png::ColorType = png::ColorType::Rgb;
+  fn channels(&self) -> &[u8] {
+    &self.data
+  }
+  fn channels_mut(&mut self) -> &mut [u8] {
+    &mut self.data
+  }
+}
+
+impl Pixel for Rgba<u8> {
+  const COLOR_TYPE: png::ColorType = png::ColorType::Rgba;
+  fn channels(&self) -> &[u8] {
+    &self.data
+  }
+  fn channels_mut(&mut self) -> &mut [u8] {
+    &mut self.data
+  }
+}
+
 pub struct ImageBuffer<T> {
   width: u32,
   height: u32,
@@ -11,68 +84,104 @@ pub struct ImageBuffer<T> {
 
 impl ImageBuffer<Luma<u8>> {
   pub fn new(width: u32, height: u32) -> Self {
+    Self::from_pixel(width, height, Luma { data: [255] })
+  }
+}
+
+impl<T> ImageBuffer<T> {
+  pub fn width(&self) -> u32 {
+    self.width
+  }
+
+  pub fn height(&self) -> u32 {
+    self.height
+  }
+}
+
+impl<P: Pixel> ImageBuffer<P> {
+  pub fn from_pixel(width: u32, height: u32, pixel: P) -> Self {
     ImageBuffer {
       width,
       height,
-      data: vec![Luma { data: [255] }; (width * height) as usize]
+      data: vec![pixel; (width * height) as usize]
     }
   }
-  
-  pub fn enumerate_pixels_mut(&mut self) -> impl Iterator<Item = (u32, u32, &mut Luma<u8>)> + '_ {
-    struct PixelIterator<'a> {
-      width: u32,
-      height: u32,
-      data: &'a mut [Luma<u8>],
-      x: u32,
-      y: u32,
-    }
-    
-    impl<'a> Iterator for PixelIterator<'a> {
-      type Item = (u32, u32, &'a mut Luma<u8>);
-      
-      fn next(&mut self) -> Option<Self::Item> {
-        if self.y >= self.height {
-          return None;
-        }
-        
-        let x = self.x;
-        let y = self.y;
-        let idx = (y * self.width + x) as usize;
-        
-        // 次の位置を計算
-        self.x += 1;
-        if self.x >= self.width {
-          self.x = 0;
-          self.y += 1;
-        }
-        
-        // unsafe: データスライスから1要素だけ取り出す
-        let pixel = unsafe {
-          &mut *(&mut self.data[idx] as *mut Luma<u8>)
-        };
-        
-        Some((x, y, pixel))
+
+  pub fn get_pixel(&self, x: u32, y: u32) -> &P {
+    &self.data[(y * self.width + x) as usize]
+  }
+
+  pub fn put_pixel(&mut self, x: u32, y: u32, pixel: P) {
+    self.data[(y * self.width + x) as usize] = pixel;
+  }
+
+  pub fn enumerate_pixels_mut(&mut self) -> impl Iterator<Item = (u32, u32, &mut P)> + '_ {
+    let width = self.width;
+    self.data.iter_mut().enumerate().map(move |(i, pixel)| {
+      let i = i as u32;
+      (i % width, i / width, pixel)
+    })
+  }
+
+  // src を (x, y) を左上として書き写す。はみ出した部分は捨てる
+  pub fn copy_from(&mut self, src: &ImageBuffer<P>, x: u32, y: u32) {
+    for sy in 0..src.height.min(self.height.saturating_sub(y)) {
+      for sx in 0..src.width.min(self.width.saturating_sub(x)) {
+        self.put_pixel(x + sx, y + sy, src.get_pixel(sx, sy).clone());
       }
     }
-    
-    let data = unsafe {
-      std::slice::from_raw_parts_mut(
-        self.data.as_mut_ptr() as *mut Luma<u8>,
-        self.data.len()
-      )
+  }
+
+  // チャンネルを並べた生のバイト列
+  pub fn as_bytes(&self) -> Vec<u8> {
+    self.data.iter().flat_map(|pixel| pixel.channels().iter().copied()).collect()
+  }
+
+  pub fn save(&self, path: &str) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+    encoder.set_color(P::COLOR_TYPE);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&self.as_bytes()).map_err(|e| e.to_string())
+  }
+}
+
+// ラスタライズ結果のビットマップと配置
+// left はビットマップ左端の原点からのオフセット、top はベースラインから上端までの高さ（ピクセル）
+pub struct GlyphBitmap<P> {
+  pub image: ImageBuffer<P>,
+  pub left: i32,
+  pub top: i32,
+}
+
+// グリフを覆うピクセル格子。x0 は左端、y1 は上端のグリフ座標（y軸上向き）
+pub(crate) struct Frame {
+  pub(crate) x0: i32,
+  pub(crate) y1: i32,
+  pub(crate) width: u32,
+  pub(crate) height: u32,
+}
+
+impl Frame {
+  pub(crate) fn new(path: &Path, padding: i32) -> Frame {
+    let Some((x_min, y_min, x_max, y_max)) = path.bounds() else {
+      return Frame { x0: 0, y1: 0, width: 0, height: 0 };
     };
-    
-    PixelIterator {
-      width: self.width,
-      height: self.height,
-      data,
-      x: 0,
-      y: 0,
-    }
+    let x0 = x_min.floor() as i32 - padding;
+    let y0 = y_min.floor() as i32 - padding;
+    let x1 = x_max.ceil() as i32 + padding;
+    let y1 = y_max.ceil() as i32 + padding;
+    Frame { x0, y1, width: (x1 - x0) as u32, height: (y1 - y0) as u32 }
+  }
+
+  // 画像座標 (x, y) のピクセル中心をグリフ座標に変換する
+  pub(crate) fn center(&self, x: u32, y: u32) -> Point {
+    Point::new(self.x0 as f64 + x as f64 + 0.5, self.y1 as f64 - y as f64 - 0.5)
   }
-  
-  pub fn save(&self, _path: &str) -> Result<(), String> {
-    // 画像を保存する実装
-    todo!("画像保存機能の実装")
+
+  // グリフ座標を画像座標（y軸下向き）に変換する
+  pub(crate) fn to_image(&self, p: Point) -> Point {
+    Point::new(p.x - self.x0 as f64, self.y1 as f64 - p.y)
   }
-} 
\ No newline at end of file
+}
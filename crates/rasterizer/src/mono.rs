@@ -0,0 +1,399 @@
+use crate::geometry::{Path, Point};
+use crate::{Frame, ImageBuffer, Luma};
+
+// SCANTYPE で選ばれるドロップアウト制御の規則
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DropoutMode {
+  // 規則1, 2のみ（ピクセル中心が輪郭の内側または輪郭上なら点灯）
+  None,
+  // 規則3 / 4：ドロップアウトした区間の左（下）のピクセルを点灯する
+  Simple { exclude_stubs: bool },
+  // 規則5 / 6：区間の中点に近いピクセルを点灯する
+  Smart { exclude_stubs: bool },
+}
+
+impl DropoutMode {
+  pub fn from_scan_type(scan_type: u16) -> DropoutMode {
+    match scan_type {
+      0 => DropoutMode::Simple { exclude_stubs: false },
+      1 => DropoutMode::Simple { exclude_stubs: true },
+      4 => DropoutMode::Smart { exclude_stubs: false },
+      5 => DropoutMode::Smart { exclude_stubs: true },
+      _ => DropoutMode::None,
+    }
+  }
+
+  fn exclude_stubs(&self) -> bool {
+    matches!(self, DropoutMode::Simple { exclude_stubs: true } | DropoutMode::Smart { exclude_stubs: true })
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MonoOptions {
+  // SCANCTRL の引数。下位8ビットがppemのしきい値、ビット8〜13が条件
+  pub scan_control: u16,
+  // SCANTYPE の引数
+  pub scan_type: u16,
+  pub rotated: bool,
+  pub stretched: bool,
+}
+
+impl Default for MonoOptions {
+  fn default() -> Self {
+    MonoOptions {
+      scan_control: 0x01FF,
+      scan_type: 4,
+      rotated: false,
+      stretched: false,
+    }
+  }
+}
+
+impl MonoOptions {
+  // SCANCTRL の規則に従って、この ppem でドロップアウト制御を行うか判定する
+  pub fn dropout_enabled(&self, ppem: u32) -> bool {
+    let flags = self.scan_control;
+    let threshold = (flags & 0xFF) as u32;
+    if threshold == 0xFF {
+      return true;
+    }
+    if threshold == 0 {
+      return false;
+    }
+    let mut enabled = false;
+    if flags & 0x0100 != 0 && ppem <= threshold {
+      enabled = true;
+    }
+    if flags & 0x0200 != 0 && self.rotated {
+      enabled = true;
+    }
+    if flags & 0x0400 != 0 && self.stretched {
+      enabled = true;
+    }
+    if flags & 0x0800 != 0 && ppem > threshold {
+      enabled = false;
+    }
+    if flags & 0x1000 != 0 && !self.rotated {
+      enabled = false;
+    }
+    if flags & 0x2000 != 0 && !self.stretched {
+      enabled = false;
+    }
+    enabled
+  }
+
+  pub fn dropout_mode(&self, ppem: u32) -> DropoutMode {
+    if self.dropout_enabled(ppem) {
+      DropoutMode::from_scan_type(self.scan_type)
+    } else {
+      DropoutMode::None
+    }
+  }
+}
+
+// 1ビット/ピクセルのビットマップ。各行は最上位ビットが左端で、pitch バイトに詰める
+pub struct MonoBitmap {
+  pub width: u32,
+  pub height: u32,
+  pub pitch: u32,
+  pub data: Vec<u8>,
+  pub left: i32,
+  pub top: i32,
+}
+
+impl MonoBitmap {
+  fn new(width: u32, height: u32, left: i32, top: i32) -> Self {
+    let pitch = width.div_ceil(8);
+    MonoBitmap { width, height, pitch, data: vec![0; (pitch * height) as usize], left, top }
+  }
+
+  pub fn get(&self, x: u32, y: u32) -> bool {
+    self.data[(y * self.pitch + x / 8) as usize] & (0x80 >> (x % 8)) != 0
+  }
+
+  pub fn set(&mut self, x: u32, y: u32) {
+    self.data[(y * self.pitch + x / 8) as usize] |= 0x80 >> (x % 8);
+  }
+
+  // 白地に黒のグレースケール画像
+  pub fn to_image(&self) -> ImageBuffer<Luma<u8>> {
+    let mut image = ImageBuffer::new(self.width, self.height);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+      if self.get(x, y) {
+        pixel.data[0] = 0;
+      }
+    }
+    image
+  }
+
+  // Netpbm の P4 (バイナリPBM)。1 が黒で、行の詰め方はこの構造体と同じ
+  pub fn to_pbm(&self) -> Vec<u8> {
+    let mut pbm = format!("P4\n{} {}\n", self.width, self.height).into_bytes();
+    pbm.extend_from_slice(&self.data);
+    pbm
+  }
+
+  // 組み込み向けのCヘッダ。name は識別子として使える文字列にすること
+  pub fn to_c_header(&self, name: &str) -> String {
+    let upper = name.to_uppercase();
+    let mut header = String::new();
+    header.push_str(&format!("// {}: {}x{} 1bpp, MSB first, left={} top={}\n", name, self.width, self.height, self.left, self.top));
+    header.push_str(&format!("#define {}_WIDTH {}\n", upper, self.width));
+    header.push_str(&format!("#define {}_HEIGHT {}\n", upper, self.height));
+    header.push_str(&format!("#define {}_PITCH {}\n", upper, self.pitch));
+    header.push_str(&format!("static const unsigned char {}[] = {{\n", name));
+    for row in self.data.chunks(self.pitch.max(1) as usize) {
+      let bytes: Vec<String> = row.iter().map(|b| format!("0x{:02x},", b)).collect();
+      header.push_str(&format!("  {}\n", bytes.join(" ")));
+    }
+    header.push_str("};\n");
+    header
+  }
+}
+
+// 走査線 c と輪郭の交点から、非ゼロ巻き数規則で内側になる区間を求める
+// horizontal が真なら y = c の水平線、偽なら x = c の垂直線で走査する
+fn spans(polylines: &[Vec<Point>], c: f64, horizontal: bool) -> Vec<(f64, f64)> {
+  let mut crossings: Vec<(f64, i32)> = Vec::new();
+  for polyline in polylines {
+    for edge in polyline.windows(2) {
+      let (a, b) = if horizontal {
+        ((edge[0].y, edge[0].x), (edge[1].y, edge[1].x))
+      } else {
+        ((edge[0].x, edge[0].y), (edge[1].x, edge[1].y))
+      };
+      if (a.0 <= c && b.0 > c) || (b.0 <= c && a.0 > c) {
+        let v = a.1 + (c - a.0) / (b.0 - a.0) * (b.1 - a.1);
+        crossings.push((v, if b.0 > a.0 { 1 } else { -1 }));
+      }
+    }
+  }
+  crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+  let mut result = Vec::new();
+  let mut winding = 0;
+  let mut start = 0.0;
+  for (v, direction) in crossings {
+    let previous = winding;
+    winding += direction;
+    if previous == 0 && winding != 0 {
+      start = v;
+    } else if previous != 0 && winding == 0 {
+      result.push((start, v));
+    }
+  }
+  result
+}
+
+// 隣の走査線で同じ領域が続いていなければスタブ（ストロークの端）とみなす
+fn is_stub(polylines: &[Vec<Point>], c: f64, span: (f64, f64), horizontal: bool) -> bool {
+  let continues = |c: f64| {
+    spans(polylines, c, horizontal).iter()
+      .any(|&(s, e)| s <= span.1 + 1.0 && e >= span.0 - 1.0)
+  };
+  !(continues(c - 1.0) && continues(c + 1.0))
+}
+
+// ピクセル中心でサンプリングする2値化。TrueType のドロップアウト制御に従う
+pub fn rasterize_mono(path: &Path, scale: f64, ppem: u32, options: &MonoOptions) -> MonoBitmap {
+  let path = path.scale(scale);
+  let frame = Frame::new(&path, 1);
+  let polylines = path.flatten(0.01);
+  let mut bitmap = MonoBitmap::new(frame.width, frame.height, frame.x0, frame.y1);
+  let (width, height) = (frame.width as i64, frame.height as i64);
+  let (x0, y1) = (frame.x0 as f64, frame.y1 as f64);
+
+  // 規則1, 2：ピクセル中心が区間（両端を含む）に入るピクセルを点灯
+  let mut row_dropouts = Vec::new();
+  for j in 0..height {
+    let cy = y1 - j as f64 - 0.5;
+    for span in spans(&polylines, cy, true) {
+      let first = (span.0 - x0 - 0.5).ceil() as i64;
+      let last = (span.1 - x0 - 0.5).floor() as i64;
+      if first > last {
+        row_dropouts.push((j, cy, span, last, first));
+      }
+      for i in first.max(0)..=last.min(width - 1) {
+        bitmap.set(i as u32, j as u32);
+      }
+    }
+  }
+
+  let mode = options.dropout_mode(ppem);
+  if mode == DropoutMode::None {
+    return bitmap;
+  }
+
+  let mut column_dropouts = Vec::new();
+  for i in 0..width {
+    let cx = x0 + i as f64 + 0.5;
+    for span in spans(&polylines, cx, false) {
+      // y軸は上向きなので、区間の上端が小さい行番号になる
+      let first = (y1 - span.1 - 0.5).ceil() as i64;
+      let last = (y1 - span.0 - 0.5).floor() as i64;
+      if first > last {
+        // 上のピクセルは last、下のピクセルは first
+        column_dropouts.push((i, cx, span, first, last));
+      }
+    }
+  }
+
+  let in_bounds = |i: i64, j: i64| i >= 0 && i < width && j >= 0 && j < height;
+
+  // 区間の両側のピクセル (low, high) のどちらも消えていればどちらかを点灯する
+  // low は左（行）または下（列）のピクセル
+  for (j, cy, span, low, high) in row_dropouts {
+    if !(in_bounds(low, j) && in_bounds(high, j)) {
+      continue;
+    }
+    if bitmap.get(low as u32, j as u32) || bitmap.get(high as u32, j as u32) {
+      continue;
+    }
+    if mode.exclude_stubs() && is_stub(&polylines, cy, span, true) {
+      continue;
+    }
+    let i = match mode {
+      DropoutMode::Smart { .. } => {
+        let middle = (span.0 + span.1) / 2.0 - x0 - 0.5;
+        if middle - low as f64 <= high as f64 - middle { low } else { high }
+      }
+      _ => low,
+    };
+    bitmap.set(i as u32, j as u32);
+  }
+
+  for (i, cx, span, low, high) in column_dropouts {
+    if !(in_bounds(i, low) && in_bounds(i, high)) {
+      continue;
+    }
+    if bitmap.get(i as u32, low as u32) || bitmap.get(i as u32, high as u32) {
+      continue;
+    }
+    if mode.exclude_stubs() && is_stub(&polylines, cx, span, false) {
+      continue;
+    }
+    let j = match mode {
+      DropoutMode::Smart { .. } => {
+        let middle = y1 - (span.0 + span.1) / 2.0 - 0.5;
+        if low as f64 - middle <= middle - high as f64 { low } else { high }
+      }
+      _ => low,
+    };
+    bitmap.set(i as u32, j as u32);
+  }
+
+  bitmap
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::geometry::Segment;
+
+  fn rectangle(x_min: f64, y_min: f64, x_max: f64, y_max: f64) -> Path {
+    let corners = [Point::new(x_min, y_min), Point::new(x_min, y_max), Point::new(x_max, y_max), Point::new(x_max, y_min)];
+    let mut path = Path::new();
+    path.contours.push((0..4).map(|i| Segment::Line(corners[i], corners[(i + 1) % 4])).collect());
+    path
+  }
+
+  // 点灯したピクセルの中心（y軸上向きの座標）
+  fn lit(bitmap: &MonoBitmap) -> Vec<(f64, f64)> {
+    let mut centers = Vec::new();
+    for y in 0..bitmap.height {
+      for x in 0..bitmap.width {
+        if bitmap.get(x, y) {
+          centers.push((bitmap.left as f64 + x as f64 + 0.5, bitmap.top as f64 - y as f64 - 0.5));
+        }
+      }
+    }
+    centers
+  }
+
+  fn render(path: &Path, scan_type: u16) -> Vec<(f64, f64)> {
+    let options = MonoOptions { scan_type, ..Default::default() };
+    lit(&rasterize_mono(path, 1.0, 12, &options))
+  }
+
+  #[test]
+  fn scan_control_conditions() {
+    // (SCANCTRL, rotated, stretched, ppem, 有効か)
+    let cases = [
+      (0x01FF, false, false, 200, true),
+      (0x0100, false, false, 8, false),
+      (0x0110, false, false, 16, true),
+      (0x0110, false, false, 17, false),
+      (0x0210, false, false, 20, false),
+      (0x0210, true, false, 20, true),
+      (0x0410, false, true, 20, true),
+      (0x0910, false, false, 12, true),
+      (0x0B10, true, false, 20, false),
+      (0x1110, false, false, 12, false),
+      (0x1110, true, false, 12, true),
+      (0x2110, false, false, 12, false),
+      (0x2110, false, true, 12, true),
+    ];
+    for (scan_control, rotated, stretched, ppem, expected) in cases {
+      let options = MonoOptions { scan_control, rotated, stretched, ..Default::default() };
+      assert_eq!(options.dropout_enabled(ppem), expected, "SCANCTRL {:#06x} rotated={} stretched={} ppem={}", scan_control, rotated, stretched, ppem);
+    }
+  }
+
+  #[test]
+  fn scan_type_rules() {
+    assert_eq!(DropoutMode::from_scan_type(0), DropoutMode::Simple { exclude_stubs: false });
+    assert_eq!(DropoutMode::from_scan_type(1), DropoutMode::Simple { exclude_stubs: true });
+    assert_eq!(DropoutMode::from_scan_type(2), DropoutMode::None);
+    assert_eq!(DropoutMode::from_scan_type(4), DropoutMode::Smart { exclude_stubs: false });
+    assert_eq!(DropoutMode::from_scan_type(5), DropoutMode::Smart { exclude_stubs: true });
+    let options = MonoOptions { scan_control: 0x0110, ..Default::default() };
+    assert_eq!(options.dropout_mode(20), DropoutMode::None);
+  }
+
+  #[test]
+  fn thin_horizontal_stroke_is_filled_by_dropout_control() {
+    // 高さ 0.3 の棒はピクセル中心 y = 0.5 と 1.5 の間にあり、規則1, 2では何も点灯しない
+    let bar = rectangle(0.2, 1.1, 7.8, 1.4);
+    assert!(render(&bar, 2).is_empty());
+    let row = |y: f64| (0..8).map(|i| (i as f64 + 0.5, y)).collect::<Vec<_>>();
+    // Simple は下のピクセル、Smart は区間の中点 1.25 に近い上のピクセル
+    assert_eq!(render(&bar, 0), row(0.5));
+    assert_eq!(render(&bar, 4), row(1.5));
+    // スタブを除くと、隣の列に続きのない両端の列だけが残らない
+    assert_eq!(render(&bar, 1), row(0.5)[1..7]);
+    assert_eq!(render(&bar, 5), row(1.5)[1..7]);
+  }
+
+  #[test]
+  fn thin_vertical_stroke_is_filled_by_dropout_control() {
+    let bar = rectangle(1.1, 0.2, 1.4, 7.8);
+    let column = |x: f64| {
+      let mut centers: Vec<(f64, f64)> = (0..8).map(|j| (x, j as f64 + 0.5)).collect();
+      centers.reverse();
+      centers
+    };
+    // Simple は左のピクセル、Smart は中点に近い右のピクセル
+    assert_eq!(render(&bar, 0), column(0.5));
+    assert_eq!(render(&bar, 4), column(1.5));
+  }
+
+  #[test]
+  fn pbm_and_c_header_share_the_row_packing() {
+    let mut bitmap = MonoBitmap::new(10, 2, -1, 5);
+    bitmap.set(0, 0);
+    bitmap.set(9, 1);
+    assert_eq!(bitmap.data, [0x80, 0x00, 0x00, 0x40]);
+    assert_eq!(bitmap.to_pbm(), b"P4\n10 2\n\x80\x00\x00\x40");
+    assert_eq!(
+      bitmap.to_c_header("glyph_a"),
+      "// glyph_a: 10x2 1bpp, MSB first, left=-1 top=5\n\
+       #define GLYPH_A_WIDTH 10\n\
+       #define GLYPH_A_HEIGHT 2\n\
+       #define GLYPH_A_PITCH 2\n\
+       static const unsigned char glyph_a[] = {\n\
+       \x20 0x80, 0x00,\n\
+       \x20 0x00, 0x40,\n\
+       };\n"
+    );
+  }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use font::tables::cpal::Color;
+use font::tables::paint::{linear_gradient_end, resolve_color, sweep_position, ClipBox, ColorLine, CompositeMode, Paint};
+use font::truetype::Transform;
+use crate::color::{coverage, to_bitmap};
+use crate::geometry::{Path, Point, Segment};
+use crate::{Frame, GlyphBitmap, Rgba};
+
+// 乗算済みの RGBA（0.0〜1.0）
+type Premultiplied = [f32; 4];
+
+// COLR バージョン 1 の Paint を描く。キャンバスはフレームと同じ大きさの乗算済み RGBA
+struct PaintRenderer<'a> {
+  outlines: &'a HashMap<u16, Path>,
+  palette: &'a [Color],
+  foreground: Color,
+  frame: Frame,
+}
+
+fn premultiply(color: Color) -> Premultiplied {
+  let a = color.a as f32 / 255.0;
+  [color.r as f32 / 255.0 * a, color.g as f32 / 255.0 * a, color.b as f32 / 255.0 * a, a]
+}
+
+// 2点円錐グラデーションで点 p を通る円のうち t が最大のもの。円の半径が負になる t は使わない
+fn radial_position(p: (f32, f32), c0: (f32, f32), r0: f32, c1: (f32, f32), r1: f32) -> Option<f32> {
+  let (cdx, cdy, dr) = (c1.0 - c0.0, c1.1 - c0.1, r1 - r0);
+  let (pdx, pdy) = (p.0 - c0.0, p.1 - c0.1);
+  let a = cdx * cdx + cdy * cdy - dr * dr;
+  let b = pdx * cdx + pdy * cdy + r0 * dr;
+  let c = pdx * pdx + pdy * pdy - r0 * r0;
+  let radius = |t: f32| r0 + dr * t;
+  if a.abs() < 1e-6 {
+    // 円が一方の円に接しながら広がる場合は1次方程式になる
+    if b == 0.0 {
+      return None;
+    }
+    let t = c / (2.0 * b);
+    return (radius(t) >= 0.0).then_some(t);
+  }
+  let discriminant = b * b - a * c;
+  if discriminant < 0.0 {
+    return None;
+  }
+  let root = discriminant.sqrt();
+  let (t0, t1) = ((b + root) / a, (b - root) / a);
+  let (large, small) = if t0 > t1 { (t0, t1) } else { (t1, t0) };
+  [large, small].into_iter().find(|&t| radius(t) >= 0.0)
+}
+
+// Porter-Duff の合成で source と backdrop に掛ける係数
+fn porter_duff(mode: CompositeMode, source_alpha: f32, backdrop_alpha: f32) -> Option<(f32, f32)> {
+  let (a_s, a_b) = (source_alpha, backdrop_alpha);
+  Some(match mode {
+    CompositeMode::Clear => (0.0, 0.0),
+    CompositeMode::Src => (1.0, 0.0),
+    CompositeMode::Dest => (0.0, 1.0),
+    CompositeMode::SrcOver => (1.0, 1.0 - a_s),
+    CompositeMode::DestOver => (1.0 - a_b, 1.0),
+    CompositeMode::SrcIn => (a_b, 0.0),
+    CompositeMode::DestIn => (0.0, a_s),
+    CompositeMode::SrcOut => (1.0 - a_b, 0.0),
+    CompositeMode::DestOut => (0.0, 1.0 - a_s),
+    CompositeMode::SrcAtop => (a_b, 1.0 - a_s),
+    CompositeMode::DestAtop => (1.0 - a_b, a_s),
+    CompositeMode::Xor => (1.0 - a_b, 1.0 - a_s),
+    CompositeMode::Plus => (1.0, 1.0),
+    _ => return None,
+  })
+}
+
+// 成分ごとのブレンド関数 B(Cb, Cs)
+fn separable(mode: CompositeMode, cb: f32, cs: f32) -> f32 {
+  let hard_light = |cb: f32, cs: f32| {
+    if cs <= 0.5 { cb * 2.0 * cs } else { let s = 2.0 * cs - 1.0; cb + s - cb * s }
+  };
+  match mode {
+    CompositeMode::Multiply => cb * cs,
+    CompositeMode::Screen => cb + cs - cb * cs,
+    CompositeMode::Overlay => hard_light(cs, cb),
+    CompositeMode::Darken => cb.min(cs),
+    CompositeMode::Lighten => cb.max(cs),
+    CompositeMode::ColorDodge => {
+      if cb == 0.0 { 0.0 } else if cs >= 1.0 { 1.0 } else { (cb / (1.0 - cs)).min(1.0) }
+    }
+    CompositeMode::ColorBurn => {
+      if cb >= 1.0 { 1.0 } else if cs <= 0.0 { 0.0 } else { 1.0 - ((1.0 - cb) / cs).min(1.0) }
+    }
+    CompositeMode::HardLight => hard_light(cb, cs),
+    CompositeMode::SoftLight => {
+      if cs <= 0.5 {
+        cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb)
+      } else {
+        let d = if cb <= 0.25 { ((16.0 * cb - 12.0) * cb + 4.0) * cb } else { cb.sqrt() };
+        cb + (2.0 * cs - 1.0) * (d - cb)
+      }
+    }
+    CompositeMode::Difference => (cb - cs).abs(),
+    CompositeMode::Exclusion => cb + cs - 2.0 * cb * cs,
+    _ => cs,
+  }
+}
+
+fn luminosity(c: [f32; 3]) -> f32 {
+  0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+}
+
+fn clip_color(c: [f32; 3]) -> [f32; 3] {
+  let l = luminosity(c);
+  let n = c[0].min(c[1]).min(c[2]);
+  let x = c[0].max(c[1]).max(c[2]);
+  c.map(|v| {
+    let mut v = v;
+    if n < 0.0 && l != n {
+      v = l + (v - l) * l / (l - n);
+    }
+    if x > 1.0 && x != l {
+      v = l + (v - l) * (1.0 - l) / (x - l);
+    }
+    v
+  })
+}
+
+fn set_luminosity(c: [f32; 3], l: f32) -> [f32; 3] {
+  let d = l - luminosity(c);
+  clip_color(c.map(|v| v + d))
+}
+
+fn saturation(c: [f32; 3]) -> f32 {
+  c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+fn set_saturation(c: [f32; 3], s: f32) -> [f32; 3] {
+  let max = c[0].max(c[1]).max(c[2]);
+  let min = c[0].min(c[1]).min(c[2]);
+  if max <= min {
+    return [0.0; 3];
+  }
+  c.map(|v| (v - min) * s / (max - min))
+}
+
+// 色相・彩度・色・輝度のブレンド関数
+fn non_separable(mode: CompositeMode, cb: [f32; 3], cs: [f32; 3]) -> Option<[f32; 3]> {
+  Some(match mode {
+    CompositeMode::HslHue => set_luminosity(set_saturation(cs, saturation(cb)), luminosity(cb)),
+    CompositeMode::HslSaturation => set_luminosity(set_saturation(cb, saturation(cs)), luminosity(cb)),
+    CompositeMode::HslColor => set_luminosity(cs, luminosity(cb)),
+    CompositeMode::HslLuminosity => set_luminosity(cb, luminosity(cs)),
+    _ => return None,
+  })
+}
+
+// 1ピクセルの合成。ブレンドモードは W3C Compositing and Blending の式に従う
+fn composite(mode: CompositeMode, source: Premultiplied, backdrop: Premultiplied) -> Premultiplied {
+  let (a_s, a_b) = (source[3], backdrop[3]);
+  if let Some((fs, fb)) = porter_duff(mode, a_s, a_b) {
+    return [0, 1, 2, 3].map(|c| (source[c] * fs + backdrop[c] * fb).min(1.0));
+  }
+  // ブレンド関数はストレートアルファの色に当てはめる
+  let unpremultiply = |p: Premultiplied| {
+    let a = p[3];
+    if a > 0.0 { [p[0] / a, p[1] / a, p[2] / a] } else { [0.0; 3] }
+  };
+  let (cs, cb) = (unpremultiply(source), unpremultiply(backdrop));
+  let blended = non_separable(mode, cb, cs).unwrap_or_else(|| [0, 1, 2].map(|c| separable(mode, cb[c], cs[c])));
+  let mut result = [0.0; 4];
+  for c in 0..3 {
+    result[c] = source[c] * (1.0 - a_b) + backdrop[c] * (1.0 - a_s) + a_s * a_b * blended[c];
+  }
+  result[3] = a_s + a_b * (1.0 - a_s);
+  result
+}
+
+impl PaintRenderer<'_> {
+  fn pixels(&self) -> usize {
+    (self.frame.width * self.frame.height) as usize
+  }
+
+  // ピクセルの中心を Paint の座標に戻して色を決める
+  fn gradient<F: Fn((f32, f32)) -> Option<f32>>(&self, line: &ColorLine, ctm: &Transform, position: F) -> Vec<Premultiplied> {
+    let Some(inverse) = ctm.invert() else {
+      return vec![[0.0; 4]; self.pixels()];
+    };
+    let mut canvas = Vec::with_capacity(self.pixels());
+    for y in 0..self.frame.height {
+      for x in 0..self.frame.width {
+        let center = self.frame.center(x, y);
+        let p = inverse.apply(center.x as f32, center.y as f32);
+        canvas.push(match position(p) {
+          Some(t) => line.color_at(t, self.palette, self.foreground),
+          None => [0.0; 4],
+        });
+      }
+    }
+    canvas
+  }
+
+  // ctm は Paint の座標からピクセル単位の座標（y軸上向き）への変換
+  fn render(&self, paint: &Paint, ctm: &Transform) -> Vec<Premultiplied> {
+    match paint {
+      Paint::Layers(layers) => {
+        let mut canvas = vec![[0.0; 4]; self.pixels()];
+        for layer in layers {
+          for (pixel, source) in canvas.iter_mut().zip(self.render(layer, ctm)) {
+            *pixel = composite(CompositeMode::SrcOver, source, *pixel);
+          }
+        }
+        canvas
+      }
+      Paint::Solid { palette_index, alpha } => {
+        vec![premultiply(resolve_color(*palette_index, *alpha, self.palette, self.foreground)); self.pixels()]
+      }
+      Paint::LinearGradient { line, p0, p1, p2 } => {
+        let p3 = linear_gradient_end(*p0, *p1, *p2);
+        let (dx, dy) = (p3.0 - p0.0, p3.1 - p0.1);
+        let length = dx * dx + dy * dy;
+        self.gradient(line, ctm, |p| (length > 0.0).then(|| ((p.0 - p0.0) * dx + (p.1 - p0.1) * dy) / length))
+      }
+      Paint::RadialGradient { line, c0, r0, c1, r1 } => self.gradient(line, ctm, |p| radial_position(p, *c0, *r0, *c1, *r1)),
+      Paint::SweepGradient { line, center, start_angle, end_angle } => self.gradient(line, ctm, |p| {
+        let degrees = (p.1 - center.1).atan2(p.0 - center.0).to_degrees().rem_euclid(360.0);
+        Some(sweep_position(degrees, *start_angle, *end_angle))
+      }),
+      Paint::Glyph { glyph_id, paint } => {
+        let Some(outline) = self.outlines.get(glyph_id) else {
+          return vec![[0.0; 4]; self.pixels()];
+        };
+        let mask = coverage(&outline.apply(ctm), &self.frame);
+        let mut canvas = self.render(paint, ctm);
+        for (pixel, coverage) in canvas.iter_mut().zip(mask) {
+          *pixel = pixel.map(|c| c * coverage);
+        }
+        canvas
+      }
+      Paint::Transform { transform, paint } => self.render(paint, &transform.then(ctm)),
+      Paint::Composite { source, mode, backdrop } => {
+        let backdrop = self.render(backdrop, ctm);
+        self.render(source, ctm).into_iter().zip(backdrop)
+          .map(|(source, backdrop)| composite(*mode, source, backdrop))
+          .collect()
+      }
+    }
+  }
+}
+
+// 変換を当てはめたグリフの輪郭をすべて集める（描画範囲を決めるのに使う）
+fn collect_outlines(paint: &Paint, ctm: &Transform, outlines: &HashMap<u16, Path>, union: &mut Path) {
+  match paint {
+    Paint::Layers(layers) => layers.iter().for_each(|layer| collect_outlines(layer, ctm, outlines, union)),
+    Paint::Glyph { glyph_id, .. } => {
+      if let Some(outline) = outlines.get(glyph_id) {
+        union.contours.extend(outline.apply(ctm).contours);
+      }
+    }
+    Paint::Transform { transform, paint } => collect_outlines(paint, &transform.then(ctm), outlines, union),
+    Paint::Composite { source, backdrop, .. } => {
+      collect_outlines(source, ctm, outlines, union);
+      collect_outlines(backdrop, ctm, outlines, union);
+    }
+    Paint::Solid { .. } | Paint::LinearGradient { .. } | Paint::RadialGradient { .. } | Paint::SweepGradient { .. } => {}
+  }
+}
+
+fn rectangle(x_min: f64, y_min: f64, x_max: f64, y_max: f64) -> Path {
+  let mut path = Path::new();
+  let corners = [Point::new(x_min, y_min), Point::new(x_max, y_min), Point::new(x_max, y_max), Point::new(x_min, y_max)];
+  path.contours.push((0..4).map(|i| Segment::Line(corners[i], corners[(i + 1) % 4])).collect());
+  path
+}
+
+// COLR バージョン 1 の Paint を RGBA のビットマップにする。outlines は Paint が使うグリフの輪郭（フォント単位）
+// clip があればその矩形で切り抜き、なければグリフの輪郭が入る範囲を描く。背景は透明で、色はストレートアルファ
+pub fn rasterize_paint(
+  paint: &Paint,
+  outlines: &HashMap<u16, Path>,
+  palette: &[Color],
+  foreground: Color,
+  clip: Option<ClipBox>,
+  scale: f64,
+) -> GlyphBitmap<Rgba<u8>> {
+  let ctm = Transform::scale(scale as f32, scale as f32);
+  let clip = clip.map(|clip| {
+    rectangle(clip.x_min as f64, clip.y_min as f64, clip.x_max as f64, clip.y_max as f64).scale(scale)
+  });
+  let frame = match &clip {
+    Some(rect) => Frame::new(rect, 0),
+    None => {
+      let mut union = Path::new();
+      collect_outlines(paint, &ctm, outlines, &mut union);
+      Frame::new(&union, 1)
+    }
+  };
+  let renderer = PaintRenderer { outlines, palette, foreground, frame };
+  let mut canvas = renderer.render(paint, &ctm);
+  if let Some(rect) = &clip {
+    for (pixel, coverage) in canvas.iter_mut().zip(coverage(rect, &renderer.frame)) {
+      *pixel = pixel.map(|c| c * coverage);
+    }
+  }
+  to_bitmap(&canvas, &renderer.frame)
+}
@@ -0,0 +1,112 @@
+use crate::geometry::{Path, Point};
+use crate::{Frame, GlyphBitmap, ImageBuffer, Luma};
+
+// 符号付き面積を画素ごとに積算し、行方向の累積和で被覆率を求めるスキャンコンバータ
+pub struct Rasterizer {
+  width: usize,
+  height: usize,
+  accumulation: Vec<f32>,
+}
+
+impl Rasterizer {
+  pub fn new(width: u32, height: u32) -> Self {
+    let (width, height) = (width as usize, height as usize);
+    Rasterizer {
+      width,
+      height,
+      // 右端からはみ出す分の余白を持たせる
+      accumulation: vec![0.0; width * height + 4],
+    }
+  }
+
+  // 画像座標（y軸下向き）の線分を積算する
+  pub fn draw_line(&mut self, p0: Point, p1: Point) {
+    if (p0.y - p1.y).abs() <= f64::EPSILON {
+      return;
+    }
+    let clamp_x = |p: Point| Point::new(p.x.clamp(0.0, self.width as f64), p.y);
+    let (direction, p0, p1) = if p0.y < p1.y {
+      (1.0, clamp_x(p0), clamp_x(p1))
+    } else {
+      (-1.0, clamp_x(p1), clamp_x(p0))
+    };
+    let dxdy = (p1.x - p0.x) / (p1.y - p0.y);
+    let mut x = p0.x;
+    if p0.y < 0.0 {
+      x -= p0.y * dxdy;
+    }
+
+    let y_start = p0.y.max(0.0) as usize;
+    let y_end = self.height.min(p1.y.ceil().max(0.0) as usize);
+    for y in y_start..y_end {
+      let line_start = y * self.width;
+      let dy = ((y + 1) as f64).min(p1.y) - (y as f64).max(p0.y);
+      let x_next = x + dxdy * dy;
+      let d = (dy * direction) as f32;
+      let (x0, x1) = if x < x_next { (x, x_next) } else { (x_next, x) };
+      let x0_floor = x0.floor();
+      let x0i = x0_floor as usize;
+      let x1_ceil = x1.ceil();
+      let x1i = x1_ceil as usize;
+
+      if x1i <= x0i + 1 {
+        // 1画素内に収まる場合は台形の面積で左右に振り分ける
+        let xmf = (0.5 * (x + x_next) - x0_floor) as f32;
+        self.accumulation[line_start + x0i] += d - d * xmf;
+        self.accumulation[line_start + x0i + 1] += d * xmf;
+      } else {
+        let s = (1.0 / (x1 - x0)) as f32;
+        let x0f = (x0 - x0_floor) as f32;
+        let a0 = 0.5 * s * (1.0 - x0f) * (1.0 - x0f);
+        let x1f = (x1 - x1_ceil + 1.0) as f32;
+        let am = 0.5 * s * x1f * x1f;
+        self.accumulation[line_start + x0i] += d * a0;
+        if x1i == x0i + 2 {
+          self.accumulation[line_start + x0i + 1] += d * (1.0 - a0 - am);
+        } else {
+          let a1 = s * (1.5 - x0f);
+          self.accumulation[line_start + x0i + 1] += d * (a1 - a0);
+          for xi in x0i + 2..x1i - 1 {
+            self.accumulation[line_start + xi] += d * s;
+          }
+          let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+          self.accumulation[line_start + x1i - 1] += d * (1.0 - a2 - am);
+        }
+        self.accumulation[line_start + x1i] += d * am;
+      }
+      x = x_next;
+    }
+  }
+
+  // 画素ごとの被覆率 (0.0〜1.0)
+  pub fn coverage(&self) -> Vec<f32> {
+    let mut total = 0.0;
+    self.accumulation[..self.width * self.height].iter()
+      .map(|a| {
+        total += a;
+        total.abs().min(1.0)
+      })
+      .collect()
+  }
+}
+
+const FLATTEN_TOLERANCE: f64 = 0.05;
+
+// アンチエイリアスをかけた被覆率のビットマップ。インクのある画素ほど値が大きい
+pub fn rasterize(path: &Path, scale: f64) -> GlyphBitmap<Luma<u8>> {
+  let path = path.scale(scale);
+  let frame = Frame::new(&path, 1);
+  let mut rasterizer = Rasterizer::new(frame.width, frame.height);
+  for polyline in path.flatten(FLATTEN_TOLERANCE) {
+    for edge in polyline.windows(2) {
+      rasterizer.draw_line(frame.to_image(edge[0]), frame.to_image(edge[1]));
+    }
+  }
+
+  let mut image = ImageBuffer::from_pixel(frame.width, frame.height, Luma { data: [0] });
+  for ((_, _, pixel), coverage) in image.enumerate_pixels_mut().zip(rasterizer.coverage()) {
+    pixel.data[0] = (coverage * 255.0).round() as u8;
+  }
+
+  GlyphBitmap { image, left: frame.x0, top: frame.y1 }
+}
@@ -0,0 +1,577 @@
+use crate::geometry::{signed_area, winding_number, Path, Point, Segment};
+use crate::{Frame, GlyphBitmap, ImageBuffer, Luma, Rgb};
+
+// 距離場の生成パラメータ
+#[derive(Debug, Clone, Copy)]
+pub struct SdfOptions {
+  // フォント単位からピクセルへの倍率（ppem / units_per_em）
+  pub scale: f64,
+  // 0〜255 に割り当てる距離の幅（ピクセル）。輪郭上が 128 付近になる
+  pub range: f64,
+  // MSDFでコーナーとみなす角度（ラジアン）
+  pub angle_threshold: f64,
+}
+
+impl Default for SdfOptions {
+  fn default() -> Self {
+    SdfOptions {
+      scale: 1.0,
+      range: 4.0,
+      angle_threshold: 3.0,
+    }
+  }
+}
+
+// 符号付き距離。比較は距離の絶対値、同じなら接線との直交度で行う
+#[derive(Debug, Clone, Copy)]
+struct SignedDistance {
+  distance: f64,
+  dot: f64,
+}
+
+impl SignedDistance {
+  const INFINITE: SignedDistance = SignedDistance { distance: f64::INFINITY, dot: 1.0 };
+
+  fn closer_than(&self, other: &SignedDistance) -> bool {
+    let (a, b) = (self.distance.abs(), other.distance.abs());
+    a < b || (a == b && self.dot < other.dot)
+  }
+}
+
+// 点 p からセグメント上の最近点までの符号付き距離と、そのパラメータ t
+// 符号は進行方向の右側を正とする
+fn segment_distance(segment: &Segment, p: Point) -> (SignedDistance, f64) {
+  let candidates = match *segment {
+    Segment::Line(p0, p1) => {
+      let ab = p1 - p0;
+      let t = if ab.dot(ab) == 0.0 { 0.0 } else { (p - p0).dot(ab) / ab.dot(ab) };
+      vec![t]
+    }
+    Segment::Quad(p0, p1, p2) => {
+      // |B(t) - p|^2 の微分が 0 となる t を3次方程式で求める
+      let qa = p0 - p;
+      let ab = p1 - p0;
+      let br = p2 - p1 - ab;
+      let mut ts = solve_cubic(
+        br.dot(br),
+        3.0 * ab.dot(br),
+        2.0 * ab.dot(ab) + qa.dot(br),
+        qa.dot(ab),
+      );
+      ts.push(0.0);
+      ts.push(1.0);
+      ts
+    }
+    Segment::Cubic(..) => cubic_candidates(segment, p),
+  };
+
+  let mut best = SignedDistance::INFINITE;
+  let mut best_t = 0.0;
+  for t in candidates {
+    let t = t.clamp(0.0, 1.0);
+    let q = segment.point(t);
+    let direction = segment.direction(t);
+    let offset = q - p;
+    let distance = offset.length();
+    let sign = if direction.cross(offset) >= 0.0 { 1.0 } else { -1.0 };
+    let dot = direction.normalize().dot(offset.normalize()).abs();
+    let candidate = SignedDistance { distance: sign * distance, dot };
+    if candidate.closer_than(&best) {
+      best = candidate;
+      best_t = t;
+    }
+  }
+  (best, best_t)
+}
+
+const CUBIC_SEARCH_STARTS: usize = 8;
+const CUBIC_SEARCH_STEPS: usize = 8;
+
+// 3次ベジェは5次方程式になるため、等間隔の初期値からニュートン法で最近点を探す
+fn cubic_candidates(segment: &Segment, p: Point) -> Vec<f64> {
+  let Segment::Cubic(p0, p1, p2, p3) = *segment else {
+    return vec![0.0];
+  };
+  let ab = p1 - p0;
+  let br = p2 - p1 - ab;
+  let as_ = (p3 - p2) - (p2 - p1) - br;
+
+  let mut ts = vec![0.0, 1.0];
+  for i in 0..=CUBIC_SEARCH_STARTS {
+    let mut t = i as f64 / CUBIC_SEARCH_STARTS as f64;
+    for _ in 0..CUBIC_SEARCH_STEPS {
+      let qe = p0 - p + ab * (3.0 * t) + br * (3.0 * t * t) + as_ * (t * t * t);
+      let d1 = ab * 3.0 + br * (6.0 * t) + as_ * (3.0 * t * t);
+      let d2 = br * 6.0 + as_ * (6.0 * t);
+      let denominator = d1.dot(d1) + qe.dot(d2);
+      if denominator == 0.0 {
+        break;
+      }
+      t -= qe.dot(d1) / denominator;
+      if !(0.0..=1.0).contains(&t) {
+        break;
+      }
+    }
+    if (0.0..=1.0).contains(&t) {
+      ts.push(t);
+    }
+  }
+  ts
+}
+
+// 端点の外側では接線の延長線までの距離を使う（MSDFの角を保つため）
+fn pseudo_distance(segment: &Segment, p: Point, distance: SignedDistance, t: f64) -> f64 {
+  let (origin, direction, outside) = if t <= 0.0 {
+    let direction = segment.direction(0.0).normalize();
+    (segment.start(), direction, (p - segment.start()).dot(direction) < 0.0)
+  } else if t >= 1.0 {
+    let direction = segment.direction(1.0).normalize();
+    (segment.end(), direction, (p - segment.end()).dot(direction) > 0.0)
+  } else {
+    return distance.distance;
+  };
+  if outside {
+    let pseudo = direction.cross(origin - p);
+    if pseudo.abs() <= distance.distance.abs() {
+      return pseudo;
+    }
+  }
+  distance.distance
+}
+
+fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+  if a.abs() < 1e-14 {
+    if b.abs() < 1e-14 {
+      return vec![];
+    }
+    return vec![-c / b];
+  }
+  let discriminant = b * b - 4.0 * a * c;
+  if discriminant > 0.0 {
+    let s = discriminant.sqrt();
+    vec![(-b + s) / (2.0 * a), (-b - s) / (2.0 * a)]
+  } else if discriminant == 0.0 {
+    vec![-b / (2.0 * a)]
+  } else {
+    vec![]
+  }
+}
+
+// x^3 + a x^2 + b x + c = 0 の実数解
+fn solve_cubic_normed(a: f64, b: f64, c: f64) -> Vec<f64> {
+  let a2 = a * a;
+  let q = (a2 - 3.0 * b) / 9.0;
+  let r = (a * (2.0 * a2 - 9.0 * b) + 27.0 * c) / 54.0;
+  let r2 = r * r;
+  let q3 = q * q * q;
+  let a = a / 3.0;
+  if r2 < q3 {
+    let t = (r / q3.sqrt()).clamp(-1.0, 1.0).acos();
+    let q = -2.0 * q.sqrt();
+    vec![
+      q * (t / 3.0).cos() - a,
+      q * ((t + 2.0 * std::f64::consts::PI) / 3.0).cos() - a,
+      q * ((t - 2.0 * std::f64::consts::PI) / 3.0).cos() - a,
+    ]
+  } else {
+    let u = -r.signum() * (r.abs() + (r2 - q3).sqrt()).cbrt();
+    let v = if u == 0.0 { 0.0 } else { q / u };
+    let mut roots = vec![(u + v) - a];
+    if (0.5 * 3f64.sqrt() * (u - v)).abs() < 1e-14 {
+      roots.push(-0.5 * (u + v) - a);
+    }
+    roots
+  }
+}
+
+fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+  if a != 0.0 {
+    let bn = b / a;
+    if bn.abs() < 1e6 {
+      return solve_cubic_normed(bn, c / a, d / a);
+    }
+  }
+  solve_quadratic(b, c, d)
+}
+
+fn distance_to_byte(distance: f64, range: f64) -> u8 {
+  ((distance / range + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+const FLATTEN_TOLERANCE: f64 = 0.01;
+
+// 単一チャンネルの符号付き距離場。輪郭の内側が 128 より大きくなる
+pub fn generate_sdf(path: &Path, options: &SdfOptions) -> GlyphBitmap<Luma<u8>> {
+  let path = path.scale(options.scale);
+  let frame = Frame::new(&path, (options.range / 2.0).ceil() as i32);
+  let polylines = path.flatten(FLATTEN_TOLERANCE);
+  let mut image = ImageBuffer::from_pixel(frame.width, frame.height, Luma { data: [0] });
+
+  for (x, y, pixel) in image.enumerate_pixels_mut() {
+    let p = frame.center(x, y);
+    let distance = true_distance(&path, &polylines, p);
+    pixel.data[0] = distance_to_byte(distance, options.range);
+  }
+
+  GlyphBitmap { image, left: frame.x0, top: frame.y1 }
+}
+
+// 最近接エッジまでの距離に、非ゼロ巻き数規則で内外の符号を付ける
+fn true_distance(path: &Path, polylines: &[Vec<Point>], p: Point) -> f64 {
+  let distance = path.segments()
+    .map(|segment| segment_distance(segment, p).0.distance.abs())
+    .fold(f64::INFINITY, f64::min);
+  if winding_number(polylines, p) != 0 { distance } else { -distance }
+}
+
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const YELLOW: u8 = RED | GREEN;
+const MAGENTA: u8 = RED | BLUE;
+const CYAN: u8 = GREEN | BLUE;
+const WHITE: u8 = RED | GREEN | BLUE;
+
+struct ColoredSegment {
+  segment: Segment,
+  color: u8,
+}
+
+// 次の色に切り替える。banned と1色だけ重なる場合はそれを避ける
+fn switch_color(color: u8, seed: &mut u64, banned: u8) -> u8 {
+  let combined = color & banned;
+  if combined == RED || combined == GREEN || combined == BLUE {
+    return combined ^ WHITE;
+  }
+  if color == 0 || color == WHITE {
+    let start = [CYAN, MAGENTA, YELLOW];
+    let next = start[(*seed % 3) as usize];
+    *seed /= 3;
+    return next;
+  }
+  let shifted = (color as u32) << (1 + (*seed & 1));
+  *seed >>= 1;
+  ((shifted | shifted >> 3) as u8) & WHITE
+}
+
+fn is_corner(a: Point, b: Point, cross_threshold: f64) -> bool {
+  a.dot(b) <= 0.0 || a.cross(b).abs() > cross_threshold
+}
+
+fn symmetrical_trichotomy(position: usize, n: usize) -> usize {
+  (3.0 + 2.875 * position as f64 / (n - 1) as f64 - 1.4375 + 0.5) as usize - 2
+}
+
+fn split_in_thirds(segment: &Segment) -> [Segment; 3] {
+  let (first, rest) = segment.split(1.0 / 3.0);
+  let (second, third) = rest.split(0.5);
+  [first, second, third]
+}
+
+// msdfgen の edgeColoringSimple と同じ方針で、コーナーを挟むエッジが同じ色を共有しないよう塗り分ける
+fn color_edges(path: &Path, angle_threshold: f64) -> Vec<Vec<ColoredSegment>> {
+  let cross_threshold = angle_threshold.sin();
+  let mut seed: u64 = 0;
+  let mut result = Vec::new();
+
+  for contour in &path.contours {
+    if contour.is_empty() {
+      continue;
+    }
+    let n = contour.len();
+    let corners: Vec<usize> = (0..n)
+      .filter(|&i| {
+        let previous = contour[(i + n - 1) % n].direction(1.0).normalize();
+        let current = contour[i].direction(0.0).normalize();
+        is_corner(previous, current, cross_threshold)
+      })
+      .collect();
+
+    let colored = match corners.len() {
+      0 => contour.iter().map(|&segment| ColoredSegment { segment, color: WHITE }).collect(),
+      1 => {
+        // 涙型：コーナーが1つだけなら輪郭を3色に分ける。エッジが足りなければ3分割する
+        let first = switch_color(WHITE, &mut seed, 0);
+        let colors = [first, WHITE, switch_color(first, &mut seed, 0)];
+        let (segments, corner) = if n >= 3 {
+          (contour.clone(), corners[0])
+        } else {
+          (contour.iter().flat_map(split_in_thirds).collect::<Vec<_>>(), corners[0] * 3)
+        };
+        let m = segments.len();
+        (0..m)
+          .map(|i| ColoredSegment {
+            segment: segments[(corner + i) % m],
+            color: colors[symmetrical_trichotomy(i, m)],
+          })
+          .collect()
+      }
+      _ => {
+        let mut color = switch_color(WHITE, &mut seed, 0);
+        let initial = color;
+        let mut spline = 0;
+        let start = corners[0];
+        let mut colored = Vec::with_capacity(n);
+        for i in 0..n {
+          let index = (start + i) % n;
+          if spline + 1 < corners.len() && corners[spline + 1] == index {
+            spline += 1;
+            let banned = if spline == corners.len() - 1 { initial } else { 0 };
+            color = switch_color(color, &mut seed, banned);
+          }
+          colored.push(ColoredSegment { segment: contour[index], color });
+        }
+        colored
+      }
+    };
+    result.push(colored);
+  }
+  result
+}
+
+fn median(a: f64, b: f64, c: f64) -> f64 {
+  a.min(b).max(a.max(b).min(c))
+}
+
+// 多チャンネル符号付き距離場（MSDF）。3チャンネルの中央値が元の距離場に相当する
+pub fn generate_msdf(path: &Path, options: &SdfOptions) -> GlyphBitmap<Rgb<u8>> {
+  let path = path.scale(options.scale);
+  let frame = Frame::new(&path, (options.range / 2.0).ceil() as i32);
+  let polylines = path.flatten(FLATTEN_TOLERANCE);
+  // エッジの右側を内側とみなすため、反時計回りの輪郭が外側のフォントでは符号を反転する
+  let orientation = if signed_area(&polylines) > 0.0 { -1.0 } else { 1.0 };
+  let edges: Vec<ColoredSegment> = color_edges(&path, options.angle_threshold).into_iter().flatten().collect();
+  let mut image = ImageBuffer::from_pixel(frame.width, frame.height, Rgb { data: [0, 0, 0] });
+
+  for (x, y, pixel) in image.enumerate_pixels_mut() {
+    let p = frame.center(x, y);
+    let mut channels = [f64::NEG_INFINITY; 3];
+    for (channel, value) in channels.iter_mut().enumerate() {
+      let mask = 1 << channel;
+      let mut best = SignedDistance::INFINITE;
+      let mut best_edge = None;
+      for edge in edges.iter().filter(|edge| edge.color & mask != 0) {
+        let (distance, t) = segment_distance(&edge.segment, p);
+        if distance.closer_than(&best) {
+          best = distance;
+          best_edge = Some((edge, t));
+        }
+      }
+      if let Some((edge, t)) = best_edge {
+        *value = orientation * pseudo_distance(&edge.segment, p, best, t);
+      }
+    }
+
+    // 中央値の内外判定が巻き数と食い違う画素は、単一チャンネルの距離で置き換える
+    let inside = winding_number(&polylines, p) != 0;
+    if (median(channels[0], channels[1], channels[2]) > 0.0) != inside {
+      let distance = true_distance(&path, &polylines, p);
+      channels = [distance; 3];
+    }
+
+    pixel.data = channels.map(|d| distance_to_byte(d, options.range));
+  }
+
+  GlyphBitmap { image, left: frame.x0, top: frame.y1 }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f64::consts::{FRAC_PI_4, PI};
+
+  fn p(x: f64, y: f64) -> Point {
+    Point::new(x, y)
+  }
+
+  fn polygon(points: &[Point]) -> Path {
+    let mut path = Path::new();
+    path.contours.push((0..points.len()).map(|i| Segment::Line(points[i], points[(i + 1) % points.len()])).collect());
+    path
+  }
+
+  // TrueType と同じく外側の輪郭を時計回り（y軸上向き）にした正方形
+  fn square(x0: f64, y0: f64, size: f64) -> Path {
+    polygon(&[p(x0, y0), p(x0, y0 + size), p(x0 + size, y0 + size), p(x0 + size, y0)])
+  }
+
+  // 45度ずつ、接線の交点を制御点にした2次ベジェの円（時計回り）
+  fn quad_circle(center: Point, r: f64) -> Path {
+    let on = |i: usize| {
+      let a = -(i as f64) * FRAC_PI_4;
+      center + p(a.cos(), a.sin()) * r
+    };
+    let off = |i: usize| {
+      let a = -(i as f64 + 0.5) * FRAC_PI_4;
+      center + p(a.cos(), a.sin()) * (r / (FRAC_PI_4 / 2.0).cos())
+    };
+    let mut path = Path::new();
+    path.contours.push((0..8).map(|i| Segment::Quad(on(i), off(i), on(i + 1))).collect());
+    path
+  }
+
+  // 90度ずつの3次ベジェの円（時計回り）
+  fn cubic_circle(center: Point, r: f64) -> Path {
+    let k = 4.0 / 3.0 * (PI / 8.0).tan() * r;
+    let mut path = Path::new();
+    path.contours.push(
+      (0..4)
+        .map(|i| {
+          let (a0, a1) = (-(i as f64) * PI / 2.0, -(i as f64 + 1.0) * PI / 2.0);
+          let (d0, d1) = (p(a0.cos(), a0.sin()), p(a1.cos(), a1.sin()));
+          // 時計回りの接線は (sin, -cos)
+          let (t0, t1) = (p(a0.sin(), -a0.cos()), p(a1.sin(), -a1.cos()));
+          Segment::Cubic(center + d0 * r, center + d0 * r + t0 * k, center + d1 * r - t1 * k, center + d1 * r)
+        })
+        .collect(),
+    );
+    path
+  }
+
+  fn distance(path: &Path, point: Point) -> f64 {
+    true_distance(path, &path.flatten(FLATTEN_TOLERANCE), point)
+  }
+
+  fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!((actual - expected).abs() <= tolerance, "{} と {} の差が {} を超えています", actual, expected, tolerance);
+  }
+
+  #[test]
+  fn line_distance_is_perpendicular_inside_and_to_endpoint_outside() {
+    let line = Segment::Line(p(0.0, 0.0), p(10.0, 0.0));
+    let (d, t) = segment_distance(&line, p(4.0, -3.0));
+    // 進行方向の右側が正
+    assert_close(d.distance, 3.0, 1e-12);
+    assert_close(t, 0.4, 1e-12);
+    let (d, t) = segment_distance(&line, p(13.0, 4.0));
+    assert_close(d.distance, -5.0, 1e-12);
+    assert_eq!(t, 1.0);
+  }
+
+  #[test]
+  fn quad_distance_matches_parabola() {
+    // y = x^2（-1 ≤ x ≤ 1）
+    let parabola = Segment::Quad(p(-1.0, 1.0), p(0.0, -1.0), p(1.0, 1.0));
+    assert_close(segment_distance(&parabola, p(0.0, -1.0)).0.distance.abs(), 1.0, 1e-9);
+    // (0, 1.2) からの最近点は x^2 = 0.7（距離の2乗は 0.7 + 0.5^2）
+    assert_close(segment_distance(&parabola, p(0.0, 1.2)).0.distance.abs(), 0.95f64.sqrt(), 1e-9);
+    // 範囲外の点は端点までの距離
+    assert_close(segment_distance(&parabola, p(3.0, 1.0)).0.distance.abs(), 2.0, 1e-9);
+  }
+
+  #[test]
+  fn cubic_distance_matches_straight_cubic_and_circle_arc() {
+    let straight = Segment::Cubic(p(0.0, 0.0), p(3.0, 0.0), p(7.0, 0.0), p(10.0, 0.0));
+    assert_close(segment_distance(&straight, p(2.5, 6.0)).0.distance.abs(), 6.0, 1e-9);
+    assert_close(segment_distance(&straight, p(-3.0, 4.0)).0.distance.abs(), 5.0, 1e-9);
+    // 3次ベジェの円弧の半径の誤差は 0.03% 未満
+    let arc = cubic_circle(p(0.0, 0.0), 100.0).contours[0][0];
+    for degrees in [-80.0f64, -45.0, -10.0] {
+      let a = degrees.to_radians();
+      assert_close(segment_distance(&arc, p(a.cos(), a.sin()) * 40.0).0.distance.abs(), 60.0, 0.03);
+    }
+  }
+
+  #[test]
+  fn square_distance_is_signed_by_winding() {
+    let path = square(0.0, 0.0, 10.0);
+    assert_close(distance(&path, p(5.0, 5.0)), 5.0, 1e-9);
+    assert_close(distance(&path, p(2.0, 7.0)), 2.0, 1e-9);
+    assert_close(distance(&path, p(15.0, 5.0)), -5.0, 1e-9);
+    // 角の外側は角までのユークリッド距離
+    assert_close(distance(&path, p(13.0, 14.0)), -5.0, 1e-9);
+    assert_close(distance(&path, p(-3.0, -4.0)), -5.0, 1e-9);
+  }
+
+  #[test]
+  fn circle_distance_matches_analytic_for_quads_and_cubics() {
+    let center = p(20.0, 20.0);
+    let r = 10.0;
+    // 2次ベジェの8分割は半径が最大 0.31% ずれる。3次ベジェは 0.03%
+    for (path, tolerance) in [(quad_circle(center, r), 0.04), (cubic_circle(center, r), 0.005)] {
+      for i in 0..16 {
+        let a = i as f64 * PI / 8.0 + 0.1;
+        let direction = p(a.cos(), a.sin());
+        for offset in [-6.0, -1.5, 0.5, 4.0] {
+          let point = center + direction * (r + offset);
+          assert_close(distance(&path, point), -offset, tolerance);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn sdf_pixels_encode_distance_around_the_edge() {
+    let options = SdfOptions { scale: 1.0, range: 4.0, ..Default::default() };
+    let bitmap = generate_sdf(&square(0.0, 0.0, 8.0), &options);
+    assert_eq!((bitmap.left, bitmap.top), (-2, 10));
+    assert_eq!((bitmap.image.width(), bitmap.image.height()), (12, 12));
+    // 画素 (x, y) の中心は (left + x + 0.5, top - y - 0.5)
+    let value = |x: f64, y: f64| bitmap.image.get_pixel((x - 0.5 - bitmap.left as f64) as u32, (bitmap.top as f64 - y - 0.5) as u32).data[0];
+    assert_eq!(value(4.5, 4.5), 255);
+    assert_eq!(value(0.5, 4.5), distance_to_byte(0.5, 4.0));
+    assert_eq!(value(-0.5, 4.5), distance_to_byte(-0.5, 4.0));
+    assert_eq!(value(-1.5, 4.5), distance_to_byte(-1.5, 4.0));
+    assert_eq!(value(8.5, 8.5), distance_to_byte(-(0.5f64.hypot(0.5)), 4.0));
+  }
+
+  #[test]
+  fn edge_coloring_separates_corners() {
+    let colored = color_edges(&square(0.0, 0.0, 10.0), 3.0);
+    let colors: Vec<u8> = colored[0].iter().map(|edge| edge.color).collect();
+    assert_eq!(colors.len(), 4);
+    for i in 0..4 {
+      let (a, b) = (colors[i], colors[(i + 1) % 4]);
+      // 角を挟む2辺は2色ずつで、共有するのは1色だけ
+      assert_eq!(a.count_ones(), 2);
+      assert_ne!(a, b);
+      assert_eq!((a & b).count_ones(), 1);
+    }
+
+    // 角のない輪郭は白（全チャンネル）
+    assert!(color_edges(&cubic_circle(p(0.0, 0.0), 10.0), 3.0)[0].iter().all(|edge| edge.color == WHITE));
+
+    // 角が1つの涙型は3分割して両端の色を変える
+    let mut teardrop = Path::new();
+    teardrop.contours.push(vec![Segment::Cubic(p(0.0, 0.0), p(-10.0, 15.0), p(10.0, 15.0), p(0.0, 0.0))]);
+    let colors: Vec<u8> = color_edges(&teardrop, 3.0)[0].iter().map(|edge| edge.color).collect();
+    assert_eq!(colors.len(), 3);
+    assert_eq!(colors[1], WHITE);
+    assert_ne!(colors[0], colors[2]);
+    assert_eq!((colors[0] & colors[2]).count_ones(), 1);
+  }
+
+  #[test]
+  fn msdf_median_keeps_square_corners_sharp() {
+    let options = SdfOptions { scale: 1.0, range: 2.0, ..Default::default() };
+    let bitmap = generate_msdf(&square(0.0, 0.0, 4.0), &options);
+    assert_eq!((bitmap.left, bitmap.top, bitmap.image.width(), bitmap.image.height()), (-1, 5, 6, 6));
+    // 正方形の中央値は、各辺への擬似距離のうち最も外側のもの（チェビシェフ距離）になる
+    let reference: Vec<Vec<u8>> = (0..6)
+      .map(|y| {
+        (0..6)
+          .map(|x| {
+            let (px, py) = (-1.0 + x as f64 + 0.5, 5.0 - y as f64 - 0.5);
+            let distance = [px, 4.0 - px, py, 4.0 - py].into_iter().fold(f64::INFINITY, f64::min);
+            distance_to_byte(distance, 2.0)
+          })
+          .collect()
+      })
+      .collect();
+    let medians: Vec<Vec<u8>> = (0..6)
+      .map(|y| {
+        (0..6)
+          .map(|x| {
+            let [r, g, b] = bitmap.image.get_pixel(x, y).data;
+            median(r as f64, g as f64, b as f64) as u8
+          })
+          .collect()
+      })
+      .collect();
+    assert_eq!(medians, reference);
+    // 角の外側の画素は、単一チャンネルの SDF（ユークリッド距離）より輪郭に近い値を持つ
+    let corner = generate_sdf(&square(0.0, 0.0, 4.0), &options).image.get_pixel(5, 0).data[0];
+    assert!(medians[0][5] > corner);
+    assert!(medians[0][5] < 128);
+  }
+}
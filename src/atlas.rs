@@ -0,0 +1,184 @@
+use font::Sfnt;
+use rasterizer::atlas::{pack, AtlasEntry, AtlasFontInfo, AtlasOptions};
+use rasterizer::{generate_msdf, generate_sdf, rasterize, GlyphBitmap, Luma, Path, Pixel, Rgb, SdfOptions};
+
+enum Mode {
+  Coverage,
+  Sdf,
+  Msdf,
+}
+
+enum Format {
+  Json,
+  BmFont,
+}
+
+struct AtlasArgs {
+  font_path: String,
+  chars: Vec<char>,
+  size: u32,
+  mode: Mode,
+  sdf_range: f64,
+  options: AtlasOptions,
+  format: Format,
+  output: String,
+}
+
+const USAGE: &str = "使い方: get_a_glyph atlas <font-file> [--chars <文字列>] [--range U+0020-U+007E] [--chars-file <file>] \
+[--size <px>] [--mode coverage|sdf|msdf] [--sdf-range <px>] [--width <px>] [--spacing <px>] [--format json|fnt] [-o <出力名>]";
+
+// "U+0020-U+007E" や "0x3042" のようなコードポイント範囲を展開する
+pub fn parse_range(range: &str) -> Result<Vec<char>, String> {
+  let parse_code_point = |s: &str| {
+    let hex = s.trim().trim_start_matches("U+").trim_start_matches("u+").trim_start_matches("0x");
+    u32::from_str_radix(hex, 16).map_err(|_| format!("コードポイントを解釈できません: {}", s))
+  };
+  let (start, end) = match range.split_once('-') {
+    Some((start, end)) => (parse_code_point(start)?, parse_code_point(end)?),
+    None => {
+      let c = parse_code_point(range)?;
+      (c, c)
+    }
+  };
+  if start > end {
+    return Err(format!("範囲の始まりが終わりより後ろです: {}", range));
+  }
+  Ok((start..=end).filter_map(char::from_u32).collect())
+}
+
+fn parse_args(args: &[String]) -> Result<AtlasArgs, String> {
+  let mut font_path = None;
+  let mut chars = Vec::new();
+  let mut size = 32;
+  let mut mode = Mode::Coverage;
+  let mut sdf_range = 4.0;
+  let mut options = AtlasOptions::default();
+  let mut format = Format::Json;
+  let mut output = "atlas".to_string();
+
+  let mut iter = args.iter();
+  while let Some(arg) = iter.next() {
+    let mut value = || iter.next().ok_or(format!("{} に値を指定してください", arg));
+    match arg.as_str() {
+      "--chars" => chars.extend(value()?.chars()),
+      "--range" => chars.extend(parse_range(value()?)?),
+      "--chars-file" => {
+        let path = value()?;
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        chars.extend(text.chars().filter(|&c| c != '\n' && c != '\r'));
+      }
+      "--size" => size = value()?.parse().map_err(|_| "--size には整数を指定してください")?,
+      "--mode" => mode = match value()?.as_str() {
+        "coverage" => Mode::Coverage,
+        "sdf" => Mode::Sdf,
+        "msdf" => Mode::Msdf,
+        other => return Err(format!("不明なモードです: {}", other)),
+      },
+      "--sdf-range" => sdf_range = value()?.parse().map_err(|_| "--sdf-range には数値を指定してください")?,
+      "--width" => options.width = value()?.parse().map_err(|_| "--width には整数を指定してください")?,
+      "--spacing" => options.spacing = value()?.parse().map_err(|_| "--spacing には整数を指定してください")?,
+      "--format" => format = match value()?.as_str() {
+        "json" => Format::Json,
+        "fnt" => Format::BmFont,
+        other => return Err(format!("不明な形式です: {}", other)),
+      },
+      "-o" | "--output" => output = value()?.clone(),
+      _ if font_path.is_none() && !arg.starts_with('-') => font_path = Some(arg.clone()),
+      _ => return Err(format!("不明な引数です: {}", arg)),
+    }
+  }
+
+  // 重複を除く（最初に現れた順を保つ）
+  let mut seen = std::collections::HashSet::new();
+  chars.retain(|c| seen.insert(*c));
+  if chars.is_empty() {
+    return Err("--chars, --range, --chars-file のいずれかで文字を指定してください".to_string());
+  }
+
+  Ok(AtlasArgs {
+    font_path: font_path.ok_or("フォントファイルのパスを引数として指定してください")?,
+    chars,
+    size,
+    mode,
+    sdf_range,
+    options,
+    format,
+    output,
+  })
+}
+
+fn build<P: Pixel>(
+  args: &AtlasArgs,
+  font: &Sfnt,
+  font_data: &[u8],
+  background: P,
+  render: impl Fn(&Path) -> GlyphBitmap<P>,
+) -> Result<(), String> {
+  let head_table = font.get_head_table(font_data)?;
+  let hhea_table = font.get_hhea_table(font_data)?;
+  let hmtx_table = font.get_hmtx_table(font_data)?;
+  let glyf_table = font.get_glyf_table(font_data)?;
+  let scale = args.size as f64 / head_table.units_per_em as f64;
+
+  let mut entries = Vec::new();
+  for &c in &args.chars {
+    let glyph_id = font.get_glyph_id(&c.to_string(), font_data)?;
+    let mut path = Path::new();
+    if let Err(e) = glyf_table.draw_glyph(glyph_id as usize, &mut path) {
+      eprintln!("U+{:04X} をスキップします: {}", c as u32, e);
+      continue;
+    }
+    let advance = hmtx_table.get_metric(glyph_id).map(|m| m.advance_width).unwrap_or(0);
+    entries.push(AtlasEntry {
+      codepoint: c as u32,
+      glyph_id,
+      advance: advance as f64 * scale,
+      bitmap: render(&path),
+    });
+  }
+
+  let atlas = pack(entries, &args.options, background)?;
+
+  let image_file = format!("{}.png", args.output);
+  atlas.image.save(&image_file)?;
+
+  let face = std::path::Path::new(&args.font_path)
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_default();
+  let info = AtlasFontInfo {
+    face,
+    size: args.size,
+    line_height: (hhea_table.ascender as f64 - hhea_table.descender as f64 + hhea_table.line_gap as f64) * scale,
+    base: hhea_table.ascender as f64 * scale,
+  };
+  // メタデータからは画像をファイル名だけで参照する
+  let image_name = std::path::Path::new(&image_file)
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or(image_file.clone());
+  let (metadata, metadata_file) = match args.format {
+    Format::Json => (atlas.to_json(&info, &image_name), format!("{}.json", args.output)),
+    Format::BmFont => (atlas.to_bmfont(&info, &image_name), format!("{}.fnt", args.output)),
+  };
+  std::fs::write(&metadata_file, metadata).map_err(|e| format!("{}: {}", metadata_file, e))?;
+
+  eprintln!("{} と {} を書き出しました（{}グリフ）", image_file, metadata_file, atlas.glyphs.len());
+  Ok(())
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+  let args = parse_args(args).map_err(|e| format!("{}\n{}", e, USAGE))?;
+  let font_data = std::fs::read(&args.font_path).map_err(|_| "フォントファイルの読み込みに失敗しました")?;
+  let font: Sfnt = font::Parse::from_bytes(&font_data)?;
+
+  let head_table = font.get_head_table(&font_data)?;
+  let scale = args.size as f64 / head_table.units_per_em as f64;
+  let sdf_options = SdfOptions { scale, range: args.sdf_range, ..Default::default() };
+
+  match args.mode {
+    Mode::Coverage => build(&args, &font, &font_data, Luma { data: [0] }, |path| rasterize(path, scale)),
+    Mode::Sdf => build(&args, &font, &font_data, Luma { data: [0] }, |path| generate_sdf(path, &sdf_options)),
+    Mode::Msdf => build(&args, &font, &font_data, Rgb { data: [0, 0, 0] }, |path| generate_msdf(path, &sdf_options)),
+  }
+}
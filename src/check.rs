@@ -0,0 +1,102 @@
+use crate::atlas::parse_range;
+use font::analysis::{Direction, OutlineReport};
+use font::truetype::outline::Outline;
+use font::Sfnt;
+
+struct CheckArgs {
+  font_path: String,
+  // 空なら全グリフを調べる
+  chars: Vec<char>,
+  verbose: bool,
+}
+
+const USAGE: &str = "使い方: get_a_glyph check <font-file> [--chars <文字列>] [--range U+0020-U+007E] [--verbose]";
+
+fn parse_args(args: &[String]) -> Result<CheckArgs, String> {
+  let mut font_path = None;
+  let mut chars = Vec::new();
+  let mut verbose = false;
+
+  let mut iter = args.iter();
+  while let Some(arg) = iter.next() {
+    let mut value = || iter.next().ok_or(format!("{} に値を指定してください", arg));
+    match arg.as_str() {
+      "--chars" => chars.extend(value()?.chars()),
+      "--range" => chars.extend(parse_range(value()?)?),
+      "--verbose" => verbose = true,
+      _ if font_path.is_none() && !arg.starts_with('-') => font_path = Some(arg.clone()),
+      _ => return Err(format!("不明な引数です: {}", arg)),
+    }
+  }
+
+  Ok(CheckArgs {
+    font_path: font_path.ok_or("フォントファイルのパスを引数として指定してください")?,
+    chars,
+    verbose,
+  })
+}
+
+fn print_report(label: &str, report: &OutlineReport, verbose: bool) {
+  let issues = report.issues();
+  if issues.is_empty() && !verbose {
+    return;
+  }
+  println!("{}:", label);
+  if verbose {
+    for (index, contour) in report.contours.iter().enumerate() {
+      let direction = match contour.direction {
+        Direction::Clockwise => "時計回り",
+        Direction::CounterClockwise => "反時計回り",
+        Direction::Degenerate => "向きなし",
+      };
+      let parent = contour.parent.map(|parent| format!(" 親 輪郭{}", parent)).unwrap_or_default();
+      println!("  輪郭{}: {} 面積 {:.1} 深さ {}{}", index, direction, contour.area, contour.depth, parent);
+    }
+  }
+  for issue in issues {
+    println!("  {}", issue);
+  }
+}
+
+// 問題のあるグリフがあれば終了コードで知らせる
+pub fn run(args: &[String]) -> Result<(), String> {
+  let args = parse_args(args).map_err(|e| format!("{}\n{}", e, USAGE))?;
+  let font_data = std::fs::read(&args.font_path).map_err(|_| "フォントファイルの読み込みに失敗しました")?;
+  let font: Sfnt = font::Parse::from_bytes(&font_data)?;
+  let glyf_table = font.get_glyf_table(&font_data)?;
+
+  let targets: Vec<(u16, String)> = if args.chars.is_empty() {
+    let num_glyphs = font.get_maxp_table(&font_data)?.num_glyphs;
+    (0..num_glyphs).map(|glyph_id| (glyph_id, format!("グリフ{}", glyph_id))).collect()
+  } else {
+    args.chars.iter()
+      .map(|&c| Ok((font.get_glyph_id(&c.to_string(), &font_data)?, format!("U+{:04X} '{}'", c as u32, c))))
+      .collect::<Result<_, String>>()?
+  };
+
+  let (mut checked, mut skipped, mut failed) = (0, 0, 0);
+  for (glyph_id, label) in &targets {
+    let glyph = match glyf_table.get_glyph_data(*glyph_id as usize) {
+      Ok(glyph) => glyph,
+      Err(e) => {
+        if args.verbose {
+          eprintln!("{} をスキップします: {}", label, e);
+        }
+        skipped += 1;
+        continue;
+      }
+    };
+    let report = Outline::from_simple_glyph(&glyph).analyze();
+    print_report(label, &report, args.verbose);
+    checked += 1;
+    if !report.is_clean() {
+      failed += 1;
+    }
+  }
+
+  eprintln!("{}グリフを検査しました（{}グリフをスキップ）", checked, skipped);
+  if failed > 0 {
+    return Err(format!("問題のあるグリフが{}個あります", failed));
+  }
+  Ok(())
+}
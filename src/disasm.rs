@@ -0,0 +1,119 @@
+use font::hinting::disasm::{assemble, listing};
+use font::Sfnt;
+
+struct DisasmArgs {
+  font_path: Option<String>,
+  glyph_name: Option<String>,
+  glyph_id: Option<u16>,
+  fpgm: bool,
+  prep: bool,
+  // 出力したリストを組み立て直して元の命令列と一致するか確かめる
+  verify: bool,
+  // 指定があればフォントの代わりにこのリストを命令列に組み立てる
+  assemble: Option<String>,
+  // リスト（--assemble なら命令列）の出力先
+  output: Option<String>,
+}
+
+const USAGE: &str = "使い方: get_a_glyph disasm <font-file> [<character>|--glyph-id <id>] [--fpgm] [--prep] [--verify] [-o <listing.txt>]\n\
+       get_a_glyph disasm --assemble <listing.txt> [-o <output.bin>]";
+
+fn parse_args(args: &[String]) -> Result<DisasmArgs, String> {
+  let mut positional = Vec::new();
+  let mut glyph_id = None;
+  let mut fpgm = false;
+  let mut prep = false;
+  let mut verify = false;
+  let mut assemble = None;
+  let mut output = None;
+
+  let mut iter = args.iter();
+  while let Some(arg) = iter.next() {
+    let mut value = || iter.next().ok_or(format!("{} に値を指定してください", arg));
+    match arg.as_str() {
+      "--glyph-id" => glyph_id = Some(value()?.parse().map_err(|_| "--glyph-id には整数を指定してください")?),
+      "--fpgm" => fpgm = true,
+      "--prep" => prep = true,
+      "--verify" => verify = true,
+      "--assemble" => assemble = Some(value()?.clone()),
+      "-o" | "--output" => output = Some(value()?.clone()),
+      _ if !arg.starts_with('-') => positional.push(arg.clone()),
+      _ => return Err(format!("不明な引数です: {}", arg)),
+    }
+  }
+
+  let mut positional = positional.into_iter();
+  let font_path = positional.next();
+  if font_path.is_none() && assemble.is_none() {
+    return Err("フォントファイルのパスを引数として指定してください".to_string());
+  }
+  Ok(DisasmArgs { font_path, glyph_name: positional.next(), glyph_id, fpgm, prep, verify, assemble, output })
+}
+
+// テキストのリストを命令列に組み立てる。出力先がなければ16進で表示する
+fn run_assemble(source: &str, output: Option<&str>) -> Result<(), String> {
+  let text = std::fs::read_to_string(source).map_err(|e| format!("{} を読み込めません: {}", source, e))?;
+  let bytecode = assemble(&text)?;
+  match output {
+    Some(output) => std::fs::write(output, &bytecode).map_err(|e| e.to_string()),
+    None => {
+      for line in bytecode.chunks(16) {
+        println!("{}", line.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" "));
+      }
+      Ok(())
+    }
+  }
+}
+
+// 命令列のリストを out に足す。verify なら組み立て直して元と一致するか確かめ、結果を標準エラーに出す
+fn write_program(out: &mut String, label: &str, bytecode: &[u8], verify: bool) -> Result<(), String> {
+  out.push_str(&format!("== {} ({}バイト) ==\n", label, bytecode.len()));
+  let text = listing(bytecode).map_err(|e| format!("{}: {}", label, e))?;
+  out.push_str(&text);
+  if verify {
+    if assemble(&text)? != bytecode {
+      return Err(format!("{}: 組み立て直した命令列が元と一致しません", label));
+    }
+    eprintln!("{}: 組み立て直した命令列が元と一致しました", label);
+  }
+  Ok(())
+}
+
+// グリフを指定しなければ fpgm と prep を出力する。出力先があればリストをそこに書く
+pub fn run(args: &[String]) -> Result<(), String> {
+  let args = parse_args(args).map_err(|e| format!("{}\n{}", e, USAGE))?;
+  if let Some(source) = &args.assemble {
+    return run_assemble(source, args.output.as_deref());
+  }
+  let font_path = args.font_path.as_deref().unwrap_or_default();
+  let font_data = std::fs::read(font_path).map_err(|_| "フォントファイルの読み込みに失敗しました")?;
+  let font: Sfnt = font::Parse::from_bytes(&font_data)?;
+
+  let glyph = match (&args.glyph_name, args.glyph_id) {
+    (_, Some(glyph_id)) => Some((format!("グリフ{}", glyph_id), font.get_glyph_data_by_id(glyph_id, &font_data)?)),
+    (Some(name), None) => Some((format!("'{}'", name), font.get_glyph_data(name, &font_data)?)),
+    (None, None) => None,
+  };
+  let (fpgm, prep) = if glyph.is_none() && !args.fpgm && !args.prep { (true, true) } else { (args.fpgm, args.prep) };
+  let mut out = String::new();
+  for (enabled, tag) in [(fpgm, "fpgm"), (prep, "prep")] {
+    if !enabled {
+      continue;
+    }
+    if font.has_table(tag) {
+      write_program(&mut out, tag, &font.read_table(tag, &font_data)?, args.verify)?;
+    } else {
+      out.push_str(&format!("== {} はありません ==\n", tag));
+    }
+  }
+  if let Some((label, glyph)) = glyph {
+    write_program(&mut out, &label, &glyph.instructions, args.verify)?;
+  }
+  match &args.output {
+    Some(output) => std::fs::write(output, out).map_err(|e| format!("{} に書き込めません: {}", output, e)),
+    None => {
+      print!("{}", out);
+      Ok(())
+    }
+  }
+}
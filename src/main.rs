@@ -1,32 +1,46 @@
 extern crate font;
 extern crate rasterizer;
 
-use font::Sfnt;
-use font::truetype::outline::simple_glyph_to_svg;
+mod atlas;
+mod check;
+mod disasm;
+mod metrics;
+mod palette;
+mod render;
+mod style;
+mod svg;
+mod trace;
+
+// サブコマンドの実装
+type Subcommand = fn(&[String]) -> Result<(), String>;
 //use rasterizer::{Luma, ImageBuffer};
 
 fn main() {
     // フォントファイルを引数で受け取る
     let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        eprintln!("フォントファイルのパスを引数として指定してください");
-        std::process::exit(1);
+
+    // サブコマンド
+    let subcommand: Option<Subcommand> = match args.get(1).map(|s| s.as_str()) {
+        Some("atlas") => Some(atlas::run),
+        Some("check") => Some(check::run),
+        Some("disasm") => Some(disasm::run),
+        Some("metrics") => Some(metrics::run),
+        Some("render") => Some(render::run),
+        Some("svg") => Some(svg::run),
+        Some("trace") => Some(trace::run),
+        _ => None,
+    };
+    if let Some(run) = subcommand {
+        if let Err(e) = run(&args[2..]) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
     }
-    let font_path = &args[1];
-    let font_data = std::fs::read(font_path).expect("フォントファイルの読み込みに失敗しました");
-    let font: Sfnt = font::Parse::from_bytes(&font_data).expect("Type 1フォントのパースに失敗しました");
 
-    // グリフのアウトラインデータを取得
-    if args.len() < 3 {
-        eprintln!("グリフ名を引数として指定してください");
+    // サブコマンドがなければ SVG を出力する
+    if let Err(e) = svg::run(&args[1..]) {
+        eprintln!("{}", e);
         std::process::exit(1);
     }
-    let glyph_name = &args[2];
-    let glyph_data = font.get_glyph_data(glyph_name, &font_data).expect("グリフデータの取得に失敗しました");
-
-    let svg_path = simple_glyph_to_svg(&glyph_data);
-    println!("{}", svg_path);
-
-    // TrueTypeフォントの場合
-
 }
@@ -0,0 +1,104 @@
+use crate::atlas::parse_range;
+use font::json::escape_json;
+use font::measure::{FontColour, OutlineMetrics};
+use font::Sfnt;
+
+struct MetricsArgs {
+  font_path: String,
+  chars: Vec<char>,
+  output: Option<String>,
+}
+
+const USAGE: &str = "使い方: get_a_glyph metrics <font-file> [--chars <文字列>] [--range U+0021-U+007E] [-o <output.json>]";
+
+fn parse_args(args: &[String]) -> Result<MetricsArgs, String> {
+  let mut font_path = None;
+  let mut chars = Vec::new();
+  let mut output = None;
+
+  let mut iter = args.iter();
+  while let Some(arg) = iter.next() {
+    let mut value = || iter.next().ok_or(format!("{} に値を指定してください", arg));
+    match arg.as_str() {
+      "--chars" => chars.extend(value()?.chars()),
+      "--range" => chars.extend(parse_range(value()?)?),
+      "-o" | "--output" => output = Some(value()?.clone()),
+      _ if font_path.is_none() && !arg.starts_with('-') => font_path = Some(arg.clone()),
+      _ => return Err(format!("不明な引数です: {}", arg)),
+    }
+  }
+
+  // 文字の指定がなければ ASCII の図形文字を測る
+  if chars.is_empty() {
+    chars = parse_range("U+0021-U+007E")?;
+  }
+  let mut seen = std::collections::HashSet::new();
+  chars.retain(|c| seen.insert(*c));
+
+  Ok(MetricsArgs {
+    font_path: font_path.ok_or("フォントファイルのパスを引数として指定してください")?,
+    chars,
+    output,
+  })
+}
+
+fn number(value: Option<f64>) -> String {
+  value.map(|v| format!("{:.2}", v)).unwrap_or("null".to_string())
+}
+
+fn to_json(glyphs: &[(char, u16, OutlineMetrics, f64)], colour: &FontColour, units_per_em: u16, height: f64) -> String {
+  let entries: Vec<String> = glyphs.iter()
+    .map(|(c, glyph_id, metrics, advance)| {
+      let centroid = metrics.centroid
+        .map(|(x, y)| format!("[{:.2}, {:.2}]", x, y))
+        .unwrap_or("null".to_string());
+      format!(
+        "    {{\"char\": \"{}\", \"codepoint\": {}, \"glyph_id\": {}, \"advance\": {}, \"area\": {:.2}, \"perimeter\": {:.2}, \"centroid\": {}, \"vertical_stem\": {}, \"horizontal_stem\": {}, \"darkness\": {:.4}}}",
+        escape_json(&c.to_string()), *c as u32, glyph_id, advance, metrics.area, metrics.perimeter, centroid,
+        number(metrics.vertical_stem), number(metrics.horizontal_stem), metrics.darkness(*advance, height)
+      )
+    })
+    .collect();
+  format!(
+    "{{\n  \"units_per_em\": {},\n  \"height\": {},\n  \"summary\": {{\"glyphs\": {}, \"average_darkness\": {:.4}, \"colour\": {:.4}, \"average_vertical_stem\": {}, \"average_horizontal_stem\": {}}},\n  \"glyphs\": [\n{}\n  ]\n}}\n",
+    units_per_em, height, colour.glyphs, colour.average_darkness, colour.colour,
+    number(colour.average_vertical_stem), number(colour.average_horizontal_stem), entries.join(",\n")
+  )
+}
+
+// 濃さは送り幅 × (ascender - descender) の枠に対する割合
+pub fn run(args: &[String]) -> Result<(), String> {
+  let args = parse_args(args).map_err(|e| format!("{}\n{}", e, USAGE))?;
+  let font_data = std::fs::read(&args.font_path).map_err(|_| "フォントファイルの読み込みに失敗しました")?;
+  let font: Sfnt = font::Parse::from_bytes(&font_data)?;
+
+  let head_table = font.get_head_table(&font_data)?;
+  let hhea_table = font.get_hhea_table(&font_data)?;
+  let hmtx_table = font.get_hmtx_table(&font_data)?;
+  let height = hhea_table.ascender as f64 - hhea_table.descender as f64;
+
+  let mut glyphs = Vec::new();
+  for &c in &args.chars {
+    let glyph_id = font.get_glyph_id(&c.to_string(), &font_data)?;
+    let metrics = match font.get_outline_metrics(glyph_id, &font_data) {
+      Ok(metrics) => metrics,
+      Err(e) => {
+        eprintln!("U+{:04X} をスキップします: {}", c as u32, e);
+        continue;
+      }
+    };
+    let advance = hmtx_table.get_metric(glyph_id).map(|m| m.advance_width).unwrap_or(0) as f64;
+    glyphs.push((c, glyph_id, metrics, advance));
+  }
+
+  let summary: Vec<(OutlineMetrics, f64)> = glyphs.iter().map(|&(_, _, metrics, advance)| (metrics, advance)).collect();
+  let colour = FontColour::new(&summary, height);
+  let json = to_json(&glyphs, &colour, head_table.units_per_em, height);
+  match &args.output {
+    Some(path) => std::fs::write(path, json).map_err(|e| format!("{}: {}", path, e)),
+    None => {
+      print!("{}", json);
+      Ok(())
+    }
+  }
+}
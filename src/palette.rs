@@ -0,0 +1,55 @@
+use font::tables::cpal::{USABLE_WITH_DARK_BACKGROUND, USABLE_WITH_LIGHT_BACKGROUND};
+use font::Sfnt;
+
+// CPAL のパレットの一覧。名前は name テーブルから引く
+fn describe_palettes(font: &Sfnt, font_data: &[u8]) -> String {
+  let Ok(cpal_table) = font.get_cpal_table(font_data) else {
+    return "CPAL テーブルがありません".to_string();
+  };
+  let name_table = font.get_name_table(font_data).ok();
+  let mut lines = vec![format!("パレットは{}個あります:", cpal_table.palettes.len())];
+  for (index, palette) in cpal_table.palettes.iter().enumerate() {
+    let label = palette.label
+      .and_then(|id| name_table.as_ref()?.get(id).map(|name| format!(" \"{}\"", name)))
+      .unwrap_or_default();
+    let usage = match (palette.palette_type & USABLE_WITH_LIGHT_BACKGROUND != 0, palette.palette_type & USABLE_WITH_DARK_BACKGROUND != 0) {
+      (true, true) => " (明るい背景・暗い背景向け)",
+      (true, false) => " (明るい背景向け)",
+      (false, true) => " (暗い背景向け)",
+      (false, false) => "",
+    };
+    lines.push(format!("  {}{}{}: {}色", index, label, usage, palette.colors.len()));
+  }
+  lines.join("\n")
+}
+
+// --palette の番号がフォントのパレットの数を超えていれば、パレットの一覧をつけてエラーにする
+pub fn check_palette(font: &Sfnt, font_data: &[u8], palette: Option<usize>) -> Result<(), String> {
+  let (Some(palette), Ok(cpal_table)) = (palette, font.get_cpal_table(font_data)) else {
+    return Ok(());
+  };
+  if palette < cpal_table.palettes.len() {
+    return Ok(());
+  }
+  Err(format!("パレット{}はありません\n{}", palette, describe_palettes(font, font_data)))
+}
+
+// カラーグリフでないときに --palette を指定されたら知らせる
+pub fn warn_unused(palette: Option<usize>) {
+  if palette.is_some() {
+    eprintln!("警告: カラーグリフではないので --palette は無視します");
+  }
+}
+
+// --coords の値。カンマ区切りの正規化した軸の座標（-1.0〜1.0）
+pub fn parse_coords(value: &str) -> Result<Vec<f32>, String> {
+  value.split(',')
+    .map(|v| {
+      let c: f32 = v.trim().parse().map_err(|_| format!("--coords には -1〜1 の数をカンマ区切りで指定してください: {}", value))?;
+      if !(-1.0..=1.0).contains(&c) {
+        return Err(format!("--coords の値は -1〜1 で指定してください: {}", v));
+      }
+      Ok(c)
+    })
+    .collect()
+}
@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use crate::palette::{check_palette, parse_coords, warn_unused};
+use crate::style::OutlineEffects;
+use font::bitmap::{BitmapGlyph, ImageFormat};
+use font::hinting::{HintedGlyph, HintingTarget};
+use font::tables::colr::ColorLayer;
+use font::tables::cpal::Color;
+use font::tables::paint::{ClipBox, Paint};
+use font::Sfnt;
+use rasterizer::lcd::{DEFAULT_FILTER, LIGHT_FILTER, NO_FILTER};
+use rasterizer::{
+  rasterize, rasterize_bitmap, rasterize_layers, rasterize_lcd, rasterize_mono, rasterize_paint, rasterize_with, AutoHinter, GammaOptions, LcdOptions, MonoOptions, Path,
+  RasterOptions, StemDarkening, SubpixelLayout, SubpixelOrder,
+};
+
+struct RenderArgs {
+  font_path: String,
+  glyph_name: String,
+  size: u32,
+  lcd: Option<LcdOptions>,
+  mono: Option<MonoOptions>,
+  scan_type: Option<u16>,
+  scan_control: Option<u16>,
+  hinting: bool,
+  autohint: bool,
+  gasp: bool,
+  palette: Option<usize>,
+  // COLR バージョン 1 の可変の Paint に当てはめる座標
+  coords: Vec<f32>,
+  // 埋め込みビットマップの PNG を拡大縮小せずにそのまま書き出す
+  embedded_png: bool,
+  gamma: GammaOptions,
+  stem_darkening: bool,
+  stem_width: Option<f64>,
+  white_on_black: bool,
+  effects: OutlineEffects,
+  output: String,
+}
+
+const USAGE: &str = "使い方: get_a_glyph render <font-file> <character> [--size <px>] \
+[--lcd rgb|bgr|vrgb|vbgr] [--lcd-filter default|light|none|<w1,w2,...>] \
+[--mono [--scan-type <n>] [--scan-control <n>]] [--hinting|--autohint] [--no-gasp] [--palette <n>] [--coords <c1,c2,...>] [--embedded-png] [--gamma <g>] [--contrast <c>] \
+[--stem-darkening [--stem-width <units>]] [--background white|black] \
+[--embolden <units>] [--oblique <度>] [--transform a,b,c,d,dx,dy] \
+[--stroke <units> [--join miter|round|bevel] [--miter-limit <n>] [--cap butt|round|square]] [--remove-overlaps] [-o <output.png|output.pbm|output.h>]";
+
+fn parse_filter(filter: &str) -> Result<Vec<f32>, String> {
+  match filter {
+    "default" => Ok(DEFAULT_FILTER.to_vec()),
+    "light" => Ok(LIGHT_FILTER.to_vec()),
+    "none" => Ok(NO_FILTER.to_vec()),
+    weights => {
+      let weights: Vec<f32> = weights.split(',')
+        .map(|w| w.trim().parse::<f32>().map_err(|_| format!("フィルタの係数を解釈できません: {}", w)))
+        .collect::<Result<_, _>>()?;
+      if weights.len().is_multiple_of(2) {
+        return Err("フィルタのタップ数は奇数にしてください".to_string());
+      }
+      Ok(weights)
+    }
+  }
+}
+
+// 10進数または 0x で始まる16進数
+fn parse_integer(s: &str) -> Option<u16> {
+  match s.strip_prefix("0x") {
+    Some(hex) => u16::from_str_radix(hex, 16).ok(),
+    None => s.parse().ok(),
+  }
+}
+
+fn parse_args(args: &[String]) -> Result<RenderArgs, String> {
+  let mut positional = Vec::new();
+  let mut size = 32;
+  let mut lcd: Option<LcdOptions> = None;
+  let mut filter = None;
+  let mut mono: Option<MonoOptions> = None;
+  let mut scan_type = None;
+  let mut scan_control = None;
+  let mut hinting = false;
+  let mut autohint = false;
+  let mut gasp = true;
+  let mut palette = None;
+  let mut coords = Vec::new();
+  let mut embedded_png = false;
+  let mut gamma = GammaOptions::default();
+  let mut stem_darkening = false;
+  let mut stem_width = None;
+  let mut white_on_black = false;
+  let mut effects = OutlineEffects::default();
+  let mut output = "glyph.png".to_string();
+
+  let mut iter = args.iter();
+  while let Some(arg) = iter.next() {
+    let mut value = || iter.next().ok_or(format!("{} に値を指定してください", arg));
+    match arg.as_str() {
+      "--size" => size = value()?.parse().map_err(|_| "--size には整数を指定してください")?,
+      "--lcd" => {
+        let (layout, order) = match value()?.as_str() {
+          "rgb" => (SubpixelLayout::Horizontal, SubpixelOrder::Rgb),
+          "bgr" => (SubpixelLayout::Horizontal, SubpixelOrder::Bgr),
+          "vrgb" => (SubpixelLayout::Vertical, SubpixelOrder::Rgb),
+          "vbgr" => (SubpixelLayout::Vertical, SubpixelOrder::Bgr),
+          other => return Err(format!("不明なサブピクセル配列です: {}", other)),
+        };
+        lcd = Some(LcdOptions { layout, order, ..Default::default() });
+      }
+      "--lcd-filter" => filter = Some(parse_filter(value()?)?),
+      "--mono" => mono = Some(MonoOptions::default()),
+      "--scan-type" => scan_type = Some(value()?.parse().map_err(|_| "--scan-type には整数を指定してください")?),
+      "--scan-control" => scan_control = Some(parse_integer(value()?).ok_or("--scan-control には整数を指定してください")?),
+      "--hinting" => hinting = true,
+      "--autohint" => autohint = true,
+      "--no-gasp" => gasp = false,
+      "--palette" => palette = Some(value()?.parse().map_err(|_| "--palette には整数を指定してください")?),
+      "--coords" => coords = parse_coords(value()?)?,
+      "--embedded-png" => embedded_png = true,
+      "--gamma" => gamma.gamma = value()?.parse().map_err(|_| "--gamma には数値を指定してください")?,
+      "--contrast" => gamma.contrast = value()?.parse().map_err(|_| "--contrast には数値を指定してください")?,
+      "--stem-darkening" => stem_darkening = true,
+      "--stem-width" => stem_width = Some(value()?.parse().map_err(|_| "--stem-width には数値を指定してください")?),
+      "--background" => white_on_black = match value()?.as_str() {
+        "white" => false,
+        "black" => true,
+        other => return Err(format!("背景色は white か black を指定してください: {}", other)),
+      },
+      "-o" | "--output" => output = value()?.clone(),
+      _ if OutlineEffects::is_flag(arg) => effects.parse_flag(arg),
+      _ if OutlineEffects::is_arg(arg) => effects.parse_arg(arg, value()?)?,
+      _ if !arg.starts_with('-') => positional.push(arg.clone()),
+      _ => return Err(format!("不明な引数です: {}", arg)),
+    }
+  }
+
+  if let (Some(lcd), Some(filter)) = (lcd.as_mut(), filter) {
+    lcd.filter = filter;
+  }
+  if gamma.gamma <= 0.0 {
+    return Err("--gamma には正の数を指定してください".to_string());
+  }
+  if lcd.is_some() && mono.is_some() {
+    return Err("--lcd と --mono は同時に指定できません".to_string());
+  }
+  // ヒンティングした輪郭はピクセル格子に合わせてあるので、後から形を変えると意味がなくなる
+  if hinting && (!effects.is_empty() || stem_darkening) {
+    return Err("--hinting は輪郭の効果や --stem-darkening と同時に指定できません".to_string());
+  }
+  if hinting && autohint {
+    return Err("--hinting と --autohint は同時に指定できません".to_string());
+  }
+  let mut positional = positional.into_iter();
+  Ok(RenderArgs {
+    font_path: positional.next().ok_or("フォントファイルのパスを引数として指定してください")?,
+    glyph_name: positional.next().ok_or("グリフ名を引数として指定してください")?,
+    size,
+    lcd,
+    mono,
+    scan_type,
+    scan_control,
+    hinting,
+    autohint,
+    gasp,
+    palette,
+    coords,
+    embedded_png,
+    gamma,
+    stem_darkening,
+    stem_width,
+    white_on_black,
+    effects,
+    output,
+  })
+}
+
+// "l" の縦のステムを水平な走査線で測った幅を基準のステム幅とみなす
+// 外接矩形の幅と違い、セリフや斜体の傾きで広がった分を含まない
+fn estimate_stem_width(font: &Sfnt, font_data: &[u8]) -> Option<f64> {
+  let glyph_id = font.get_glyph_id("l", font_data).ok()?;
+  font.get_outline_metrics(glyph_id, font_data).ok()?.vertical_stem
+}
+
+// グリフの命令を実行して、size ピクセルの格子に合わせた輪郭を得る
+fn hint_glyph(font: &Sfnt, font_data: &[u8], args: &RenderArgs, target: HintingTarget) -> Result<HintedGlyph, String> {
+  let ppem = u16::try_from(args.size).map_err(|_| "--size が大きすぎます")?;
+  let glyph_id = font.get_glyph_id(&args.glyph_name, font_data)?;
+  let mut hinter = font.get_hinter(font_data)?;
+  hinter.set_coords(&args.coords);
+  font.hint_glyph(&mut hinter, glyph_id, ppem, target, font_data)
+}
+
+// カラーグリフのレイヤーを重ねて透明な背景の RGBA の PNG にする。文字色のレイヤーは黒（--background black なら白）
+fn render_color(font: &Sfnt, font_data: &[u8], args: &RenderArgs, layers: &[ColorLayer]) -> Result<(), String> {
+  let units_per_em = font.get_head_table(font_data)?.units_per_em as f64;
+  let foreground = if args.white_on_black { [255, 255, 255, 255] } else { [0, 0, 0, 255] };
+  let glyf_table = font.get_glyf_table(font_data)?;
+  let layers = layers.iter()
+    .map(|layer| {
+      let mut path = Path::new();
+      glyf_table.draw_glyph(layer.glyph_id as usize, &mut path)?;
+      Ok((path, layer.color.map_or(foreground, |color| color.to_rgba())))
+    })
+    .collect::<Result<Vec<_>, String>>()?;
+  rasterize_layers(&layers, args.size as f64 / units_per_em).image.save(&args.output)
+}
+
+// COLR バージョン 1 の Paint を描いて透明な背景の RGBA の PNG にする。文字色は render_color と同じ
+fn render_paint(font: &Sfnt, font_data: &[u8], args: &RenderArgs, paint: &Paint, clip: Option<ClipBox>) -> Result<(), String> {
+  let units_per_em = font.get_head_table(font_data)?.units_per_em as f64;
+  let foreground = if args.white_on_black { Color { r: 255, g: 255, b: 255, a: 255 } } else { Color { r: 0, g: 0, b: 0, a: 255 } };
+  let palette = font.get_palette(args.palette.unwrap_or(0), font_data)?;
+  let glyf_table = font.get_glyf_table(font_data)?;
+  let outlines = paint.glyph_ids().into_iter()
+    .map(|glyph_id| {
+      let mut path = Path::new();
+      glyf_table.draw_glyph(glyph_id as usize, &mut path)?;
+      Ok((glyph_id, path))
+    })
+    .collect::<Result<HashMap<_, _>, String>>()?;
+  rasterize_paint(paint, &outlines, &palette, foreground, clip, args.size as f64 / units_per_em).image.save(&args.output)
+}
+
+// 埋め込みビットマップ（CBDT、sbix）を --size に拡大縮小して RGBA の PNG にする
+// --embedded-png なら画像をそのまま書き出す（sbix の JPEG・TIFF は元の形式のまま）
+fn render_bitmap(args: &RenderArgs, glyph: &BitmapGlyph) -> Result<(), String> {
+  if args.embedded_png {
+    return std::fs::write(&args.output, &glyph.data).map_err(|e| e.to_string());
+  }
+  if glyph.format != ImageFormat::Png {
+    return Err(format!("{} の埋め込みビットマップは展開できません。--embedded-png でそのまま書き出せます", glyph.format.name()));
+  }
+  rasterize_bitmap(glyph, args.size)?.image.save(&args.output)
+}
+
+// PNGは白地に黒（--background black なら黒地に白）で書き出す
+pub fn run(args: &[String]) -> Result<(), String> {
+  let args = parse_args(args).map_err(|e| format!("{}\n{}", e, USAGE))?;
+  let font_data = std::fs::read(&args.font_path).map_err(|_| "フォントファイルの読み込みに失敗しました")?;
+  let font: Sfnt = font::Parse::from_bytes(&font_data)?;
+
+  check_palette(&font, &font_data, args.palette)?;
+  // カラーグリフは効果やヒンティング、白黒・LCD を指定しなければ色付きで描く
+  let plain = args.effects.is_empty() && args.mono.is_none() && args.lcd.is_none() && !args.hinting && !args.autohint;
+  if plain {
+    let glyph_id = font.get_glyph_id(&args.glyph_name, &font_data)?;
+    if let Some((paint, clip)) = font.get_color_paint(glyph_id, &args.coords, &font_data)? {
+      return render_paint(&font, &font_data, &args, &paint, clip);
+    }
+    let layers = font.get_color_layers(glyph_id, args.palette.unwrap_or(0), &font_data)?;
+    if let Some(layers) = layers {
+      return render_color(&font, &font_data, &args, &layers);
+    }
+    let ppem = u16::try_from(args.size).map_err(|_| "--size が大きすぎます")?;
+    if let Some(glyph) = font.get_bitmap_glyph(glyph_id, ppem, &font_data)? {
+      return render_bitmap(&args, &glyph);
+    }
+  }
+  if args.embedded_png {
+    return Err("このグリフには埋め込みビットマップがありません".to_string());
+  }
+  warn_unused(args.palette);
+
+  let mut segments = Vec::new();
+  font.draw_glyph(&args.glyph_name, &font_data, &mut segments)?;
+  let head_table = font.get_head_table(&font_data)?;
+
+  // gasp テーブルがあれば、この ppem でヒンティングとアンチエイリアスをするかをそれに従って決める
+  let gasp = if args.gasp && font.has_table("gasp") {
+    Some(font.get_gasp_table(&font_data)?.behavior(args.size.min(u16::MAX as u32) as u16))
+  } else {
+    None
+  };
+  // DOGRAY のない ppem では2値で描く
+  let mono = match (&args.mono, &args.lcd, gasp) {
+    (Some(options), _, _) => Some(*options),
+    (None, None, Some(behavior)) if !behavior.do_gray => Some(MonoOptions::default()),
+    _ => None,
+  };
+  let mut lcd = args.lcd.clone();
+  if let (Some(lcd), Some(behavior)) = (lcd.as_mut(), gasp) {
+    lcd.symmetric_smoothing = behavior.symmetric_smoothing;
+  }
+  let target = match (&mono, &lcd) {
+    (Some(_), _) => HintingTarget::Mono,
+    (_, Some(lcd)) if lcd.layout == SubpixelLayout::Vertical => HintingTarget::LcdVertical,
+    (_, Some(_)) => HintingTarget::Lcd,
+    _ => HintingTarget::Grayscale,
+  };
+  // 輪郭の効果や自動ヒンティングと組み合わせるときは gasp ではヒンティングしない
+  let gasp_hinting = gasp.is_some_and(|behavior| if lcd.is_some() { behavior.symmetric_grid_fit } else { behavior.grid_fit })
+    && !args.autohint && args.effects.is_empty() && !args.stem_darkening;
+  let hinted = if args.hinting || gasp_hinting {
+    hint_glyph(&font, &font_data, &args, target)
+      .inspect_err(|e| {
+        let reason = if args.hinting { "" } else { "gasp テーブルが指定した" };
+        eprintln!("警告: {}ヒンティングに失敗したので輪郭をそのまま描きます: {}", reason, e)
+      })
+      .ok()
+  } else {
+    None
+  };
+  // ヒンティングした輪郭は座標がすでにピクセル単位
+  let (path, scale) = match &hinted {
+    Some(glyph) => (Path::from_segments(&glyph.segments()), 1.0),
+    None => (Path::from_segments(&args.effects.apply(&segments)), args.size as f64 / head_table.units_per_em as f64),
+  };
+  let autohint = if args.autohint {
+    Some(AutoHinter::new(font.get_autohint_metrics(&font_data)?))
+  } else {
+    None
+  };
+
+  if let Some(options) = &mono {
+    // SCANCTRL の回転・変形の条件は変換の形から判断する
+    let style = &args.effects.style;
+    let t = style.transform;
+    // ドロップアウト制御は指定がなければ、ヒンティング命令の SCANCTRL / SCANTYPE に従う
+    let (scan_type, scan_control) = match &hinted {
+      Some(glyph) => (glyph.scan_type, glyph.scan_control),
+      None => (options.scan_type, options.scan_control),
+    };
+    let options = MonoOptions {
+      scan_type: args.scan_type.unwrap_or(scan_type),
+      scan_control: args.scan_control.unwrap_or(scan_control),
+      rotated: options.rotated || t.b != 0.0 || t.c != 0.0 || style.oblique != 0.0,
+      stretched: options.stretched || t.a.abs() != t.d.abs(),
+    };
+    let path = match &autohint {
+      Some(hinter) => hinter.apply(&path, scale),
+      None => path,
+    };
+    let bitmap = rasterize_mono(&path, scale, args.size, &options);
+    let extension = std::path::Path::new(&args.output).extension().and_then(|e| e.to_str());
+    return match extension {
+      Some("pbm") => std::fs::write(&args.output, bitmap.to_pbm()).map_err(|e| e.to_string()),
+      Some("h") => {
+        let c = args.glyph_name.chars().next().unwrap_or('?');
+        let name = format!("glyph_{:04x}_{}px", c as u32, args.size);
+        std::fs::write(&args.output, bitmap.to_c_header(&name)).map_err(|e| e.to_string())
+      }
+      _ => bitmap.to_image().save(&args.output),
+    };
+  }
+
+  let stem_darkening = if args.stem_darkening {
+    let units_per_em = head_table.units_per_em as f64;
+    let stem_width = match args.stem_width {
+      Some(width) => width,
+      None => estimate_stem_width(&font, &font_data).unwrap_or(units_per_em / 12.0),
+    };
+    Some(StemDarkening::new(stem_width, units_per_em))
+  } else {
+    None
+  };
+  let options = RasterOptions { gamma: args.gamma, stem_darkening, autohint };
+  // 被覆率を文字色と背景色の間で合成する
+  let ink = |coverage: u8| if args.white_on_black { coverage } else { 255 - coverage };
+
+  match &lcd {
+    Some(lcd) => {
+      let mut bitmap = rasterize_with(&path, scale, &options, |path, scale| rasterize_lcd(path, scale, lcd));
+      for (_, _, pixel) in bitmap.image.enumerate_pixels_mut() {
+        pixel.data = pixel.data.map(ink);
+      }
+      bitmap.image.save(&args.output)?;
+    }
+    None => {
+      let mut bitmap = rasterize_with(&path, scale, &options, rasterize);
+      for (_, _, pixel) in bitmap.image.enumerate_pixels_mut() {
+        pixel.data[0] = ink(pixel.data[0]);
+      }
+      bitmap.image.save(&args.output)?;
+    }
+  }
+  Ok(())
+}
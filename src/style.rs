@@ -0,0 +1,93 @@
+use font::boolean::remove_overlaps;
+use font::stroke::{stroke, LineCap, LineJoin, StrokeOptions};
+use font::transform::SyntheticStyle;
+use font::truetype::outline::PathSegment;
+use font::truetype::Transform;
+
+// 輪郭に施す合成スタイルと線の描画
+#[derive(Default)]
+pub struct OutlineEffects {
+  pub style: SyntheticStyle,
+  // --stroke で指定した線幅
+  pub stroke_width: Option<f32>,
+  pub stroke_options: StrokeOptions,
+  pub remove_overlaps: bool,
+}
+
+impl OutlineEffects {
+  // 値を取らない引数
+  pub fn is_flag(arg: &str) -> bool {
+    arg == "--remove-overlaps"
+  }
+
+  pub fn parse_flag(&mut self, arg: &str) {
+    if arg == "--remove-overlaps" {
+      self.remove_overlaps = true;
+    }
+  }
+
+  pub fn is_arg(arg: &str) -> bool {
+    matches!(arg, "--embolden" | "--oblique" | "--transform" | "--stroke" | "--join" | "--miter-limit" | "--cap")
+  }
+
+  pub fn parse_arg(&mut self, arg: &str, value: &str) -> Result<(), String> {
+    match arg {
+      "--embolden" => self.style.embolden = value.parse().map_err(|_| "--embolden には数値を指定してください")?,
+      "--oblique" => {
+        let degrees: f32 = value.parse().map_err(|_| "--oblique には角度（度）を指定してください")?;
+        self.style.oblique = degrees.to_radians().tan();
+      }
+      "--transform" => {
+        let values: Vec<f32> = value.split(',')
+          .map(|v| v.trim().parse::<f32>().map_err(|_| format!("変換の係数を解釈できません: {}", v)))
+          .collect::<Result<_, _>>()?;
+        let [a, b, c, d, x, y] = values[..] else {
+          return Err("--transform には a,b,c,d,dx,dy の6つの値を指定してください".to_string());
+        };
+        self.style.transform = Transform::new(a, b, c, d, x, y);
+      }
+      "--stroke" => {
+        let width: f32 = value.parse().map_err(|_| "--stroke には線幅を指定してください")?;
+        if width <= 0.0 {
+          return Err("--stroke には正の数を指定してください".to_string());
+        }
+        self.stroke_width = Some(width);
+      }
+      "--join" => self.stroke_options.join = match value {
+        "miter" => LineJoin::Miter,
+        "round" => LineJoin::Round,
+        "bevel" => LineJoin::Bevel,
+        other => return Err(format!("不明な継ぎ目の形です: {}", other)),
+      },
+      "--miter-limit" => {
+        self.stroke_options.miter_limit = value.parse().map_err(|_| "--miter-limit には数値を指定してください")?
+      }
+      "--cap" => self.stroke_options.cap = match value {
+        "butt" => LineCap::Butt,
+        "round" => LineCap::Round,
+        "square" => LineCap::Square,
+        other => return Err(format!("不明な端の形です: {}", other)),
+      },
+      _ => return Err(format!("不明な引数です: {}", arg)),
+    }
+    Ok(())
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.stroke_width.is_none()
+      && !self.remove_overlaps
+      && self.style.embolden == 0.0
+      && self.style.oblique == 0.0
+      && self.style.transform.is_identity()
+  }
+
+  // 合成スタイルを適用してから線を描き、最後に重なりを除く
+  pub fn apply(&self, segments: &[PathSegment]) -> Vec<PathSegment> {
+    let styled = self.style.apply(segments);
+    let stroked = match self.stroke_width {
+      Some(width) => stroke(&styled, &StrokeOptions { width, ..self.stroke_options }),
+      None => styled,
+    };
+    if self.remove_overlaps { remove_overlaps(&stroked) } else { stroked }
+  }
+}
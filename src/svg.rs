@@ -0,0 +1,115 @@
+use crate::palette::{check_palette, parse_coords, warn_unused};
+use crate::style::OutlineEffects;
+use font::svg::{outline_to_svg, SvgKind, SvgOptions, ViewBoxMode};
+use font::Sfnt;
+
+struct SvgArgs {
+  font_path: String,
+  glyph_name: String,
+  options: SvgOptions,
+  palette: Option<usize>,
+  // COLR バージョン 1 の可変の Paint に当てはめる座標
+  coords: Vec<f32>,
+  // SVG テーブルの文書を書き換えずにそのまま出力する
+  svg_document: bool,
+  effects: OutlineEffects,
+  output: Option<String>,
+}
+
+const USAGE: &str = "使い方: get_a_glyph [svg] <font-file> <character> [--view-box metrics|tight] [--guides] \
+[--symbol <id>] [--palette <n>] [--coords <c1,c2,...>] [--svg-document] [-o <output.svg>] \
+(合成スタイル: --embolden <units> --oblique <度> --transform a,b,c,d,dx,dy) \
+(線: --stroke <units> --join miter|round|bevel --miter-limit <n> --cap butt|round|square) [--remove-overlaps]";
+
+fn parse_args(args: &[String]) -> Result<SvgArgs, String> {
+  let mut positional = Vec::new();
+  let mut options = SvgOptions::default();
+  let mut palette = None;
+  let mut coords = Vec::new();
+  let mut svg_document = false;
+  let mut effects = OutlineEffects::default();
+  let mut output = None;
+
+  let mut iter = args.iter();
+  while let Some(arg) = iter.next() {
+    let mut value = || iter.next().ok_or(format!("{} に値を指定してください", arg));
+    match arg.as_str() {
+      "--view-box" => options.view_box = match value()?.as_str() {
+        "metrics" => ViewBoxMode::Metrics,
+        "tight" => ViewBoxMode::Tight,
+        other => return Err(format!("viewBox は metrics か tight を指定してください: {}", other)),
+      },
+      "--guides" => options.guides = true,
+      "--symbol" => options.kind = SvgKind::Symbol { id: value()?.clone() },
+      "--palette" => palette = Some(value()?.parse().map_err(|_| "--palette には整数を指定してください")?),
+      "--coords" => coords = parse_coords(value()?)?,
+      "--svg-document" => svg_document = true,
+      "-o" | "--output" => output = Some(value()?.clone()),
+      _ if OutlineEffects::is_flag(arg) => effects.parse_flag(arg),
+      _ if OutlineEffects::is_arg(arg) => effects.parse_arg(arg, value()?)?,
+      _ if !arg.starts_with('-') => positional.push(arg.clone()),
+      _ => return Err(format!("不明な引数です: {}", arg)),
+    }
+  }
+
+  let mut positional = positional.into_iter();
+  Ok(SvgArgs {
+    font_path: positional.next().ok_or("フォントファイルのパスを引数として指定してください")?,
+    glyph_name: positional.next().ok_or("グリフ名を引数として指定してください")?,
+    options,
+    palette,
+    coords,
+    svg_document,
+    effects,
+    output,
+  })
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+  let args = parse_args(args).map_err(|e| format!("{}\n{}", e, USAGE))?;
+  let font_data = std::fs::read(&args.font_path).map_err(|_| "フォントファイルの読み込みに失敗しました")?;
+  let font: Sfnt = font::Parse::from_bytes(&font_data)?;
+
+  check_palette(&font, &font_data, args.palette)?;
+  if args.svg_document {
+    let glyph_id = font.get_glyph_id(&args.glyph_name, &font_data)?;
+    let document = font.get_svg_document(glyph_id, &font_data)?.ok_or("このグリフには SVG テーブルの文書がありません")?;
+    return write_svg(&args, document);
+  }
+  // カラーグリフは効果を指定しなければ、SVG テーブルの文書か COLR のレイヤーごとに色を付けたものにする
+  let color = if !args.effects.is_empty() {
+    None
+  } else if let Some(svg) = font.svg_document_to_svg(&args.glyph_name, &font_data, &args.options)? {
+    Some(svg)
+  } else {
+    font.color_glyph_to_svg(&args.glyph_name, args.palette.unwrap_or(0), &args.coords, &font_data, &args.options)?
+  };
+  if color.is_none() {
+    warn_unused(args.palette);
+  }
+  let svg = if let Some(svg) = color {
+    svg
+  } else if args.effects.is_empty() {
+    font.glyph_to_svg(&args.glyph_name, &font_data, &args.options)?
+  } else {
+    let glyph_id = font.get_glyph_id(&args.glyph_name, &font_data)?;
+    let mut metrics = font.get_svg_metrics(glyph_id, &font_data)?;
+    let mut segments = Vec::new();
+    font.draw_glyph(&args.glyph_name, &font_data, &mut segments)?;
+    let advance = args.effects.style.advance(metrics.advance_width as f32);
+    metrics.advance_width = advance.round().max(0.0) as u16;
+    outline_to_svg(&args.effects.apply(&segments), &metrics, &args.options)
+  };
+  write_svg(&args, svg)
+}
+
+// 出力先がなければ標準出力に書く
+fn write_svg(args: &SvgArgs, svg: String) -> Result<(), String> {
+  match &args.output {
+    Some(path) => std::fs::write(path, svg).map_err(|e| format!("{}: {}", path, e)),
+    None => {
+      println!("{}", svg);
+      Ok(())
+    }
+  }
+}
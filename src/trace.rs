@@ -0,0 +1,94 @@
+use font::hinting::HintingTarget;
+use font::Sfnt;
+
+struct TraceArgs {
+  font_path: String,
+  glyph_name: String,
+  size: u16,
+  target: HintingTarget,
+  // 命令ごとの SVG を書き出すディレクトリ
+  frames: Option<String>,
+  output: Option<String>,
+}
+
+const USAGE: &str = "使い方: get_a_glyph trace <font-file> <character> [--size <px>] [--target mono|gray|lcd|lcd-v] \
+[--frames <dir>] [-o <trace.json>]";
+
+fn parse_args(args: &[String]) -> Result<TraceArgs, String> {
+  let mut positional = Vec::new();
+  let mut size = 12;
+  let mut target = HintingTarget::Grayscale;
+  let mut frames = None;
+  let mut output = None;
+
+  let mut iter = args.iter();
+  while let Some(arg) = iter.next() {
+    let mut value = || iter.next().ok_or(format!("{} に値を指定してください", arg));
+    match arg.as_str() {
+      "--size" => size = value()?.parse().map_err(|_| "--size には整数を指定してください")?,
+      "--target" => target = match value()?.as_str() {
+        "mono" => HintingTarget::Mono,
+        "gray" => HintingTarget::Grayscale,
+        "lcd" => HintingTarget::Lcd,
+        "lcd-v" => HintingTarget::LcdVertical,
+        other => return Err(format!("不明なヒンティングの対象です: {}", other)),
+      },
+      "--frames" => frames = Some(value()?.clone()),
+      "-o" | "--output" => output = Some(value()?.clone()),
+      _ if !arg.starts_with('-') => positional.push(arg.clone()),
+      _ => return Err(format!("不明な引数です: {}", arg)),
+    }
+  }
+
+  let mut positional = positional.into_iter();
+  Ok(TraceArgs {
+    font_path: positional.next().ok_or("フォントファイルのパスを引数として指定してください")?,
+    glyph_name: positional.next().ok_or("グリフ名を引数として指定してください")?,
+    size,
+    target,
+    frames,
+    output,
+  })
+}
+
+// グリフの命令の実行記録を JSON で出力する。--frames を指定すると命令ごとの点の位置を SVG で書き出す
+// 0000.svg が実行前で、n.svg は n 番目の命令を実行した後
+pub fn run(args: &[String]) -> Result<(), String> {
+  let args = parse_args(args).map_err(|e| format!("{}\n{}", e, USAGE))?;
+  let font_data = std::fs::read(&args.font_path).map_err(|_| "フォントファイルの読み込みに失敗しました")?;
+  let font: Sfnt = font::Parse::from_bytes(&font_data)?;
+
+  let glyph_id = font.get_glyph_id(&args.glyph_name, &font_data)?;
+  let mut hinter = font.get_hinter(&font_data)?;
+  let (_, trace) = font.trace_glyph(&mut hinter, glyph_id, args.size, args.target, &font_data)?;
+  if let Some(error) = &trace.error {
+    eprintln!("警告: 命令の実行が途中で失敗しました: {}", error);
+  }
+
+  if let Some(dir) = &args.frames {
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir, e))?;
+    let write = |index: usize, svg: String| {
+      let path = std::path::Path::new(dir).join(format!("{:04}.svg", index));
+      std::fs::write(&path, svg).map_err(|e| format!("{}: {}", path.display(), e))
+    };
+    write(0, trace.frame_svg(&trace.initial, None))?;
+    let mut result = Ok(());
+    trace.replay(|index, points| {
+      if result.is_ok() {
+        result = write(index + 1, trace.frame_svg(points, Some(index)));
+      }
+    });
+    result?;
+    eprintln!("{}枚の SVG を {} に書き出しました", trace.steps.len() + 1, dir);
+  }
+
+  let json = trace.to_json();
+  match &args.output {
+    Some(path) => std::fs::write(path, json).map_err(|e| format!("{}: {}", path, e)),
+    None if args.frames.is_some() => Ok(()),
+    None => {
+      print!("{}", json);
+      Ok(())
+    }
+  }
+}